@@ -1,6 +1,5 @@
 use criterion::{BenchmarkId, Criterion, criterion_group, criterion_main};
 use prmt::detector::{DetectionContext, detect};
-use prmt::style::Shell;
 use prmt::{ModuleContext, ModuleRegistry, Template, execute};
 use std::collections::HashSet;
 use std::hint::black_box;
@@ -30,14 +29,11 @@ fn detection_for(markers: &[&'static str]) -> DetectionContext {
 }
 
 fn ctx(no_version: bool, exit_code: Option<i32>, markers: &[&'static str]) -> ModuleContext {
-    ModuleContext {
-        no_version,
-        exit_code,
-        detection: detection_for(markers),
-        shell: Shell::None,
-        stdin_data: None,
-        cwd: std::env::current_dir().ok(),
-    }
+    let mut context = ModuleContext::new(std::env::current_dir().ok());
+    context.no_version = no_version;
+    context.exit_code = exit_code;
+    context.detection = detection_for(markers);
+    context
 }
 
 fn bench_parser(c: &mut Criterion) {
@@ -0,0 +1,38 @@
+/* C API for rendering prmt prompts in-process. Link against libprmt_ffi. */
+#ifndef PRMT_H
+#define PRMT_H
+
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+/* `exit_code` value meaning the exit code of the last command is unknown. */
+#define PRMT_NO_EXIT_CODE INT32_MIN
+
+/*
+ * Renders `format` for the last command's `exit_code` and `duration_ms` (negative when
+ * unknown). `shell` ("bash", "zsh", "fish", "xonsh", "clink", or NULL for none) selects the
+ * prompt escaping, `cwd` is the directory to render for (NULL for the current directory), and
+ * a non-zero `no_color` leaves out all styling.
+ *
+ * Returns a string to release with prmt_free, or NULL on error; see prmt_last_error.
+ */
+char *prmt_render(const char *format, int32_t exit_code, int64_t duration_ms, const char *shell,
+                  const char *cwd, int no_color);
+
+/* Releases a string returned by prmt_render. NULL is ignored. */
+void prmt_free(char *prompt);
+
+/*
+ * Why the last prmt_render on this thread returned NULL, or NULL when it has not failed.
+ * Valid until the next failing call on the same thread.
+ */
+const char *prmt_last_error(void);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* PRMT_H */
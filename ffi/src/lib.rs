@@ -0,0 +1,191 @@
+//! C API for rendering prompts in-process; see `include/prmt.h`.
+
+use prmt::style::{ColorMode, Shell};
+use prmt::{LastCommand, PromptBuilder};
+use std::cell::RefCell;
+use std::ffi::{CStr, CString, c_char, c_int};
+use std::panic::{self, AssertUnwindSafe};
+use std::ptr;
+use std::time::Duration;
+
+/// `exit_code` value meaning the exit code of the last command is unknown.
+pub const PRMT_NO_EXIT_CODE: i32 = i32::MIN;
+
+thread_local! {
+    static LAST_ERROR: RefCell<Option<CString>> = const { RefCell::new(None) };
+}
+
+fn set_last_error(message: String) {
+    let message = CString::new(message.replace('\0', " ")).unwrap_or_default();
+    LAST_ERROR.with(|last| *last.borrow_mut() = Some(message));
+}
+
+/// Borrows a C string argument, treating `NULL` as absent.
+///
+/// # Safety
+/// `text` is `NULL` or a NUL-terminated string valid for the duration of the call.
+unsafe fn optional_str<'a>(name: &str, text: *const c_char) -> Result<Option<&'a str>, String> {
+    if text.is_null() {
+        return Ok(None);
+    }
+    // SAFETY: guaranteed by the caller.
+    let text = unsafe { CStr::from_ptr(text) };
+    text.to_str()
+        .map(Some)
+        .map_err(|_| format!("{} is not valid UTF-8", name))
+}
+
+struct Request<'a> {
+    format: &'a str,
+    exit_code: i32,
+    duration_ms: i64,
+    shell: Option<&'a str>,
+    cwd: Option<&'a str>,
+    no_color: bool,
+}
+
+fn render(request: Request<'_>) -> Result<String, String> {
+    let shell = match request.shell {
+        Some(shell) => shell.parse::<Shell>()?,
+        None => Shell::None,
+    };
+    let last_command = LastCommand {
+        exit_code: (request.exit_code != PRMT_NO_EXIT_CODE).then_some(request.exit_code),
+        duration: u64::try_from(request.duration_ms)
+            .ok()
+            .map(Duration::from_millis),
+        name: None,
+    };
+    let mut builder = PromptBuilder::new(request.format)
+        .shell(shell)
+        .last_command(last_command)
+        .color(if request.no_color {
+            ColorMode::Never
+        } else {
+            ColorMode::Always
+        });
+    if let Some(cwd) = request.cwd {
+        builder = builder.cwd(cwd);
+    }
+    builder
+        .build()
+        .and_then(|prompt| prompt.render())
+        .map_err(|error| error.to_string())
+}
+
+/// Renders `format` and returns the prompt as a string to release with [`prmt_free`], or
+/// `NULL` on error, with the reason available from [`prmt_last_error`].
+///
+/// # Safety
+/// `format` is a NUL-terminated string; `shell` and `cwd` are `NULL` or NUL-terminated
+/// strings. All of them are only read during the call.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn prmt_render(
+    format: *const c_char,
+    exit_code: i32,
+    duration_ms: i64,
+    shell: *const c_char,
+    cwd: *const c_char,
+    no_color: c_int,
+) -> *mut c_char {
+    let result = panic::catch_unwind(AssertUnwindSafe(|| {
+        // SAFETY: guaranteed by the caller.
+        let request = unsafe {
+            Request {
+                format: optional_str("format", format)?.ok_or("format is NULL")?,
+                exit_code,
+                duration_ms,
+                shell: optional_str("shell", shell)?,
+                cwd: optional_str("cwd", cwd)?,
+                no_color: no_color != 0,
+            }
+        };
+        render(request)
+    }))
+    .unwrap_or_else(|_| Err("prmt panicked while rendering".to_string()))
+    .and_then(|prompt| {
+        CString::new(prompt).map_err(|_| "the prompt contains a NUL byte".to_string())
+    });
+
+    match result {
+        Ok(prompt) => prompt.into_raw(),
+        Err(error) => {
+            set_last_error(error);
+            ptr::null_mut()
+        }
+    }
+}
+
+/// Releases a string returned by [`prmt_render`]. `NULL` is ignored.
+///
+/// # Safety
+/// `prompt` is `NULL` or a string returned by [`prmt_render`] that was not freed yet.
+#[unsafe(no_mangle)]
+pub unsafe extern "C" fn prmt_free(prompt: *mut c_char) {
+    if !prompt.is_null() {
+        // SAFETY: guaranteed by the caller.
+        drop(unsafe { CString::from_raw(prompt) });
+    }
+}
+
+/// Why the last [`prmt_render`] on this thread returned `NULL`, or `NULL` when it has not
+/// failed. The string stays valid until the next failing call on the same thread.
+#[unsafe(no_mangle)]
+pub extern "C" fn prmt_last_error() -> *const c_char {
+    LAST_ERROR.with(|last| {
+        last.borrow()
+            .as_ref()
+            .map_or(ptr::null(), |error| error.as_ptr())
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render_c(format: &str, exit_code: i32) -> Result<String, String> {
+        let format = CString::new(format).unwrap();
+        let shell = CString::new("bash").unwrap();
+        unsafe {
+            let prompt = prmt_render(
+                format.as_ptr(),
+                exit_code,
+                -1,
+                shell.as_ptr(),
+                ptr::null(),
+                1,
+            );
+            if prompt.is_null() {
+                return Err(CStr::from_ptr(prmt_last_error())
+                    .to_str()
+                    .unwrap()
+                    .to_string());
+            }
+            let text = CStr::from_ptr(prompt).to_str().unwrap().to_string();
+            prmt_free(prompt);
+            Ok(text)
+        }
+    }
+
+    #[test]
+    fn renders_with_the_last_exit_code() {
+        assert_eq!(
+            render_c("{ok:green:yes}{fail:red:no}", 0).as_deref(),
+            Ok("yes")
+        );
+        assert_eq!(
+            render_c("{ok:green:yes}{fail:red:no}", 1).as_deref(),
+            Ok("no")
+        );
+        assert_eq!(
+            render_c("{ok:green:yes}", PRMT_NO_EXIT_CODE).as_deref(),
+            Ok("")
+        );
+    }
+
+    #[test]
+    fn errors_are_reported_through_last_error() {
+        let error = render_c("{nope}", 0).unwrap_err();
+        assert!(error.contains("nope"), "{error}");
+    }
+}
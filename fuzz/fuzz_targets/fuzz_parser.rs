@@ -1,10 +1,20 @@
 #![no_main]
 use libfuzzer_sys::fuzz_target;
-use prmt::parse;
+use prmt::{parse, parse_spanned};
 
 fuzz_target!(|data: &[u8]| {
     if let Ok(s) = std::str::from_utf8(data) {
-        // Fuzz the parser with arbitrary UTF-8 input
-        let _ = parse(s);
+        // Fuzz the parser with arbitrary UTF-8 input: spans must tile the input on char
+        // boundaries, and spanned parsing must agree with plain parsing.
+        let spanned = parse_spanned(s);
+        let mut end = 0;
+        for (_, span) in &spanned {
+            assert_eq!(span.start, end);
+            assert!(s.is_char_boundary(span.start) && s.is_char_boundary(span.end));
+            end = span.end;
+        }
+        assert_eq!(end, s.len());
+        let tokens: Vec<_> = spanned.into_iter().map(|(token, _)| token).collect();
+        assert_eq!(parse(s), tokens);
     }
-});
\ No newline at end of file
+});
@@ -17,11 +17,9 @@ fuzz_target!(|data: &[u8]| {
         // Fuzz the template renderer with arbitrary UTF-8 input
         let template = Template::new(s);
         let registry = setup_registry();
-        let context = ModuleContext {
-            no_version: true,
-            exit_code: Some(0),
-            ..ModuleContext::default()
-        };
+        let mut context = ModuleContext::default();
+        context.no_version = true;
+        context.exit_code = Some(0);
 
         let _ = template.render(&registry, &context);
     }
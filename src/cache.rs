@@ -0,0 +1,346 @@
+//! On-disk caches: tool versions, so a new shell's first prompt does not spawn every
+//! `--version` subprocess again, and git status, so huge repositories render instantly.
+//! Both are disabled until [`set_ttl`] or [`enable_git_status`] is called.
+
+use crate::trace::{self, Event};
+use std::collections::HashMap;
+use std::fmt::Write as _;
+use std::fs;
+use std::io::Write;
+use std::path::{Path, PathBuf};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, OnceLock};
+use std::time::{Duration, SystemTime, UNIX_EPOCH};
+
+/// How long a cached version is trusted when nothing else is configured.
+pub const DEFAULT_TTL: Duration = Duration::from_secs(3600);
+
+const VERSIONS_FILE: &str = "versions";
+const GIT_DIR: &str = "git";
+
+/// How long a git refresh may hold its lock before another one is allowed to start.
+const GIT_LOCK_TIMEOUT: Duration = Duration::from_secs(60);
+
+static TTL: OnceLock<Duration> = OnceLock::new();
+static VERSIONS: OnceLock<Mutex<HashMap<String, (u64, String)>>> = OnceLock::new();
+static GIT_STATUS: AtomicBool = AtomicBool::new(false);
+static STALE_REPOS: Mutex<Vec<PathBuf>> = Mutex::new(Vec::new());
+
+/// Enables the cache with entries expiring after `ttl`; a zero `ttl` leaves it disabled. The
+/// prmt binary calls it on every run, with [`DEFAULT_TTL`] unless configured otherwise.
+/// Only the first call has an effect.
+pub fn set_ttl(ttl: Duration) {
+    if !ttl.is_zero() {
+        let _ = TTL.set(ttl);
+    }
+}
+
+/// How long a tool version stays valid: the configured TTL, or [`DEFAULT_TTL`] while the
+/// on-disk cache is disabled.
+pub fn ttl() -> Duration {
+    TTL.get().copied().unwrap_or(DEFAULT_TTL)
+}
+
+/// `$XDG_CACHE_HOME/prmt`, or `~/.cache/prmt` when the variable is unset.
+pub fn cache_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CACHE_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".cache")))?;
+    Some(base.join("prmt"))
+}
+
+/// File holding the cached tool versions.
+pub fn versions_path() -> Option<PathBuf> {
+    Some(cache_dir()?.join(VERSIONS_FILE))
+}
+
+/// Key for a tool's version. Version managers switch tools by changing `PATH`, so entries
+/// are kept per `PATH` as well as per tool.
+pub fn version_key(tool: &str) -> String {
+    let path = std::env::var("PATH").unwrap_or_default();
+    format!("{}:{:016x}", tool, hash(&path))
+}
+
+/// FNV-1a, for every cache key and file name that must stay stable across runs and builds,
+/// unlike the standard library hasher. Trust and plugin artifacts use SHA-256 instead.
+pub(crate) fn hash(text: &str) -> u64 {
+    text.bytes().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+/// Lowercase hexadecimal of `bytes`, for digests written to disk.
+pub(crate) fn hex(bytes: &[u8]) -> String {
+    bytes.iter().fold(String::new(), |mut hex, byte| {
+        let _ = write!(hex, "{:02x}", byte);
+        hex
+    })
+}
+
+fn now() -> u64 {
+    SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map(|elapsed| elapsed.as_secs())
+        .unwrap_or(0)
+}
+
+/// Lines of `<key>\t<unix seconds>\t<version>`; anything malformed is dropped.
+fn parse_versions(text: &str) -> HashMap<String, (u64, String)> {
+    text.lines()
+        .filter_map(|line| {
+            let mut fields = line.splitn(3, '\t');
+            let key = fields.next()?;
+            let stored = fields.next()?.parse().ok()?;
+            let version = fields.next()?;
+            Some((key.to_string(), (stored, version.to_string())))
+        })
+        .collect()
+}
+
+fn versions() -> &'static Mutex<HashMap<String, (u64, String)>> {
+    VERSIONS.get_or_init(|| {
+        let entries = versions_path()
+            .and_then(|path| fs::read_to_string(path).ok())
+            .map(|text| parse_versions(&text))
+            .unwrap_or_default();
+        Mutex::new(entries)
+    })
+}
+
+fn is_fresh(stored: u64, now: u64, ttl: Duration) -> bool {
+    stored <= now && now - stored < ttl.as_secs()
+}
+
+/// The cached version for `key`, if the cache is enabled and the entry has not expired.
+pub fn version(key: &str) -> Option<String> {
+    let ttl = *TTL.get()?;
+    let entries = versions().lock().ok()?;
+    let version = entries
+        .get(key)
+        .filter(|(stored, _)| is_fresh(*stored, now(), ttl))
+        .map(|(_, version)| version.clone());
+    trace::record(|| Event::Cache {
+        kind: "version",
+        key: key.to_string(),
+        hit: version.is_some(),
+    });
+    version
+}
+
+/// Records `version` for `key` and rewrites the cache file without expired entries. The file
+/// is replaced atomically, so concurrent prompts never read a partial write.
+pub fn store_version(key: &str, version: &str) {
+    let Some(&ttl) = TTL.get() else {
+        return;
+    };
+    if key.contains(['\t', '\n']) || version.contains(['\t', '\n']) {
+        return;
+    }
+    let Ok(mut entries) = versions().lock() else {
+        return;
+    };
+    let now = now();
+    entries.insert(key.to_string(), (now, version.to_string()));
+    entries.retain(|_, (stored, _)| is_fresh(*stored, now, ttl));
+
+    let mut text = String::new();
+    for (key, (stored, version)) in entries.iter() {
+        text.push_str(&format!("{}\t{}\t{}\n", key, stored, version));
+    }
+    if let Some(dir) = cache_dir() {
+        let _ = write_atomic(&dir, VERSIONS_FILE, &text);
+    }
+}
+
+/// Writes `name` in `dir` through a temporary file renamed into place.
+pub(crate) fn write_atomic(
+    dir: &Path,
+    name: &str,
+    contents: impl AsRef<[u8]>,
+) -> std::io::Result<()> {
+    fs::create_dir_all(dir)?;
+    let temp = dir.join(format!(".{}.{}", name, std::process::id()));
+    let result = fs::File::create(&temp)
+        .and_then(|mut file| file.write_all(contents.as_ref()))
+        .and_then(|()| fs::rename(&temp, dir.join(name)));
+    if result.is_err() {
+        let _ = fs::remove_file(&temp);
+    }
+    result
+}
+
+/// Makes `{git}` show the status stored by the last refresh and queue its repository for
+/// [`take_stale_git_repos`], instead of computing the status while the prompt waits.
+pub fn enable_git_status() {
+    GIT_STATUS.store(true, Ordering::Relaxed);
+}
+
+pub fn git_status_enabled() -> bool {
+    GIT_STATUS.load(Ordering::Relaxed)
+}
+
+/// Directory holding one git status file per repository.
+pub fn git_dir() -> Option<PathBuf> {
+    Some(cache_dir()?.join(GIT_DIR))
+}
+
+fn git_file_name(repo_root: &Path) -> String {
+    format!("{:016x}", hash(&repo_root.to_string_lossy()))
+}
+
+/// Status flags stored for `repo_root` by the last refresh. The file repeats the repository
+/// path, so a hash collision reads as a miss.
+pub fn git_status(repo_root: &Path) -> Option<u8> {
+    let flags = stored_git_status(repo_root);
+    trace::record(|| Event::Cache {
+        kind: "git",
+        key: repo_root.display().to_string(),
+        hit: flags.is_some(),
+    });
+    flags
+}
+
+fn stored_git_status(repo_root: &Path) -> Option<u8> {
+    let text = fs::read_to_string(git_dir()?.join(git_file_name(repo_root))).ok()?;
+    let (flags, root) = text.trim_end_matches('\n').split_once('\t')?;
+    (Path::new(root) == repo_root)
+        .then(|| flags.parse().ok())
+        .flatten()
+}
+
+pub fn store_git_status(repo_root: &Path, flags: u8) {
+    if let Some(dir) = git_dir() {
+        let text = format!("{}\t{}\n", flags, repo_root.display());
+        let _ = write_atomic(&dir, &git_file_name(repo_root), &text);
+    }
+}
+
+/// Records that `repo_root` was rendered from a stored status that needs refreshing.
+pub fn mark_git_stale(repo_root: &Path) {
+    if let Ok(mut repos) = STALE_REPOS.lock()
+        && !repos.iter().any(|repo| repo == repo_root)
+    {
+        repos.push(repo_root.to_path_buf());
+    }
+}
+
+pub fn take_stale_git_repos() -> Vec<PathBuf> {
+    STALE_REPOS
+        .lock()
+        .map(|mut repos| std::mem::take(&mut *repos))
+        .unwrap_or_default()
+}
+
+/// Runs `refresh` unless another process is already refreshing `repo_root`, so prompts in a
+/// huge repository do not pile up refreshes. A lock older than a minute is taken over.
+pub fn with_git_lock(repo_root: &Path, refresh: impl FnOnce()) {
+    let Some(dir) = git_dir() else {
+        return;
+    };
+    if fs::create_dir_all(&dir).is_err() {
+        return;
+    }
+    let lock = dir.join(format!("{}.lock", git_file_name(repo_root)));
+    let acquired = fs::OpenOptions::new()
+        .write(true)
+        .create_new(true)
+        .open(&lock)
+        .is_ok()
+        || fs::metadata(&lock)
+            .and_then(|metadata| metadata.modified())
+            .is_ok_and(|modified| {
+                modified.elapsed().unwrap_or_default() > GIT_LOCK_TIMEOUT
+                    && fs::write(&lock, b"").is_ok()
+            });
+    if acquired {
+        refresh();
+        let _ = fs::remove_file(&lock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    fn parse_versions_skips_malformed_lines() {
+        let entries = parse_versions("node:1\t100\t22.1.0\nbroken\nrust:2\tsoon\t1.80.0\n");
+        assert_eq!(entries.len(), 1);
+        assert_eq!(entries["node:1"], (100, "22.1.0".to_string()));
+    }
+
+    #[test]
+    fn entries_expire_after_the_ttl() {
+        let ttl = Duration::from_secs(60);
+        assert!(is_fresh(100, 159, ttl));
+        assert!(!is_fresh(100, 160, ttl));
+        assert!(!is_fresh(200, 100, ttl));
+    }
+
+    #[test]
+    fn versions_are_not_read_until_a_ttl_is_set() {
+        assert_eq!(TTL.get(), None);
+        assert_eq!(version(&version_key("node")), None);
+        assert_eq!(ttl(), DEFAULT_TTL);
+    }
+
+    #[test]
+    fn version_keys_name_the_tool() {
+        assert_ne!(version_key("node"), version_key("bun"));
+        assert_eq!(version_key("go"), version_key("go"));
+        assert!(version_key("rust+nightly").starts_with("rust+nightly:"));
+    }
+
+    #[test]
+    #[serial]
+    fn git_status_round_trips_per_repository() {
+        let dir = tempfile::tempdir().unwrap();
+        let previous = std::env::var_os("XDG_CACHE_HOME");
+        unsafe {
+            std::env::set_var("XDG_CACHE_HOME", dir.path());
+        }
+
+        let repo = Path::new("/work/huge");
+        assert_eq!(git_status(repo), None);
+        store_git_status(repo, 0b101);
+        assert_eq!(git_status(repo), Some(0b101));
+        assert_eq!(git_status(Path::new("/work/other")), None);
+
+        let mut runs = 0;
+        with_git_lock(repo, || {
+            runs += 1;
+            with_git_lock(repo, || runs += 10);
+        });
+        with_git_lock(repo, || runs += 100);
+
+        unsafe {
+            match previous {
+                Some(value) => std::env::set_var("XDG_CACHE_HOME", value),
+                None => std::env::remove_var("XDG_CACHE_HOME"),
+            }
+        }
+        assert_eq!(runs, 101);
+    }
+
+    #[test]
+    fn stale_repos_are_queued_once() {
+        mark_git_stale(Path::new("/a"));
+        mark_git_stale(Path::new("/a"));
+        assert_eq!(take_stale_git_repos(), [PathBuf::from("/a")]);
+        assert!(take_stale_git_repos().is_empty());
+    }
+
+    #[test]
+    fn write_atomic_replaces_the_file() {
+        let dir = tempfile::tempdir().unwrap();
+        write_atomic(dir.path(), "versions", "old").unwrap();
+        write_atomic(dir.path(), "versions", "new").unwrap();
+        assert_eq!(
+            fs::read_to_string(dir.path().join("versions")).unwrap(),
+            "new"
+        );
+        assert_eq!(fs::read_dir(dir.path()).unwrap().count(), 1);
+    }
+}
@@ -0,0 +1,61 @@
+/// Rewrites rendered output for Clink's prompt filter. cmd.exe does not return to the first
+/// column on a bare `\n`, so every line break becomes CRLF.
+pub fn to_prompt(output: &str) -> String {
+    let mut result = String::with_capacity(output.len() + 8);
+    let mut previous = None;
+    for ch in output.chars() {
+        if ch == '\n' && previous != Some('\r') {
+            result.push('\r');
+        }
+        result.push(ch);
+        previous = Some(ch);
+    }
+    result
+}
+
+/// Turns on ANSI escape handling for the console, which legacy conhost leaves disabled.
+/// The prompt is printed by Clink rather than by prmt, whose stdout is a pipe, so the mode is
+/// set on the console output buffer itself.
+#[cfg(windows)]
+pub fn enable_virtual_terminal() {
+    use std::ffi::c_void;
+    use std::os::windows::io::AsRawHandle;
+
+    const ENABLE_VIRTUAL_TERMINAL_PROCESSING: u32 = 0x0004;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetConsoleMode(handle: *mut c_void, mode: *mut u32) -> i32;
+        fn SetConsoleMode(handle: *mut c_void, mode: u32) -> i32;
+    }
+
+    let Ok(console) = std::fs::OpenOptions::new()
+        .read(true)
+        .write(true)
+        .open("CONOUT$")
+    else {
+        return;
+    };
+    let handle = console.as_raw_handle();
+    let mut mode = 0;
+    unsafe {
+        if GetConsoleMode(handle, &mut mode) != 0 && mode & ENABLE_VIRTUAL_TERMINAL_PROCESSING == 0
+        {
+            SetConsoleMode(handle, mode | ENABLE_VIRTUAL_TERMINAL_PROCESSING);
+        }
+    }
+}
+
+#[cfg(not(windows))]
+pub fn enable_virtual_terminal() {}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn newlines_become_crlf() {
+        assert_eq!(to_prompt("a\nb\r\nc\n"), "a\r\nb\r\nc\r\n");
+        assert_eq!(to_prompt("plain"), "plain");
+    }
+}
@@ -0,0 +1,1107 @@
+use crate::cache;
+use crate::detector::detect_from;
+use crate::icons::IconSet;
+use crate::style::{AnsiStyle, ModuleStyle};
+use crate::theme::{ModuleStyles, Palette, Theme};
+use crate::width::MaxWidth;
+use sha2::{Digest, Sha256};
+use std::collections::{BTreeMap, HashSet};
+use std::fmt::Write;
+use std::io::{self, Read};
+use std::path::{Path, PathBuf};
+use std::str::FromStr;
+use toml::Value;
+
+/// Settings read from `config.toml`. Every field is optional; CLI flags and environment
+/// variables take precedence over the file.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Config {
+    pub format: Option<String>,
+    pub right_format: Option<String>,
+    pub title: Option<String>,
+    pub theme: Option<String>,
+    pub styles: Option<ModuleStyles>,
+    pub palette: Option<Palette>,
+    pub icons: Option<IconSet>,
+    pub timeout: Option<u64>,
+    pub max_width: Option<MaxWidth>,
+    /// `[cache] ttl`: seconds a cached tool version stays valid, with 0 disabling the cache.
+    pub cache_ttl: Option<u64>,
+    /// `[cache] git`: render git status from the cache and refresh it in the background.
+    pub cache_git: Option<bool>,
+    /// `[profiles.<name>]` tables, applied on top of the rest by [`Config::select_profile`].
+    pub profiles: BTreeMap<String, Config>,
+    /// Files named by `include`, not yet applied; [`read`] layers them under this config.
+    pub include: Vec<String>,
+}
+
+/// Per-project config file, looked up from the current directory upwards.
+pub const PROJECT_CONFIG: &str = ".prmt.toml";
+
+impl Config {
+    /// Overlays `other` on top of this config: its keys win, and its styles and palette
+    /// entries replace ones with the same name.
+    pub fn merge(&mut self, other: Config) {
+        fn overlay<T>(base: &mut Option<T>, value: Option<T>) {
+            if value.is_some() {
+                *base = value;
+            }
+        }
+
+        overlay(&mut self.format, other.format);
+        overlay(&mut self.right_format, other.right_format);
+        overlay(&mut self.title, other.title);
+        overlay(&mut self.theme, other.theme);
+        overlay(&mut self.icons, other.icons);
+        overlay(&mut self.timeout, other.timeout);
+        overlay(&mut self.max_width, other.max_width);
+        overlay(&mut self.cache_ttl, other.cache_ttl);
+        overlay(&mut self.cache_git, other.cache_git);
+        match (&mut self.styles, other.styles) {
+            (Some(styles), Some(other)) => styles.extend(other),
+            (styles, other) => overlay(styles, other),
+        }
+        match (&mut self.palette, other.palette) {
+            (Some(palette), Some(other)) => palette.extend(other),
+            (palette, other) => overlay(palette, other),
+        }
+        self.profiles.extend(other.profiles);
+    }
+
+    /// Overlays the named profile on the top-level settings.
+    pub fn select_profile(&mut self, name: &str) -> Result<(), String> {
+        let Some(profile) = self.profiles.remove(name) else {
+            let available: Vec<&str> = self.profiles.keys().map(String::as_str).collect();
+            return Err(if available.is_empty() {
+                format!("Unknown profile: {} (no profiles are configured)", name)
+            } else {
+                format!(
+                    "Unknown profile: {} (available: {})",
+                    name,
+                    available.join(", ")
+                )
+            });
+        };
+        self.merge(profile);
+        Ok(())
+    }
+}
+
+/// Keys accepted at the top level of a config file; profiles take all but `profiles`.
+const KEYS: &[&str] = &[
+    "include",
+    "target",
+    "format",
+    "right_format",
+    "title",
+    "theme",
+    "icons",
+    "timeout",
+    "max_width",
+    "cache",
+    "styles",
+    "palette",
+    "profiles",
+];
+
+/// Operating systems accepted in `[target.<os>]`; `unix` matches every Unix-like system.
+const TARGETS: &[&str] = &[
+    "linux", "macos", "windows", "freebsd", "openbsd", "netbsd", "android", "unix",
+];
+
+fn target_matches(target: &str) -> bool {
+    match target {
+        "unix" => cfg!(unix),
+        os => os == std::env::consts::OS,
+    }
+}
+
+/// Keys accepted in theme files.
+const THEME_KEYS: &[&str] = &["theme", "icons", "styles", "palette"];
+
+/// A problem with a config file, pinned to the dotted key it was found at. Syntax errors that
+/// cannot be tied to a key have an empty `key`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Diagnostic {
+    pub key: String,
+    pub message: String,
+}
+
+impl Diagnostic {
+    fn new(key: &str, message: impl Into<String>) -> Self {
+        Self {
+            key: key.to_string(),
+            message: message.into(),
+        }
+    }
+}
+
+impl std::fmt::Display for Diagnostic {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        if self.key.is_empty() {
+            f.write_str(self.message.trim_end())
+        } else {
+            write!(f, "{}: {}", self.key, self.message)
+        }
+    }
+}
+
+fn expect_str<'v>(key: &str, value: &'v Value) -> Result<&'v str, Diagnostic> {
+    value.as_str().ok_or_else(|| {
+        Diagnostic::new(
+            key,
+            format!("expected a string, found {}", value.type_str()),
+        )
+    })
+}
+
+fn expect_table<'v>(key: &str, value: &'v Value) -> Result<&'v toml::Table, Diagnostic> {
+    value.as_table().ok_or_else(|| {
+        Diagnostic::new(key, format!("expected a table, found {}", value.type_str()))
+    })
+}
+
+impl FromStr for Config {
+    type Err = String;
+
+    fn from_str(text: &str) -> Result<Self, Self::Err> {
+        Config::parse(text).map_err(|diagnostic| diagnostic.to_string())
+    }
+}
+
+impl Config {
+    /// Parses config file text, reporting the first problem with the key it was found at.
+    pub fn parse(text: &str) -> Result<Self, Diagnostic> {
+        let table: toml::Table =
+            toml::from_str(text).map_err(|e| Diagnostic::new("", e.to_string()))?;
+        Config::from_table(&table, "")
+    }
+
+    /// Reads the settings in `table`; `prefix` is `profiles.<name>.` inside a profile or
+    /// `target.<os>.` inside a target, which cannot nest profiles, targets, or includes. The
+    /// target sections for the running OS are applied on top, `unix` before the specific OS.
+    fn from_table(table: &toml::Table, prefix: &str) -> Result<Self, Diagnostic> {
+        let mut config = Config::default();
+        let mut targets = Vec::new();
+
+        for (key, value) in table {
+            let name = format!("{}{}", prefix, key);
+            let invalid = |message: String| Diagnostic::new(&name, message);
+            match key.as_str() {
+                "format" => config.format = Some(expect_str(&name, value)?.to_string()),
+                "right_format" => config.right_format = Some(expect_str(&name, value)?.to_string()),
+                "title" => config.title = Some(expect_str(&name, value)?.to_string()),
+                "theme" => config.theme = Some(expect_str(&name, value)?.to_string()),
+                "icons" => {
+                    let icons = IconSet::from_str(expect_str(&name, value)?).map_err(invalid)?;
+                    config.icons = Some(icons);
+                }
+                "timeout" => {
+                    let timeout = value
+                        .as_integer()
+                        .and_then(|ms| u64::try_from(ms).ok())
+                        .ok_or_else(|| {
+                            invalid("expected a non-negative number of milliseconds".to_string())
+                        })?;
+                    config.timeout = Some(timeout);
+                }
+                "max_width" => {
+                    let max_width = match value {
+                        Value::Integer(columns) => usize::try_from(*columns)
+                            .map(MaxWidth::Columns)
+                            .map_err(|_| {
+                                invalid(format!(
+                                    "invalid width {} (expected a column count or \"auto\")",
+                                    columns
+                                ))
+                            })?,
+                        value => MaxWidth::from_str(expect_str(&name, value)?).map_err(invalid)?,
+                    };
+                    config.max_width = Some(max_width);
+                }
+                "cache" => {
+                    for (setting, value) in expect_table(&name, value)? {
+                        let key = format!("{}.{}", name, setting);
+                        match setting.as_str() {
+                            "ttl" => {
+                                let ttl = value
+                                    .as_integer()
+                                    .and_then(|secs| u64::try_from(secs).ok())
+                                    .ok_or_else(|| {
+                                        Diagnostic::new(
+                                            &key,
+                                            "expected a non-negative number of seconds",
+                                        )
+                                    })?;
+                                config.cache_ttl = Some(ttl);
+                            }
+                            "git" => {
+                                let git = value.as_bool().ok_or_else(|| {
+                                    Diagnostic::new(
+                                        &key,
+                                        format!("expected a boolean, found {}", value.type_str()),
+                                    )
+                                })?;
+                                config.cache_git = Some(git);
+                            }
+                            _ => {
+                                return Err(Diagnostic::new(
+                                    &key,
+                                    "unknown key (expected ttl, git)",
+                                ));
+                            }
+                        }
+                    }
+                }
+                "styles" => {
+                    let mut styles = ModuleStyles::default();
+                    for (module, style) in expect_table(&name, value)? {
+                        let key = format!("{}.{}", name, module);
+                        styles
+                            .insert(module, expect_str(&key, style)?)
+                            .map_err(|e| Diagnostic::new(&key, e))?;
+                    }
+                    config.styles = Some(styles);
+                }
+                "palette" => {
+                    let mut palette = Palette::default();
+                    for (color_name, color) in expect_table(&name, value)? {
+                        let key = format!("{}.{}", name, color_name);
+                        palette
+                            .insert(color_name, expect_str(&key, color)?)
+                            .map_err(|e| Diagnostic::new(&key, e))?;
+                    }
+                    config.palette = Some(palette);
+                }
+                "profiles" if prefix.is_empty() => {
+                    for (profile_name, profile) in expect_table(&name, value)? {
+                        let profile_key = format!("profiles.{}", profile_name);
+                        let profile = expect_table(&profile_key, profile)?;
+                        let profile = Config::from_table(profile, &format!("{}.", profile_key))?;
+                        config.profiles.insert(profile_name.clone(), profile);
+                    }
+                }
+                "target" if prefix.is_empty() => {
+                    for (os, target) in expect_table(&name, value)? {
+                        let target_key = format!("target.{}", os);
+                        if !TARGETS.contains(&os.as_str()) {
+                            return Err(Diagnostic::new(
+                                &target_key,
+                                format!(
+                                    "unknown operating system (expected one of {})",
+                                    TARGETS.join(", ")
+                                ),
+                            ));
+                        }
+                        let target = expect_table(&target_key, target)?;
+                        let target = Config::from_table(target, &format!("{}.", target_key))?;
+                        if target_matches(os) {
+                            targets.push((os.as_str() != "unix", target));
+                        }
+                    }
+                }
+                "include" if prefix.is_empty() => {
+                    config.include = match value {
+                        Value::Array(files) => files
+                            .iter()
+                            .enumerate()
+                            .map(|(index, file)| {
+                                expect_str(&format!("{}[{}]", name, index), file)
+                                    .map(str::to_string)
+                            })
+                            .collect::<Result<_, _>>()?,
+                        value => vec![expect_str(&name, value)?.to_string()],
+                    };
+                }
+                _ => {
+                    let keys = match prefix {
+                        "" => KEYS,
+                        _ => &KEYS[2..KEYS.len() - 1],
+                    };
+                    return Err(invalid(format!(
+                        "unknown key (expected one of {})",
+                        keys.join(", ")
+                    )));
+                }
+            }
+        }
+
+        targets.sort_by_key(|(specific, _)| *specific);
+        for (_, target) in targets {
+            config.merge(target);
+        }
+        Ok(config)
+    }
+
+    /// Checks what parsing cannot: that formats, styles, and the theme are valid. Style
+    /// names resolve against the active palette and theme, so install those first.
+    pub fn diagnose(&self) -> Vec<Diagnostic> {
+        self.diagnose_with_prefix("")
+    }
+
+    fn diagnose_with_prefix(&self, prefix: &str) -> Vec<Diagnostic> {
+        let mut diagnostics = Vec::new();
+        let formats = [
+            ("format", &self.format),
+            ("right_format", &self.right_format),
+            ("title", &self.title),
+        ];
+        for (key, format) in formats {
+            let Some(format) = format else {
+                continue;
+            };
+            for error in crate::executor::check(format) {
+                diagnostics.push(Diagnostic::new(
+                    &format!("{}{}", prefix, key),
+                    error.to_string(),
+                ));
+            }
+        }
+
+        if let Some(theme) = &self.theme
+            && theme_path(theme).is_none()
+            && let Err(e) = Theme::from_str(theme)
+        {
+            diagnostics.push(Diagnostic::new(&format!("{}theme", prefix), e));
+        }
+
+        if let Some(styles) = &self.styles {
+            for (module, style) in styles.entries() {
+                if let Err(e) = <AnsiStyle as ModuleStyle>::parse(style) {
+                    let key = format!("{}styles.{}", prefix, module);
+                    diagnostics.push(Diagnostic::new(&key, e));
+                }
+            }
+        }
+
+        for (name, profile) in &self.profiles {
+            diagnostics.extend(profile.diagnose_with_prefix(&format!("profiles.{}.", name)));
+        }
+        diagnostics
+    }
+}
+
+/// `$XDG_CONFIG_HOME/prmt`, else `~/.config/prmt`.
+fn config_dir() -> Option<PathBuf> {
+    let base = std::env::var_os("XDG_CONFIG_HOME")
+        .filter(|path| !path.is_empty())
+        .map(PathBuf::from)
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config")))?;
+    Some(base.join("prmt"))
+}
+
+/// Location of the config file: `PRMT_CONFIG`, else `config.toml` in the config directory.
+pub fn config_path() -> Option<PathBuf> {
+    if let Some(path) = std::env::var_os("PRMT_CONFIG").filter(|path| !path.is_empty()) {
+        return Some(PathBuf::from(path));
+    }
+    Some(config_dir()?.join("config.toml"))
+}
+
+/// Nearest `.prmt.toml` in `start` or one of its parents.
+pub fn find_project_config(start: &Path) -> Option<PathBuf> {
+    detect_from(&HashSet::from([PROJECT_CONFIG]), start)
+        .get(PROJECT_CONFIG)
+        .map(Path::to_path_buf)
+}
+
+/// Largest config file read, so an include naming a log or a disk image cannot stall the
+/// prompt.
+const MAX_CONFIG_LEN: u64 = 1 << 20;
+
+/// SHA-256 of config files. Each file is prefixed with its length, so text moved between
+/// files counts as an edit.
+fn fingerprint(texts: &[String]) -> String {
+    let mut hasher = Sha256::new();
+    for text in texts {
+        hasher.update((text.len() as u64).to_le_bytes());
+        hasher.update(text.as_bytes());
+    }
+    cache::hex(&hasher.finalize())
+}
+
+/// Resolves an `include` entry: `~/` is the home directory, and relative paths are relative
+/// to the directory of the including file.
+fn include_path(base: &Path, include: &str) -> PathBuf {
+    if let Some(rest) = include.strip_prefix("~/")
+        && let Some(home) = dirs::home_dir()
+    {
+        return home.join(rest);
+    }
+    base.parent().unwrap_or(Path::new("")).join(include)
+}
+
+/// Reads the config file at `path`, which must be a regular file of at most
+/// [`MAX_CONFIG_LEN`] bytes: a FIFO or a device such as `/dev/zero` would never finish.
+fn read_text(path: &Path) -> Result<String, String> {
+    let error = |e: &dyn std::fmt::Display| format!("{}: {}", path.display(), e);
+    let metadata = std::fs::metadata(path).map_err(|e| error(&e))?;
+    if !metadata.is_file() {
+        return Err(error(&"not a regular file"));
+    }
+    let mut text = String::new();
+    std::fs::File::open(path)
+        .and_then(|file| file.take(MAX_CONFIG_LEN + 1).read_to_string(&mut text))
+        .map_err(|e| error(&e))?;
+    if text.len() as u64 > MAX_CONFIG_LEN {
+        return Err(error(&format_args!("larger than {} bytes", MAX_CONFIG_LEN)));
+    }
+    Ok(text)
+}
+
+/// Parses an included file. Syntax errors name only the line, as quoting the source would
+/// print whatever file the include points at.
+fn parse_include(text: &str) -> Result<Config, Diagnostic> {
+    let table: toml::Table = toml::from_str(text).map_err(|e| {
+        let line = e
+            .span()
+            .map_or(1, |span| text[..span.start].matches('\n').count() + 1);
+        Diagnostic::new("", format!("line {}: {}", line, e.message()))
+    })?;
+    Config::from_table(&table, "")
+}
+
+/// Parses `text`, the config at `path`, with its includes layered underneath, in order.
+/// `chain` holds the files currently being read to detect cycles; `texts` collects the
+/// contents of every included file.
+fn read_layers(
+    path: &Path,
+    text: &str,
+    chain: &mut Vec<PathBuf>,
+    texts: &mut Vec<String>,
+) -> Result<Config, String> {
+    let canonical = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    if let Some(start) = chain.iter().position(|file| *file == canonical) {
+        let cycle: Vec<String> = chain[start..]
+            .iter()
+            .chain([&canonical])
+            .map(|file| file.display().to_string())
+            .collect();
+        return Err(format!("Include cycle: {}", cycle.join(" -> ")));
+    }
+
+    let parsed = if chain.is_empty() {
+        Config::parse(text)
+    } else {
+        parse_include(text)
+    };
+    let mut config = parsed.map_err(|e| format!("{}: {}", path.display(), e))?;
+
+    chain.push(canonical);
+    let mut layered = Config::default();
+    for include in std::mem::take(&mut config.include) {
+        let include = include_path(path, &include);
+        let text = read_text(&include)?;
+        let included = read_layers(&include, &text, chain, texts)?;
+        texts.push(text);
+        layered.merge(included);
+    }
+    chain.pop();
+
+    layered.merge(config);
+    Ok(layered)
+}
+
+/// Reads the config at `path`, applying the files it includes first so its own keys win.
+pub fn read(path: &Path) -> Result<Config, String> {
+    read_layers(path, &read_text(path)?, &mut Vec::new(), &mut Vec::new())
+}
+
+const UNTRUSTED_FILE: &str = "untrusted";
+
+/// Allow-list of project configs, one `<fingerprint> <path>` entry per line. The fingerprint
+/// is that of the project file followed by that of the files it includes, so the includes
+/// are only read once the project file itself is known.
+fn trust_file() -> Option<PathBuf> {
+    Some(config_dir()?.join("trusted"))
+}
+
+/// Length of a trust entry's fingerprint: two SHA-256 hashes in hex.
+const TRUST_HASH_LEN: usize = 128;
+/// Length of an untrusted notice entry's fingerprint: the SHA-256 of the project file alone.
+const NOTICE_HASH_LEN: usize = 64;
+
+/// `<fingerprint> <path>` lines of `file` whose fingerprint is `hash_len` hex digits. Entries
+/// from earlier fingerprint schemes are dropped, so those configs have to be trusted again.
+fn trust_entries(file: &Path, hash_len: usize) -> Vec<(String, PathBuf)> {
+    let Ok(text) = std::fs::read_to_string(file) else {
+        return Vec::new();
+    };
+    text.lines()
+        .filter_map(|line| {
+            let (hash, path) = line.split_once(' ')?;
+            let valid = hash.len() == hash_len && hash.bytes().all(|byte| byte.is_ascii_hexdigit());
+            valid.then(|| (hash.to_string(), PathBuf::from(path)))
+        })
+        .collect()
+}
+
+/// Adds the current contents of the project config at `path` to the allow-list, replacing
+/// any earlier entry for it. Returns the canonical path that was trusted.
+pub fn trust(path: &Path) -> Result<PathBuf, String> {
+    let path = path
+        .canonicalize()
+        .map_err(|e| format!("{}: {}", path.display(), e))?;
+    let text = read_text(&path)?;
+    let mut texts = Vec::new();
+    read_layers(&path, &text, &mut Vec::new(), &mut texts)?;
+    let trust_file = trust_file().ok_or("Cannot locate the prmt config directory")?;
+
+    let mut entries = trust_entries(&trust_file, TRUST_HASH_LEN);
+    entries.retain(|(_, trusted)| *trusted != path);
+    let hash = fingerprint(std::slice::from_ref(&text)) + &fingerprint(&texts);
+    entries.push((hash, path.clone()));
+    let mut contents = String::new();
+    for (hash, trusted) in &entries {
+        let _ = writeln!(contents, "{} {}", hash, trusted.display());
+    }
+
+    if let Some(dir) = trust_file.parent() {
+        std::fs::create_dir_all(dir).map_err(|e| format!("{}: {}", dir.display(), e))?;
+    }
+    std::fs::write(&trust_file, contents)
+        .map_err(|e| format!("{}: {}", trust_file.display(), e))?;
+    Ok(path)
+}
+
+/// Reads the project config at `path`, or `None` when its current contents have not been
+/// trusted with `prmt trust`. Project files come with cloned repositories, so they are never
+/// applied unreviewed: an untrusted file is not parsed and its includes are not followed,
+/// and a broken file must not take the prompt down.
+pub fn load_project(path: &Path) -> Option<Config> {
+    let canonical = path.canonicalize().ok()?;
+    let text = read_text(&canonical).ok()?;
+    let own_hash = fingerprint(std::slice::from_ref(&text));
+    let entries = trust_entries(&trust_file()?, TRUST_HASH_LEN);
+    let includes_hash = entries.iter().find_map(|(hash, trusted)| {
+        (*trusted == canonical)
+            .then(|| hash.strip_prefix(own_hash.as_str()))
+            .flatten()
+    })?;
+    let mut texts = Vec::new();
+    let config = read_layers(path, &text, &mut Vec::new(), &mut texts).ok()?;
+    (fingerprint(&texts) == includes_hash).then_some(config)
+}
+
+/// Whether to report the untrusted project config at `path`: only the first time each path
+/// and contents are seen, so the warning is not repeated on every prompt. Seen configs are
+/// remembered in `untrusted` in the cache directory.
+pub fn first_untrusted_notice(path: &Path) -> bool {
+    let canonical = path.canonicalize().unwrap_or_else(|_| path.to_path_buf());
+    // Only the file itself counts, as its includes are not read before it is trusted. One
+    // that cannot be read is remembered as empty.
+    let text = read_text(&canonical).unwrap_or_default();
+    let Some(seen_file) = cache::cache_dir().map(|dir| dir.join(UNTRUSTED_FILE)) else {
+        return true;
+    };
+    let hash = fingerprint(&[text]);
+    let mut entries = trust_entries(&seen_file, NOTICE_HASH_LEN);
+    if entries
+        .iter()
+        .any(|(seen_hash, seen)| *seen_hash == hash && *seen == canonical)
+    {
+        return false;
+    }
+
+    entries.retain(|(_, seen)| *seen != canonical);
+    entries.push((hash, canonical));
+    let mut contents = String::new();
+    for (hash, seen) in &entries {
+        let _ = writeln!(contents, "{} {}", hash, seen.display());
+    }
+    if let Some(dir) = seen_file.parent() {
+        let _ = std::fs::create_dir_all(dir);
+    }
+    let _ = std::fs::write(&seen_file, contents);
+    true
+}
+
+/// Theme file selected by a `--theme` value: a path ending in `.toml`, or the name of a file in
+/// the `themes` directory next to the config that does not shadow a built-in theme.
+pub fn theme_path(value: &str) -> Option<PathBuf> {
+    let value = value.trim();
+    if value.ends_with(".toml") {
+        return Some(PathBuf::from(value));
+    }
+    let plain_name = !value.is_empty()
+        && value
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if !plain_name || Theme::builtin_names().any(|builtin| builtin == value) {
+        return None;
+    }
+    let path = config_dir()?.join("themes").join(format!("{}.toml", value));
+    path.is_file().then_some(path)
+}
+
+/// WASM plugin named by a placeholder: `<name>.wasm` in the `plugins` directory next to the
+/// config.
+#[cfg(feature = "wasm-plugins")]
+pub fn plugin_path(name: &str) -> Option<PathBuf> {
+    let plain_name = !name.is_empty()
+        && name
+            .bytes()
+            .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-');
+    if !plain_name {
+        return None;
+    }
+    let path = config_dir()?.join("plugins").join(format!("{}.wasm", name));
+    path.is_file().then_some(path)
+}
+
+/// Parses a theme file: a config limited to `theme`, `icons`, `[styles]` and `[palette]`, so
+/// a shared theme can only change colors and icons.
+pub fn parse_theme(text: &str) -> Result<Config, Diagnostic> {
+    let table: toml::Table =
+        toml::from_str(text).map_err(|e| Diagnostic::new("", e.to_string()))?;
+    if let Some(key) = table.keys().find(|key| !THEME_KEYS.contains(&key.as_str())) {
+        return Err(Diagnostic::new(
+            key,
+            format!(
+                "not supported in theme files (expected one of {})",
+                THEME_KEYS.join(", ")
+            ),
+        ));
+    }
+    Config::from_table(&table, "")
+}
+
+/// Reads the theme file at `path`, see [`parse_theme`].
+pub fn load_theme(path: &Path) -> Result<Config, String> {
+    let text = std::fs::read_to_string(path).map_err(|e| format!("{}: {}", path.display(), e))?;
+    parse_theme(&text).map_err(|e| format!("{}: {}", path.display(), e))
+}
+
+/// Writes `text` as a TOML basic string.
+pub fn toml_string(text: &str) -> String {
+    let mut quoted = String::with_capacity(text.len() + 2);
+    quoted.push('"');
+    for ch in text.chars() {
+        match ch {
+            '"' => quoted.push_str("\\\""),
+            '\\' => quoted.push_str("\\\\"),
+            '\n' => quoted.push_str("\\n"),
+            '\t' => quoted.push_str("\\t"),
+            ch if ch.is_control() => {
+                let _ = write!(quoted, "\\u{:04X}", ch as u32);
+            }
+            ch => quoted.push(ch),
+        }
+    }
+    quoted.push('"');
+    quoted
+}
+
+/// Reads the config at `path` and its includes; a missing file is not an error.
+pub fn load(path: &Path) -> Result<Option<Config>, String> {
+    match std::fs::metadata(path) {
+        Err(e) if e.kind() == io::ErrorKind::NotFound => Ok(None),
+        _ => read(path).map(Some),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    fn parses_all_keys() {
+        let config = Config::from_str(
+            r##"
+format = "{path:@accent} {git}"
+right_format = "{time}"
+theme = "nord"
+icons = "ascii"
+timeout = 150
+max_width = "auto"
+
+[cache]
+ttl = 600
+git = true
+
+[styles]
+git = "purple"
+
+[palette]
+accent = "#88c0d0"
+"##,
+        )
+        .expect("valid config");
+
+        assert_eq!(config.format.as_deref(), Some("{path:@accent} {git}"));
+        assert_eq!(config.right_format.as_deref(), Some("{time}"));
+        assert_eq!(config.theme.as_deref(), Some("nord"));
+        assert_eq!(config.icons, Some(IconSet::Ascii));
+        assert_eq!(config.timeout, Some(150));
+        assert_eq!(config.max_width, Some(MaxWidth::Auto));
+        assert_eq!(config.cache_ttl, Some(600));
+        assert_eq!(config.cache_git, Some(true));
+        assert_eq!(config.styles.unwrap().get("git"), Some("purple"));
+        assert!(config.palette.unwrap().get("accent").is_some());
+    }
+
+    #[test]
+    fn rejects_unknown_keys_and_bad_values() {
+        assert!(Config::from_str("colour = \"red\"").is_err());
+        assert!(Config::from_str("timeout = \"fast\"").is_err());
+        assert!(Config::from_str("[palette]\nred = \"#ff0000\"").is_err());
+        assert!(Config::from_str("format = ").is_err());
+        assert_eq!(
+            Config::from_str("[cache]\nttl = -1").unwrap_err(),
+            "cache.ttl: expected a non-negative number of seconds"
+        );
+        assert!(Config::from_str("[cache]\ndir = \"/tmp\"").is_err());
+    }
+
+    #[test]
+    fn diagnostics_name_the_key_and_expected_values() {
+        let unknown = Config::parse("colour = \"red\"").unwrap_err();
+        assert_eq!(unknown.key, "colour");
+        assert!(
+            unknown
+                .message
+                .contains("expected one of include, target, format")
+        );
+
+        let icons = Config::parse("[profiles.demo]\nicons = \"fancy\"").unwrap_err();
+        assert_eq!(icons.key, "profiles.demo.icons");
+        assert!(icons.message.contains("nerd, emoji, ascii"));
+
+        let style = Config::parse("[styles]\ngit = 3").unwrap_err();
+        assert_eq!(
+            style.to_string(),
+            "styles.git: expected a string, found integer"
+        );
+
+        let theme = parse_theme("format = \"{path}\"").unwrap_err();
+        assert_eq!(theme.key, "format");
+    }
+
+    #[test]
+    fn diagnose_checks_formats_styles_and_theme() {
+        let config = Config::parse(
+            r#"
+format = "{path} {nope}"
+theme = "solarized,primary=cyan"
+
+[styles]
+git = "purpel"
+
+[profiles.demo]
+title = "{git::major}"
+"#,
+        )
+        .unwrap();
+
+        let keys: Vec<String> = config
+            .diagnose()
+            .into_iter()
+            .map(|diagnostic| diagnostic.key)
+            .collect();
+        assert_eq!(
+            keys,
+            ["format", "theme", "styles.git", "profiles.demo.title"]
+        );
+    }
+
+    #[test]
+    fn profiles_overlay_the_top_level() {
+        let mut config = Config::from_str(
+            r#"
+format = "{path} {git} {node}"
+theme = "nord"
+
+[profiles.demo]
+format = "{path::r} "
+icons = "ascii"
+
+[profiles.minimal]
+format = "❯ "
+"#,
+        )
+        .expect("valid config");
+
+        config.select_profile("demo").unwrap();
+        assert_eq!(config.format.as_deref(), Some("{path::r} "));
+        assert_eq!(config.theme.as_deref(), Some("nord"));
+        assert_eq!(config.icons, Some(IconSet::Ascii));
+
+        let err = config.select_profile("work").unwrap_err();
+        assert_eq!(err, "Unknown profile: work (available: minimal)");
+        assert!(Config::from_str("[profiles.a.profiles.b]\nformat = \"x\"").is_err());
+        assert!(
+            Config::from_str("[profiles.a]\ncolour = \"x\"")
+                .unwrap_err()
+                .contains("profiles.a.colour")
+        );
+    }
+
+    #[test]
+    fn target_sections_apply_on_the_running_os() {
+        let config = Config::parse(&format!(
+            r#"
+format = "{{path}}"
+theme = "nord"
+
+[target.{os}]
+format = "{{path}} {{git}}"
+
+[target.unix]
+format = "unix"
+theme = "catppuccin"
+
+[target.{other}.styles]
+git = "red"
+"#,
+            os = std::env::consts::OS,
+            other = if cfg!(windows) { "linux" } else { "windows" },
+        ))
+        .expect("valid config");
+
+        assert_eq!(config.format.as_deref(), Some("{path} {git}"));
+        let theme = if cfg!(unix) { "catppuccin" } else { "nord" };
+        assert_eq!(config.theme.as_deref(), Some(theme));
+        assert!(config.styles.is_none());
+
+        let err = Config::parse("[target.amiga]\nformat = \"x\"").unwrap_err();
+        assert_eq!(err.key, "target.amiga");
+        assert!(Config::parse("[target.linux.target.macos]\nformat = \"x\"").is_err());
+    }
+
+    #[test]
+    fn merge_overlays_keys_and_extends_styles() {
+        let mut config =
+            Config::from_str("format = \"{path}\"\ntheme = \"nord\"\n[styles]\ngit = \"purple\"")
+                .unwrap();
+        config.merge(Config::from_str("format = \"{git}\"\n[styles]\npath = \"red\"").unwrap());
+
+        assert_eq!(config.format.as_deref(), Some("{git}"));
+        assert_eq!(config.theme.as_deref(), Some("nord"));
+        let styles = config.styles.unwrap();
+        assert_eq!(styles.get("git"), Some("purple"));
+        assert_eq!(styles.get("path"), Some("red"));
+    }
+
+    #[test]
+    #[serial]
+    fn project_config_requires_trust() {
+        let home = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let nested = project.path().join("src");
+        std::fs::create_dir(&nested).unwrap();
+        let path = project.path().join(PROJECT_CONFIG);
+        std::fs::write(&path, "format = \"{path:red}\"").unwrap();
+        unsafe {
+            std::env::set_var("XDG_CONFIG_HOME", home.path());
+        }
+
+        let found = find_project_config(&nested).expect("found in parent");
+        assert_eq!(load_project(&found), None);
+
+        trust(&found).unwrap();
+        let config = load_project(&found).expect("trusted");
+        assert_eq!(config.format.as_deref(), Some("{path:red}"));
+
+        std::fs::write(&path, "format = \"{env::SECRET}\"").unwrap();
+        assert_eq!(load_project(&found), None);
+
+        let base = project.path().join("base.toml");
+        std::fs::write(&base, "theme = \"nord\"").unwrap();
+        std::fs::write(&path, "include = \"base.toml\"").unwrap();
+        trust(&found).unwrap();
+        assert!(load_project(&found).is_some());
+        std::fs::write(&base, "format = \"{env::SECRET}\"").unwrap();
+        assert_eq!(load_project(&found), None);
+        std::fs::write(&base, "format = [broken").unwrap();
+        assert_eq!(load_project(&found), None);
+
+        unsafe {
+            std::env::remove_var("XDG_CONFIG_HOME");
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn untrusted_notice_is_shown_once_per_contents() {
+        let cache = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let path = project.path().join(PROJECT_CONFIG);
+        std::fs::write(&path, "format = \"{path:red}\"").unwrap();
+        unsafe {
+            std::env::set_var("XDG_CACHE_HOME", cache.path());
+        }
+
+        let first = first_untrusted_notice(&path);
+        let again = first_untrusted_notice(&path);
+        std::fs::write(&path, "format = \"{env::SECRET}\"").unwrap();
+        let edited = first_untrusted_notice(&path);
+        unsafe {
+            std::env::remove_var("XDG_CACHE_HOME");
+        }
+
+        assert!(first);
+        assert!(!again);
+        assert!(edited);
+        std::fs::write(&path, "format = [broken").unwrap();
+        assert!(load_project(&path).is_none());
+        unsafe {
+            std::env::set_var("XDG_CACHE_HOME", cache.path());
+        }
+        let broken = first_untrusted_notice(&path);
+        let broken_again = first_untrusted_notice(&path);
+        unsafe {
+            std::env::remove_var("XDG_CACHE_HOME");
+        }
+        assert!(broken);
+        assert!(!broken_again);
+        assert_eq!(fingerprint(&["a".to_string()]).len(), 64);
+        assert_ne!(
+            fingerprint(&["ab".to_string(), String::new()]),
+            fingerprint(&["a".to_string(), "b".to_string()])
+        );
+    }
+
+    #[test]
+    #[serial]
+    fn theme_files_resolve_by_name_or_path() {
+        let home = tempfile::tempdir().unwrap();
+        let themes = home.path().join("prmt").join("themes");
+        std::fs::create_dir_all(&themes).unwrap();
+        std::fs::write(
+            themes.join("ocean.toml"),
+            "theme = \"nord,accent=#88c0d0\"\nicons = \"ascii\"\n[styles]\ngit = \"@accent\"",
+        )
+        .unwrap();
+        std::fs::write(themes.join("bad.toml"), "format = \"{path}\"").unwrap();
+        unsafe {
+            std::env::set_var("XDG_CONFIG_HOME", home.path());
+        }
+
+        let path = theme_path("ocean").expect("theme file");
+        let theme = load_theme(&path).unwrap();
+        assert_eq!(theme.theme.as_deref(), Some("nord,accent=#88c0d0"));
+        assert_eq!(theme.icons, Some(IconSet::Ascii));
+        assert_eq!(theme.styles.unwrap().get("git"), Some("@accent"));
+
+        assert!(
+            load_theme(&theme_path("bad").unwrap())
+                .unwrap_err()
+                .ends_with("format: not supported in theme files (expected one of theme, icons, styles, palette)")
+        );
+        assert_eq!(theme_path("nord"), None);
+        assert_eq!(theme_path("missing"), None);
+        assert_eq!(theme_path("primary=cyan"), None);
+        assert_eq!(theme_path("~/x.toml"), Some(PathBuf::from("~/x.toml")));
+
+        unsafe {
+            std::env::remove_var("XDG_CONFIG_HOME");
+        }
+    }
+
+    #[test]
+    fn includes_are_read_with_limits_and_without_quoting_them() {
+        let dir = tempfile::tempdir().unwrap();
+        let secret = dir.path().join("secret");
+        std::fs::write(&secret, "export TOKEN=hunter2 hunter2\n").unwrap();
+        let path = dir.path().join("config.toml");
+
+        std::fs::write(
+            &path,
+            format!("include = {:?}", secret.display().to_string()),
+        )
+        .unwrap();
+        let error = read(&path).unwrap_err();
+        assert!(error.contains("line 1"), "{error}");
+        assert!(!error.contains("hunter2"), "{error}");
+
+        let large = dir.path().join("large.toml");
+        std::fs::write(&large, "#".repeat(MAX_CONFIG_LEN as usize + 1)).unwrap();
+        std::fs::write(&path, "include = \"large.toml\"").unwrap();
+        assert!(read(&path).unwrap_err().contains("larger than"));
+
+        std::fs::write(&path, "include = \"/dev/zero\"").unwrap();
+        if Path::new("/dev/zero").exists() {
+            assert!(read(&path).unwrap_err().contains("not a regular file"));
+        }
+    }
+
+    #[cfg(unix)]
+    #[test]
+    #[serial]
+    fn untrusted_project_includes_are_not_followed() {
+        let home = tempfile::tempdir().unwrap();
+        let project = tempfile::tempdir().unwrap();
+        let fifo = project.path().join("fifo.toml");
+        let fifo_path = std::ffi::CString::new(fifo.to_str().unwrap()).unwrap();
+        assert_eq!(unsafe { libc::mkfifo(fifo_path.as_ptr(), 0o600) }, 0);
+        let path = project.path().join(PROJECT_CONFIG);
+        std::fs::write(&path, "include = \"fifo.toml\"").unwrap();
+        unsafe {
+            std::env::set_var("XDG_CONFIG_HOME", home.path());
+            std::env::set_var("XDG_CACHE_HOME", home.path());
+        }
+
+        // Opening the FIFO would block until a writer shows up, so reaching the asserts at
+        // all shows it was never opened.
+        let loaded = load_project(&path);
+        let notice = first_untrusted_notice(&path);
+        let trusted = trust(&path);
+        unsafe {
+            std::env::remove_var("XDG_CONFIG_HOME");
+            std::env::remove_var("XDG_CACHE_HOME");
+        }
+
+        assert_eq!(loaded, None);
+        assert!(notice);
+        assert!(trusted.unwrap_err().contains("not a regular file"));
+    }
+
+    #[test]
+    fn includes_are_layered_under_the_including_file() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::create_dir(dir.path().join("team")).unwrap();
+        std::fs::write(
+            dir.path().join("team/base.toml"),
+            "format = \"{path}\"\ntheme = \"nord\"\n[styles]\ngit = \"purple\"",
+        )
+        .unwrap();
+        std::fs::write(dir.path().join("extra.toml"), "timeout = 50").unwrap();
+        let path = dir.path().join("config.toml");
+        std::fs::write(
+            &path,
+            "include = [\"team/base.toml\", \"extra.toml\"]\nformat = \"{git}\"\n[styles]\npath = \"red\"",
+        )
+        .unwrap();
+
+        let config = read(&path).unwrap();
+        assert_eq!(config.format.as_deref(), Some("{git}"));
+        assert_eq!(config.theme.as_deref(), Some("nord"));
+        assert_eq!(config.timeout, Some(50));
+        assert!(config.include.is_empty());
+        let styles = config.styles.unwrap();
+        assert_eq!(styles.get("git"), Some("purple"));
+        assert_eq!(styles.get("path"), Some("red"));
+    }
+
+    #[test]
+    fn include_cycles_are_errors() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("a.toml"), "include = \"b.toml\"").unwrap();
+        std::fs::write(dir.path().join("b.toml"), "include = \"a.toml\"").unwrap();
+
+        let err = read(&dir.path().join("a.toml")).unwrap_err();
+        assert!(err.starts_with("Include cycle: "), "{}", err);
+        assert!(err.ends_with("a.toml"), "{}", err);
+        assert!(Config::parse("[profiles.x]\ninclude = \"a.toml\"").is_err());
+        assert!(read(&dir.path().join("missing.toml")).is_err());
+    }
+
+    #[test]
+    fn missing_file_is_not_an_error() {
+        let dir = tempfile::tempdir().unwrap();
+        assert_eq!(load(&dir.path().join("config.toml")), Ok(None));
+    }
+}
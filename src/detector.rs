@@ -1,6 +1,7 @@
+use crate::trace::{self, Event, trace_span};
 use std::collections::hash_map::Entry;
 use std::collections::{HashMap, HashSet};
-use std::env;
+use std::fs;
 use std::path::{Path, PathBuf};
 use std::sync::Arc;
 
@@ -35,7 +36,7 @@ pub fn detect(required: &HashSet<&'static str>) -> DetectionContext {
         return DetectionContext::empty();
     }
 
-    let Ok(current_dir) = env::current_dir() else {
+    let Some(current_dir) = crate::modules::utils::current_dir() else {
         return DetectionContext::empty();
     };
 
@@ -46,25 +47,28 @@ pub fn detect_from(required: &HashSet<&'static str>, start_dir: &Path) -> Detect
     if required.is_empty() {
         return DetectionContext::empty();
     }
+    trace_span!("detect", markers = required.len());
 
     let mut found: HashMap<&'static str, PathBuf> = HashMap::with_capacity(required.len());
     let mut current_dir = start_dir.to_path_buf();
     let mut depth = 0usize;
-    let mut candidate = PathBuf::new();
 
     loop {
-        for &marker in required {
-            match found.entry(marker) {
-                Entry::Occupied(_) => continue,
-                Entry::Vacant(slot) => {
-                    candidate.clear();
-                    candidate.push(&current_dir);
-                    candidate.push(marker);
-                    if let Ok(true) = candidate.try_exists() {
-                        slot.insert(candidate.clone());
+        // One listing per level matches every marker at once; directories that can be
+        // searched but not listed fall back to probing each marker.
+        match fs::read_dir(&current_dir) {
+            Ok(entries) => {
+                for entry in entries.flatten() {
+                    let name = entry.file_name();
+                    let Some(&marker) = name.to_str().and_then(|name| required.get(name)) else {
+                        continue;
+                    };
+                    if let Entry::Vacant(slot) = found.entry(marker) {
+                        slot.insert(current_dir.join(marker));
                     }
                 }
             }
+            Err(_) => probe_markers(required, &current_dir, &mut found),
         }
 
         if found.len() == required.len() {
@@ -81,11 +85,32 @@ pub fn detect_from(required: &HashSet<&'static str>, start_dir: &Path) -> Detect
         depth += 1;
     }
 
+    for (&marker, path) in &found {
+        trace::record(|| Event::Detected {
+            marker,
+            path: path.clone(),
+        });
+    }
     DetectionContext {
         markers: Arc::new(found),
     }
 }
 
+fn probe_markers(
+    required: &HashSet<&'static str>,
+    dir: &Path,
+    found: &mut HashMap<&'static str, PathBuf>,
+) {
+    for &marker in required {
+        if let Entry::Vacant(slot) = found.entry(marker) {
+            let candidate = dir.join(marker);
+            if let Ok(true) = candidate.try_exists() {
+                slot.insert(candidate);
+            }
+        }
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -169,6 +194,52 @@ mod tests {
         assert!(ctx.get("Cargo.toml").is_none());
     }
 
+    #[test]
+    fn nearest_marker_wins_and_names_match_exactly() {
+        let tmp = tempdir().unwrap();
+        let outer = tmp.path().join("outer");
+        let inner = outer.join("inner");
+        fs::create_dir_all(&inner).unwrap();
+        fs::write(outer.join("package.json"), b"{}").unwrap();
+        fs::write(inner.join("package.json"), b"{}").unwrap();
+        fs::write(inner.join("go.mod.bak"), b"").unwrap();
+        fs::write(outer.join("go.mod"), b"module a").unwrap();
+
+        let required: HashSet<&'static str> = ["package.json", "go.mod", "Cargo.toml"]
+            .into_iter()
+            .collect();
+        let ctx = detect_from(&required, &inner);
+
+        assert_eq!(
+            ctx.get("package.json"),
+            Some(inner.join("package.json").as_path())
+        );
+        assert_eq!(ctx.get("go.mod"), Some(outer.join("go.mod").as_path()));
+        assert!(ctx.get("Cargo.toml").is_none());
+    }
+
+    // Root can list any directory, so under root this covers the listing path instead.
+    #[cfg(unix)]
+    #[test]
+    fn unlistable_directories_are_probed() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let tmp = tempdir().unwrap();
+        let project = tmp.path().join("project");
+        fs::create_dir_all(&project).unwrap();
+        fs::write(project.join("Cargo.toml"), b"[package]").unwrap();
+        fs::set_permissions(&project, fs::Permissions::from_mode(0o311)).unwrap();
+
+        let required: HashSet<&'static str> = ["Cargo.toml"].into_iter().collect();
+        let ctx = detect_from(&required, &project);
+        fs::set_permissions(&project, fs::Permissions::from_mode(0o755)).unwrap();
+
+        assert_eq!(
+            ctx.get("Cargo.toml"),
+            Some(project.join("Cargo.toml").as_path())
+        );
+    }
+
     #[test]
     fn detect_from_uses_provided_start_directory() {
         let tmp = tempdir().unwrap();
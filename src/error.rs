@@ -2,12 +2,26 @@ use thiserror::Error;
 
 #[derive(Error, Debug)]
 pub enum PromptError {
+    #[error("Parse error at bytes {}..{}: {message}", span.start, span.end)]
+    Parse {
+        span: std::ops::Range<usize>,
+        message: String,
+    },
+
     #[error("Unknown module: {0}")]
     UnknownModule(String),
 
+    /// Kept for library callers; the executor now hides a panicking module instead.
+    #[allow(dead_code)]
     #[error("Module '{0}' panicked while rendering")]
     ModulePanic(String),
 
+    /// Only raised with the `wasm-plugins` feature, but always declared so matching on the enum
+    /// does not depend on which features are enabled.
+    #[allow(dead_code)]
+    #[error("Plugin '{module}' failed: {error}")]
+    PluginError { module: String, error: String },
+
     #[error("Style error for module '{module}': {error}")]
     StyleError { module: String, error: String },
 
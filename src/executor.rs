@@ -1,17 +1,39 @@
 use crate::detector::{DetectionContext, detect_from};
 use crate::error::{PromptError, Result};
-use crate::module_trait::{ModuleContext, ModuleRef};
-use crate::parser::{Params, Token, parse};
+use crate::hooks::RenderHooks;
+use crate::icons::segment_icon;
+use crate::module_trait::{Module, ModuleContext, ModuleRef, ProjectRoot};
+use crate::parser::{Params, Token, parse_spanned, parse_template, syntax_error};
 use crate::registry::ModuleRegistry;
-use crate::style::{AnsiStyle, ModuleStyle, Shell, global_no_color};
+use crate::style::{AnsiStyle, Color, ColorMode, ModuleStyle, Shell};
+use crate::trace::{self, trace_span};
+use crate::width;
 use std::borrow::Cow;
-use std::collections::HashSet;
+use std::collections::{HashMap, HashSet};
+use std::io::{self, Write};
 use std::panic::{self, AssertUnwindSafe};
-use std::sync::{Arc, mpsc};
+use std::path::PathBuf;
+use std::sync::{Arc, Mutex, PoisonError, mpsc};
 use std::thread;
 use std::time::{Duration, Instant};
 
 const TIMEOUT_PLACEHOLDER: &str = "...";
+const TIMEOUT_TEXT_ENV: &str = "PRMT_TIMEOUT_TEXT";
+pub(crate) const SEPARATOR_MODULE: &str = "sep";
+pub(crate) const POWERLINE_MODULE: &str = "pl";
+const DEFAULT_SEPARATOR: &str = " ";
+const POWERLINE_GLYPH: &str = "\u{e0b0}";
+const POWERLINE_THIN_GLYPH: &str = "\u{e0b1}";
+/// Longest module output or `${NAME}` value kept, in bytes.
+pub(crate) const MAX_SEGMENT_LEN: usize = 4 * 1024;
+/// Longest prompt written, in bytes. Whatever comes after is replaced by an ellipsis so a
+/// runaway format cannot hand the shell megabytes of prompt.
+const MAX_OUTPUT_LEN: usize = 64 * 1024;
+
+/// Placeholder names handled by the renderer itself rather than by a module.
+pub(crate) fn is_directive(name: &str) -> bool {
+    name == SEPARATOR_MODULE || name == POWERLINE_MODULE
+}
 
 #[inline]
 fn estimate_output_size(template_len: usize) -> usize {
@@ -42,18 +64,51 @@ fn spawn_slot_render(
     let context = context.clone();
     let done = done.clone();
 
+    #[cfg(feature = "tracing")]
+    let parent = tracing::Span::current();
     thread::spawn(move || {
-        let result =
-            match panic::catch_unwind(AssertUnwindSafe(|| module.render(&format, &context))) {
-                Ok(Ok(text)) => SlotResult::Value(text),
-                Ok(Err(error)) => SlotResult::Error(error),
-                Err(_) => SlotResult::Error(PromptError::ModulePanic(module_name)),
-            };
+        #[cfg(feature = "tracing")]
+        let _parent = parent.entered();
+        let result = match render_isolated(&module_name, &module, &format, &context) {
+            Ok(text) => SlotResult::Value(text),
+            Err(error) => SlotResult::Error(error),
+        };
 
         let _ = done.send(WorkerReply { index, result });
     });
 }
 
+/// Renders `module`, hiding its segment when it panics so one broken module cannot take the
+/// whole prompt down. The panic is reported on stderr.
+pub(crate) fn render_isolated(
+    module_name: &str,
+    module: &ModuleRef,
+    format: &str,
+    context: &ModuleContext,
+) -> Result<Option<String>> {
+    panic::catch_unwind(AssertUnwindSafe(|| module.render(format, context))).unwrap_or_else(
+        |payload| {
+            let message = payload
+                .downcast_ref::<&str>()
+                .copied()
+                .or_else(|| payload.downcast_ref::<String>().map(String::as_str))
+                .unwrap_or("unknown cause");
+            eprintln!("Warning: module '{}' panicked: {}", module_name, message);
+            Ok(None)
+        },
+    )
+}
+
+/// Text shown for a module that missed the timeout: `PRMT_TIMEOUT_TEXT` when set, where an
+/// empty value hides the segment, else `...`.
+fn timeout_text() -> Option<String> {
+    match std::env::var(TIMEOUT_TEXT_ENV) {
+        Ok(text) if text.is_empty() => None,
+        Ok(text) => Some(text),
+        Err(_) => Some(TIMEOUT_PLACEHOLDER.to_string()),
+    }
+}
+
 fn recv_reply_until(
     done_rx: &mpsc::Receiver<WorkerReply>,
     deadline: Option<Instant>,
@@ -64,7 +119,7 @@ fn recv_reply_until(
                 .checked_duration_since(Instant::now())
                 .unwrap_or_default();
             if remaining.is_zero() {
-                return None;
+                return done_rx.try_recv().ok();
             }
             done_rx.recv_timeout(remaining).ok()
         }
@@ -72,16 +127,44 @@ fn recv_reply_until(
     }
 }
 
+/// Waits for pending slots until each has a result or its deadline has passed. Results
+/// that arrive late are still used while other slots are being waited for. `on_progress`
+/// runs after every reply and every passed deadline.
 fn collect_pending(
     done_rx: &mpsc::Receiver<WorkerReply>,
     slots: &mut [Slot<'_>],
     pending_count: usize,
-    deadline: Option<Instant>,
+    on_progress: &mut dyn FnMut(&mut [Slot<'_>]) -> Result<()>,
 ) -> Result<()> {
     let mut completed = 0usize;
 
     while completed < pending_count {
-        let Some(reply) = recv_reply_until(done_rx, deadline) else {
+        let now = Instant::now();
+        let mut waiting = false;
+        let mut until: Option<Instant> = None;
+        for slot in slots.iter() {
+            if let Slot::Pending {
+                deadline,
+                result: None,
+                ..
+            } = slot
+            {
+                match deadline {
+                    None => waiting = true,
+                    Some(deadline) if *deadline > now => {
+                        waiting = true;
+                        until = Some(until.map_or(*deadline, |until| until.min(*deadline)));
+                    }
+                    Some(_) => {}
+                }
+            }
+        }
+
+        let Some(reply) = recv_reply_until(done_rx, if waiting { until } else { Some(now) }) else {
+            if waiting && until.is_some() {
+                on_progress(slots)?;
+                continue;
+            }
             break;
         };
         completed += 1;
@@ -94,17 +177,178 @@ fn collect_pending(
             }
             SlotResult::Error(error) => return Err(error),
         }
+        on_progress(slots)?;
     }
 
     Ok(())
 }
 
+/// Writes slots to the output in template order as far as they are resolved, so the start
+/// of a long prompt is handed to `on_output` while later modules are still running.
+struct OrderedOutput<'s> {
+    output: &'s mut String,
+    joiner: SegmentJoiner,
+    next: usize,
+    flushed: usize,
+    budget: OutputBudget,
+    on_output: &'s mut dyn FnMut(&str),
+}
+
+impl<'s> OrderedOutput<'s> {
+    fn new(
+        output: &'s mut String,
+        shell: Shell,
+        no_color: bool,
+        on_output: &'s mut dyn FnMut(&str),
+    ) -> Self {
+        Self {
+            flushed: output.len(),
+            budget: OutputBudget::new(output),
+            output,
+            joiner: SegmentJoiner::new(shell, no_color),
+            next: 0,
+            on_output,
+        }
+    }
+
+    /// Consumes the leading resolved slots. A pending slot blocks everything after it until
+    /// its result arrives or its deadline passes; once `finished`, nothing blocks anymore.
+    fn advance(
+        &mut self,
+        slots: &mut [Slot<'_>],
+        context: &ModuleContext,
+        no_color: bool,
+        finished: bool,
+    ) -> Result<()> {
+        let now = Instant::now();
+        while let Some(slot) = slots.get_mut(self.next) {
+            if let Slot::Pending {
+                deadline,
+                result: None,
+                ..
+            } = slot
+                && !finished
+                && deadline.is_none_or(|deadline| deadline > now)
+            {
+                break;
+            }
+            let slot = std::mem::replace(slot, Slot::Rendered(None));
+            self.next += 1;
+            self.write(slot, context, no_color)?;
+        }
+        if finished {
+            self.joiner.finish(self.output);
+        }
+        if self.output.len() > self.flushed {
+            (self.on_output)(&self.output[self.flushed..]);
+            self.flushed = self.output.len();
+        }
+        Ok(())
+    }
+
+    fn write(&mut self, slot: Slot<'_>, context: &ModuleContext, no_color: bool) -> Result<()> {
+        match slot {
+            Slot::Static(text) => {
+                self.budget
+                    .text(&mut self.joiner, self.output, &text, context.shell)
+            }
+            Slot::Separator(separator) => self.budget.separator(&mut self.joiner, separator),
+            Slot::Rendered(value) => self.budget.segment(&mut self.joiner, self.output, value),
+            Slot::Pending {
+                params,
+                deadline,
+                result,
+            } => {
+                let text = match result {
+                    Some(SlotResult::Value(text)) => text,
+                    Some(SlotResult::Error(error)) => return Err(error),
+                    None if deadline.is_some() => timeout_text(),
+                    // The worker went away without replying, which only a panic that escaped
+                    // `render_isolated` does, so the segment is hidden like any panicking one.
+                    None => {
+                        eprintln!("Warning: module '{}' panicked", params.module);
+                        None
+                    }
+                };
+                let value = style_segment(text, &params, context, no_color)?;
+                self.budget.segment(&mut self.joiner, self.output, value);
+            }
+        }
+        Ok(())
+    }
+}
+
+/// Keeps a prompt within [`MAX_OUTPUT_LEN`] bytes. The piece that goes over is cut or
+/// replaced by an ellipsis, and everything after it is dropped.
+struct OutputBudget {
+    start: usize,
+    full: bool,
+}
+
+impl OutputBudget {
+    fn new(output: &str) -> Self {
+        Self {
+            start: output.len(),
+            full: false,
+        }
+    }
+
+    fn remaining(&self, output: &str) -> usize {
+        MAX_OUTPUT_LEN.saturating_sub(output.len() - self.start)
+    }
+
+    fn text(&mut self, joiner: &mut SegmentJoiner, output: &mut String, text: &str, shell: Shell) {
+        if self.full {
+            return;
+        }
+        let text = width::truncate_to_len(text, self.remaining(output), shell);
+        self.full = matches!(text, Cow::Owned(_));
+        joiner.text(output, &text);
+    }
+
+    fn separator(&self, joiner: &mut SegmentJoiner, separator: Separator) {
+        if !self.full {
+            joiner.separator(separator);
+        }
+    }
+
+    /// A styled segment cannot be cut without breaking its escapes, so one that does not fit
+    /// is dropped whole.
+    fn segment(&mut self, joiner: &mut SegmentJoiner, output: &mut String, value: Option<Segment>) {
+        if self.full {
+            return;
+        }
+        match value {
+            Some(segment) if segment.text.len() > self.remaining(output) => {
+                self.full = true;
+                joiner.text(output, "…");
+            }
+            value => joiner.segment(output, value),
+        }
+    }
+}
+
+/// A rendered placeholder along with its background, which powerline joints need.
+pub(crate) struct Segment {
+    text: String,
+    background: Option<Color>,
+}
+
 fn style_output(
     text: Option<String>,
     params: &Params,
     context: &ModuleContext,
     no_color: bool,
 ) -> Result<Option<String>> {
+    Ok(style_segment(text, params, context, no_color)?.map(|segment| segment.text))
+}
+
+fn style_segment(
+    text: Option<String>,
+    params: &Params,
+    context: &ModuleContext,
+    no_color: bool,
+) -> Result<Option<Segment>> {
     let Some(text) = text else {
         return Ok(None);
     };
@@ -112,28 +356,278 @@ fn style_output(
     if text.is_empty() && params.prefix.is_empty() && params.suffix.is_empty() {
         return Ok(None);
     }
+    let text = width::truncate_to_len(&text, MAX_SEGMENT_LEN, Shell::None);
+    let text = truncate_text(&text, params)?;
 
     let estimated_len = params.prefix.len() + text.len() + params.suffix.len();
-    let mut segment = String::with_capacity(estimated_len);
+    let mut segment = String::with_capacity(estimated_len + 8);
 
+    if let Some(icon) = segment_icon(&params.module) {
+        segment.push_str(icon);
+        segment.push(' ');
+    }
     if !params.prefix.is_empty() {
         segment.push_str(&params.prefix);
     }
-    segment.push_str(&text);
+    segment.push_str(&context.shell.escape_text(&text));
     if !params.suffix.is_empty() {
         segment.push_str(&params.suffix);
     }
 
     if params.style.is_empty() || no_color {
-        return Ok(Some(segment));
+        return Ok(Some(Segment {
+            text: segment,
+            background: None,
+        }));
     }
 
-    let style = AnsiStyle::parse(&params.style).map_err(|error| PromptError::StyleError {
-        module: params.module.to_string(),
-        error,
-    })?;
+    let style =
+        AnsiStyle::parse_cached(&params.style).map_err(|error| PromptError::StyleError {
+            module: params.module.to_string(),
+            error,
+        })?;
     let styled = style.apply_with_shell(&segment, context.shell);
-    Ok(Some(styled))
+    Ok(Some(Segment {
+        text: styled,
+        background: style.background,
+    }))
+}
+
+pub(crate) enum Separator {
+    Text(String),
+    /// Glyph whose colors are derived from the neighboring segment backgrounds
+    Powerline(String),
+}
+
+/// Renders a `{sep:style:text}` directive, whose text defaults to a single space, or a
+/// `{pl::glyph}` powerline joint.
+pub(crate) fn render_separator(
+    params: &Params,
+    context: &ModuleContext,
+    no_color: bool,
+) -> Result<Separator> {
+    if params.module == POWERLINE_MODULE {
+        return Ok(Separator::Powerline(params.format.to_string()));
+    }
+
+    let text = if params.format.is_empty() {
+        DEFAULT_SEPARATOR
+    } else {
+        &params.format
+    };
+    Ok(Separator::Text(
+        style_output(Some(text.to_string()), params, context, no_color)?.unwrap_or_default(),
+    ))
+}
+
+/// Assembles rendered pieces so that separators only appear between two non-empty segments.
+/// Literal text acts as a boundary and drops any separator waiting for a following segment,
+/// except a powerline joint, which closes the previous segment instead.
+pub(crate) struct SegmentJoiner {
+    pending: Option<Separator>,
+    has_segment: bool,
+    background: Option<Color>,
+    shell: Shell,
+    no_color: bool,
+}
+
+impl SegmentJoiner {
+    pub(crate) fn new(shell: Shell, no_color: bool) -> Self {
+        Self {
+            pending: None,
+            has_segment: false,
+            background: None,
+            shell,
+            no_color,
+        }
+    }
+
+    pub(crate) fn text(&mut self, output: &mut String, text: &str) {
+        if text.is_empty() {
+            return;
+        }
+        if let Some(Separator::Powerline(glyph)) = self.pending.take() {
+            self.write_powerline(output, &glyph, None);
+        }
+        self.has_segment = false;
+        self.background = None;
+        output.push_str(text);
+    }
+
+    pub(crate) fn segment(&mut self, output: &mut String, value: Option<Segment>) {
+        if let Some(value) = value {
+            self.begin_segment(output, value.background);
+            output.push_str(&value.text);
+        }
+    }
+
+    /// Flushes a waiting separator before a non-empty segment is written to `output`.
+    pub(crate) fn begin_segment(&mut self, output: &mut String, background: Option<Color>) {
+        match self.pending.take() {
+            Some(Separator::Text(separator)) => output.push_str(&separator),
+            Some(Separator::Powerline(glyph)) => self.write_powerline(output, &glyph, background),
+            None => {}
+        }
+        self.has_segment = true;
+        self.background = background;
+    }
+
+    pub(crate) fn separator(&mut self, separator: Separator) {
+        if self.has_segment {
+            self.pending = Some(separator);
+            self.has_segment = false;
+        }
+    }
+
+    /// Closes a trailing powerline joint once all pieces have been written.
+    pub(crate) fn finish(&mut self, output: &mut String) {
+        if let Some(Separator::Powerline(glyph)) = self.pending.take() {
+            self.write_powerline(output, &glyph, None);
+        }
+    }
+
+    fn write_powerline(&self, output: &mut String, glyph: &str, next: Option<Color>) {
+        let same_background = self.background.is_some() && self.background == next;
+        let glyph = match glyph {
+            "" if same_background => POWERLINE_THIN_GLYPH,
+            "" => POWERLINE_GLYPH,
+            glyph => glyph,
+        };
+        if self.no_color {
+            output.push_str(glyph);
+            return;
+        }
+
+        let style = AnsiStyle {
+            color: if same_background {
+                None
+            } else {
+                self.background
+            },
+            background: next,
+            ..AnsiStyle::default()
+        };
+        output.push_str(&style.apply_with_shell(glyph, self.shell));
+    }
+}
+
+/// Fills an empty style field from the configured per-module default styles.
+pub(crate) fn apply_default_style(params: &mut Params<'_>) {
+    if params.style.is_empty()
+        && !is_directive(&params.module)
+        && let Some(style) = crate::theme::module_styles().get(&params.module)
+    {
+        params.style = Cow::Owned(style.to_string());
+    }
+}
+
+/// Picks the half of an `ok|fail` style field that matches the last exit code, so one
+/// placeholder can be styled differently after a failed command.
+pub(crate) fn apply_status_style(params: &mut Params<'_>, exit_code: Option<i32>) {
+    if let Some((ok, fail)) = params.style.split_once('|') {
+        let style = if exit_code.unwrap_or(0) == 0 {
+            ok
+        } else {
+            fail
+        };
+        params.style = Cow::Owned(style.to_string());
+    }
+}
+
+/// Per-machine default format of `module` from `PRMT_<MODULE>_FORMAT`, e.g. `PRMT_GIT_FORMAT`.
+pub(crate) fn default_format(module: &str) -> Option<String> {
+    let name = format!("PRMT_{}_FORMAT", module.to_ascii_uppercase());
+    std::env::var(name).ok().filter(|format| !format.is_empty())
+}
+
+/// Fills an empty format field from `PRMT_<MODULE>_FORMAT`.
+pub(crate) fn apply_default_format(params: &mut Params<'_>) {
+    if params.format.is_empty()
+        && !is_directive(&params.module)
+        && let Some(format) = default_format(&params.module)
+    {
+        params.format = Cow::Owned(format);
+    }
+}
+
+/// Evaluates a `when=` placeholder condition: `VAR` holds when the variable is set and
+/// non-empty, `!VAR` when it is not.
+pub(crate) fn condition_holds(condition: &str) -> bool {
+    let (negated, name) = match condition.strip_prefix('!') {
+        Some(name) => (true, name),
+        None => (false, condition),
+    };
+    let present = std::env::var_os(name).is_some_and(|value| !value.is_empty());
+    present != negated
+}
+
+fn validate_condition(params: &Params) -> Result<()> {
+    let Some(condition) = params.when.as_deref() else {
+        return Ok(());
+    };
+    let name = condition.strip_prefix('!').unwrap_or(condition);
+    if name.is_empty() || name.contains(['=', '\0']) {
+        return Err(PromptError::InvalidFormat {
+            module: params.module.to_string(),
+            format: format!("when={}", condition),
+            valid_formats: "when=VAR, when=!VAR".to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// The placeholder's own `timeout=MS` option.
+fn placeholder_timeout(params: &Params) -> Result<Option<Duration>> {
+    let Some(timeout) = params.timeout.as_deref() else {
+        return Ok(None);
+    };
+    timeout
+        .parse()
+        .map(|ms| Some(Duration::from_millis(ms)))
+        .map_err(|_| PromptError::InvalidFormat {
+            module: params.module.to_string(),
+            format: format!("timeout={}", timeout),
+            valid_formats: "timeout=MS".to_string(),
+        })
+}
+
+/// The placeholder's own `truncate=N` option, in display columns; negative keeps the end.
+fn placeholder_truncate(params: &Params) -> Result<Option<isize>> {
+    let Some(truncate) = params.truncate.as_deref() else {
+        return Ok(None);
+    };
+    truncate
+        .parse()
+        .map(Some)
+        .map_err(|_| PromptError::InvalidFormat {
+            module: params.module.to_string(),
+            format: format!("truncate={}", truncate),
+            valid_formats: "truncate=N, truncate=-N".to_string(),
+        })
+}
+
+/// Module text cut to the placeholder's `truncate=N` width. Styles a module prints itself
+/// are not counted, and the text is measured before shell escaping.
+fn truncate_text<'t>(text: &'t str, params: &Params) -> Result<Cow<'t, str>> {
+    Ok(match placeholder_truncate(params)? {
+        None => Cow::Borrowed(text),
+        Some(columns) if columns < 0 => {
+            width::truncate_start_to_width(text, columns.unsigned_abs(), Shell::None)
+        }
+        Some(columns) => width::truncate_to_width(text, columns.unsigned_abs(), Shell::None),
+    })
+}
+
+/// Value of `${NAME}` in literal text; unset variables expand to nothing and long ones are
+/// cut at [`MAX_SEGMENT_LEN`].
+pub(crate) fn lookup_var(name: &str) -> String {
+    let value = std::env::var_os(name)
+        .map(|value| value.to_string_lossy().into_owned())
+        .unwrap_or_default();
+    match width::truncate_to_len(&value, MAX_SEGMENT_LEN, Shell::None) {
+        Cow::Owned(cut) => cut,
+        Cow::Borrowed(_) => value,
+    }
 }
 
 #[allow(dead_code)]
@@ -143,12 +637,119 @@ pub fn render_template(
     context: &ModuleContext,
     no_color: bool,
 ) -> Result<String> {
-    let tokens = parse(template);
+    let parsed = parse_template(template);
+    let tokens = parsed.iter().map(Token::as_borrowed).collect();
     render_tokens(tokens, registry, context, no_color, template.len(), None)
 }
 
+/// Like [`render_template`], but hands the prompt to `on_output` piece by piece: each piece
+/// is written as soon as every segment before it has rendered, so the start of a long
+/// prompt can be shown while slow modules are still running. The pieces concatenate to the
+/// returned string.
+#[allow(dead_code)]
+pub fn render_template_streaming(
+    template: &str,
+    registry: &ModuleRegistry,
+    context: &ModuleContext,
+    no_color: bool,
+    mut on_output: impl FnMut(&str),
+) -> Result<String> {
+    let parsed = parse_template(template);
+    let mut output = String::with_capacity(estimate_output_size(template.len()));
+    render_tokens_into(
+        parsed.iter().map(Token::as_borrowed).collect(),
+        registry,
+        context,
+        no_color,
+        None,
+        &mut output,
+        &mut on_output,
+    )?;
+    Ok(output)
+}
+
+/// Like [`render_template`], but appends the prompt to `buf`, so a caller rendering many
+/// prompts can reuse one allocation. On error `buf` is left as it was.
+#[allow(dead_code)]
+pub fn render_template_into(
+    template: &str,
+    buf: &mut String,
+    registry: &ModuleRegistry,
+    context: &ModuleContext,
+    no_color: bool,
+) -> Result<()> {
+    let parsed = parse_template(template);
+    let start = buf.len();
+    buf.reserve(estimate_output_size(template.len()));
+    let result = render_tokens_into(
+        parsed.iter().map(Token::as_borrowed).collect(),
+        registry,
+        context,
+        no_color,
+        None,
+        buf,
+        &mut |_| {},
+    );
+    if result.is_err() {
+        buf.truncate(start);
+    }
+    result
+}
+
+/// Like [`render_template_streaming`], but writes each piece to `writer` as soon as it is
+/// ready instead of returning the prompt. The first write error stops the output and is
+/// returned once rendering finishes.
+#[allow(dead_code)]
+pub fn render_template_to<W: Write + ?Sized>(
+    template: &str,
+    writer: &mut W,
+    registry: &ModuleRegistry,
+    context: &ModuleContext,
+    no_color: bool,
+) -> Result<()> {
+    let mut sink = WriteSink::new(writer);
+    render_template_streaming(template, registry, context, no_color, |piece| {
+        sink.write(piece)
+    })?;
+    sink.finish()
+}
+
+/// Collects the first error of a writer fed from an `on_output` callback, which cannot fail.
+#[allow(dead_code)]
+struct WriteSink<'w, W: Write + ?Sized> {
+    writer: &'w mut W,
+    error: Option<io::Error>,
+}
+
+#[allow(dead_code)]
+impl<'w, W: Write + ?Sized> WriteSink<'w, W> {
+    fn new(writer: &'w mut W) -> Self {
+        Self {
+            writer,
+            error: None,
+        }
+    }
+
+    fn write(&mut self, piece: &str) {
+        if self.error.is_none()
+            && let Err(error) = self.writer.write_all(piece.as_bytes())
+        {
+            self.error = Some(error);
+        }
+    }
+
+    fn finish(self) -> Result<()> {
+        if let Some(error) = self.error {
+            return Err(error.into());
+        }
+        self.writer.flush()?;
+        Ok(())
+    }
+}
+
 enum PlanItem<'a> {
     Static(Cow<'a, str>),
+    Separator(Separator),
     Fast {
         params: Params<'a>,
         module: ModuleRef,
@@ -156,14 +757,17 @@ enum PlanItem<'a> {
     Blocking {
         params: Params<'a>,
         module: ModuleRef,
+        timeout: Option<Duration>,
     },
 }
 
 enum Slot<'a> {
     Static(Cow<'a, str>),
-    Rendered(Option<String>),
+    Separator(Separator),
+    Rendered(Option<Segment>),
     Pending {
-        params: Params<'a>,
+        params: Box<Params<'a>>,
+        deadline: Option<Instant>,
         result: Option<SlotResult>,
     },
 }
@@ -174,21 +778,76 @@ fn render_tokens<'a>(
     context: &ModuleContext,
     no_color: bool,
     template_len: usize,
-    timeout: Option<Duration>,
+    deadline: Option<Instant>,
 ) -> Result<String> {
+    let mut output = String::with_capacity(estimate_output_size(template_len));
+    render_tokens_into(
+        tokens,
+        registry,
+        context,
+        no_color,
+        deadline,
+        &mut output,
+        &mut |_| {},
+    )?;
+    Ok(output)
+}
+
+/// Renders `tokens` onto the end of `output`, also handing each finished stretch to
+/// `on_output` in order as soon as everything before it is done.
+fn render_tokens_into<'a>(
+    tokens: Vec<Token<'a>>,
+    registry: &ModuleRegistry,
+    context: &ModuleContext,
+    no_color: bool,
+    deadline: Option<Instant>,
+    output: &mut String,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
     let mut plan: Vec<PlanItem<'a>> = Vec::with_capacity(tokens.len());
     let mut blocking_count = 0usize;
+    let mut timed = false;
 
     for token in tokens {
         match token {
             Token::Text(text) => plan.push(PlanItem::Static(text)),
-            Token::Placeholder(params) => {
+            Token::Var(name) => {
+                let value = lookup_var(&name);
+                let value = match context.shell.escape_text(&value) {
+                    Cow::Owned(escaped) => escaped,
+                    Cow::Borrowed(_) => value,
+                };
+                plan.push(PlanItem::Static(Cow::Owned(value)));
+            }
+            Token::Placeholder(params)
+                if params
+                    .when
+                    .as_deref()
+                    .is_some_and(|when| !condition_holds(when)) => {}
+            Token::Placeholder(params) if is_directive(&params.module) => {
+                plan.push(PlanItem::Separator(render_separator(
+                    &params, context, no_color,
+                )?));
+            }
+            Token::Placeholder(mut params) => {
+                apply_default_style(&mut params);
+                apply_status_style(&mut params, context.exit_code);
+                apply_default_format(&mut params);
                 let module = registry
                     .get(&params.module)
                     .ok_or_else(|| PromptError::UnknownModule(params.module.to_string()))?;
-                if module.is_blocking() {
+                // A placeholder with its own timeout runs on a worker so it can be abandoned.
+                let timeout = placeholder_timeout(&params)?;
+                if timeout.is_some() {
+                    timed = true;
+                }
+                if module.is_blocking() || timeout.is_some() {
                     blocking_count += 1;
-                    plan.push(PlanItem::Blocking { params, module });
+                    plan.push(PlanItem::Blocking {
+                        params,
+                        module,
+                        timeout,
+                    });
                 } else {
                     plan.push(PlanItem::Fast { params, module });
                 }
@@ -196,19 +855,20 @@ fn render_tokens<'a>(
         }
     }
 
-    let use_threads = blocking_count > 1 || (blocking_count == 1 && timeout.is_some());
+    let use_threads = blocking_count > 1 || (blocking_count == 1 && (deadline.is_some() || timed));
 
     if !use_threads {
-        return render_plan_inline(plan, context, no_color, template_len);
+        return render_plan_inline(plan, context, no_color, output, on_output);
     }
 
     render_plan_parallel(
         plan,
         context,
         no_color,
-        template_len,
-        timeout,
+        deadline,
         blocking_count,
+        output,
+        on_output,
     )
 }
 
@@ -216,38 +876,50 @@ fn render_plan_inline<'a>(
     plan: Vec<PlanItem<'a>>,
     context: &ModuleContext,
     no_color: bool,
-    template_len: usize,
-) -> Result<String> {
-    let mut output = String::with_capacity(estimate_output_size(template_len));
+    output: &mut String,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
+    let mut joiner = SegmentJoiner::new(context.shell, no_color);
+    let mut budget = OutputBudget::new(output);
+    let mut flushed = output.len();
 
     for item in plan {
         match item {
-            PlanItem::Static(text) => output.push_str(&text),
-            PlanItem::Fast { params, module } | PlanItem::Blocking { params, module } => {
-                let text = module.render(&params.format, context)?;
-                if let Some(value) = style_output(text, &params, context, no_color)? {
-                    output.push_str(&value);
-                }
+            PlanItem::Static(text) => budget.text(&mut joiner, output, &text, context.shell),
+            PlanItem::Separator(separator) => budget.separator(&mut joiner, separator),
+            PlanItem::Fast { params, module } | PlanItem::Blocking { params, module, .. } => {
+                let text = render_isolated(&params.module, &module, &params.format, context)?;
+                let value = style_segment(text, &params, context, no_color)?;
+                budget.segment(&mut joiner, output, value);
             }
         }
+        if output.len() > flushed {
+            on_output(&output[flushed..]);
+            flushed = output.len();
+        }
+    }
+    joiner.finish(output);
+    if output.len() > flushed {
+        on_output(&output[flushed..]);
     }
 
-    Ok(output)
+    Ok(())
 }
 
 fn render_plan_parallel<'a>(
     plan: Vec<PlanItem<'a>>,
     context: &ModuleContext,
     no_color: bool,
-    template_len: usize,
-    timeout: Option<Duration>,
+    deadline: Option<Instant>,
     blocking_count: usize,
-) -> Result<String> {
-    let deadline = timeout.map(|timeout| Instant::now() + timeout);
+    output: &mut String,
+    on_output: &mut dyn FnMut(&str),
+) -> Result<()> {
+    let started = Instant::now();
     let (done_tx, done_rx) = mpsc::channel();
 
     for (index, item) in plan.iter().enumerate() {
-        if let PlanItem::Blocking { params, module } = item {
+        if let PlanItem::Blocking { params, module, .. } = item {
             spawn_slot_render(
                 index,
                 &params.module,
@@ -264,105 +936,644 @@ fn render_plan_parallel<'a>(
     for item in plan {
         match item {
             PlanItem::Static(text) => slots.push(Slot::Static(text)),
+            PlanItem::Separator(separator) => slots.push(Slot::Separator(separator)),
             PlanItem::Fast { params, module } => {
-                let text = module.render(&params.format, context)?;
-                let rendered = style_output(text, &params, context, no_color)?;
+                let text = render_isolated(&params.module, &module, &params.format, context)?;
+                let rendered = style_segment(text, &params, context, no_color)?;
                 slots.push(Slot::Rendered(rendered));
             }
-            PlanItem::Blocking { params, .. } => {
+            PlanItem::Blocking {
+                params, timeout, ..
+            } => {
+                let own = timeout.map(|timeout| started + timeout);
                 slots.push(Slot::Pending {
-                    params,
+                    params: Box::new(params),
+                    deadline: match (deadline, own) {
+                        (Some(deadline), Some(own)) => Some(deadline.min(own)),
+                        (deadline, own) => deadline.or(own),
+                    },
                     result: None,
                 });
             }
-        }
+        }
+    }
+
+    let mut ordered = OrderedOutput::new(output, context.shell, no_color, on_output);
+    ordered.advance(&mut slots, context, no_color, false)?;
+    collect_pending(&done_rx, &mut slots, blocking_count, &mut |slots| {
+        ordered.advance(slots, context, no_color, false)
+    })?;
+    ordered.advance(&mut slots, context, no_color, true)
+}
+
+#[allow(dead_code)]
+pub fn execute(
+    format_str: &str,
+    no_version: bool,
+    exit_code: Option<i32>,
+    no_color: bool,
+) -> Result<String> {
+    execute_with_shell(
+        format_str,
+        no_version,
+        exit_code,
+        no_color,
+        Shell::None,
+        None,
+        None,
+    )
+}
+
+pub fn execute_with_shell(
+    format_str: &str,
+    no_version: bool,
+    exit_code: Option<i32>,
+    no_color: bool,
+    shell: Shell,
+    stdin_data: Option<Arc<serde_json::Value>>,
+    timeout: Option<Duration>,
+) -> Result<String> {
+    let last_command = LastCommand {
+        exit_code,
+        ..LastCommand::default()
+    };
+    let mut outputs = execute_formats(
+        &[format_str],
+        no_version,
+        &last_command,
+        no_color,
+        shell,
+        stdin_data,
+        timeout,
+    )?;
+    Ok(outputs.pop().unwrap_or_default())
+}
+
+/// What the shell reports about the command that just finished.
+#[derive(Debug, Clone, Default)]
+pub struct LastCommand {
+    pub exit_code: Option<i32>,
+    pub duration: Option<Duration>,
+    pub name: Option<String>,
+}
+
+/// Renders several formats, such as a left and a right prompt, with one module registry
+/// and one detection pass. Outputs are returned in the order of `formats`, and `timeout` is
+/// a single budget shared by all of them.
+pub fn execute_formats(
+    formats: &[&str],
+    no_version: bool,
+    last_command: &LastCommand,
+    no_color: bool,
+    shell: Shell,
+    stdin_data: Option<Arc<serde_json::Value>>,
+    timeout: Option<Duration>,
+) -> Result<Vec<String>> {
+    let options = RenderOptions {
+        no_version,
+        color: if no_color {
+            ColorMode::Never
+        } else {
+            ColorMode::Auto
+        },
+        shell,
+        stdin_data,
+        timeout,
+        ..RenderOptions::default()
+    };
+    render_formats(formats, None, last_command, &options)
+}
+
+/// How a prompt is rendered, for entry points that take a caller-supplied registry. Settings
+/// are added over time, so outside this crate start from [`Default`] and set the fields you
+/// need.
+#[derive(Debug, Clone, Default)]
+#[non_exhaustive]
+pub struct RenderOptions {
+    pub no_version: bool,
+    pub color: ColorMode,
+    pub shell: Shell,
+    /// JSON for the `json` module, such as the status line data an editor passes on stdin.
+    pub stdin_data: Option<Arc<serde_json::Value>>,
+    pub timeout: Option<Duration>,
+    /// Directory the prompt is rendered for; the process's current directory when unset.
+    pub cwd: Option<PathBuf>,
+    /// Callbacks run around every module render.
+    pub hooks: RenderHooks,
+    /// Values handed to modules as [`ModuleContext::values`].
+    pub values: Arc<HashMap<String, String>>,
+    /// Modules whose placeholders render as hidden without running them or detecting their
+    /// markers, e.g. to mute git on a slow network filesystem.
+    pub disabled: HashSet<String>,
+}
+
+/// Renders `format_str` with the modules of `registry`, falling back to the built-in module
+/// of the same name for placeholders it does not cover. Custom modules get the same marker
+/// detection, parallel rendering, timeout, and styling as built-in ones.
+#[allow(dead_code)]
+pub fn execute_with_registry(
+    format_str: &str,
+    registry: &ModuleRegistry,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+) -> Result<String> {
+    let mut outputs = render_formats(&[format_str], Some(registry), last_command, options)?;
+    Ok(outputs.pop().unwrap_or_default())
+}
+
+/// Like [`execute_formats`], with every setting taken from `options`.
+pub fn execute_formats_with(
+    formats: &[&str],
+    last_command: &LastCommand,
+    options: &RenderOptions,
+) -> Result<Vec<String>> {
+    render_formats(formats, None, last_command, options)
+}
+
+/// Like [`execute_with_registry`], but streams the prompt to `writer` as it renders, so
+/// leading segments reach a socket or terminal while slow modules are still running.
+#[allow(dead_code)]
+pub fn execute_to<W: Write + ?Sized>(
+    format_str: &str,
+    writer: &mut W,
+    registry: &ModuleRegistry,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+) -> Result<()> {
+    trace_span!("render", formats = 1);
+    let parsed = parse_template(format_str);
+    let tokens: Vec<Token<'_>> = parsed.iter().map(Token::as_borrowed).collect();
+    let registry = build_registry(tokens.iter(), Some(registry), options)?;
+    let context = module_context(&registry, last_command, options);
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    let mut output = String::with_capacity(estimate_output_size(format_str.len()));
+    let mut sink = WriteSink::new(writer);
+    render_tokens_into(
+        tokens,
+        &registry,
+        &context,
+        options.color.no_color(),
+        deadline,
+        &mut output,
+        &mut |piece| sink.write(piece),
+    )?;
+    sink.finish()
+}
+
+fn render_formats(
+    formats: &[&str],
+    custom: Option<&ModuleRegistry>,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+) -> Result<Vec<String>> {
+    trace_span!("render", formats = formats.len());
+    let parsed: Vec<_> = formats
+        .iter()
+        .map(|format| parse_template(format))
+        .collect();
+    let token_lists: Vec<Vec<Token<'_>>> = parsed
+        .iter()
+        .map(|tokens| tokens.iter().map(Token::as_borrowed).collect())
+        .collect();
+    let registry = build_registry(token_lists.iter().flatten(), custom, options)?;
+    let context = module_context(&registry, last_command, options);
+    let resolved_no_color = options.color.no_color();
+    let deadline = options.timeout.map(|timeout| Instant::now() + timeout);
+    token_lists
+        .into_iter()
+        .zip(formats)
+        .map(|(tokens, format)| {
+            render_tokens(
+                tokens,
+                &registry,
+                &context,
+                resolved_no_color,
+                format.len(),
+                deadline,
+            )
+        })
+        .collect()
+}
+
+/// Context for rendering with `registry`, detecting the markers its modules need from the
+/// current directory.
+fn module_context(
+    registry: &ModuleRegistry,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+) -> ModuleContext {
+    let required_markers = registry.required_markers();
+    let (cwd, cwd_deleted) = match &options.cwd {
+        Some(cwd) => (Some(cwd.clone()), false),
+        None => crate::modules::utils::working_dir(),
+    };
+    let detection = if required_markers.is_empty() {
+        DetectionContext::default()
+    } else if let Some(current_dir) = cwd.as_deref() {
+        detect_from(&required_markers, current_dir)
+    } else {
+        DetectionContext::default()
+    };
+    ModuleContext {
+        no_version: options.no_version,
+        exit_code: last_command.exit_code,
+        duration: last_command.duration,
+        command: last_command.name.clone(),
+        detection,
+        shell: options.shell,
+        stdin_data: options.stdin_data.clone(),
+        cwd,
+        cwd_deleted,
+        project_root: ProjectRoot::default(),
+        values: Arc::clone(&options.values),
+    }
+}
+
+/// A piece of a prompt rendered by [`render_sequentially`].
+enum Piece<'p> {
+    Text(String),
+    Module {
+        params: &'p Params<'p>,
+        text: Option<String>,
+        elapsed: Duration,
+    },
+}
+
+/// Renders `format_str` token by token, running each module on its own so its time can be
+/// measured, and hands every piece to `visit` in template order. Directives and
+/// placeholders whose `when=` condition fails are skipped.
+fn render_sequentially(
+    format_str: &str,
+    custom: Option<&ModuleRegistry>,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+    visit: &mut dyn FnMut(Piece<'_>),
+) -> Result<()> {
+    let parsed = parse_template(format_str);
+    let registry = build_registry(parsed.iter(), custom, options)?;
+    let context = module_context(&registry, last_command, options);
+
+    for token in parsed.iter() {
+        let params = match token {
+            Token::Text(text) => {
+                visit(Piece::Text(text.to_string()));
+                continue;
+            }
+            Token::Var(name) => {
+                visit(Piece::Text(lookup_var(name)));
+                continue;
+            }
+            Token::Placeholder(params) => params,
+        };
+        if is_directive(&params.module)
+            || params
+                .when
+                .as_deref()
+                .is_some_and(|when| !condition_holds(when))
+        {
+            continue;
+        }
+        let mut params = params.as_borrowed();
+        apply_default_style(&mut params);
+        apply_status_style(&mut params, context.exit_code);
+        apply_default_format(&mut params);
+        let module = registry
+            .get(&params.module)
+            .ok_or_else(|| PromptError::UnknownModule(params.module.to_string()))?;
+        let started = Instant::now();
+        let text = render_isolated(&params.module, &module, &params.format, &context)?;
+        visit(Piece::Module {
+            params: &params,
+            text,
+            elapsed: started.elapsed(),
+        });
+    }
+
+    Ok(())
+}
+
+/// Renders each placeholder of `format_str` on its own, one after another, and returns how
+/// long every module took, in template order. Used by `--bench` for per-module breakdowns.
+pub fn time_modules(
+    format_str: &str,
+    no_version: bool,
+    last_command: &LastCommand,
+    shell: Shell,
+    stdin_data: Option<Arc<serde_json::Value>>,
+) -> Result<Vec<(String, Duration)>> {
+    let options = RenderOptions {
+        no_version,
+        shell,
+        stdin_data,
+        ..RenderOptions::default()
+    };
+    let mut timings = Vec::new();
+    render_sequentially(format_str, None, last_command, &options, &mut |piece| {
+        if let Piece::Module {
+            params, elapsed, ..
+        } = piece
+        {
+            timings.push((params.module.to_string(), elapsed));
+        }
+    })?;
+    Ok(timings)
+}
+
+/// One piece of a prompt as plain data, for status bars, editors, and tests that should not
+/// have to parse ANSI escapes.
+#[derive(Debug, Clone, PartialEq)]
+pub struct SegmentData {
+    /// Module that rendered the segment, or `None` for literal text.
+    pub module: Option<String>,
+    /// Output of the module, or the literal text, without prefix, suffix, or escapes.
+    pub text: String,
+    pub style: String,
+    pub prefix: String,
+    pub suffix: String,
+    /// How long the module took to render.
+    pub elapsed: Duration,
+}
+
+impl SegmentData {
+    pub fn to_json(&self) -> serde_json::Value {
+        serde_json::json!({
+            "module": self.module,
+            "text": self.text,
+            "style": self.style,
+            "prefix": self.prefix,
+            "suffix": self.suffix,
+            "elapsed_ms": self.elapsed.as_secs_f64() * 1000.0,
+        })
+    }
+}
+
+/// Renders `format_str` into segments instead of a styled string: one per literal text and
+/// one per module that produced output, in template order. Modules from `registry` take
+/// precedence over built-in ones. Modules run one after another so each timing is the
+/// module's own, and the timeout of `options` does not apply.
+pub fn execute_segments(
+    format_str: &str,
+    registry: &ModuleRegistry,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+) -> Result<Vec<SegmentData>> {
+    let mut segments = Vec::new();
+    render_sequentially(
+        format_str,
+        Some(registry),
+        last_command,
+        options,
+        &mut |piece| match piece {
+            Piece::Text(text) if !text.is_empty() => segments.push(SegmentData {
+                module: None,
+                text,
+                style: String::new(),
+                prefix: String::new(),
+                suffix: String::new(),
+                elapsed: Duration::ZERO,
+            }),
+            Piece::Text(_) => {}
+            Piece::Module {
+                params,
+                text: Some(text),
+                elapsed,
+            } if !text.is_empty() || !params.prefix.is_empty() || !params.suffix.is_empty() => {
+                segments.push(SegmentData {
+                    module: Some(params.module.to_string()),
+                    text,
+                    style: params.style.to_string(),
+                    prefix: params.prefix.to_string(),
+                    suffix: params.suffix.to_string(),
+                    elapsed,
+                })
+            }
+            Piece::Module { .. } => {}
+        },
+    )?;
+    Ok(segments)
+}
+
+/// How one placeholder of a template rendered, for `prmt --explain`.
+#[derive(Debug, Clone, PartialEq)]
+pub struct Explanation {
+    /// The placeholder as written in the template, e.g. `{git:purple}`.
+    pub placeholder: String,
+    pub module: String,
+    /// Format passed to the module, after `PRMT_<MODULE>_FORMAT` defaults.
+    pub format: String,
+    /// Style applied to the output, after configured defaults.
+    pub style: String,
+    /// Output of the module, or `None` when the segment was hidden.
+    pub text: Option<String>,
+    /// Why the segment was hidden, when it was.
+    pub hidden: Option<String>,
+}
+
+/// What each module returned during a render, keyed by module and format. Recording goes
+/// through an after-render hook, so [`explain`] can report on a prompt without rendering it
+/// a second time.
+#[derive(Debug, Clone, Default)]
+pub struct RenderLog {
+    results: Arc<Mutex<LoggedResults>>,
+}
+
+type LoggedResults = HashMap<(String, String), std::result::Result<Option<String>, String>>;
+
+impl RenderLog {
+    /// Records every module render that goes through `hooks`.
+    pub fn record(&self, hooks: &mut RenderHooks) {
+        let results = Arc::clone(&self.results);
+        hooks.after_render(move |event| {
+            let result = match event.result {
+                Ok(text) => Ok(text.clone()),
+                Err(error) => Err(error.to_string()),
+            };
+            results
+                .lock()
+                .unwrap_or_else(PoisonError::into_inner)
+                .insert((event.module.to_string(), event.format.to_string()), result);
+        });
     }
 
-    collect_pending(&done_rx, &mut slots, blocking_count, deadline)?;
+    fn result(
+        &self,
+        module: &str,
+        format: &str,
+    ) -> Option<std::result::Result<Option<String>, String>> {
+        self.results
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .get(&(module.to_string(), format.to_string()))
+            .cloned()
+    }
+}
 
-    let mut output = String::with_capacity(estimate_output_size(template_len));
-    for slot in slots {
-        match slot {
-            Slot::Static(text) => output.push_str(&text),
-            Slot::Rendered(Some(value)) => output.push_str(&value),
-            Slot::Rendered(None) => {}
-            Slot::Pending { params, result } => {
-                let text = match result {
-                    Some(SlotResult::Value(text)) => text,
-                    Some(SlotResult::Error(error)) => return Err(error),
-                    None => {
-                        if timeout.is_some() {
-                            Some(TIMEOUT_PLACEHOLDER.to_string())
-                        } else {
-                            return Err(PromptError::ModulePanic(params.module.to_string()));
-                        }
-                    }
-                };
-                if let Some(value) = style_output(text, &params, context, no_color)? {
-                    output.push_str(&value);
-                }
-            }
+/// Reports what each placeholder of `format_str` produced in the render `log` recorded, or
+/// why it produced nothing. Modules from `registry` take precedence over built-in ones, and
+/// a module error is reported instead of failing the whole template.
+pub fn explain(
+    format_str: &str,
+    registry: &ModuleRegistry,
+    last_command: &LastCommand,
+    options: &RenderOptions,
+    log: &RenderLog,
+) -> Result<Vec<Explanation>> {
+    let tokens = parse_spanned(format_str);
+    let registry = build_registry(
+        tokens.iter().map(|(token, _)| token),
+        Some(registry),
+        options,
+    )?;
+    let context = module_context(&registry, last_command, options);
+
+    let mut explanations = Vec::new();
+    for (token, span) in &tokens {
+        let Token::Placeholder(params) = token else {
+            continue;
+        };
+        if is_directive(&params.module) {
+            continue;
         }
+        let mut params = params.as_borrowed();
+        apply_default_style(&mut params);
+        apply_status_style(&mut params, context.exit_code);
+        apply_default_format(&mut params);
+        let module = registry
+            .get(&params.module)
+            .ok_or_else(|| PromptError::UnknownModule(params.module.to_string()))?;
+
+        let result = match params.when.as_deref() {
+            _ if options.disabled.contains(params.module.as_ref()) => Err("disabled".to_string()),
+            Some(when) if !condition_holds(when) => Err(format!("when={} does not hold", when)),
+            _ => match log.result(&params.module, &params.format) {
+                Some(Ok(Some(text)))
+                    if text.is_empty() && params.prefix.is_empty() && params.suffix.is_empty() =>
+                {
+                    Err("module printed nothing".to_string())
+                }
+                Some(Ok(Some(text))) => Ok(text),
+                Some(Ok(None)) => Err(hidden_reason(&module, &context)),
+                Some(Err(error)) => Err(format!("error: {}", error)),
+                None => Err("did not finish rendering".to_string()),
+            },
+        };
+        let (text, hidden) = match result {
+            Ok(text) => (Some(text), None),
+            Err(reason) => (None, Some(reason)),
+        };
+        explanations.push(Explanation {
+            placeholder: format_str[span.clone()].to_string(),
+            module: params.module.to_string(),
+            format: params.format.to_string(),
+            style: params.style.to_string(),
+            text,
+            hidden,
+        });
     }
+    Ok(explanations)
+}
 
-    Ok(output)
+/// Best guess at why `module` returned nothing: modules tied to project files only show
+/// when one of them is found.
+fn hidden_reason(module: &ModuleRef, context: &ModuleContext) -> String {
+    let markers = module.fs_markers();
+    if !markers.is_empty()
+        && markers
+            .iter()
+            .all(|marker| context.marker_path(marker).is_none())
+    {
+        return format!("no {} found", markers.join(" or "));
+    }
+    "module returned nothing".to_string()
 }
 
-#[allow(dead_code)]
-pub fn execute(
-    format_str: &str,
-    no_version: bool,
-    exit_code: Option<i32>,
-    no_color: bool,
-) -> Result<String> {
-    execute_with_shell(
-        format_str,
-        no_version,
-        exit_code,
-        no_color,
-        Shell::None,
+/// Whether any placeholder of `formats` renders through a blocking module, i.e. whether a
+/// prompt rendered with a zero timeout can still change once slow modules finish.
+pub fn has_blocking_modules(formats: &[&str]) -> bool {
+    let parsed: Vec<_> = formats
+        .iter()
+        .map(|format| parse_template(format))
+        .collect();
+    let token_lists: Vec<Vec<Token<'_>>> = parsed
+        .iter()
+        .map(|tokens| tokens.iter().map(Token::as_borrowed).collect())
+        .collect();
+    let Ok(registry) = build_registry(
+        token_lists.iter().flatten(),
         None,
-        None,
-    )
+        &RenderOptions::default(),
+    ) else {
+        return false;
+    };
+    token_lists.iter().flatten().any(|token| match token {
+        Token::Placeholder(params) => registry
+            .get(&params.module)
+            .is_some_and(|module| module.is_blocking()),
+        _ => false,
+    })
 }
 
-pub fn execute_with_shell(
-    format_str: &str,
-    no_version: bool,
-    exit_code: Option<i32>,
-    no_color: bool,
-    shell: Shell,
-    stdin_data: Option<Arc<serde_json::Value>>,
-    timeout: Option<Duration>,
-) -> Result<String> {
-    let tokens = parse(format_str);
-    let registry = build_registry(&tokens)?;
-    let required_markers = registry.required_markers();
-    let cwd = std::env::current_dir().ok();
-    let detection = if required_markers.is_empty() {
-        DetectionContext::default()
-    } else if let Some(current_dir) = cwd.as_deref() {
-        detect_from(&required_markers, current_dir)
-    } else {
-        DetectionContext::default()
-    };
-    let context = ModuleContext {
-        no_version,
-        exit_code,
-        detection,
-        shell,
-        stdin_data,
-        cwd,
-    };
-    let resolved_no_color = no_color || global_no_color();
-    render_tokens(
-        tokens,
-        &registry,
-        &context,
-        resolved_no_color,
-        format_str.len(),
-        timeout,
-    )
+/// Validates every placeholder of a template against the module set without rendering,
+/// collecting all problems instead of stopping at the first one.
+pub fn check(format_str: &str) -> Vec<PromptError> {
+    check_with(format_str, None)
+}
+
+/// Like [`check`], validating placeholders of modules in `custom` with those modules.
+pub(crate) fn check_with(format_str: &str, custom: Option<&ModuleRegistry>) -> Vec<PromptError> {
+    let mut errors = Vec::new();
+
+    for (token, span) in parse_spanned(format_str) {
+        let mut params = match token {
+            Token::Placeholder(params) => params,
+            Token::Text(_) => {
+                errors.extend(syntax_error(format_str, span));
+                continue;
+            }
+            Token::Var(_) => continue,
+        };
+        apply_default_style(&mut params);
+        apply_default_format(&mut params);
+
+        if let Err(error) = validate_condition(&params) {
+            errors.push(error);
+        }
+        if let Err(error) = placeholder_timeout(&params) {
+            errors.push(error);
+        }
+        if let Err(error) = placeholder_truncate(&params) {
+            errors.push(error);
+        }
+
+        if !is_directive(&params.module) {
+            let module = custom
+                .and_then(|custom| custom.get(&params.module))
+                .or_else(|| instantiate_module(&params.module));
+            let Some(module) = module else {
+                errors.push(PromptError::UnknownModule(params.module.to_string()));
+                continue;
+            };
+
+            if let Err(error) = module.validate_format(&params.format) {
+                errors.push(error);
+            }
+        }
+
+        let styles = match params.style.split_once('|') {
+            Some((ok, fail)) => vec![ok, fail],
+            None => vec![params.style.as_ref()],
+        };
+        for style in styles {
+            if let Err(error) = AnsiStyle::parse(style) {
+                errors.push(PromptError::StyleError {
+                    module: params.module.to_string(),
+                    error,
+                });
+            }
+        }
+    }
+
+    errors
 }
 
 #[cfg(test)]
@@ -396,22 +1607,36 @@ fn render_placeholder(
     let text = if timeout.is_some() {
         render_module_with_timeout(&params.module, module, &params.format, context, timeout)?
     } else {
-        module.render(&params.format, context)?
+        render_isolated(&params.module, module, &params.format, context)?
     };
     style_output(text, params, context, no_color)
 }
 
-fn build_registry(tokens: &[Token<'_>]) -> Result<ModuleRegistry> {
+/// Registry of the modules `tokens` use, taken from `custom` when it has them and from the
+/// built-in modules otherwise, each wrapped in the hooks of `options`. Modules `options`
+/// disables are replaced by one that always hides its segment.
+fn build_registry<'t, 'a: 't>(
+    tokens: impl IntoIterator<Item = &'t Token<'a>>,
+    custom: Option<&ModuleRegistry>,
+    options: &RenderOptions,
+) -> Result<ModuleRegistry> {
     let mut registry = ModuleRegistry::new();
     let mut required: HashSet<&str> = HashSet::new();
 
     for token in tokens {
         if let Token::Placeholder(params) = token {
             let name: &str = &params.module;
-            if required.insert(name) {
-                let module = instantiate_module(name)
+            if !is_directive(name) && required.insert(name) {
+                if options.disabled.contains(name) {
+                    registry.register(name.to_string(), Arc::new(DisabledModule));
+                    continue;
+                }
+                let module = custom
+                    .and_then(|custom| custom.get(name))
+                    .or_else(|| instantiate_module(name))
                     .ok_or_else(|| PromptError::UnknownModule(name.to_string()))?;
-                registry.register(name.to_string(), module);
+                let module = trace::instrument(name, module);
+                registry.register(name.to_string(), options.hooks.wrap(name, module));
             }
         }
     }
@@ -419,25 +1644,70 @@ fn build_registry(tokens: &[Token<'_>]) -> Result<ModuleRegistry> {
     Ok(registry)
 }
 
-fn instantiate_module(name: &str) -> Option<ModuleRef> {
+/// Stands in for a disabled module: accepts any format and never shows.
+struct DisabledModule;
+
+impl Module for DisabledModule {
+    fn validate_format(&self, _format: &str) -> Result<()> {
+        Ok(())
+    }
+
+    fn render(&self, _format: &str, _context: &ModuleContext) -> Result<Option<String>> {
+        Ok(None)
+    }
+}
+
+type Instantiate = fn() -> ModuleRef;
+
+/// Modules built into prmt, by placeholder name, in the order `prmt modules` lists them.
+const BUILTIN_MODULES: &[(&str, Instantiate)] = {
     use crate::modules::*;
-    Some(match name {
-        "path" => Arc::new(path::PathModule::new()),
-        "git" => Arc::new(git::GitModule::new()),
-        "env" => Arc::new(env::EnvModule::new()),
-        "ok" => Arc::new(ok::OkModule::new()),
-        "fail" => Arc::new(fail::FailModule::new()),
-        "rust" => Arc::new(rust::RustModule::new()),
-        "node" => Arc::new(node::NodeModule::new()),
-        "python" => Arc::new(python::PythonModule::new()),
-        "go" => Arc::new(go::GoModule::new()),
-        "elixir" => Arc::new(elixir::ElixirModule::new()),
-        "deno" => Arc::new(deno::DenoModule::new()),
-        "bun" => Arc::new(bun::BunModule::new()),
-        "time" => Arc::new(time::TimeModule),
-        "json" => Arc::new(json::JsonModule::new()),
-        _ => return None,
-    })
+    &[
+        ("path", || Arc::new(path::PathModule::new())),
+        ("git", || Arc::new(git::GitModule::new())),
+        ("env", || Arc::new(env::EnvModule::new())),
+        ("host", || Arc::new(host::HostModule::new())),
+        ("user", || Arc::new(user::UserModule::new())),
+        ("cloud", || Arc::new(cloud::CloudModule::new())),
+        ("guix", || Arc::new(guix::GuixModule::new())),
+        ("ok", || Arc::new(ok::OkModule::new())),
+        ("fail", || Arc::new(fail::FailModule::new())),
+        ("status", || Arc::new(status::StatusModule::new())),
+        ("rust", || Arc::new(rust::RustModule::new())),
+        ("node", || Arc::new(node::NodeModule::new())),
+        ("pm", || Arc::new(pm::PmModule::new())),
+        ("python", || Arc::new(python::PythonModule::new())),
+        ("go", || Arc::new(go::GoModule::new())),
+        ("elixir", || Arc::new(elixir::ElixirModule::new())),
+        ("deno", || Arc::new(deno::DenoModule::new())),
+        ("bun", || Arc::new(bun::BunModule::new())),
+        ("java", || Arc::new(java::JavaModule::new())),
+        ("terraform", || Arc::new(terraform::TerraformModule::new())),
+        ("time", || Arc::new(time::TimeModule)),
+        ("duration", || Arc::new(duration::DurationModule)),
+        ("command", || Arc::new(command::CommandModule)),
+        ("cmd", || Arc::new(cmd::CmdModule::new())),
+        ("json", || Arc::new(json::JsonModule::new())),
+    ]
+};
+
+/// Every built-in module with its placeholder name.
+#[allow(dead_code)]
+pub fn builtin_modules() -> impl Iterator<Item = (&'static str, ModuleRef)> {
+    BUILTIN_MODULES
+        .iter()
+        .map(|(name, instantiate)| (*name, instantiate()))
+}
+
+fn instantiate_module(name: &str) -> Option<ModuleRef> {
+    if let Some((_, instantiate)) = BUILTIN_MODULES.iter().find(|(builtin, _)| *builtin == name) {
+        return Some(instantiate());
+    }
+    #[cfg(feature = "wasm-plugins")]
+    if let Some(plugin) = crate::modules::wasm::WasmModule::find(name) {
+        return Some(Arc::new(plugin));
+    }
+    Some(Arc::new(crate::modules::plugin::PluginModule::find(name)?))
 }
 
 #[cfg(test)]
@@ -445,6 +1715,7 @@ mod tests {
     use super::*;
     use crate::error::Result;
     use crate::module_trait::Module;
+    use crate::parser::parse;
     use serial_test::serial;
 
     struct SlowModule {
@@ -533,6 +1804,9 @@ mod tests {
             format: Cow::Borrowed(""),
             prefix: Cow::Borrowed(""),
             suffix: Cow::Borrowed(""),
+            when: None,
+            timeout: None,
+            truncate: None,
         }
     }
 
@@ -617,6 +1891,9 @@ mod tests {
             format: Cow::Borrowed(""),
             prefix: Cow::Borrowed("["),
             suffix: Cow::Borrowed("]"),
+            when: None,
+            timeout: None,
+            truncate: None,
         };
         let result =
             render_placeholder(&module, &params, &ctx, true, Some(Duration::from_millis(5)))
@@ -626,11 +1903,17 @@ mod tests {
 
     #[test]
     #[serial]
-    fn panic_module_returns_error() {
+    fn panic_module_hides_its_segment() {
         let module: ModuleRef = Arc::new(PanicModule);
         let ctx = test_context();
-        let err = render_module_with_timeout("panic", &module, "", &ctx, None).unwrap_err();
-        assert!(matches!(err, PromptError::ModulePanic(name) if name == "panic"));
+        let text = render_module_with_timeout("panic", &module, "", &ctx, None).unwrap();
+        assert_eq!(text, None);
+
+        assert_eq!(render_with(&[], "a {panic} b"), "a  b");
+        assert_eq!(
+            render_with(&[("slow", "slow")], "a {panic} {slow} {slow::x} b"),
+            "a  slow slow b"
+        );
     }
 
     #[test]
@@ -650,13 +1933,16 @@ mod tests {
         spawn_slot_render(0, "slow", &slow, "", &ctx, &done_tx);
         spawn_slot_render(1, "fast", &fast, "", &ctx, &done_tx);
         drop(done_tx);
+        let deadline = Some(Instant::now() + Duration::from_millis(5));
         let mut slots = vec![
             Slot::Pending {
-                params: test_params(),
+                params: Box::new(test_params()),
+                deadline,
                 result: None,
             },
             Slot::Pending {
-                params: test_params(),
+                params: Box::new(test_params()),
+                deadline,
                 result: None,
             },
         ];
@@ -665,13 +1951,7 @@ mod tests {
             .recv_timeout(Duration::from_millis(100))
             .expect("fast module should finish before slow timeout");
 
-        collect_pending(
-            &done_rx,
-            &mut slots,
-            2,
-            Some(Instant::now() + Duration::from_millis(5)),
-        )
-        .unwrap();
+        collect_pending(&done_rx, &mut slots, 2, &mut |_| Ok(())).unwrap();
 
         match &slots[0] {
             Slot::Pending { result: None, .. } => {}
@@ -683,7 +1963,9 @@ mod tests {
                 result: Some(SlotResult::Error(error)),
                 ..
             } => panic!("unexpected error: {error}"),
-            Slot::Static(_) | Slot::Rendered(_) => panic!("expected pending slot"),
+            Slot::Static(_) | Slot::Separator(_) | Slot::Rendered(_) => {
+                panic!("expected pending slot")
+            }
         }
         match &slots[1] {
             Slot::Pending {
@@ -701,8 +1983,515 @@ mod tests {
             Slot::Pending { result: None, .. } => {
                 panic!("fast slot should have completed before timeout")
             }
-            Slot::Static(_) | Slot::Rendered(_) => panic!("expected pending slot"),
+            Slot::Static(_) | Slot::Separator(_) | Slot::Rendered(_) => {
+                panic!("expected pending slot")
+            }
+        }
+    }
+
+    /// A registry of modules that render the given values, plus `none`, `err`, and `panic`,
+    /// which return nothing, fail, and panic.
+    fn test_registry(modules: &[(&str, &'static str)]) -> ModuleRegistry {
+        let mut registry = ModuleRegistry::new();
+        for &(name, value) in modules {
+            registry.register(
+                name,
+                Arc::new(SlowModule {
+                    delay: Duration::ZERO,
+                    value,
+                }),
+            );
+        }
+        registry.register("none", Arc::new(NoneModule));
+        registry.register("err", Arc::new(ErrorModule));
+        registry.register("panic", Arc::new(PanicModule));
+        registry
+    }
+
+    fn render_registry(registry: &ModuleRegistry, template: &str) -> String {
+        render_tokens(
+            parse(template),
+            registry,
+            &test_context(),
+            false,
+            template.len(),
+            None,
+        )
+        .unwrap()
+    }
+
+    /// `template` rendered in color over a [`test_registry`] of `modules`.
+    fn render_with(modules: &[(&str, &'static str)], template: &str) -> String {
+        render_registry(&test_registry(modules), template)
+    }
+
+    #[test]
+    fn zsh_escapes_module_output_but_not_template_text() {
+        let context = ModuleContext {
+            shell: Shell::Zsh,
+            ..test_context()
+        };
+        let template = "%~ {a}";
+        let output = render_tokens(
+            parse(template),
+            &test_registry(&[("a", "100%")]),
+            &context,
+            true,
+            template.len(),
+            None,
+        )
+        .unwrap();
+        assert_eq!(output, "%~ 100%%");
+    }
+
+    #[test]
+    fn separators_only_join_non_empty_segments() {
+        let modules = [("a", "A"), ("b", "B")];
+        assert_eq!(render_with(&modules, "{a}{sep}{none}{sep}{b}"), "A B");
+        assert_eq!(render_with(&modules, "{none}{sep}{a}{sep}{none}"), "A");
+        assert_eq!(render_with(&modules, "[{a}{sep:: | }{b}]"), "[A | B]");
+        assert_eq!(render_with(&modules, "{a}{sep}>{sep}{b}"), "A>B");
+    }
+
+    #[test]
+    fn powerline_joints_blend_segment_backgrounds() {
+        let render = |template| render_with(&[("a", "A")], template);
+
+        assert_eq!(
+            render("{a:+red}{pl}{none:+green}{pl}{a:+blue}{pl}"),
+            "\x1b[41mA\x1b[0m\x1b[31m\x1b[44m\u{e0b0}\x1b[0m\x1b[44mA\x1b[0m\x1b[34m\u{e0b0}\x1b[0m"
+        );
+        assert_eq!(
+            render("{a:+red}{pl}{a:+red}"),
+            "\x1b[41mA\x1b[0m\x1b[41m\u{e0b1}\x1b[0m\x1b[41mA\x1b[0m"
+        );
+        assert_eq!(
+            render("{a:+red}{pl::>} $"),
+            "\x1b[41mA\x1b[0m\x1b[31m>\x1b[0m $"
+        );
+        assert_eq!(render("{none:+red}{pl}{a}"), "A");
+    }
+
+    #[test]
+    #[serial]
+    fn empty_style_uses_module_default() {
+        crate::theme::set_module_styles("a=red".parse().unwrap());
+        let outputs = [
+            render_with(&[("a", "A")], "{a}"),
+            render_with(&[("a", "A")], "{a:blue}"),
+        ];
+        crate::theme::reset_module_styles_for_tests();
+
+        assert_eq!(outputs, ["\x1b[31mA\x1b[0m", "\x1b[34mA\x1b[0m"]);
+    }
+
+    struct BlockingValueModule(&'static str);
+
+    impl Module for BlockingValueModule {
+        fn is_blocking(&self) -> bool {
+            true
+        }
+
+        fn render(&self, _format: &str, _context: &ModuleContext) -> Result<Option<String>> {
+            Ok(Some(self.0.to_string()))
+        }
+    }
+
+    #[test]
+    fn execute_formats_renders_each_format() {
+        let outputs = execute_formats(
+            &["{ok::code}", "{fail::code}"],
+            true,
+            &LastCommand {
+                exit_code: Some(1),
+                ..LastCommand::default()
+            },
+            true,
+            Shell::None,
+            None,
+            None,
+        )
+        .expect("render");
+        assert_eq!(outputs, vec!["".to_string(), "1".to_string()]);
+    }
+
+    #[test]
+    fn status_styles_follow_the_exit_code() {
+        let render = |exit_code| {
+            execute_formats(
+                &["{status:green|red:ok=✓;fail=✗ $code}"],
+                true,
+                &LastCommand {
+                    exit_code: Some(exit_code),
+                    ..LastCommand::default()
+                },
+                false,
+                Shell::None,
+                None,
+                None,
+            )
+            .expect("render")
+            .remove(0)
+        };
+        let style = |style| AnsiStyle::parse(style).unwrap();
+        assert_eq!(render(0), style("green").apply_with_shell("✓", Shell::None));
+        assert_eq!(render(2), style("red").apply_with_shell("✗ 2", Shell::None));
+
+        assert!(check("{status:green|red}").is_empty());
+        assert_eq!(check("{status:green|nope}").len(), 1);
+        assert_eq!(check("{status:green|red|blue}").len(), 1);
+    }
+
+    #[test]
+    #[serial]
+    fn module_format_env_fills_empty_format_fields() {
+        unsafe {
+            std::env::set_var("PRMT_FAIL_FORMAT", "code");
+            std::env::set_var("PRMT_ENV_FORMAT", "HOME");
+        }
+        let outputs = execute_formats(
+            &["{fail}", "{fail::x}"],
+            true,
+            &LastCommand {
+                exit_code: Some(2),
+                ..LastCommand::default()
+            },
+            true,
+            Shell::None,
+            None,
+            None,
+        );
+        let errors = check("{env}");
+        unsafe {
+            std::env::remove_var("PRMT_FAIL_FORMAT");
+            std::env::remove_var("PRMT_ENV_FORMAT");
+        }
+
+        assert_eq!(outputs.expect("render"), vec!["2", "x"]);
+        assert!(errors.is_empty(), "{:?}", errors);
+        assert_eq!(check("{env}").len(), 1);
+    }
+
+    #[test]
+    fn zero_timeout_defers_blocking_modules() {
+        let (started_tx, _started_rx) = std::sync::mpsc::sync_channel(1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let mut registry = ModuleRegistry::new();
+        registry.register(
+            "slow",
+            Arc::new(BlockingModule {
+                started: started_tx,
+                release: std::sync::Mutex::new(release_rx),
+            }),
+        );
+
+        let template = "fast {slow}";
+        let output = render_tokens(
+            parse(template),
+            &registry,
+            &test_context(),
+            true,
+            template.len(),
+            Some(Instant::now()),
+        )
+        .unwrap();
+        drop(release_tx);
+        assert_eq!(output, format!("fast {}", TIMEOUT_PLACEHOLDER));
+    }
+
+    #[test]
+    fn expired_deadline_still_collects_finished_modules() {
+        let module: ModuleRef = Arc::new(SlowModule {
+            delay: Duration::ZERO,
+            value: "fast",
+        });
+        let (done_tx, done_rx) = mpsc::channel();
+        spawn_slot_render(0, "fast", &module, "", &test_context(), &done_tx);
+        drop(done_tx);
+        thread::sleep(Duration::from_millis(50));
+
+        let reply = recv_reply_until(&done_rx, Some(Instant::now())).expect("finished reply");
+        assert!(matches!(reply.result, SlotResult::Value(Some(_))));
+    }
+
+    #[test]
+    #[serial]
+    fn placeholder_timeout_abandons_only_that_module() {
+        let (started_tx, _started_rx) = std::sync::mpsc::sync_channel(1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let mut registry = ModuleRegistry::new();
+        registry.register(
+            "slow",
+            Arc::new(BlockingModule {
+                started: started_tx,
+                release: std::sync::Mutex::new(release_rx),
+            }),
+        );
+        registry.register(
+            "late",
+            Arc::new(SlowModule {
+                delay: Duration::from_millis(30),
+                value: "late",
+            }),
+        );
+
+        let template = "{late} {slow:timeout=10}";
+        let output = render_tokens(
+            parse(template),
+            &registry,
+            &test_context(),
+            true,
+            template.len(),
+            None,
+        );
+        drop(release_tx);
+        assert_eq!(output.unwrap(), format!("late {}", TIMEOUT_PLACEHOLDER));
+
+        assert_eq!(check("{git:timeout=10}").len(), 0);
+        let errors = check("{git:timeout=soon}");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("timeout=MS"));
+    }
+
+    #[test]
+    fn truncate_option_cuts_module_text_by_display_width() {
+        let output = render_with(
+            &[("dir", "\x1b[1m~/projects/日本/path")],
+            "{dir:::[:]:truncate=8} {dir:::truncate=-8}",
+        );
+        assert_eq!(output, "[\x1b[1m~/proje…\x1b[0m] …\x1b[1m本/path");
+
+        assert_eq!(check("{path::truncate=-20}").len(), 0);
+        let errors = check("{path::truncate=wide}");
+        assert_eq!(errors.len(), 1);
+        assert!(errors[0].to_string().contains("truncate=N"));
+    }
+
+    #[test]
+    #[serial]
+    fn oversized_segments_and_prompts_are_cut() {
+        let big: &'static str = "x".repeat(1 << 20).leak();
+        let render = |template: &str| render_with(&[("big", big)], template);
+
+        let output = render("{big}");
+        assert_eq!(output.len(), MAX_SEGMENT_LEN);
+        assert!(output.ends_with('…'));
+
+        let output = render(&"{big} ".repeat(64));
+        assert!(output.len() <= MAX_OUTPUT_LEN);
+        assert!(output.ends_with("… …"));
+
+        unsafe {
+            std::env::set_var("PRMT_TEST_HUGE", "y".repeat(1 << 20));
+        }
+        let output = render("${PRMT_TEST_HUGE}");
+        let literal = render(&"z".repeat(1 << 20));
+        unsafe {
+            std::env::remove_var("PRMT_TEST_HUGE");
+        }
+        assert_eq!(output.len(), MAX_SEGMENT_LEN);
+        assert_eq!(literal.len(), MAX_OUTPUT_LEN);
+        assert!(literal.ends_with('…'));
+    }
+
+    #[test]
+    fn segments_list_text_and_visible_modules() {
+        let segments = execute_segments(
+            "> {fast:red:full:[:]}{none:blue} {sep}",
+            &test_registry(&[("fast", "fast")]),
+            &LastCommand::default(),
+            &RenderOptions::default(),
+        )
+        .unwrap();
+
+        let pieces: Vec<_> = segments
+            .iter()
+            .map(|segment| (segment.module.as_deref(), segment.text.as_str()))
+            .collect();
+        assert_eq!(pieces, [(None, "> "), (Some("fast"), "fast"), (None, " ")]);
+        let fast = &segments[1];
+        assert_eq!(
+            (
+                fast.style.as_str(),
+                fast.prefix.as_str(),
+                fast.suffix.as_str()
+            ),
+            ("red", "[", "]")
+        );
+        assert_eq!(fast.to_json()["module"], "fast");
+        assert!(segments[0].to_json()["module"].is_null());
+    }
+
+    #[test]
+    fn explain_reports_output_and_hidden_reasons() {
+        let registry = test_registry(&[("fast", "fast")]);
+        let template =
+            "> {fast:red:full} {none} {err} {fast::when=PRMT_EXPLAIN_UNSET} {sep} {panic}";
+        let log = RenderLog::default();
+        let mut options = RenderOptions::default();
+        log.record(&mut options.hooks);
+        let _ = execute_with_registry(template, &registry, &LastCommand::default(), &options);
+
+        let explanations = explain(
+            template,
+            &registry,
+            &LastCommand::default(),
+            &RenderOptions::default(),
+            &log,
+        )
+        .unwrap();
+
+        let rows: Vec<_> = explanations
+            .iter()
+            .map(|explanation| {
+                (
+                    explanation.placeholder.as_str(),
+                    explanation.text.as_deref(),
+                    explanation.hidden.as_deref(),
+                )
+            })
+            .collect();
+        assert_eq!(
+            rows,
+            [
+                ("{fast:red:full}", Some("fast"), None),
+                ("{none}", None, Some("module returned nothing")),
+                (
+                    "{err}",
+                    None,
+                    Some("error: Invalid format 'bad' for module 'err'. Valid formats: valid")
+                ),
+                (
+                    "{fast::when=PRMT_EXPLAIN_UNSET}",
+                    None,
+                    Some("when=PRMT_EXPLAIN_UNSET does not hold")
+                ),
+                ("{panic}", None, Some("did not finish rendering")),
+            ]
+        );
+        assert_eq!(
+            (
+                explanations[0].format.as_str(),
+                explanations[0].style.as_str()
+            ),
+            ("full", "red")
+        );
+    }
+
+    #[test]
+    fn disabled_modules_render_as_hidden() {
+        let mut registry = ModuleRegistry::new();
+        registry.register("err", Arc::new(ErrorModule));
+        let options = RenderOptions {
+            disabled: ["err", "nosuch"].map(String::from).into(),
+            color: ColorMode::Never,
+            ..RenderOptions::default()
+        };
+
+        let output = execute_with_registry(
+            "a{err:red::[:]}{nosuch}b",
+            &registry,
+            &LastCommand::default(),
+            &options,
+        )
+        .unwrap();
+        assert_eq!(output, "ab");
+    }
+
+    #[test]
+    fn render_into_appends_and_keeps_the_buffer_on_error() {
+        let registry = test_registry(&[("fast", "fast")]);
+        let context = test_context();
+
+        let mut buf = String::from("> ");
+        render_template_into("[{fast}]", &mut buf, &registry, &context, true).unwrap();
+        assert_eq!(buf, "> [fast]");
+        assert!(render_template_into("{fast} {err}", &mut buf, &registry, &context, true).is_err());
+        assert_eq!(buf, "> [fast]");
+    }
+
+    #[test]
+    fn streaming_writes_leading_text_before_slow_modules_finish() {
+        let (started_tx, _started_rx) = std::sync::mpsc::sync_channel(1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let mut registry = ModuleRegistry::new();
+        registry.register(
+            "slow",
+            Arc::new(BlockingModule {
+                started: started_tx,
+                release: std::sync::Mutex::new(release_rx),
+            }),
+        );
+
+        let mut chunks = Vec::new();
+        let output = render_template_streaming(
+            "start {slow:timeout=5000} end",
+            &registry,
+            &test_context(),
+            true,
+            |chunk| {
+                chunks.push(chunk.to_string());
+                let _ = release_tx.send(());
+            },
+        )
+        .unwrap();
+
+        assert_eq!(chunks, ["start ", "slow end"]);
+        assert_eq!(output, "start slow end");
+    }
+
+    #[test]
+    #[serial]
+    fn timeout_text_can_hide_late_segments() {
+        let (started_tx, _started_rx) = std::sync::mpsc::sync_channel(1);
+        let (release_tx, release_rx) = std::sync::mpsc::channel();
+        let mut registry = ModuleRegistry::new();
+        registry.register(
+            "slow",
+            Arc::new(BlockingModule {
+                started: started_tx,
+                release: std::sync::Mutex::new(release_rx),
+            }),
+        );
+
+        let mut outputs = Vec::new();
+        for text in ["…", ""] {
+            unsafe {
+                std::env::set_var(TIMEOUT_TEXT_ENV, text);
+            }
+            let template = "fast {slow:::[:]}";
+            outputs.push(render_tokens(
+                parse(template),
+                &registry,
+                &test_context(),
+                true,
+                template.len(),
+                Some(Instant::now()),
+            ));
         }
+        unsafe {
+            std::env::remove_var(TIMEOUT_TEXT_ENV);
+        }
+        drop(release_tx);
+
+        let outputs: Vec<String> = outputs.into_iter().map(Result::unwrap).collect();
+        assert_eq!(outputs, ["fast […]", "fast "]);
+    }
+
+    #[test]
+    fn has_blocking_modules_checks_every_format() {
+        assert!(!has_blocking_modules(&["{path}", "{time}"]));
+        assert!(has_blocking_modules(&["{path}", "{git}"]));
+    }
+
+    #[test]
+    fn separator_joins_parallel_segments() {
+        let mut registry = ModuleRegistry::new();
+        registry.register("a", Arc::new(BlockingValueModule("A")));
+        registry.register("b", Arc::new(BlockingValueModule("B")));
+        registry.register("none", Arc::new(NoneModule));
+
+        assert_eq!(render_registry(&registry, "{a}{sep}{none}{sep}{b}"), "A B");
     }
 
     #[test]
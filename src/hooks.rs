@@ -0,0 +1,116 @@
+//! Callbacks that embedders run around every module render, for logging, filtering, or
+//! collecting metrics.
+
+use crate::error::Result;
+use crate::module_trait::{Module, ModuleContext, ModuleRef};
+use std::fmt;
+use std::sync::Arc;
+use std::time::{Duration, Instant};
+
+/// One finished module render, as seen by an after-render hook.
+#[allow(dead_code)]
+pub struct RenderEvent<'a> {
+    pub module: &'a str,
+    pub format: &'a str,
+    pub elapsed: Duration,
+    pub result: &'a Result<Option<String>>,
+}
+
+type BeforeHook = dyn Fn(&str, &str) -> bool + Send + Sync;
+type AfterHook = dyn Fn(&RenderEvent<'_>) + Send + Sync;
+
+/// Hooks run around each module render. Blocking modules render on worker threads, so hooks
+/// may be called from several threads at once.
+#[derive(Clone, Default)]
+pub struct RenderHooks {
+    before: Vec<Arc<BeforeHook>>,
+    after: Vec<Arc<AfterHook>>,
+}
+
+impl RenderHooks {
+    /// Runs `hook` with the module name and format before each render; returning `false`
+    /// skips the module and hides its segment.
+    #[allow(dead_code)]
+    pub fn before_render(&mut self, hook: impl Fn(&str, &str) -> bool + Send + Sync + 'static) {
+        self.before.push(Arc::new(hook));
+    }
+
+    /// Runs `hook` after each render that was not skipped, with how long it took and what it
+    /// returned.
+    #[allow(dead_code)]
+    pub fn after_render(&mut self, hook: impl Fn(&RenderEvent<'_>) + Send + Sync + 'static) {
+        self.after.push(Arc::new(hook));
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.before.is_empty() && self.after.is_empty()
+    }
+
+    /// `module` with these hooks around its renders, or `module` itself when there are none.
+    pub(crate) fn wrap(&self, name: &str, module: ModuleRef) -> ModuleRef {
+        if self.is_empty() {
+            return module;
+        }
+        Arc::new(HookedModule {
+            name: name.to_string(),
+            module,
+            hooks: self.clone(),
+        })
+    }
+}
+
+impl fmt::Debug for RenderHooks {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        f.debug_struct("RenderHooks")
+            .field("before", &self.before.len())
+            .field("after", &self.after.len())
+            .finish()
+    }
+}
+
+struct HookedModule {
+    name: String,
+    module: ModuleRef,
+    hooks: RenderHooks,
+}
+
+impl Module for HookedModule {
+    fn fs_markers(&self) -> &'static [&'static str] {
+        self.module.fs_markers()
+    }
+
+    fn is_blocking(&self) -> bool {
+        self.module.is_blocking()
+    }
+
+    fn formats(&self) -> &'static str {
+        self.module.formats()
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        self.module.validate_format(format)
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        if !self
+            .hooks
+            .before
+            .iter()
+            .all(|hook| hook(&self.name, format))
+        {
+            return Ok(None);
+        }
+        let started = Instant::now();
+        let result = self.module.render(format, context);
+        let event = RenderEvent {
+            module: &self.name,
+            format,
+            elapsed: started.elapsed(),
+            result: &result,
+        };
+        for hook in &self.hooks.after {
+            hook(&event);
+        }
+        result
+    }
+}
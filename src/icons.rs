@@ -0,0 +1,121 @@
+use std::str::FromStr;
+use std::sync::atomic::{AtomicU8, Ordering};
+
+const ICONS_UNKNOWN: u8 = 0;
+const ICONS_NONE: u8 = 1;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum IconSet {
+    Nerd = 2,
+    Emoji = 3,
+    Ascii = 4,
+}
+
+impl FromStr for IconSet {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "nerd" => Ok(IconSet::Nerd),
+            "emoji" => Ok(IconSet::Emoji),
+            "ascii" => Ok(IconSet::Ascii),
+            other => Err(format!(
+                "Unknown icon set: {} (supported values: nerd, emoji, ascii)",
+                other
+            )),
+        }
+    }
+}
+
+impl IconSet {
+    /// Name accepted by `--icons`.
+    pub fn name(self) -> &'static str {
+        match self {
+            IconSet::Nerd => "nerd",
+            IconSet::Emoji => "emoji",
+            IconSet::Ascii => "ascii",
+        }
+    }
+}
+
+/// Module name followed by its Nerd Font, emoji, and ASCII icon.
+const ICONS: &[(&str, &str, &str, &str)] = &[
+    ("path", "\u{f07c}", "📁", ""),
+    ("git", "\u{e0a0}", "🌿", "git"),
+    ("rust", "\u{e7a8}", "🦀", "rs"),
+    ("node", "\u{e718}", "⬢", "node"),
+    ("pm", "\u{e71e}", "📦", "pm"),
+    ("python", "\u{e73c}", "🐍", "py"),
+    ("go", "\u{e627}", "🐹", "go"),
+    ("elixir", "\u{e62d}", "💧", "ex"),
+    ("deno", "🦕", "🦕", "deno"),
+    ("bun", "🥟", "🥟", "bun"),
+    ("java", "\u{e738}", "☕", "java"),
+    ("terraform", "\u{e69a}", "💠", "tf"),
+    ("time", "\u{f017}", "🕐", ""),
+    ("cloud", "\u{f0c2}", "☁", "cloud"),
+    ("guix", "\u{f325}", "🐃", "guix"),
+];
+
+static ICON_SET_STATE: AtomicU8 = AtomicU8::new(ICONS_UNKNOWN);
+
+/// Icon for `module` in `set`, or `None` when the module has no icon there.
+pub fn icon(module: &str, set: IconSet) -> Option<&'static str> {
+    let (_, nerd, emoji, ascii) = ICONS.iter().find(|(name, ..)| *name == module)?;
+    let icon = match set {
+        IconSet::Nerd => nerd,
+        IconSet::Emoji => emoji,
+        IconSet::Ascii => ascii,
+    };
+    (!icon.is_empty()).then_some(*icon)
+}
+
+/// The icon set prepended to module segments, read from `PRMT_ICONS` unless set explicitly.
+pub fn active_icon_set() -> Option<IconSet> {
+    match ICON_SET_STATE.load(Ordering::Relaxed) {
+        ICONS_UNKNOWN => {
+            let set = std::env::var("PRMT_ICONS")
+                .ok()
+                .and_then(|value| value.parse().ok());
+            set_icon_set(set);
+            set
+        }
+        2 => Some(IconSet::Nerd),
+        3 => Some(IconSet::Emoji),
+        4 => Some(IconSet::Ascii),
+        _ => None,
+    }
+}
+
+pub fn set_icon_set(set: Option<IconSet>) {
+    ICON_SET_STATE.store(set.map_or(ICONS_NONE, |set| set as u8), Ordering::Relaxed);
+}
+
+/// Icon to put in front of a `module` segment, if icons are enabled.
+pub(crate) fn segment_icon(module: &str) -> Option<&'static str> {
+    active_icon_set().and_then(|set| icon(module, set))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn icon_sets_cover_language_modules() {
+        assert_eq!(icon("rust", IconSet::Emoji), Some("🦀"));
+        assert_eq!(icon("rust", IconSet::Ascii), Some("rs"));
+        assert_eq!(icon("git", IconSet::Nerd), Some("\u{e0a0}"));
+    }
+
+    #[test]
+    fn missing_icons_yield_none() {
+        assert_eq!(icon("path", IconSet::Ascii), None);
+        assert_eq!(icon("json", IconSet::Nerd), None);
+    }
+
+    #[test]
+    fn icon_set_from_str() {
+        assert_eq!(IconSet::from_str("Nerd").unwrap(), IconSet::Nerd);
+        assert!(IconSet::from_str("fancy").is_err());
+    }
+}
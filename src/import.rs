@@ -0,0 +1,480 @@
+use crate::config::toml_string;
+use crate::theme::Palette;
+use std::collections::HashSet;
+use std::fmt::Write;
+use std::str::FromStr;
+use toml::{Table, Value};
+
+/// Starship modules with a prmt counterpart, as `(starship name, prmt module, default style,
+/// enabled by default)`.
+const MODULES: &[(&str, &str, &str, bool)] = &[
+    ("directory", "path", "bold cyan", true),
+    ("git_branch", "git", "bold purple", true),
+    ("git_commit", "git", "bold green", true),
+    ("git_state", "git", "bold yellow", true),
+    ("git_status", "git", "bold red", true),
+    ("git_metrics", "git", "bold green", false),
+    ("nodejs", "node", "bold green", true),
+    ("rust", "rust", "bold red", true),
+    ("python", "python", "yellow bold", true),
+    ("golang", "go", "bold cyan", true),
+    ("java", "java", "red dimmed", true),
+    ("terraform", "terraform", "bold 105", true),
+    ("elixir", "elixir", "bold purple", true),
+    ("deno", "deno", "green bold", true),
+    ("bun", "bun", "bold red", true),
+    ("cmd_duration", "duration", "bold yellow", true),
+    ("time", "time", "bold yellow", false),
+];
+
+/// Modules `$all` expands to, in starship's default order.
+const ALL: &[&str] = &[
+    "directory",
+    "git_branch",
+    "git_commit",
+    "git_state",
+    "git_metrics",
+    "git_status",
+    "bun",
+    "deno",
+    "elixir",
+    "golang",
+    "java",
+    "nodejs",
+    "python",
+    "rust",
+    "terraform",
+    "cmd_duration",
+    "line_break",
+    "time",
+    "status",
+    "character",
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum Source {
+    Starship,
+}
+
+impl FromStr for Source {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "starship" => Ok(Source::Starship),
+            other => Err(format!(
+                "Unknown import source: {} (supported values: starship)",
+                other
+            )),
+        }
+    }
+}
+
+/// Converted config text, plus notes on everything that could not be carried over.
+#[derive(Debug)]
+pub struct Import {
+    pub config: String,
+    pub warnings: Vec<String>,
+}
+
+/// Translates a `starship.toml` into a prmt config. Supported modules become placeholders
+/// with the module's style; module formats and symbols are not carried over.
+pub fn starship(text: &str) -> Result<Import, String> {
+    let table: Table = toml::from_str(text).map_err(|e| e.to_string())?;
+    let mut converter = Converter {
+        table: &table,
+        output: String::new(),
+        last_module: None,
+        warnings: Vec::new(),
+        reported: HashSet::new(),
+    };
+
+    let mut config = String::from("# Imported from starship.toml by `prmt import starship`\n");
+    let format = table
+        .get("format")
+        .and_then(Value::as_str)
+        .unwrap_or("$all");
+    let _ = writeln!(
+        config,
+        "format = {}",
+        toml_string(&converter.convert(format))
+    );
+    if let Some(right) = table.get("right_format").and_then(Value::as_str) {
+        let _ = writeln!(
+            config,
+            "right_format = {}",
+            toml_string(&converter.convert(right))
+        );
+    }
+
+    let palette = table
+        .get("palette")
+        .and_then(Value::as_str)
+        .and_then(|name| table.get("palettes")?.get(name)?.as_table());
+    if let Some(palette) = palette {
+        config.push_str("\n[palette]\n");
+        for (name, color) in palette {
+            let color = color.as_str().unwrap_or_default();
+            match Palette::default().insert(name, color) {
+                Ok(()) => {
+                    let _ = writeln!(config, "{} = {}", name, toml_string(color));
+                }
+                Err(e) => converter
+                    .warnings
+                    .push(format!("palette color {} skipped: {}", name, e)),
+            }
+        }
+    }
+
+    Ok(Import {
+        config,
+        warnings: converter.warnings,
+    })
+}
+
+struct Converter<'t> {
+    table: &'t Table,
+    output: String,
+    /// prmt module emitted last, so runs like `$git_branch$git_status` become one `{git}`.
+    last_module: Option<&'static str>,
+    warnings: Vec<String>,
+    reported: HashSet<String>,
+}
+
+impl Converter<'_> {
+    fn convert(&mut self, format: &str) -> String {
+        self.last_module = None;
+        let chars: Vec<char> = format.chars().collect();
+        self.walk(&chars, None);
+        std::mem::take(&mut self.output)
+    }
+
+    /// Walks a starship format string. `[text](style)` applies `style` to the modules in
+    /// `text`; `(...)` groups are inlined since empty prmt placeholders already disappear.
+    fn walk(&mut self, chars: &[char], group_style: Option<&str>) {
+        let mut i = 0;
+        while i < chars.len() {
+            match chars[i] {
+                '\\' if i + 1 < chars.len() => {
+                    self.text(chars[i + 1]);
+                    i += 2;
+                }
+                '$' => {
+                    let (name, end) = variable(chars, i + 1);
+                    if name.is_empty() {
+                        self.text('$');
+                    } else {
+                        self.module(&name, group_style);
+                    }
+                    i = end;
+                }
+                '[' => {
+                    let Some(close) = closing(chars, i, '[', ']') else {
+                        self.text('[');
+                        i += 1;
+                        continue;
+                    };
+                    let inner = &chars[i + 1..close];
+                    match chars.get(close + 1) {
+                        Some('(') => {
+                            let end = closing(chars, close + 1, '(', ')').unwrap_or(chars.len());
+                            let style: String =
+                                chars[close + 2..end.min(chars.len())].iter().collect();
+                            self.walk(inner, Some(&style));
+                            i = end + 1;
+                        }
+                        _ => {
+                            self.walk(inner, group_style);
+                            i = close + 1;
+                        }
+                    }
+                }
+                '(' => {
+                    let end = closing(chars, i, '(', ')').unwrap_or(chars.len());
+                    self.walk(&chars[i + 1..end], group_style);
+                    i = end + 1;
+                }
+                ch => {
+                    self.text(ch);
+                    i += 1;
+                }
+            }
+        }
+    }
+
+    fn text(&mut self, ch: char) {
+        match ch {
+            '\\' | '{' | '}' => {
+                self.output.push('\\');
+                self.output.push(ch);
+            }
+            '\n' => self.output.push_str("\\n"),
+            _ => self.output.push(ch),
+        }
+        if !ch.is_whitespace() {
+            self.last_module = None;
+        }
+    }
+
+    fn module(&mut self, name: &str, group_style: Option<&str>) {
+        let settings = self.table.get(name).and_then(Value::as_table);
+        let setting = |key: &str| settings.and_then(|table| table.get(key));
+        let style = |default: &str| {
+            let style = group_style
+                .or_else(|| setting("style").and_then(Value::as_str))
+                .unwrap_or(default);
+            convert_style(style)
+        };
+
+        match name {
+            "all" => {
+                for module in ALL {
+                    self.module(module, group_style);
+                }
+            }
+            "line_break" => {
+                self.output.push_str("\\n");
+                self.last_module = None;
+            }
+            "character" => {
+                let symbol = |key: &str, default: &str| {
+                    let symbol = setting(key).and_then(Value::as_str).unwrap_or(default);
+                    symbol_parts(symbol)
+                };
+                let (ok, ok_style) = symbol("success_symbol", "[❯](bold green)");
+                let (fail, fail_style) = symbol("error_symbol", "[❯](bold red)");
+                self.placeholder("ok", &ok_style, &ok);
+                self.placeholder("fail", &fail_style, &fail);
+            }
+            "status" => {
+                if setting("disabled").and_then(Value::as_bool) == Some(false) {
+                    let style = style("bold red");
+                    self.placeholder("fail", &style, "code");
+                }
+            }
+            _ if name.starts_with("env_var.") => {
+                let var = &name["env_var.".len()..];
+                let style = self
+                    .table
+                    .get("env_var")
+                    .and_then(|env| env.get(var)?.get("style")?.as_str())
+                    .or(group_style)
+                    .map(convert_style)
+                    .unwrap_or_default();
+                self.placeholder("env", &style, var);
+            }
+            _ => {
+                let Some(&(_, module, default_style, enabled)) =
+                    MODULES.iter().find(|(starship, ..)| *starship == name)
+                else {
+                    if self.reported.insert(name.to_string()) {
+                        self.warnings
+                            .push(format!("unsupported starship module skipped: {}", name));
+                    }
+                    return;
+                };
+                let disabled = setting("disabled")
+                    .and_then(Value::as_bool)
+                    .unwrap_or(!enabled);
+                if disabled || self.last_module == Some(module) {
+                    return;
+                }
+                let format = match module {
+                    "duration" => setting("min_time")
+                        .and_then(Value::as_integer)
+                        .map(|ms| (ms / 1000).to_string())
+                        .unwrap_or_default(),
+                    _ => String::new(),
+                };
+                let style = style(default_style);
+                self.placeholder(module, &style, &format);
+                self.last_module = Some(module);
+            }
+        }
+    }
+
+    fn placeholder(&mut self, module: &str, style: &str, format: &str) {
+        self.output.push('{');
+        self.output.push_str(module);
+        if !style.is_empty() || !format.is_empty() {
+            self.output.push(':');
+            self.output.push_str(style);
+        }
+        if !format.is_empty() {
+            self.output.push(':');
+            for ch in format.chars() {
+                if matches!(ch, '\\' | '{' | '}' | ':') {
+                    self.output.push('\\');
+                }
+                self.output.push(ch);
+            }
+        }
+        self.output.push('}');
+        self.last_module = None;
+    }
+}
+
+/// Reads `$name`, `${name}` or `$env_var.NAME` starting after the `$`; returns the name and the
+/// index after it.
+fn variable(chars: &[char], start: usize) -> (String, usize) {
+    if chars.get(start) == Some(&'{') {
+        let end = chars[start..]
+            .iter()
+            .position(|&ch| ch == '}')
+            .map_or(chars.len(), |offset| start + offset);
+        return (chars[start + 1..end].iter().collect(), end + 1);
+    }
+
+    let ident = |from: usize| {
+        chars[from..]
+            .iter()
+            .position(|ch| !(ch.is_ascii_alphanumeric() || *ch == '_'))
+            .map_or(chars.len(), |offset| from + offset)
+    };
+    let mut end = ident(start);
+    let mut name: String = chars[start..end].iter().collect();
+    if name == "env_var" && chars.get(end) == Some(&'.') {
+        let var_end = ident(end + 1);
+        name = chars[start..var_end].iter().collect();
+        end = var_end;
+    }
+    (name, end)
+}
+
+/// Index of the bracket closing the one at `open_at`, skipping escaped characters.
+fn closing(chars: &[char], open_at: usize, open: char, close: char) -> Option<usize> {
+    let mut depth = 0;
+    let mut i = open_at;
+    while i < chars.len() {
+        match chars[i] {
+            '\\' => i += 1,
+            ch if ch == open => depth += 1,
+            ch if ch == close => {
+                depth -= 1;
+                if depth == 0 {
+                    return Some(i);
+                }
+            }
+            _ => {}
+        }
+        i += 1;
+    }
+    None
+}
+
+/// Splits a `[❯](bold green)` symbol into its text and converted style.
+fn symbol_parts(symbol: &str) -> (String, String) {
+    let parsed = symbol
+        .strip_prefix('[')
+        .and_then(|rest| rest.split_once("]("))
+        .and_then(|(text, rest)| Some((text, rest.strip_suffix(')')?)));
+    match parsed {
+        Some((text, style)) => (text.to_string(), convert_style(style)),
+        None => (symbol.to_string(), String::new()),
+    }
+}
+
+/// Converts a starship style such as `bold fg:#ff8800 bg:blue` to `#ff8800+blue.bold`.
+fn convert_style(style: &str) -> String {
+    let mut fg = "";
+    let mut bg = "";
+    let mut parts = Vec::new();
+    for word in style.split_whitespace() {
+        match word {
+            "bold" | "italic" | "underline" | "strikethrough" => parts.push(word),
+            "dimmed" => parts.push("dim"),
+            "inverted" => parts.push("reverse"),
+            "blink" | "hidden" | "none" => {}
+            _ => match word.strip_prefix("bg:") {
+                Some(color) => bg = color,
+                None => fg = word.strip_prefix("fg:").unwrap_or(word),
+            },
+        }
+    }
+
+    let color = match bg {
+        "" => fg.to_string(),
+        bg => format!("{}+{}", fg, bg),
+    };
+    if !color.is_empty() {
+        parts.insert(0, &color);
+    }
+    parts.join(".")
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::config::Config;
+
+    #[test]
+    fn converts_modules_styles_and_palette() {
+        let import = starship(
+            r##"
+format = """
+[┌](bold blue) $directory$git_branch$git_status$nodejs$kubernetes
+$character"""
+right_format = "$cmd_duration$time"
+palette = "ocean"
+
+[directory]
+style = "fg:deep bg:black italic"
+
+[cmd_duration]
+min_time = 5000
+
+[time]
+disabled = false
+
+[character]
+success_symbol = "[➜](bold green)"
+
+[palettes.ocean]
+deep = "#5e81ac"
+"##,
+        )
+        .expect("valid starship config");
+
+        let config = Config::from_str(&import.config).expect("valid prmt config");
+        assert_eq!(
+            config.format.as_deref(),
+            Some(
+                "┌ {path:deep+black.italic}{git:purple.bold}{node:green.bold}\\n{ok:green.bold:➜}{fail:red.bold:❯}"
+            )
+        );
+        assert_eq!(
+            config.right_format.as_deref(),
+            Some("{duration:yellow.bold:5}{time:yellow.bold}")
+        );
+        assert!(config.palette.unwrap().get("deep").is_some());
+        assert_eq!(
+            import.warnings,
+            ["unsupported starship module skipped: kubernetes"]
+        );
+    }
+
+    #[test]
+    fn escapes_literal_text_and_expands_all() {
+        let import = starship("format = '''\\$ {x} ($env_var.USER )$all'''").unwrap();
+        let config = Config::from_str(&import.config).unwrap();
+        let format = config.format.unwrap();
+
+        assert!(format.starts_with("$ \\{x\\} {env::USER} {path:cyan.bold}{git:purple.bold}"));
+        assert!(format.ends_with("\\n{ok:green.bold:❯}{fail:red.bold:❯}"));
+        assert!(!format.contains("{time"));
+    }
+
+    #[test]
+    fn convert_style_maps_starship_words() {
+        assert_eq!(convert_style("bold purple"), "purple.bold");
+        assert_eq!(convert_style("dimmed inverted fg:208"), "208.dim.reverse");
+        assert_eq!(convert_style("bg:#1e1e2e"), "+#1e1e2e");
+        assert_eq!(convert_style("none"), "");
+    }
+
+    #[test]
+    fn source_from_str() {
+        assert_eq!(Source::from_str("Starship"), Ok(Source::Starship));
+        assert!(Source::from_str("oh-my-posh").is_err());
+    }
+}
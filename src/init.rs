@@ -0,0 +1,283 @@
+use std::str::FromStr;
+
+const PRMT: &str = "::PRMT::";
+
+const BASH_INIT: &str = r#"_prmt_now() {
+    if [[ -n "${EPOCHREALTIME:-}" ]]; then
+        _prmt_now_ms=$(( ${EPOCHREALTIME/[.,]/} / 1000 ))
+    else
+        _prmt_now_ms=$(( SECONDS * 1000 ))
+    fi
+}
+_prmt_preexec() {
+    [[ -n "${_prmt_armed:-}" && -z "${COMP_LINE:-}" ]] || return
+    _prmt_armed=
+    [[ "$BASH_COMMAND" != _prmt_precmd ]] || return
+    _prmt_now
+    _prmt_start=$_prmt_now_ms
+    _prmt_cmd=${BASH_COMMAND%% *}
+}
+_prmt_precmd() {
+    local code=$?
+    local -a args=(--shell bash-ps1 --code "$code")
+    if [[ -n "${_prmt_start:-}" ]]; then
+        _prmt_now
+        args+=(--duration "$(( _prmt_now_ms - _prmt_start ))" --cmd "$_prmt_cmd")
+        _prmt_start=
+    fi
+    local output
+    output="$(::PRMT:: "${args[@]}")"
+    PS1="${output%%$'\x1e'*}"
+    return $code
+}
+_prmt_arm() {
+    _prmt_armed=1
+}
+trap '_prmt_preexec' DEBUG
+if [[ ";${PROMPT_COMMAND:-};" != *";_prmt_precmd;"* ]]; then
+    PROMPT_COMMAND="_prmt_precmd${PROMPT_COMMAND:+;$PROMPT_COMMAND}"$'\n'"_prmt_arm"
+fi
+"#;
+
+const ZSH_INIT: &str = r#"zmodload zsh/datetime
+_prmt_apply() {
+    PROMPT="${1%%$'\x1e'*}"
+    if [[ "$1" == *$'\x1e'* ]]; then
+        RPROMPT="${1#*$'\x1e'}"
+    fi
+}
+_prmt_preexec() {
+    _prmt_start=$EPOCHREALTIME
+    _prmt_cmd=${${(z)1}[1]}
+}
+_prmt_precmd() {
+    local code=$?
+    local -a args=(--shell zsh --code "$code")
+    if [[ -n "${_prmt_start:-}" ]]; then
+        local -i duration=$(( (EPOCHREALTIME - _prmt_start) * 1000 ))
+        args+=(--duration "$duration" --cmd "$_prmt_cmd")
+        _prmt_start=
+    fi
+    if [[ -n "${_prmt_async_file:-}" ]]; then
+        command rm -f -- "$_prmt_async_file"
+        args+=(--async "$_prmt_async_file" --async-notify $$)
+    fi
+    _prmt_apply "$(::PRMT:: "${args[@]}")"
+}
+if [[ -n "${PRMT_ASYNC:-}" ]]; then
+    _prmt_async_file="${TMPDIR:-/tmp}/prmt-$$.prompt"
+    TRAPUSR1() {
+        [[ -r "$_prmt_async_file" ]] || return
+        _prmt_apply "$(<"$_prmt_async_file")"
+        command rm -f -- "$_prmt_async_file"
+        zle && zle reset-prompt
+    }
+fi
+typeset -ga precmd_functions preexec_functions
+if (( ! ${precmd_functions[(I)_prmt_precmd]} )); then
+    precmd_functions=(_prmt_precmd $precmd_functions)
+fi
+if (( ! ${preexec_functions[(I)_prmt_preexec]} )); then
+    preexec_functions+=(_prmt_preexec)
+fi
+"#;
+
+const FISH_INIT: &str = r#"function fish_prompt
+    set -l code $status
+    set -l args --shell fish --code $code
+    if test -n "$CMD_DURATION"
+        set -a args --duration $CMD_DURATION
+    end
+    if set -q history[1]
+        set -a args --cmd (string split ' ' -- $history[1])[1]
+    end
+    set -l output (::PRMT:: $args | string collect)
+    set -l parts (string split -m 1 \x1e -- $output)
+    set -g _prmt_right $parts[2]
+    printf '%s' $parts[1]
+end
+
+function fish_right_prompt
+    printf '%s' $_prmt_right
+end
+"#;
+
+const XONSH_INIT: &str = r#"_prmt_right = ['']
+
+def _prmt_prompt():
+    history = __xonsh__.history
+    args = ['--shell', 'xonsh', '--code', '0']
+    if len(history) > 0:
+        last = history[-1]
+        start, end = last.ts
+        args = ['--shell', 'xonsh', '--code', str(last.rtn),
+                '--duration', str(int((end - start) * 1000)),
+                '--cmd', (last.cmd.split() or [''])[0]]
+    left, _, right = $(::PRMT:: @(args)).partition('\x1e')
+    _prmt_right[0] = right
+    return left
+
+$PROMPT = _prmt_prompt
+$RIGHT_PROMPT = lambda: _prmt_right[0]
+"#;
+
+const POWERSHELL_INIT: &str = r#"function global:prompt {
+    $code = if ($?) { 0 } elseif ($LASTEXITCODE) { $LASTEXITCODE } else { 1 }
+    $prmtArgs = @('--shell', 'none', '--code', $code)
+    $last = Get-History -Count 1
+    if ($last -and $last.Id -ne $global:_prmt_last_id) {
+        $global:_prmt_last_id = $last.Id
+        $ms = [long]($last.EndExecutionTime - $last.StartExecutionTime).TotalMilliseconds
+        $prmtArgs += @('--duration', $ms, '--cmd', ($last.CommandLine.Trim() -split '\s+')[0])
+    }
+    ((& ::PRMT:: @prmtArgs) -join "`n").Split([char]0x1e)[0]
+}
+"#;
+
+const CLINK_INIT: &str = r#"local prmt_exe = ::PRMT::
+local prmt_cmd = ""
+local prmt_right = ""
+
+clink.onendedit(function(line)
+    prmt_cmd = line:match("^%s*(%S+)") or ""
+end)
+
+local prmt_filter = clink.promptfilter(5)
+
+function prmt_filter:filter(prompt)
+    local code = os.geterrorlevel and os.geterrorlevel() or 0
+    local command = '""' .. prmt_exe .. '" --shell clink --code ' .. code
+    if prmt_cmd ~= "" then
+        command = command .. ' --cmd "' .. prmt_cmd:gsub('"', "") .. '"'
+    end
+    local handle = io.popen(command .. '"')
+    local output = handle:read("*a") or ""
+    handle:close()
+    local left, right = output:match("^(.-)(.*)$")
+    prmt_right = right or ""
+    return left or output, false
+end
+
+function prmt_filter:rightfilter(prompt)
+    return prmt_right, false
+end
+"#;
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum InitShell {
+    Bash,
+    Zsh,
+    Fish,
+    Xonsh,
+    PowerShell,
+    Clink,
+}
+
+impl FromStr for InitShell {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "bash" => Ok(InitShell::Bash),
+            "zsh" => Ok(InitShell::Zsh),
+            "fish" => Ok(InitShell::Fish),
+            "xonsh" => Ok(InitShell::Xonsh),
+            "powershell" | "pwsh" => Ok(InitShell::PowerShell),
+            "clink" | "cmd" => Ok(InitShell::Clink),
+            other => Err(format!(
+                "Unknown shell: {} (supported values: bash, zsh, fish, xonsh, powershell, clink)",
+                other
+            )),
+        }
+    }
+}
+
+/// Quotes `path` as a single word for `shell`.
+fn quote(path: &str, shell: InitShell) -> String {
+    match shell {
+        InitShell::Bash | InitShell::Zsh => format!("'{}'", path.replace('\'', r"'\''")),
+        InitShell::Fish | InitShell::Xonsh => {
+            format!("'{}'", path.replace('\\', r"\\").replace('\'', r"\'"))
+        }
+        InitShell::PowerShell => format!("'{}'", path.replace('\'', "''")),
+        InitShell::Clink => format!("\"{}\"", path.replace('\\', r"\\").replace('"', r#"\""#)),
+    }
+}
+
+/// Integration snippet that installs prmt as the prompt of `shell`, invoking the binary at
+/// `exe`. The format comes from `PRMT_FORMAT` at prompt time; shells with a right prompt also
+/// pick up `PRMT_FORMAT_RIGHT`.
+pub fn script(shell: InitShell, exe: &str) -> String {
+    let template = match shell {
+        InitShell::Bash => BASH_INIT,
+        InitShell::Zsh => ZSH_INIT,
+        InitShell::Fish => FISH_INIT,
+        InitShell::Xonsh => XONSH_INIT,
+        InitShell::PowerShell => POWERSHELL_INIT,
+        InitShell::Clink => CLINK_INIT,
+    };
+    template.replace(PRMT, &quote(exe, shell))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn bash_script_hooks_prompt_command() {
+        let script = script(InitShell::Bash, "/usr/local/bin/prmt");
+        assert!(script.contains(r#"output="$('/usr/local/bin/prmt' "${args[@]}")""#));
+        assert!(script.contains("PROMPT_COMMAND=\"_prmt_precmd"));
+    }
+
+    #[test]
+    fn scripts_pass_duration_and_command() {
+        for shell in [
+            InitShell::Bash,
+            InitShell::Zsh,
+            InitShell::Fish,
+            InitShell::Xonsh,
+            InitShell::PowerShell,
+        ] {
+            let script = script(shell, "prmt");
+            assert!(script.contains("--duration"), "{:?}", shell);
+            assert!(script.contains("--cmd"), "{:?}", shell);
+        }
+        assert!(script(InitShell::Zsh, "prmt").contains("preexec_functions+=(_prmt_preexec)"));
+    }
+
+    #[test]
+    fn scripts_split_the_right_prompt() {
+        assert!(script(InitShell::Zsh, "prmt").contains("RPROMPT="));
+        assert!(script(InitShell::Fish, "prmt").contains("function fish_right_prompt"));
+        assert!(script(InitShell::Xonsh, "prmt").contains("$RIGHT_PROMPT"));
+        assert!(script(InitShell::Clink, "prmt").contains("function prmt_filter:rightfilter"));
+    }
+
+    #[test]
+    fn zsh_script_supports_async_updates() {
+        let script = script(InitShell::Zsh, "prmt");
+        assert!(script.contains("args+=(--async \"$_prmt_async_file\" --async-notify $$)"));
+        assert!(script.contains("TRAPUSR1()"));
+    }
+
+    #[test]
+    fn scripts_quote_the_binary_path() {
+        assert!(script(InitShell::Zsh, "/opt/it's/prmt").contains(r"'/opt/it'\''s/prmt'"));
+        assert!(script(InitShell::Fish, "/opt/it's/prmt").contains(r"'/opt/it\'s/prmt'"));
+        assert!(
+            script(InitShell::PowerShell, r"C:\it's\prmt.exe").contains(r"'C:\it''s\prmt.exe'")
+        );
+        assert!(
+            script(InitShell::Clink, r"C:\bin\prmt.exe")
+                .contains(r#"local prmt_exe = "C:\\bin\\prmt.exe""#)
+        );
+    }
+
+    #[test]
+    fn init_shell_from_str() {
+        assert_eq!(InitShell::from_str("pwsh").unwrap(), InitShell::PowerShell);
+        assert_eq!(InitShell::from_str("Fish").unwrap(), InitShell::Fish);
+        assert!(InitShell::from_str("tcsh").is_err());
+    }
+}
@@ -1,19 +1,39 @@
+pub mod cache;
+pub mod clink;
+pub mod config;
 pub mod detector;
 pub mod error;
 mod executor;
+pub mod hooks;
+pub mod icons;
 pub mod memo;
 pub mod module_trait;
 pub mod modules;
 pub mod parser;
+pub mod presets;
+pub mod prompt;
 pub mod registry;
 pub mod style;
 pub mod template;
+pub mod theme;
+pub mod tmux;
+mod trace;
+pub mod tz;
+pub mod width;
+pub mod xonsh;
 
 // Re-export main types and functions
 pub use error::{PromptError, Result};
-pub use executor::{execute, render_template};
+pub use executor::{
+    Explanation, LastCommand, RenderLog, RenderOptions, SegmentData, check, execute,
+    execute_formats, execute_formats_with, execute_segments, execute_to, execute_with_registry,
+    explain, has_blocking_modules, render_template, render_template_into,
+    render_template_streaming, render_template_to, time_modules,
+};
+pub use hooks::{RenderEvent, RenderHooks};
 pub use module_trait::{Module, ModuleContext};
-pub use parser::{Params, Token, parse};
+pub use parser::{Params, Token, parse, parse_cached, parse_spanned};
+pub use prompt::{Prompt, PromptBuilder};
 pub use registry::ModuleRegistry;
 pub use style::{AnsiStyle, ModuleStyle};
 pub use template::Template;
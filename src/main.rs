@@ -1,21 +1,36 @@
+use std::collections::{HashMap, HashSet};
 use std::env;
-#[cfg(target_os = "linux")]
 use std::fs;
-use std::io::Read;
+use std::io::{IsTerminal, Read};
+use std::path::PathBuf;
 use std::process::ExitCode;
 use std::str::FromStr;
-use std::sync::Arc;
+use std::sync::{Arc, Mutex};
 use std::time::{Duration, Instant};
 
+mod cache;
+mod clink;
+mod config;
 mod detector;
 mod error;
 mod executor;
+mod hooks;
+mod icons;
+mod import;
+mod init;
 mod memo;
 mod module_trait;
 mod modules;
 mod parser;
+mod presets;
 mod registry;
 mod style;
+mod theme;
+mod tmux;
+mod trace;
+mod tz;
+mod width;
+mod xonsh;
 
 const VERSION: &str = env!("CARGO_PKG_VERSION");
 const HELP: &str = "\
@@ -23,34 +38,193 @@ prmt - Ultra-fast customizable shell prompt generator
 
 USAGE:
     prmt [OPTIONS] [FORMAT]
+    prmt check [FORMAT]
+    prmt check --config[=FILE]
+    prmt init <SHELL>
+    prmt trust [PATH]
+    prmt import starship [PATH]
+    prmt config show [OPTIONS] [FORMAT]
+    prmt modules
 
 ARGS:
     <FORMAT>           Format string (default from PRMT_FORMAT env var)
 
+COMMANDS:
+    check              Validate modules, formats, and styles without rendering
+                       (with --config, validate the config, project, and theme files)
+    init <SHELL>       Print the prompt setup for bash, zsh, fish, xonsh, powershell, or clink
+    trust [PATH]       Allow the nearest .prmt.toml (or the one at PATH) to override the config
+    import starship [PATH]  Print a prmt config converted from starship.toml
+    config show        Print the effective settings after applying flags, env, and config files
+    modules            List the built-in modules with their formats and project markers
+                       (also --list-modules)
+
 OPTIONS:
-    -f, --format <FORMAT>    Format string
+    -f, --format <FORMAT>    Format string, or - to read it from stdin; repeat to render several
+                            prompts in one pass, printed one per line
+        --format-file <PATH>  Read the format from a file (like --format, repeatable)
+    -0, --null              Separate the prompts of repeated --format with NUL instead
+        --preset <NAME>     Built-in format: minimal, powerline, verbose, nerd-icons
     -n, --no-version        Skip version detection for speed
     -t, --timeout <MS>      Prompt timeout in ms (default 0, disabled)
-    -d, --debug             Show debug information and timing
-    -b, --bench             Run benchmark (100 iterations, ignores module timeout)
+    -d, --debug[=FMT]       Show debug information and timing, as text (default) or a json
+                            report with module times, subprocesses, cache hits, and markers
+        --warn-slow <TIME>  Warn on stderr about modules slower than TIME, e.g. 50ms or 1s
+        --explain           After the prompt, list each placeholder's module, format, style,
+                            and output, or why its segment was hidden
+    -b, --bench[=N]         Run benchmark (N iterations, default 100, ignores module timeout)
+        --bench-warmup <N>  Untimed runs before the benchmark (default 0)
+        --bench-format <FMT>  Benchmark report as text (default) or json with per-module timings
         --stdin             Read JSON from stdin (enables json module)
         --code <CODE>       Exit code of the last command (for ok/fail modules)
-        --no-color          Disable colored output
-        --shell <SHELL>     Wrap ANSI escapes for the specified shell (bash, zsh, none)
+        --preserve-code     Exit with the --code status, so $? survives PROMPT_COMMAND
+        --duration <MS>     Run time of the last command in ms (for the duration module)
+        --cmd <NAME>        Name of the last command (for the command module)
+        --set <KEY=VALUE>   Extra context for modules and plugins, e.g. jobs=2 (repeatable)
+        --disable <NAMES>   Hide these modules without running them, e.g. git,node (repeatable)
+        --color <WHEN>      Color output: always, never, or auto (a terminal unless NO_COLOR
+                            is set, or anywhere when CLICOLOR_FORCE is set)
+        --no-color          Same as --color never
+        --output <FORMAT>   Output styles as ansi (default), tmux status-line directives, or json segments
+        --shell <SHELL>     Wrap ANSI escapes for the specified shell (bash, bash-ps1, zsh, fish, xonsh, clink, none)
+        --max-width <N>     Truncate each line to N columns, or auto for the terminal width
+        --theme <THEME>     Theme for @name styles: built-in name and/or name=style list
+        --title <FORMAT>    Also set the terminal title, rendered from FORMAT without styles
+        --marks             Wrap the prompt in FinalTerm/iTerm2 shell integration marks
+        --right <FORMAT>    Also render a right prompt, printed after a \x1e separator
+        --async <FILE>      Print cheap modules now; write the full prompt to FILE when ready
+        --async-notify <PID>  Send SIGUSR1 to PID once the --async prompt is written
+        --icons <SET>       Prepend module icons from a built-in set (nerd, emoji, ascii)
+        --profile <NAME>    Apply a [profiles.NAME] table from the config file
     -h, --help             Print help
     -V, --version          Print version
 ";
 
+/// Set on the background process that renders the full prompt for `--async`.
+const ASYNC_CHILD_ENV: &str = "PRMT_ASYNC_CHILD";
+/// The `-f -` format read by the parent `--async` pass, since the child's stdin is not the
+/// one the format came from.
+const ASYNC_FORMAT_ENV: &str = "PRMT_ASYNC_FORMAT";
+/// Set on the background process that refreshes stored git status for these repositories.
+const GIT_REFRESH_ENV: &str = "PRMT_GIT_REFRESH";
+
+/// Separates the left and right prompt when `--right` is given; the init snippets split on it.
+const RIGHT_PROMPT_SEPARATOR: &str = "\x1e";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Output {
+    Ansi,
+    Tmux,
+    Json,
+}
+
+impl FromStr for Output {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "ansi" => Ok(Output::Ansi),
+            "tmux" => Ok(Output::Tmux),
+            "json" => Ok(Output::Json),
+            other => Err(format!(
+                "Unknown output: {} (supported values: ansi, tmux, json)",
+                other
+            )),
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+enum ReportFormat {
+    #[default]
+    Text,
+    Json,
+}
+
+impl FromStr for ReportFormat {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "text" => Ok(ReportFormat::Text),
+            "json" => Ok(ReportFormat::Json),
+            other => Err(format!(
+                "Unknown report format: {} (supported values: text, json)",
+                other
+            )),
+        }
+    }
+}
+
+const DEFAULT_BENCH_ITERATIONS: usize = 100;
+
+impl Output {
+    fn name(self) -> &'static str {
+        match self {
+            Output::Ansi => "ansi",
+            Output::Tmux => "tmux",
+            Output::Json => "json",
+        }
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+enum Command {
+    Check,
+    Init,
+    Trust,
+    Import,
+    Config,
+    ConfigShow,
+    Modules,
+}
+
 struct Cli {
+    command: Option<Command>,
+    init_shell: Option<init::InitShell>,
+    import_source: Option<import::Source>,
+    command_path: Option<String>,
     format: Option<String>,
+    /// Formats of `--format` after the first, rendered alongside it.
+    extra_formats: Vec<String>,
+    /// Position among the formats of the `-f -` one, read from stdin by [`read_stdin_format`]
+    /// rather than while parsing.
+    stdin_format: Option<usize>,
+    null: bool,
     no_version: bool,
     timeout: Option<u64>,
-    debug: bool,
-    bench: bool,
+    /// Debug report to print after rendering, if any.
+    debug: Option<ReportFormat>,
+    explain: bool,
+    /// Modules that take longer than this are named in a warning.
+    warn_slow: Option<Duration>,
+    /// Benchmark iterations; `None` renders the prompt normally.
+    bench: Option<usize>,
+    bench_warmup: usize,
+    bench_format: ReportFormat,
     stdin: bool,
     code: Option<i32>,
-    no_color: bool,
+    preserve_code: bool,
+    duration: Option<u64>,
+    cmd: Option<String>,
+    values: HashMap<String, String>,
+    disabled: HashSet<String>,
+    /// `--color`; without it, colors are on unless `NO_COLOR` is set.
+    color: Option<style::ColorMode>,
     shell: Option<style::Shell>,
+    output: Option<Output>,
+    max_width: Option<width::MaxWidth>,
+    theme: Option<String>,
+    title: Option<String>,
+    marks: bool,
+    right: Option<String>,
+    async_file: Option<String>,
+    async_notify: Option<i32>,
+    icons: Option<icons::IconSet>,
+    profile: Option<String>,
+    preset: Option<&'static str>,
+    check_config: bool,
+    config_file: Option<String>,
 }
 
 fn parse_args() -> Result<Cli, lexopt::Error> {
@@ -64,15 +238,44 @@ where
 {
     use lexopt::prelude::*;
 
+    let mut command = None;
+    let mut init_shell = None;
+    let mut import_source = None;
+    let mut command_path = None;
     let mut format = None;
+    let mut extra_formats = Vec::new();
+    let mut null = false;
+    let mut stdin_format = None;
     let mut no_version = false;
     let mut timeout = None;
-    let mut debug = false;
-    let mut bench = false;
+    let mut debug = None;
+    let mut explain = false;
+    let mut warn_slow = None;
+    let mut bench = None;
+    let mut bench_warmup = 0;
+    let mut bench_format = ReportFormat::default();
     let mut stdin = false;
     let mut code = None;
-    let mut no_color = false;
+    let mut preserve_code = false;
+    let mut duration = None;
+    let mut cmd = None;
+    let mut values = HashMap::new();
+    let mut disabled = HashSet::new();
+    let mut color = None;
     let mut shell = None;
+    let mut output = None;
+    let mut max_width = None;
+    let mut theme = None;
+    let mut title = None;
+    let mut marks = false;
+    let mut right = None;
+    let mut async_file = None;
+    let mut async_notify = None;
+    let mut icons = None;
+    let mut profile = None;
+    let mut preset = None;
+    let mut check_config = false;
+    let mut config_file = None;
 
     let mut parser = lexopt::Parser::from_iter(args);
     while let Some(arg) = parser.next()? {
@@ -86,7 +289,23 @@ where
                 std::process::exit(0);
             }
             Short('f') | Long("format") => {
-                format = Some(parser.value()?.string()?);
+                let value = parser.value()?.string()?;
+                if value == "-" {
+                    if stdin_format.is_some() {
+                        return Err("stdin can only provide one format".into());
+                    }
+                    stdin_format = Some(usize::from(format.is_some()) + extra_formats.len());
+                }
+                add_format(&mut format, &mut extra_formats, value);
+            }
+            Long("format-file") => {
+                let path = PathBuf::from(parser.value()?);
+                let text =
+                    fs::read_to_string(&path).map_err(|e| format!("{}: {}", path.display(), e))?;
+                add_format(&mut format, &mut extra_formats, format_from_file(text));
+            }
+            Short('0') | Long("null") => {
+                null = true;
             }
             Short('n') | Long("no-version") => {
                 no_version = true;
@@ -95,10 +314,32 @@ where
                 timeout = Some(parser.value()?.parse()?);
             }
             Short('d') | Long("debug") => {
-                debug = true;
+                debug = Some(match parser.optional_value() {
+                    Some(value) => value.parse()?,
+                    None => ReportFormat::Text,
+                });
+            }
+            Long("warn-slow") => {
+                warn_slow = Some(parse_threshold(&parser.value()?.string()?)?);
+            }
+            Long("explain") => {
+                explain = true;
             }
             Short('b') | Long("bench") => {
-                bench = true;
+                let iterations = match parser.optional_value() {
+                    Some(value) => value.parse()?,
+                    None => DEFAULT_BENCH_ITERATIONS,
+                };
+                if iterations == 0 {
+                    return Err("--bench needs at least one iteration".into());
+                }
+                bench = Some(iterations);
+            }
+            Long("bench-warmup") => {
+                bench_warmup = parser.value()?.parse()?;
+            }
+            Long("bench-format") => {
+                bench_format = parser.value()?.parse()?;
             }
             Long("stdin") => {
                 stdin = true;
@@ -106,16 +347,121 @@ where
             Long("code") => {
                 code = Some(parser.value()?.parse()?);
             }
+            Long("preserve-code") => {
+                preserve_code = true;
+            }
+            Long("duration") => {
+                duration = Some(parser.value()?.parse()?);
+            }
+            Long("cmd") => {
+                cmd = Some(parser.value()?.string()?);
+            }
+            Long("set") => {
+                let pair = parser.value()?.string()?;
+                let Some((key, value)) = pair.split_once('=') else {
+                    return Err(format!("--set expects KEY=VALUE, got \"{}\"", pair).into());
+                };
+                values.insert(key.to_string(), value.to_string());
+            }
+            Long("disable") => {
+                let names = parser.value()?.string()?;
+                disabled.extend(
+                    names
+                        .split(',')
+                        .map(str::trim)
+                        .filter(|name| !name.is_empty())
+                        .map(str::to_string),
+                );
+            }
+            Long("color") => {
+                color = Some(style::ColorMode::from_str(&parser.value()?.string()?)?);
+            }
             Long("no-color") => {
-                no_color = true;
+                color = Some(style::ColorMode::Never);
             }
             Long("shell") => {
                 let value = parser.value()?.string()?;
                 shell = Some(style::Shell::from_str(&value)?);
             }
+            Long("output") => {
+                let value = parser.value()?.string()?;
+                output = Some(Output::from_str(&value)?);
+            }
+            Long("max-width") => {
+                let value = parser.value()?.string()?;
+                max_width = Some(width::MaxWidth::from_str(&value)?);
+            }
+            Long("theme") => {
+                theme = Some(parser.value()?.string()?);
+            }
+            Long("title") => {
+                title = Some(parser.value()?.string()?);
+            }
+            Long("marks") => {
+                marks = true;
+            }
+            Long("right") => {
+                right = Some(parser.value()?.string()?);
+            }
+            Long("async") => {
+                async_file = Some(parser.value()?.string()?);
+            }
+            Long("async-notify") => {
+                async_notify = Some(parser.value()?.parse()?);
+            }
+            Long("icons") => {
+                let value = parser.value()?.string()?;
+                icons = Some(icons::IconSet::from_str(&value)?);
+            }
+            Long("profile") => {
+                profile = Some(parser.value()?.string()?);
+            }
+            Long("preset") => {
+                preset = Some(presets::find(&parser.value()?.string()?)?);
+            }
+            Long("list-modules") => {
+                command = Some(Command::Modules);
+            }
+            Long("config") => {
+                check_config = true;
+                config_file = parser.optional_value().map(|v| v.string()).transpose()?;
+            }
             arg if matches!(&arg, Value(_)) => {
                 let Value(val) = arg else { unreachable!() };
-                if format.is_none() {
+                if command.is_none() && format.is_none() && val == "check" {
+                    command = Some(Command::Check);
+                } else if command.is_none() && format.is_none() && val == "init" {
+                    command = Some(Command::Init);
+                } else if command.is_none() && format.is_none() && val == "trust" {
+                    command = Some(Command::Trust);
+                } else if command == Some(Command::Init) && init_shell.is_none() {
+                    init_shell = Some(init::InitShell::from_str(&val.string()?)?);
+                } else if command.is_none() && format.is_none() && val == "import" {
+                    command = Some(Command::Import);
+                } else if command.is_none() && format.is_none() && val == "modules" {
+                    command = Some(Command::Modules);
+                } else if command.is_none() && format.is_none() && val == "config" {
+                    command = Some(Command::Config);
+                } else if command == Some(Command::Config) && val == "show" {
+                    command = Some(Command::ConfigShow);
+                } else if command == Some(Command::Import) && import_source.is_none() {
+                    import_source = Some(import::Source::from_str(&val.string()?)?);
+                } else if matches!(command, Some(Command::Trust | Command::Import))
+                    && command_path.is_none()
+                {
+                    command_path = Some(val.string()?);
+                } else if format.is_none()
+                    && !matches!(
+                        command,
+                        Some(
+                            Command::Init
+                                | Command::Trust
+                                | Command::Import
+                                | Command::Config
+                                | Command::Modules
+                        )
+                    )
+                {
                     format = Some(val.string()?);
                 } else {
                     return Err(Value(val).unexpected());
@@ -125,16 +471,49 @@ where
         }
     }
 
+    if stdin && stdin_format.is_some() {
+        return Err("--stdin and -f - cannot both read stdin".into());
+    }
+
     Ok(Cli {
+        command,
+        init_shell,
+        import_source,
+        command_path,
         format,
+        extra_formats,
+        stdin_format,
+        null,
         no_version,
         timeout,
         debug,
+        explain,
+        warn_slow,
         bench,
+        bench_warmup,
+        bench_format,
         stdin,
         code,
-        no_color,
+        preserve_code,
+        duration,
+        cmd,
+        values,
+        disabled,
+        color,
         shell,
+        output,
+        max_width,
+        theme,
+        title,
+        marks,
+        right,
+        async_file,
+        async_notify,
+        icons,
+        profile,
+        preset,
+        check_config,
+        config_file,
     })
 }
 
@@ -144,6 +523,8 @@ fn shell_from_name(value: &str) -> Option<style::Shell> {
     match name {
         "zsh" => Some(style::Shell::Zsh),
         "bash" => Some(style::Shell::Bash),
+        "fish" => Some(style::Shell::Fish),
+        "xonsh" => Some(style::Shell::Xonsh),
         _ => None,
     }
 }
@@ -157,6 +538,10 @@ fn detect_shell_from_env() -> Option<style::Shell> {
         return Some(style::Shell::Bash);
     }
 
+    if env::var("XONSH_VERSION").is_ok() {
+        return Some(style::Shell::Xonsh);
+    }
+
     if let Ok(shell_path) = env::var("SHELL")
         && let Some(shell) = shell_from_name(&shell_path)
     {
@@ -181,8 +566,8 @@ fn detect_shell_from_parent_process() -> Option<style::Shell> {
     }
 
     let cmdline = fs::read_to_string(format!("/proc/{}/cmdline", ppid)).ok()?;
-    let first = cmdline.split('\0').next().unwrap_or("");
-    shell_from_name(first)
+    // Interpreted shells such as xonsh run as `python /usr/bin/xonsh`
+    cmdline.split('\0').take(2).find_map(shell_from_name)
 }
 
 #[cfg(not(target_os = "linux"))]
@@ -215,7 +600,14 @@ fn resolve_shell_from_sources(
 }
 
 fn main() -> ExitCode {
-    let cli = match parse_args() {
+    if let Some(repos) = env::var_os(GIT_REFRESH_ENV) {
+        for repo in env::split_paths(&repos) {
+            modules::git::refresh_status_cache(&repo);
+        }
+        return ExitCode::SUCCESS;
+    }
+
+    let mut cli = match parse_args() {
         Ok(cli) => cli,
         Err(e) => {
             eprintln!("Error: {}", e);
@@ -223,17 +615,167 @@ fn main() -> ExitCode {
             return ExitCode::FAILURE;
         }
     };
+    let stdin_format = match read_stdin_format(&mut cli) {
+        Ok(format) => format,
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            return ExitCode::FAILURE;
+        }
+    };
+
+    if cli.command == Some(Command::Init) {
+        return handle_init(cli.init_shell);
+    }
 
+    if cli.command == Some(Command::Trust) {
+        return handle_trust(cli.command_path);
+    }
+
+    if cli.command == Some(Command::Import) {
+        return handle_import(cli.import_source, cli.command_path);
+    }
+
+    if cli.command == Some(Command::Modules) {
+        print!("{}", modules_listing());
+        return ExitCode::SUCCESS;
+    }
+
+    if cli.check_config {
+        if cli.command != Some(Command::Check) {
+            eprintln!("Error: --config is only supported by check");
+            return ExitCode::FAILURE;
+        }
+        return handle_check_config(cli.config_file.map(PathBuf::from));
+    }
+
+    if cli.command == Some(Command::Config) {
+        eprintln!("Error: missing action for config (show)");
+        return ExitCode::FAILURE;
+    }
+
+    let env_preset = match env::var("PRMT_PRESET").ok().filter(|name| !name.is_empty()) {
+        Some(name) => match presets::find(&name) {
+            Ok(preset) => Some(preset),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => None,
+    };
     let format = cli
         .format
+        .or(cli.preset.map(str::to_string))
         .or_else(|| env::var("PRMT_FORMAT").ok())
+        .or(env_preset.map(str::to_string));
+
+    let profile = cli.profile.or_else(|| {
+        env::var("PRMT_PROFILE")
+            .ok()
+            .filter(|name| !name.is_empty())
+    });
+
+    // The config files are only read when no format or profile was given, so the usual prompt
+    // invocation never touches the filesystem for them.
+    let mut config = if format.is_none() || profile.is_some() {
+        match load_config(profile.as_deref()) {
+            Ok(config) => config,
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else {
+        config::Config::default()
+    };
+
+    // A theme file is a base layer of styles, palette, and icons under the config's own.
+    let theme = cli
+        .theme
+        .or_else(|| env::var("PRMT_THEME").ok())
+        .or(config.theme.take());
+    let theme = match theme.as_deref().and_then(config::theme_path) {
+        Some(path) => match config::load_theme(&path) {
+            Ok(mut theme_file) => {
+                let theme = theme_file.theme.take();
+                theme_file.merge(config);
+                config = theme_file;
+                theme
+            }
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        },
+        None => theme,
+    };
+
+    let format = format
+        .or(config.format)
         .unwrap_or_else(|| "{path:cyan} {node:green} {git:purple}".to_string());
 
-    let shell = resolve_shell(cli.shell);
+    if let Some(value) = &theme {
+        match theme::Theme::from_str(value) {
+            Ok(theme) => theme::set_active_theme(theme),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    }
+
+    if let Ok(value) = env::var("PRMT_PALETTE") {
+        match theme::Palette::from_str(&value) {
+            Ok(palette) => theme::set_palette(palette),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else if let Some(palette) = config.palette {
+        theme::set_palette(palette);
+    }
+
+    if let Ok(value) = env::var("PRMT_STYLES") {
+        match theme::ModuleStyles::from_str(&value) {
+            Ok(styles) => theme::set_module_styles(styles),
+            Err(e) => {
+                eprintln!("Error: {}", e);
+                return ExitCode::FAILURE;
+            }
+        }
+    } else if let Some(styles) = config.styles {
+        theme::set_module_styles(styles);
+    }
+
+    if cli.icons.is_some() {
+        icons::set_icon_set(cli.icons);
+    } else if config.icons.is_some() && env::var_os("PRMT_ICONS").is_none() {
+        icons::set_icon_set(config.icons);
+    }
+
+    if cli.command == Some(Command::Check) {
+        return handle_check(&format);
+    }
+
+    let output = cli.output.unwrap_or(Output::Ansi);
+    // tmux styles its status line itself: no prompt delimiters, and it maps colors down to
+    // what the terminal supports.
+    let shell = match output {
+        Output::Tmux => {
+            if env::var_os("PRMT_COLOR_DEPTH").is_none() {
+                style::set_color_depth(style::ColorDepth::TrueColor);
+            }
+            style::Shell::None
+        }
+        Output::Ansi => resolve_shell(cli.shell),
+        Output::Json => style::Shell::None,
+    };
 
     let timeout_ms = cli
         .timeout
         .or_else(|| env::var("PRMT_TIMEOUT").ok()?.parse().ok())
+        .or(config.timeout)
         .unwrap_or(0);
     let timeout = if timeout_ms > 0 {
         Some(Duration::from_millis(timeout_ms))
@@ -241,46 +783,807 @@ fn main() -> ExitCode {
         None
     };
 
+    let cache_ttl = env::var("PRMT_CACHE_TTL")
+        .ok()
+        .and_then(|secs| secs.parse().ok())
+        .or(config.cache_ttl)
+        .map_or(cache::DEFAULT_TTL, Duration::from_secs);
+    cache::set_ttl(cache_ttl);
+    if cli.bench.is_none() {
+        parser::disable_parse_cache();
+    }
+    let git_cache = match env::var("PRMT_GIT_CACHE") {
+        Ok(value) => !value.is_empty(),
+        Err(_) => config.cache_git.unwrap_or(false),
+    };
+    if git_cache {
+        cache::enable_git_status();
+    }
+
+    let max_width = cli
+        .max_width
+        .or_else(|| width::MaxWidth::from_str(&env::var("PRMT_MAX_WIDTH").ok()?).ok())
+        .or(config.max_width)
+        .and_then(width::MaxWidth::columns);
+
     let stdin_data = if cli.stdin { read_stdin_json() } else { None };
+    let right = cli
+        .right
+        .or_else(|| env::var("PRMT_FORMAT_RIGHT").ok())
+        .or(config.right_format);
+    let title_format = match output {
+        Output::Tmux | Output::Json => None,
+        Output::Ansi => cli
+            .title
+            .or_else(|| env::var("PRMT_TITLE").ok())
+            .or(config.title),
+    };
+    let marks = output == Output::Ansi
+        && (cli.marks || env::var("PRMT_MARKS").is_ok_and(|value| !value.is_empty()));
+
+    // The first --async pass never waits for blocking modules; the background pass does.
+    let async_update = cli.async_file.is_some() && env::var_os(ASYNC_CHILD_ENV).is_some();
+    let deferred = cli.async_file.is_some() && !async_update;
+    let timeout = if deferred {
+        Some(Duration::ZERO)
+    } else {
+        timeout
+    };
+
+    let color = resolve_color(cli.color);
 
-    let result = if cli.bench {
+    if cli.command == Some(Command::ConfigShow) {
+        let settings = [
+            ("format", Some(format)),
+            ("right_format", right),
+            ("title", title_format),
+            ("profile", profile),
+            ("theme", theme),
+            ("shell", Some(shell.name().to_string())),
+            ("output", Some(output.name().to_string())),
+            ("color", Some(color_name(color).to_string())),
+            (
+                "icons",
+                icons::active_icon_set().map(|set| set.name().to_string()),
+            ),
+            ("timeout", Some(format!("{}ms", timeout_ms))),
+            ("cache_ttl", Some(format!("{}s", cache_ttl.as_secs()))),
+            ("git_cache", Some(git_cache.to_string())),
+            ("max_width", max_width.map(|columns| columns.to_string())),
+            ("marks", Some(marks.to_string())),
+        ];
+        print!("{}", effective_config(&settings));
+        return ExitCode::SUCCESS;
+    }
+
+    let last_command = executor::LastCommand {
+        exit_code: cli.code,
+        duration: cli.duration.map(Duration::from_millis),
+        name: cli.cmd,
+    };
+    let mut options = executor::RenderOptions {
+        no_version: cli.no_version,
+        color,
+        shell,
+        stdin_data: stdin_data.clone(),
+        timeout,
+        values: Arc::new(cli.values),
+        disabled: cli.disabled,
+        ..executor::RenderOptions::default()
+    };
+    if let Some(threshold) = cli.warn_slow {
+        options.hooks.after_render(move |event| {
+            if event.elapsed > threshold {
+                eprintln!(
+                    "Warning: module '{}' took {:.1}ms (limit {}ms)",
+                    event.module,
+                    millis(event.elapsed),
+                    threshold.as_millis()
+                );
+            }
+        });
+    }
+    let render_log = executor::RenderLog::default();
+    if cli.explain {
+        render_log.record(&mut options.hooks);
+    }
+    let module_times = Arc::new(Mutex::new(Vec::new()));
+    if cli.debug == Some(ReportFormat::Json) {
+        trace::start_recording();
+        let module_times = Arc::clone(&module_times);
+        options.hooks.after_render(move |event| {
+            let (shown, error) = match event.result {
+                Ok(text) => (text.is_some(), None),
+                Err(error) => (false, Some(error.to_string())),
+            };
+            if let Ok(mut times) = module_times.lock() {
+                times.push(serde_json::json!({
+                    "module": event.module,
+                    "format": event.format,
+                    "elapsed_ms": millis(event.elapsed),
+                    "shown": shown,
+                    "error": error,
+                }));
+            }
+        });
+    }
+    // Repeated --format prompts come first, then the right prompt.
+    let formats: Vec<&str> = std::iter::once(&format)
+        .chain(&cli.extra_formats)
+        .chain(&right)
+        .map(String::as_str)
+        .collect();
+    let separator = if cli.null { "\0" } else { "\n" };
+    let result = if let Some(iterations) = cli.bench {
+        let bench = Bench {
+            iterations,
+            warmup: cli.bench_warmup,
+            report: cli.bench_format,
+        };
         handle_bench(
             &format,
+            &bench,
             cli.no_version,
-            cli.code,
-            cli.no_color,
+            &last_command,
+            color.no_color(),
             shell,
-            stdin_data,
+            stdin_data.clone(),
         )
+    } else if output == Output::Json {
+        formats
+            .iter()
+            .map(|format| {
+                let segments = executor::execute_segments(
+                    format,
+                    &registry::ModuleRegistry::new(),
+                    &last_command,
+                    &options,
+                )?;
+                let segments: Vec<_> = segments.iter().map(|segment| segment.to_json()).collect();
+                Ok(serde_json::Value::Array(segments))
+            })
+            .collect::<error::Result<Vec<_>>>()
+            .map(|outputs| json_output(outputs, right.is_some()))
     } else {
-        let start = cli.debug.then(Instant::now);
-        let output = executor::execute_with_shell(
-            &format,
-            cli.no_version,
-            cli.code,
-            cli.no_color,
-            shell,
-            stdin_data,
-            timeout,
-        );
-        if let Some(start) = start {
-            let elapsed = start.elapsed();
-            eprintln!("Format: {}", format);
-            eprintln!("Execution time: {:.2}ms", elapsed.as_secs_f64() * 1000.0);
+        let started = Instant::now();
+        let output = executor::execute_formats_with(&formats, &last_command, &options);
+        match cli.debug {
+            Some(ReportFormat::Text) => {
+                let elapsed = started.elapsed();
+                eprintln!("Format: {}", format);
+                for format in &cli.extra_formats {
+                    eprintln!("Format: {}", format);
+                }
+                if let Some(right) = &right {
+                    eprintln!("Right format: {}", right);
+                }
+                eprintln!("Execution time: {:.2}ms", elapsed.as_secs_f64() * 1000.0);
+            }
+            Some(ReportFormat::Json) => {
+                let modules = std::mem::take(&mut *module_times.lock().unwrap());
+                let report =
+                    debug_report(&formats, started.elapsed(), modules, trace::take_events());
+                eprintln!("{}", report);
+            }
+            None => {}
+        }
+        if cli.explain {
+            for format in &formats {
+                match executor::explain(
+                    format,
+                    &registry::ModuleRegistry::new(),
+                    &last_command,
+                    &options,
+                    &render_log,
+                ) {
+                    Ok(explanations) => eprint!("{}", explain_table(&explanations)),
+                    Err(e) => eprintln!("Error: {}", e),
+                }
+            }
+        }
+        let output = output.map(|outputs| {
+            let outputs = outputs
+                .iter()
+                .map(|output| match max_width {
+                    Some(columns) => width::truncate_lines(output, columns, shell),
+                    None => output.clone(),
+                })
+                .enumerate()
+                .map(|(index, output)| match index {
+                    0 if marks => style::mark_prompt(&output, cli.code, shell),
+                    _ => output,
+                })
+                .collect::<Vec<_>>();
+            join_outputs(outputs, right.is_some(), separator)
+        });
+        match &title_format {
+            // The title shares the prompt's timeout budget.
+            Some(title_format) => output.and_then(|output| {
+                let timeout = timeout.map(|timeout| timeout.saturating_sub(started.elapsed()));
+                let options = executor::RenderOptions {
+                    color: style::ColorMode::Never,
+                    shell: style::Shell::None,
+                    timeout,
+                    ..options.clone()
+                };
+                let title =
+                    executor::execute_formats_with(&[title_format], &last_command, &options)?
+                        .concat();
+                Ok(style::title_sequence(&title, shell) + &output)
+            }),
+            None => output,
         }
-        output
     };
 
-    match result {
+    let result = match (output, shell) {
+        (Output::Tmux, _) => result.map(|output| tmux::to_status(&output)),
+        (_, style::Shell::Xonsh) => result.map(|output| xonsh::to_prompt(&output)),
+        (_, style::Shell::Clink) => {
+            clink::enable_virtual_terminal();
+            result.map(|output| clink::to_prompt(&output))
+        }
+        _ => result,
+    };
+
+    if let Some(file) = cli.async_file.as_deref() {
+        if async_update {
+            return match result.map_err(|e| e.to_string()).and_then(|output| {
+                write_async_prompt(file, &output, cli.async_notify).map_err(|e| e.to_string())
+            }) {
+                Ok(()) => ExitCode::SUCCESS,
+                Err(e) => {
+                    eprintln!("Error: {}", e);
+                    ExitCode::FAILURE
+                }
+            };
+        }
+
+        let formats: Vec<&str> = [Some(&format), right.as_ref(), title_format.as_ref()]
+            .into_iter()
+            .flatten()
+            .map(String::as_str)
+            .collect();
+        if result.is_ok()
+            && executor::has_blocking_modules(&formats)
+            && let Err(e) = spawn_async_update(stdin_data.as_deref(), stdin_format.as_deref())
+        {
+            eprintln!("Error: failed to start async render: {}", e);
+        }
+    }
+
+    let status = match result {
         Ok(output) => {
             print!("{}", output);
+            let stale = cache::take_stale_git_repos();
+            if !stale.is_empty()
+                && let Err(e) = spawn_git_refresh(&stale)
+            {
+                eprintln!("Error: failed to start git status refresh: {}", e);
+            }
             ExitCode::SUCCESS
         }
         Err(e) => {
             eprintln!("Error: {}", e);
             ExitCode::FAILURE
         }
+    };
+    match cli.code {
+        Some(code) if cli.preserve_code => preserved_exit_code(code),
+        _ => status,
+    }
+}
+
+/// `--preserve-code`: exits with the status the shell reported, even when rendering failed,
+/// keeping only the low byte as a Unix shell would.
+fn preserved_exit_code(code: i32) -> ExitCode {
+    ExitCode::from(code as u8)
+}
+
+/// Color mode for `--color`. Without the flag colors stay on unless `NO_COLOR` is set, because
+/// shells capture the prompt, so stdout is never a terminal; `auto` asks for the TTY check.
+fn resolve_color(color: Option<style::ColorMode>) -> style::ColorMode {
+    match color {
+        Some(style::ColorMode::Auto) => {
+            let colored = auto_color(
+                env::var_os("NO_COLOR").is_some(),
+                env::var_os("CLICOLOR_FORCE").is_some_and(|value| value != "0"),
+                std::io::stdout().is_terminal(),
+            );
+            if colored {
+                style::ColorMode::Always
+            } else {
+                style::ColorMode::Never
+            }
+        }
+        Some(color) => color,
+        None => style::ColorMode::Auto,
+    }
+}
+
+/// `--color auto`: `NO_COLOR` wins, then `CLICOLOR_FORCE`, then whether stdout is a terminal.
+fn auto_color(no_color: bool, clicolor_force: bool, is_terminal: bool) -> bool {
+    !no_color && (clicolor_force || is_terminal)
+}
+
+fn color_name(color: style::ColorMode) -> &'static str {
+    if color.no_color() {
+        return "none";
+    }
+    match style::color_depth() {
+        style::ColorDepth::TrueColor => "truecolor",
+        style::ColorDepth::Ansi256 => "256",
+        style::ColorDepth::Ansi16 => "16",
+    }
+}
+
+/// `--explain` output: one row per placeholder with what it rendered, or why it is hidden.
+fn explain_table(explanations: &[executor::Explanation]) -> String {
+    use std::fmt::Write;
+
+    let or_dash = |value: &str| match value {
+        "" => "-".to_string(),
+        value => value.to_string(),
+    };
+    let mut rows = vec![[
+        "PLACEHOLDER".to_string(),
+        "MODULE".to_string(),
+        "FORMAT".to_string(),
+        "STYLE".to_string(),
+        "OUTPUT".to_string(),
+    ]];
+    for explanation in explanations {
+        let output = match (&explanation.text, &explanation.hidden) {
+            (Some(text), _) => format!("{:?}", text),
+            (None, Some(reason)) => format!("hidden: {}", reason),
+            (None, None) => "hidden".to_string(),
+        };
+        rows.push([
+            explanation.placeholder.clone(),
+            explanation.module.clone(),
+            or_dash(&explanation.format),
+            or_dash(&explanation.style),
+            output,
+        ]);
+    }
+
+    let mut widths = [0; 4];
+    for row in &rows {
+        for (width, cell) in widths.iter_mut().zip(row) {
+            *width = (*width).max(width::display_width(cell, style::Shell::None));
+        }
+    }
+    let mut table = String::new();
+    for row in &rows {
+        for (width, cell) in widths.iter().zip(row) {
+            let padding = width - width::display_width(cell, style::Shell::None);
+            let _ = write!(table, "{}{}  ", cell, " ".repeat(padding));
+        }
+        let _ = writeln!(table, "{}", row[4]);
+    }
+    table
+}
+
+/// `prmt config show` output: the config files that were considered, the resolved settings,
+/// then the active theme, default styles, palette, and default formats as TOML tables.
+fn effective_config(settings: &[(&str, Option<String>)]) -> String {
+    use std::fmt::Write;
+
+    let mut output = String::new();
+    match config::config_path() {
+        Some(path) if path.is_file() => {
+            let _ = writeln!(output, "# config: {}", path.display());
+        }
+        Some(path) => {
+            let _ = writeln!(output, "# config: {} (not found)", path.display());
+        }
+        None => {}
+    }
+    let project = env::current_dir()
+        .ok()
+        .and_then(|dir| config::find_project_config(&dir));
+    if let Some(path) = project {
+        let trusted = config::load_project(&path).is_some();
+        let note = if trusted { "" } else { " (untrusted, ignored)" };
+        let _ = writeln!(output, "# project: {}{}", path.display(), note);
+    }
+    if let Some(path) = cache::versions_path() {
+        let _ = writeln!(output, "# cache: {}", path.display());
+    }
+
+    for (key, value) in settings {
+        if let Some(value) = value {
+            let _ = writeln!(output, "{} = {}", key, config::toml_string(value));
+        }
+    }
+
+    let theme = theme::active_theme();
+    let styles = theme::module_styles();
+    let sections = [("theme", theme.entries()), ("styles", styles.entries())];
+    for (section, entries) in sections {
+        let _ = writeln!(output, "\n[{}]", section);
+        for (name, value) in entries {
+            let _ = writeln!(output, "{} = {}", name, config::toml_string(value));
+        }
+    }
+
+    let _ = writeln!(output, "\n[palette]");
+    for (name, color) in theme::palette().entries() {
+        let _ = writeln!(
+            output,
+            "{} = {}",
+            name,
+            config::toml_string(&color.to_string())
+        );
+    }
+
+    // PRMT_<MODULE>_FORMAT defaults
+    let mut formats: Vec<(String, String)> = env::vars()
+        .filter_map(|(name, value)| {
+            let module = name.strip_prefix("PRMT_")?.strip_suffix("_FORMAT")?;
+            (!module.is_empty() && !value.is_empty()).then(|| (module.to_lowercase(), value))
+        })
+        .collect();
+    formats.sort_unstable();
+    let _ = writeln!(output, "\n[formats]");
+    for (module, format) in formats {
+        let _ = writeln!(output, "{} = {}", module, config::toml_string(&format));
+    }
+
+    output
+}
+
+/// User config with the nearest trusted `.prmt.toml` layered on top, then `profile`.
+fn load_config(profile: Option<&str>) -> Result<config::Config, String> {
+    let mut config = match config::config_path() {
+        Some(path) => config::load(&path)?.unwrap_or_default(),
+        None => config::Config::default(),
+    };
+
+    let project = env::current_dir()
+        .ok()
+        .and_then(|dir| config::find_project_config(&dir));
+    if let Some(path) = project {
+        match config::load_project(&path) {
+            Some(project) => config.merge(project),
+            None if config::first_untrusted_notice(&path) => eprintln!(
+                "prmt: ignoring untrusted {} (run 'prmt trust' to allow it)",
+                path.display()
+            ),
+            None => {}
+        }
+    }
+
+    if let Some(profile) = profile {
+        config.select_profile(profile)?;
+    }
+    Ok(config)
+}
+
+fn handle_trust(path: Option<String>) -> ExitCode {
+    let path = match path.map(PathBuf::from) {
+        Some(path) if path.is_dir() => path.join(config::PROJECT_CONFIG),
+        Some(path) => path,
+        None => match env::current_dir()
+            .ok()
+            .and_then(|dir| config::find_project_config(&dir))
+        {
+            Some(path) => path,
+            None => {
+                eprintln!("Error: no {} found", config::PROJECT_CONFIG);
+                return ExitCode::FAILURE;
+            }
+        },
+    };
+
+    match config::trust(&path) {
+        Ok(path) => {
+            println!("Trusted {}", path.display());
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}", e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+fn handle_import(source: Option<import::Source>, path: Option<String>) -> ExitCode {
+    let Some(import::Source::Starship) = source else {
+        eprintln!("Error: missing source for import (starship)");
+        return ExitCode::FAILURE;
+    };
+
+    let path = path
+        .map(PathBuf::from)
+        .or_else(|| env::var_os("STARSHIP_CONFIG").map(PathBuf::from))
+        .or_else(|| dirs::home_dir().map(|home| home.join(".config").join("starship.toml")));
+    let Some(path) = path else {
+        eprintln!("Error: cannot locate starship.toml");
+        return ExitCode::FAILURE;
+    };
+
+    let result = fs::read_to_string(&path)
+        .map_err(|e| e.to_string())
+        .and_then(|text| import::starship(&text));
+    match result {
+        Ok(import) => {
+            for warning in &import.warnings {
+                eprintln!("Warning: {}", warning);
+            }
+            print!("{}", import.config);
+            ExitCode::SUCCESS
+        }
+        Err(e) => {
+            eprintln!("Error: {}: {}", path.display(), e);
+            ExitCode::FAILURE
+        }
+    }
+}
+
+/// One entry per built-in module: its name and formats, then the files or directories that
+/// show it, if any.
+fn modules_listing() -> String {
+    let modules: Vec<_> = executor::builtin_modules().collect();
+    let name_width = modules
+        .iter()
+        .map(|(name, _)| name.len())
+        .max()
+        .unwrap_or(0);
+    let mut listing = String::new();
+    for (name, module) in &modules {
+        listing.push_str(&format!("{:<name_width$}  {}\n", name, module.formats()));
+        let markers = module.fs_markers();
+        if !markers.is_empty() {
+            listing.push_str(&format!(
+                "{:<name_width$}  markers: {}\n",
+                "",
+                markers.join(", ")
+            ));
+        }
+    }
+    listing
+}
+
+fn handle_init(shell: Option<init::InitShell>) -> ExitCode {
+    let Some(shell) = shell else {
+        eprintln!("Error: missing shell for init (bash, zsh, fish, xonsh, powershell, clink)");
+        return ExitCode::FAILURE;
+    };
+
+    let exe = env::current_exe()
+        .ok()
+        .and_then(|path| path.to_str().map(str::to_string))
+        .unwrap_or_else(|| "prmt".to_string());
+    print!("{}", init::script(shell, &exe));
+    ExitCode::SUCCESS
+}
+
+/// Re-runs prmt with the same arguments in the background to render the full `--async`
+/// prompt. The child writes to the file instead of stdout, so the shell does not wait on it.
+fn spawn_async_update(
+    stdin_data: Option<&serde_json::Value>,
+    stdin_format: Option<&str>,
+) -> std::io::Result<()> {
+    use std::io::Write;
+    use std::process::{Command, Stdio};
+
+    let mut command = Command::new(env::current_exe()?);
+    if let Some(format) = stdin_format {
+        command.env(ASYNC_FORMAT_ENV, format);
+    }
+    let mut child = command
+        .args(env::args_os().skip(1))
+        .env(ASYNC_CHILD_ENV, "1")
+        .stdin(if stdin_data.is_some() {
+            Stdio::piped()
+        } else {
+            Stdio::null()
+        })
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    if let (Some(data), Some(mut stdin)) = (stdin_data, child.stdin.take()) {
+        stdin.write_all(data.to_string().as_bytes())?;
+    }
+    Ok(())
+}
+
+/// Starts a detached `prmt` that recomputes the git status of `repos` for the next prompt.
+fn spawn_git_refresh(repos: &[PathBuf]) -> std::io::Result<()> {
+    use std::process::{Command, Stdio};
+
+    let repos = env::join_paths(repos).map_err(std::io::Error::other)?;
+    Command::new(env::current_exe()?)
+        .env(GIT_REFRESH_ENV, repos)
+        .stdin(Stdio::null())
+        .stdout(Stdio::null())
+        .stderr(Stdio::null())
+        .spawn()?;
+    Ok(())
+}
+
+/// Replaces `file` with the finished prompt in one rename, so readers never see a partial
+/// write, then signals the waiting shell.
+fn write_async_prompt(file: &str, output: &str, notify: Option<i32>) -> std::io::Result<()> {
+    let tmp = format!("{}.{}.tmp", file, std::process::id());
+    fs::write(&tmp, output)?;
+    fs::rename(&tmp, file)?;
+    #[cfg(unix)]
+    if let Some(pid) = notify {
+        unsafe {
+            libc::kill(pid, libc::SIGUSR1);
+        }
+    }
+    #[cfg(not(unix))]
+    let _ = notify;
+    Ok(())
+}
+
+fn handle_check(format: &str) -> ExitCode {
+    let errors = executor::check(format);
+    if errors.is_empty() {
+        println!("OK");
+        return ExitCode::SUCCESS;
+    }
+
+    for error in &errors {
+        eprintln!("Error: {}", error);
+        if let error::PromptError::Parse { span, .. } = error {
+            eprint!("{}", caret_line(format, span.clone()));
+        }
     }
+    ExitCode::FAILURE
+}
+
+/// `format` with carets under the bytes of `span`, for pointing at a parse error.
+fn caret_line(format: &str, span: std::ops::Range<usize>) -> String {
+    let before = width::display_width(&format[..span.start], style::Shell::None);
+    let under = width::display_width(&format[span], style::Shell::None).max(1);
+    format!(
+        "  {}\n  {}{}\n",
+        format,
+        " ".repeat(before),
+        "^".repeat(under)
+    )
+}
+
+/// Validates config files without rendering: the one given, or the user config, the nearest
+/// project config (trusted or not), and the theme file they select. Every problem is reported
+/// with its file and key.
+fn handle_check_config(file: Option<PathBuf>) -> ExitCode {
+    let files = match file {
+        Some(file) => vec![file],
+        None => {
+            let project = env::current_dir()
+                .ok()
+                .and_then(|dir| config::find_project_config(&dir));
+            config::config_path()
+                .filter(|path| path.is_file())
+                .into_iter()
+                .chain(project)
+                .collect()
+        }
+    };
+
+    let mut errors = Vec::new();
+    let mut checked = Vec::new();
+    let mut merged = config::Config::default();
+    for path in files {
+        match config::read(&path) {
+            Ok(config) => {
+                merged.merge(config.clone());
+                checked.push((path, config));
+            }
+            Err(e) => errors.push(e),
+        }
+    }
+
+    if let Some(path) = merged.theme.as_deref().and_then(config::theme_path) {
+        let theme_file = fs::read_to_string(&path)
+            .map_err(|e| config::Diagnostic {
+                key: String::new(),
+                message: e.to_string(),
+            })
+            .and_then(|text| config::parse_theme(&text));
+        match theme_file {
+            Ok(mut theme_file) => {
+                checked.push((path, theme_file.clone()));
+                merged.theme = theme_file.theme.take();
+                theme_file.merge(merged);
+                merged = theme_file;
+            }
+            Err(diagnostic) => errors.push(format!("{}: {}", path.display(), diagnostic)),
+        }
+    }
+
+    // Styles may name palette colors and theme styles from any of the files.
+    if let Some(palette) = merged.palette {
+        theme::set_palette(palette);
+    }
+    if let Some(Ok(theme)) = merged.theme.as_deref().map(theme::Theme::from_str) {
+        theme::set_active_theme(theme);
+    }
+    for (path, config) in &checked {
+        for diagnostic in config.diagnose() {
+            errors.push(format!("{}: {}", path.display(), diagnostic));
+        }
+    }
+
+    if checked.is_empty() && errors.is_empty() {
+        println!("No config files found");
+        return ExitCode::SUCCESS;
+    }
+    if errors.is_empty() {
+        println!("OK");
+        return ExitCode::SUCCESS;
+    }
+    for error in &errors {
+        eprintln!("Error: {}", error);
+    }
+    ExitCode::FAILURE
+}
+
+/// A `--warn-slow` threshold: `50ms`, `1s`, or a bare number of milliseconds.
+fn parse_threshold(value: &str) -> Result<Duration, String> {
+    let value = value.trim();
+    let (number, unit) = match value.strip_suffix("ms") {
+        Some(number) => (number, Duration::from_millis(1)),
+        None => match value.strip_suffix('s') {
+            Some(number) => (number, Duration::from_secs(1)),
+            None => (value, Duration::from_millis(1)),
+        },
+    };
+    number
+        .trim()
+        .parse::<u32>()
+        .map(|count| unit * count)
+        .map_err(|_| format!("Invalid time: {} (expected e.g. 50ms or 1s)", value))
+}
+
+/// Records a `--format`: the first is the prompt, later ones are rendered alongside it.
+fn add_format(format: &mut Option<String>, extra_formats: &mut Vec<String>, value: String) {
+    if format.is_none() {
+        *format = Some(value);
+    } else {
+        extra_formats.push(value);
+    }
+}
+
+/// A format read from a file or stdin, without the line ending editors put at the end.
+fn format_from_file(mut text: String) -> String {
+    if text.ends_with('\n') {
+        text.pop();
+        if text.ends_with('\r') {
+            text.pop();
+        }
+    }
+    text
+}
+
+/// Replaces the `-f -` format with the text on stdin, or in an `--async` child with the text
+/// its parent read, and returns it.
+fn read_stdin_format(cli: &mut Cli) -> Result<Option<String>, String> {
+    let Some(index) = cli.stdin_format else {
+        return Ok(None);
+    };
+    let text = match env::var(ASYNC_FORMAT_ENV) {
+        Ok(text) if env::var_os(ASYNC_CHILD_ENV).is_some() => text,
+        _ => {
+            let mut text = String::new();
+            std::io::stdin()
+                .read_to_string(&mut text)
+                .map_err(|e| format!("cannot read format from stdin: {}", e))?;
+            format_from_file(text)
+        }
+    };
+    let slot = match index {
+        0 => cli.format.as_mut(),
+        index => cli.extra_formats.get_mut(index - 1),
+    };
+    if let Some(slot) = slot {
+        *slot = text.clone();
+    }
+    Ok(Some(text))
 }
 
 fn read_stdin_json() -> Option<Arc<serde_json::Value>> {
@@ -289,42 +1592,178 @@ fn read_stdin_json() -> Option<Arc<serde_json::Value>> {
     serde_json::from_str(&buf).ok().map(Arc::new)
 }
 
+struct Bench {
+    iterations: usize,
+    warmup: usize,
+    report: ReportFormat,
+}
+
+/// Nearest-rank percentile of an ascending, non-empty list of timings.
+fn percentile(sorted: &[Duration], percent: usize) -> Duration {
+    let rank = (sorted.len() * percent).div_ceil(100).max(1);
+    sorted[rank - 1]
+}
+
+fn millis(duration: Duration) -> f64 {
+    duration.as_secs_f64() * 1000.0
+}
+
+/// The `--debug=json` report: how long the formats took, every module render, and what the
+/// recorder saw while they ran.
+fn debug_report(
+    formats: &[&str],
+    elapsed: Duration,
+    modules: Vec<serde_json::Value>,
+    events: Vec<trace::Event>,
+) -> serde_json::Value {
+    let mut subprocesses = Vec::new();
+    let mut cache = Vec::new();
+    let mut detected = serde_json::Map::new();
+    for event in events {
+        match event {
+            trace::Event::Spawn { program, elapsed } => subprocesses.push(serde_json::json!({
+                "program": program,
+                "elapsed_ms": millis(elapsed),
+            })),
+            trace::Event::Cache { kind, key, hit } => cache.push(serde_json::json!({
+                "kind": kind,
+                "key": key,
+                "hit": hit,
+            })),
+            trace::Event::Detected { marker, path } => {
+                detected.insert(marker.to_string(), path.display().to_string().into());
+            }
+        }
+    }
+    serde_json::json!({
+        "formats": formats,
+        "elapsed_ms": millis(elapsed),
+        "modules": modules,
+        "subprocesses": subprocesses,
+        "cache": cache,
+        "detected": detected,
+    })
+}
+
+/// Joins the rendered prompts: those of repeated `--format` with `separator`, then the right
+/// prompt, the last of `outputs` when `right` is set, after [`RIGHT_PROMPT_SEPARATOR`].
+/// `--output json` text: the segment array of a lone prompt, else an object holding every
+/// prompt's array under `prompts` and the right prompt's under `right`, so it stays one JSON
+/// document.
+fn json_output(mut outputs: Vec<serde_json::Value>, right: bool) -> String {
+    let right = if right { outputs.pop() } else { None };
+    if right.is_none() && outputs.len() == 1 {
+        return outputs.swap_remove(0).to_string();
+    }
+    let mut object = serde_json::Map::new();
+    object.insert("prompts".to_string(), serde_json::Value::Array(outputs));
+    if let Some(right) = right {
+        object.insert("right".to_string(), right);
+    }
+    serde_json::Value::Object(object).to_string()
+}
+
+fn join_outputs(mut outputs: Vec<String>, right: bool, separator: &str) -> String {
+    let right = if right { outputs.pop() } else { None };
+    let mut output = outputs.join(separator);
+    if let Some(right) = right {
+        output.push_str(RIGHT_PROMPT_SEPARATOR);
+        output.push_str(&right);
+    }
+    output
+}
+
+fn bench_stats(times: &mut [Duration]) -> serde_json::Value {
+    times.sort();
+    let avg = times.iter().sum::<Duration>() / times.len() as u32;
+    serde_json::json!({
+        "min_ms": millis(times[0]),
+        "avg_ms": millis(avg),
+        "p50_ms": millis(percentile(times, 50)),
+        "p90_ms": millis(percentile(times, 90)),
+        "p99_ms": millis(percentile(times, 99)),
+        "max_ms": millis(times[times.len() - 1]),
+    })
+}
+
 fn handle_bench(
     format: &str,
+    bench: &Bench,
     no_version: bool,
-    exit_code: Option<i32>,
+    last_command: &executor::LastCommand,
     no_color: bool,
     shell: style::Shell,
     stdin_data: Option<Arc<serde_json::Value>>,
 ) -> error::Result<String> {
-    let mut times = Vec::new();
-
-    for _ in 0..100 {
-        let start = Instant::now();
-        let _ = executor::execute_with_shell(
-            format,
+    let formats = [format];
+    let render = || {
+        executor::execute_formats(
+            &formats,
             no_version,
-            exit_code,
+            last_command,
             no_color,
             shell,
             stdin_data.clone(),
             None,
-        )?;
+        )
+    };
+
+    for _ in 0..bench.warmup {
+        render()?;
+    }
+
+    let mut times = Vec::with_capacity(bench.iterations);
+    let mut modules: Vec<(String, Vec<Duration>)> = Vec::new();
+    for _ in 0..bench.iterations {
+        let start = Instant::now();
+        render()?;
         times.push(start.elapsed());
+
+        if bench.report == ReportFormat::Json {
+            let timings = executor::time_modules(
+                format,
+                no_version,
+                last_command,
+                shell,
+                stdin_data.clone(),
+            )?;
+            for (index, (name, elapsed)) in timings.into_iter().enumerate() {
+                match modules.get_mut(index) {
+                    Some((_, samples)) => samples.push(elapsed),
+                    None => modules.push((name, vec![elapsed])),
+                }
+            }
+        }
     }
 
-    times.sort();
-    let min = times[0];
-    let max = times[99];
-    let avg: std::time::Duration = times.iter().sum::<std::time::Duration>() / 100;
-    let p99 = times[98];
+    if bench.report == ReportFormat::Json {
+        let modules: Vec<serde_json::Value> = modules
+            .iter_mut()
+            .map(|(name, samples)| {
+                let mut stats = bench_stats(samples);
+                stats["name"] = serde_json::Value::from(name.as_str());
+                stats
+            })
+            .collect();
+        let report = serde_json::json!({
+            "format": format,
+            "iterations": bench.iterations,
+            "warmup": bench.warmup,
+            "total": bench_stats(&mut times),
+            "modules": modules,
+        });
+        return Ok(format!("{}\n", report));
+    }
 
+    times.sort();
+    let avg = times.iter().sum::<Duration>() / times.len() as u32;
     Ok(format!(
-        "100 runs: min={:.2}ms avg={:.2}ms max={:.2}ms p99={:.2}ms\n",
-        min.as_secs_f64() * 1000.0,
-        avg.as_secs_f64() * 1000.0,
-        max.as_secs_f64() * 1000.0,
-        p99.as_secs_f64() * 1000.0
+        "{} runs: min={:.2}ms avg={:.2}ms max={:.2}ms p99={:.2}ms\n",
+        times.len(),
+        millis(times[0]),
+        millis(avg),
+        millis(times[times.len() - 1]),
+        millis(percentile(&times, 99))
     ))
 }
 
@@ -372,6 +1811,464 @@ mod tests {
         assert_eq!(cli.format.as_deref(), Some("{path}"));
     }
 
+    #[test]
+    fn parse_args_accepts_icons() {
+        let cli = parse_args_from(["prmt", "--icons", "emoji"]).expect("parse args");
+
+        assert_eq!(cli.icons, Some(icons::IconSet::Emoji));
+        assert!(parse_args_from(["prmt", "--icons", "fancy"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_accepts_profile() {
+        let cli = parse_args_from(["prmt", "--profile", "demo"]).expect("parse args");
+
+        assert_eq!(cli.profile.as_deref(), Some("demo"));
+        assert!(cli.format.is_none());
+    }
+
+    #[test]
+    fn parse_args_accepts_preset() {
+        let cli = parse_args_from(["prmt", "--preset", "minimal"]).expect("parse args");
+
+        assert_eq!(cli.preset, presets::find("minimal").ok());
+        assert!(parse_args_from(["prmt", "--preset", "fancy"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_accepts_title() {
+        let cli =
+            parse_args_from(["prmt", "--title", "{path}", "{path:cyan}"]).expect("parse args");
+
+        assert_eq!(cli.title.as_deref(), Some("{path}"));
+        assert_eq!(cli.format.as_deref(), Some("{path:cyan}"));
+    }
+
+    #[test]
+    fn parse_args_accepts_bench_options() {
+        let cli = parse_args_from(["prmt", "--bench", "{path}"]).expect("parse args");
+        assert_eq!(cli.bench, Some(DEFAULT_BENCH_ITERATIONS));
+        assert_eq!(cli.format.as_deref(), Some("{path}"));
+
+        let cli = parse_args_from([
+            "prmt",
+            "--bench=20",
+            "--bench-warmup",
+            "3",
+            "--bench-format",
+            "json",
+        ])
+        .expect("parse args");
+        assert_eq!(cli.bench, Some(20));
+        assert_eq!(cli.bench_warmup, 3);
+        assert_eq!(cli.bench_format, ReportFormat::Json);
+
+        assert!(parse_args_from(["prmt", "--bench=0"]).is_err());
+        assert!(parse_args_from(["prmt", "--bench-format", "csv"]).is_err());
+    }
+
+    #[test]
+    fn percentile_uses_the_nearest_rank() {
+        let times: Vec<Duration> = (1..=100).map(Duration::from_millis).collect();
+        assert_eq!(percentile(&times, 50), Duration::from_millis(50));
+        assert_eq!(percentile(&times, 99), Duration::from_millis(99));
+        assert_eq!(percentile(&times[..1], 90), Duration::from_millis(1));
+    }
+
+    #[test]
+    fn caret_line_points_at_the_span_by_column() {
+        assert_eq!(caret_line("✓ {git", 4..5), "  ✓ {git\n    ^\n");
+        assert_eq!(caret_line("a {:red}", 2..8), "  a {:red}\n    ^^^^^^\n");
+    }
+
+    #[test]
+    fn parse_args_accepts_right() {
+        let cli = parse_args_from(["prmt", "--right", "{time}", "{path}"]).expect("parse args");
+        assert_eq!(cli.right.as_deref(), Some("{time}"));
+        assert_eq!(cli.format.as_deref(), Some("{path}"));
+    }
+
+    #[test]
+    fn parse_args_accepts_async() {
+        let cli = parse_args_from(["prmt", "--async", "/tmp/p", "--async-notify", "42", "{git}"])
+            .expect("parse args");
+        assert_eq!(cli.async_file.as_deref(), Some("/tmp/p"));
+        assert_eq!(cli.async_notify, Some(42));
+    }
+
+    #[test]
+    fn parse_args_accepts_last_command() {
+        let cli = parse_args_from(["prmt", "--duration", "2500", "--cmd", "cargo", "{duration}"])
+            .expect("parse args");
+        assert_eq!(cli.duration, Some(2500));
+        assert_eq!(cli.cmd.as_deref(), Some("cargo"));
+    }
+
+    #[test]
+    fn parse_args_accepts_output() {
+        let cli = parse_args_from(["prmt", "--output", "tmux", "{path}"]).expect("parse args");
+        assert_eq!(cli.output, Some(Output::Tmux));
+        let cli = parse_args_from(["prmt", "--output", "json"]).expect("parse args");
+        assert_eq!(cli.output, Some(Output::Json));
+        assert!(parse_args_from(["prmt", "--output", "html"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_collects_set_values() {
+        let cli = parse_args_from([
+            "prmt",
+            "--set",
+            "jobs=2",
+            "--set",
+            "keymap=vi=cmd",
+            "{path}",
+        ])
+        .expect("parse args");
+        assert_eq!(cli.values.get("jobs").map(String::as_str), Some("2"));
+        assert_eq!(cli.values.get("keymap").map(String::as_str), Some("vi=cmd"));
+        assert!(parse_args_from(["prmt", "--set", "jobs"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_accepts_debug_format() {
+        let cli = parse_args_from(["prmt", "-d", "{path}"]).expect("parse args");
+        assert_eq!(cli.debug, Some(ReportFormat::Text));
+        let cli = parse_args_from(["prmt", "--debug=json", "{path}"]).expect("parse args");
+        assert_eq!(cli.debug, Some(ReportFormat::Json));
+        let cli = parse_args_from(["prmt", "{path}"]).expect("parse args");
+        assert_eq!(cli.debug, None);
+        assert!(parse_args_from(["prmt", "--debug=yaml", "{path}"]).is_err());
+    }
+
+    #[test]
+    fn debug_report_groups_recorded_events() {
+        let events = vec![
+            trace::Event::Detected {
+                marker: "Cargo.toml",
+                path: PathBuf::from("/work/Cargo.toml"),
+            },
+            trace::Event::Cache {
+                kind: "version",
+                key: "rust".to_string(),
+                hit: false,
+            },
+            trace::Event::Spawn {
+                program: "rustc".to_string(),
+                elapsed: Duration::from_millis(12),
+            },
+        ];
+        let modules = vec![serde_json::json!({"module": "rust", "shown": true})];
+        let report = debug_report(&["{rust}"], Duration::from_millis(20), modules, events);
+        assert_eq!(report["formats"][0], "{rust}");
+        assert_eq!(report["elapsed_ms"], 20.0);
+        assert_eq!(report["modules"][0]["module"], "rust");
+        assert_eq!(report["subprocesses"][0]["program"], "rustc");
+        assert_eq!(report["subprocesses"][0]["elapsed_ms"], 12.0);
+        assert_eq!(report["cache"][0]["kind"], "version");
+        assert_eq!(report["cache"][0]["hit"], false);
+        assert_eq!(report["detected"]["Cargo.toml"], "/work/Cargo.toml");
+    }
+
+    #[test]
+    fn parse_args_accepts_explain() {
+        let cli = parse_args_from(["prmt", "--explain", "{path}"]).expect("parse args");
+        assert!(cli.explain);
+        assert_eq!(cli.format.as_deref(), Some("{path}"));
+    }
+
+    #[test]
+    fn explain_table_aligns_columns_and_gives_reasons() {
+        let explanations = [
+            executor::Explanation {
+                placeholder: "{path:cyan}".to_string(),
+                module: "path".to_string(),
+                format: String::new(),
+                style: "cyan".to_string(),
+                text: Some("~/src".to_string()),
+                hidden: None,
+            },
+            executor::Explanation {
+                placeholder: "{git}".to_string(),
+                module: "git".to_string(),
+                format: "short".to_string(),
+                style: String::new(),
+                text: None,
+                hidden: Some("no .git found".to_string()),
+            },
+        ];
+        assert_eq!(
+            explain_table(&explanations),
+            "\
+PLACEHOLDER  MODULE  FORMAT  STYLE  OUTPUT
+{path:cyan}  path    -       cyan   \"~/src\"
+{git}        git     short   -      hidden: no .git found
+"
+        );
+    }
+
+    #[test]
+    fn parse_args_collects_disabled_modules() {
+        let cli = parse_args_from(["prmt", "--disable", "git, node", "--disable=go", "{path}"])
+            .expect("parse args");
+        let mut disabled: Vec<_> = cli.disabled.iter().map(String::as_str).collect();
+        disabled.sort_unstable();
+        assert_eq!(disabled, ["git", "go", "node"]);
+    }
+
+    #[test]
+    fn parse_args_accepts_color_modes() {
+        let cli = parse_args_from(["prmt", "{path}"]).expect("parse args");
+        assert_eq!(cli.color, None);
+        let cli = parse_args_from(["prmt", "--color", "always", "{path}"]).expect("parse args");
+        assert_eq!(cli.color, Some(style::ColorMode::Always));
+        let cli = parse_args_from(["prmt", "--color=auto", "{path}"]).expect("parse args");
+        assert_eq!(cli.color, Some(style::ColorMode::Auto));
+        let cli = parse_args_from(["prmt", "--no-color", "{path}"]).expect("parse args");
+        assert_eq!(cli.color, Some(style::ColorMode::Never));
+        assert!(parse_args_from(["prmt", "--color", "sometimes"]).is_err());
+    }
+
+    #[test]
+    fn auto_color_prefers_no_color_then_force_then_terminal() {
+        assert!(auto_color(false, false, true));
+        assert!(!auto_color(false, false, false));
+        assert!(auto_color(false, true, false));
+        assert!(!auto_color(true, true, true));
+    }
+
+    #[test]
+    fn parse_args_collects_repeated_formats() {
+        let cli = parse_args_from([
+            "prmt", "-f", "{path}", "-f", "{git}", "--format", "{time}", "-0",
+        ])
+        .expect("parse args");
+        assert_eq!(cli.format.as_deref(), Some("{path}"));
+        assert_eq!(cli.extra_formats, ["{git}", "{time}"]);
+        assert!(cli.null);
+    }
+
+    #[test]
+    fn stdin_format_is_resolved_after_parsing() {
+        let mut cli = parse_args_from(["prmt", "-f", "{path}", "-f", "-", "--async", "/tmp/p"])
+            .expect("parse args");
+        assert_eq!(cli.stdin_format, Some(1));
+        assert_eq!(cli.extra_formats, ["-"]);
+
+        unsafe {
+            env::set_var(ASYNC_CHILD_ENV, "1");
+            env::set_var(ASYNC_FORMAT_ENV, "{git}");
+        }
+        let format = read_stdin_format(&mut cli);
+        unsafe {
+            env::remove_var(ASYNC_CHILD_ENV);
+            env::remove_var(ASYNC_FORMAT_ENV);
+        }
+        assert_eq!(format, Ok(Some("{git}".to_string())));
+        assert_eq!(cli.format.as_deref(), Some("{path}"));
+        assert_eq!(cli.extra_formats, ["{git}"]);
+        assert!(parse_args_from(["prmt", "-f", "-", "-f", "-"]).is_err());
+    }
+
+    #[test]
+    fn json_output_is_one_document() {
+        let segments = |text: &str| serde_json::json!([{ "module": null, "text": text }]);
+        let single = json_output(vec![segments("a")], false);
+        assert_eq!(
+            serde_json::from_str::<serde_json::Value>(&single).unwrap(),
+            segments("a")
+        );
+
+        let several = json_output(vec![segments("a"), segments("b"), segments("r")], true);
+        let parsed: serde_json::Value = serde_json::from_str(&several).expect("valid JSON");
+        assert_eq!(
+            parsed["prompts"],
+            serde_json::json!([segments("a"), segments("b")])
+        );
+        assert_eq!(parsed["right"], segments("r"));
+
+        let parsed: serde_json::Value =
+            serde_json::from_str(&json_output(vec![segments("a"), segments("b")], false))
+                .expect("valid JSON");
+        assert!(parsed.get("right").is_none());
+    }
+
+    #[test]
+    fn join_outputs_separates_prompts_before_the_right_prompt() {
+        let outputs = |items: &[&str]| items.iter().map(|item| item.to_string()).collect();
+        assert_eq!(join_outputs(outputs(&["a", "b"]), false, "\0"), "a\0b");
+        assert_eq!(
+            join_outputs(outputs(&["a", "b", "r"]), true, "\n"),
+            format!("a\nb{}r", RIGHT_PROMPT_SEPARATOR)
+        );
+        assert_eq!(
+            join_outputs(outputs(&["a", "r"]), true, "\n"),
+            format!("a{}r", RIGHT_PROMPT_SEPARATOR)
+        );
+    }
+
+    #[test]
+    fn parse_args_reads_format_files() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("prompt.fmt");
+        std::fs::write(&path, "{path:cyan}\n{ok:green}{fail:red} \n").unwrap();
+
+        let cli = parse_args_from([
+            "prmt".into(),
+            std::ffi::OsString::from("--format-file"),
+            path.clone().into(),
+            "-f".into(),
+            "{git}".into(),
+        ])
+        .expect("parse args");
+        assert_eq!(
+            cli.format.as_deref(),
+            Some("{path:cyan}\n{ok:green}{fail:red} ")
+        );
+        assert_eq!(cli.extra_formats, ["{git}"]);
+
+        let missing = dir.path().join("missing.fmt");
+        let err = parse_args_from([
+            "prmt".into(),
+            "--format-file".into(),
+            missing.into_os_string(),
+        ])
+        .err()
+        .expect("missing file");
+        assert!(err.to_string().contains("missing.fmt"));
+    }
+
+    #[test]
+    fn format_from_file_drops_one_trailing_line_ending() {
+        assert_eq!(format_from_file("{path}\r\n".to_string()), "{path}");
+        assert_eq!(format_from_file("{path}\n\n".to_string()), "{path}\n");
+        assert_eq!(format_from_file("{path}".to_string()), "{path}");
+    }
+
+    #[test]
+    fn parse_args_accepts_preserve_code() {
+        let cli = parse_args_from(["prmt", "--code", "130", "--preserve-code", "{ok}"])
+            .expect("parse args");
+        assert!(cli.preserve_code);
+        assert_eq!(cli.code, Some(130));
+        assert_eq!(preserved_exit_code(130), ExitCode::from(130));
+        assert_eq!(preserved_exit_code(256 + 2), ExitCode::from(2));
+    }
+
+    #[test]
+    fn parse_args_accepts_warn_slow_thresholds() {
+        let cli = parse_args_from(["prmt", "--warn-slow", "50ms", "{git}"]).expect("parse args");
+        assert_eq!(cli.warn_slow, Some(Duration::from_millis(50)));
+        assert_eq!(parse_threshold("2s"), Ok(Duration::from_secs(2)));
+        assert_eq!(parse_threshold("75"), Ok(Duration::from_millis(75)));
+        assert!(parse_threshold("fast").is_err());
+        assert!(parse_args_from(["prmt", "--warn-slow", "1m", "{git}"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_accepts_marks() {
+        let cli = parse_args_from(["prmt", "--marks", "{path}"]).expect("parse args");
+        assert!(cli.marks);
+    }
+
+    #[test]
+    fn parse_args_accepts_max_width() {
+        let cli = parse_args_from(["prmt", "--max-width", "auto"]).expect("parse args");
+        assert_eq!(cli.max_width, Some(width::MaxWidth::Auto));
+
+        let cli = parse_args_from(["prmt", "--max-width", "40"]).expect("parse args");
+        assert_eq!(cli.max_width, Some(width::MaxWidth::Columns(40)));
+    }
+
+    #[test]
+    fn parse_args_recognizes_check_command() {
+        let cli = parse_args_from(["prmt", "check", "{path}"]).expect("parse args");
+
+        assert_eq!(cli.command, Some(Command::Check));
+        assert_eq!(cli.format.as_deref(), Some("{path}"));
+    }
+
+    #[test]
+    fn parse_args_recognizes_init_command() {
+        let cli = parse_args_from(["prmt", "init", "zsh"]).expect("parse args");
+
+        assert_eq!(cli.command, Some(Command::Init));
+        assert_eq!(cli.init_shell, Some(init::InitShell::Zsh));
+        assert!(cli.format.is_none());
+        assert!(parse_args_from(["prmt", "init", "tcsh"]).is_err());
+        assert!(parse_args_from(["prmt", "init", "zsh", "{path}"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_recognizes_modules_command() {
+        let cli = parse_args_from(["prmt", "modules"]).expect("parse args");
+        assert_eq!(cli.command, Some(Command::Modules));
+        let cli = parse_args_from(["prmt", "--list-modules"]).expect("parse args");
+        assert_eq!(cli.command, Some(Command::Modules));
+        assert!(parse_args_from(["prmt", "modules", "{path}"]).is_err());
+    }
+
+    #[test]
+    fn modules_listing_shows_formats_and_markers() {
+        let listing = modules_listing();
+        assert!(listing.starts_with("path       relative, r, absolute, a"));
+        assert!(listing.contains("\nrust       full, f, short, s, major, m, edition, e,"));
+        assert!(listing.contains("\n           markers: Cargo.toml\n"));
+        assert!(listing.contains("\njson       Provide a dot-path"));
+    }
+
+    #[test]
+    fn parse_args_recognizes_trust_command() {
+        let cli = parse_args_from(["prmt", "trust"]).expect("parse args");
+        assert_eq!(cli.command, Some(Command::Trust));
+        assert!(cli.command_path.is_none());
+
+        let cli = parse_args_from(["prmt", "trust", "ops/.prmt.toml"]).expect("parse args");
+        assert_eq!(cli.command_path.as_deref(), Some("ops/.prmt.toml"));
+        assert!(cli.format.is_none());
+        assert!(parse_args_from(["prmt", "trust", "a", "b"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_recognizes_import_command() {
+        let cli = parse_args_from(["prmt", "import", "starship", "s.toml"]).expect("parse args");
+
+        assert_eq!(cli.command, Some(Command::Import));
+        assert_eq!(cli.import_source, Some(import::Source::Starship));
+        assert_eq!(cli.command_path.as_deref(), Some("s.toml"));
+        assert!(parse_args_from(["prmt", "import", "oh-my-posh"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_recognizes_config_show_command() {
+        let cli = parse_args_from(["prmt", "config", "show", "--shell", "zsh", "{git}"])
+            .expect("parse args");
+
+        assert_eq!(cli.command, Some(Command::ConfigShow));
+        assert_eq!(cli.format.as_deref(), Some("{git}"));
+        assert!(parse_args_from(["prmt", "config", "list"]).is_err());
+    }
+
+    #[test]
+    fn parse_args_accepts_check_config() {
+        let cli = parse_args_from(["prmt", "check", "--config"]).expect("parse args");
+        assert_eq!(cli.command, Some(Command::Check));
+        assert!(cli.check_config);
+        assert!(cli.config_file.is_none());
+
+        let cli = parse_args_from(["prmt", "check", "--config=team.toml"]).expect("parse args");
+        assert_eq!(cli.config_file.as_deref(), Some("team.toml"));
+    }
+
+    #[test]
+    fn parse_args_treats_check_after_format_as_error() {
+        let err = match parse_args_from(["prmt", "{path}", "check"]) {
+            Ok(_) => panic!("expected error"),
+            Err(err) => err,
+        };
+
+        assert_eq!(err.to_string(), "unexpected argument \"check\"");
+    }
+
     #[test]
     fn parse_args_rejects_multiple_positional_formats() {
         let err = match parse_args_from(["prmt", "{path}", "{git}"]) {
@@ -1,28 +1,108 @@
+use crate::cache;
 use once_cell::sync::Lazy;
 use std::collections::HashMap;
 use std::path::{Path, PathBuf};
-use std::sync::{Arc, OnceLock, RwLock};
+use std::sync::{Arc, RwLock};
+use std::time::Instant;
 
-pub type VersionSlot = OnceLock<Option<Arc<str>>>;
+/// A fetched version: when it was fetched, the tool key it was fetched for, and the value.
+#[derive(Clone)]
+pub struct VersionMemo {
+    fetched: Instant,
+    tool: String,
+    value: Option<Arc<str>>,
+}
+
+impl VersionMemo {
+    /// The value, unless it is older than the cache TTL or was fetched for another tool key,
+    /// so a long-running process picks up upgrades like a new shell would.
+    fn get(&self, tool: &str) -> Option<Option<Arc<str>>> {
+        (self.tool == tool && self.fetched.elapsed() < cache::ttl()).then(|| self.value.clone())
+    }
+}
+
+pub type VersionSlot = RwLock<Option<VersionMemo>>;
 
-pub static NODE_VERSION: VersionSlot = OnceLock::new();
-pub static RUST_VERSION: VersionSlot = OnceLock::new();
-pub static PYTHON_VERSION: VersionSlot = OnceLock::new();
-pub static GO_VERSION: VersionSlot = OnceLock::new();
-pub static DENO_VERSION: VersionSlot = OnceLock::new();
-pub static BUN_VERSION: VersionSlot = OnceLock::new();
-pub static ELIXIR_VERSION: VersionSlot = OnceLock::new();
+pub static RUST_VERSION: VersionSlot = RwLock::new(None);
+pub static PYTHON_VERSION: VersionSlot = RwLock::new(None);
+pub static GO_VERSION: VersionSlot = RwLock::new(None);
+pub static DENO_VERSION: VersionSlot = RwLock::new(None);
+pub static BUN_VERSION: VersionSlot = RwLock::new(None);
+pub static ELIXIR_VERSION: VersionSlot = RwLock::new(None);
+pub static TERRAFORM_VERSION: VersionSlot = RwLock::new(None);
 
-pub fn memoized_version<F>(slot: &VersionSlot, fetch: F) -> Option<Arc<str>>
+/// Version of `tool`, fetched once per process until the cache TTL passes. Successful
+/// fetches also go through the on-disk [`cache`], so later processes can skip `fetch`
+/// entirely.
+pub fn memoized_version<F>(slot: &VersionSlot, tool: &str, fetch: F) -> Option<Arc<str>>
 where
     F: FnOnce() -> Option<String>,
 {
-    if let Some(value) = slot.get() {
-        return value.clone();
+    if let Some(value) = slot.read().ok().and_then(|memo| memo.as_ref()?.get(tool)) {
+        return value;
     }
 
-    let value = fetch().map(|v| Arc::<str>::from(v.into_boxed_str()));
-    let _ = slot.set(value.clone());
+    let value = fetch_cached(tool, fetch);
+    if let Ok(mut memo) = slot.write() {
+        *memo = Some(VersionMemo {
+            fetched: Instant::now(),
+            tool: tool.to_string(),
+            value: value.clone(),
+        });
+    }
+    value
+}
+
+fn fetch_cached<F>(tool: &str, fetch: F) -> Option<Arc<str>>
+where
+    F: FnOnce() -> Option<String>,
+{
+    let key = cache::version_key(tool);
+    let value = match cache::version(&key) {
+        Some(version) => Some(version),
+        None => {
+            let version = fetch();
+            if let Some(version) = &version {
+                cache::store_version(&key, version);
+            }
+            version
+        }
+    };
+    value.map(|v| Arc::<str>::from(v.into_boxed_str()))
+}
+
+/// Versions memoized per binary, for tools whose binary on `PATH` can change while the
+/// process runs.
+pub type VersionMap = Lazy<RwLock<HashMap<String, VersionMemo>>>;
+
+pub static NODE_VERSIONS: VersionMap = Lazy::new(|| RwLock::new(HashMap::new()));
+
+/// Like [`memoized_version`], but memoized under `tool`, which names the resolved binary.
+pub fn memoized_version_for<F>(versions: &VersionMap, tool: &str, fetch: F) -> Option<Arc<str>>
+where
+    F: FnOnce() -> Option<String>,
+{
+    if let Some(value) = versions
+        .read()
+        .ok()
+        .and_then(|map| map.get(tool)?.get(tool))
+    {
+        return value;
+    }
+
+    let value = fetch_cached(tool, fetch);
+    if let Ok(mut map) = versions.write() {
+        let now = Instant::now();
+        map.retain(|_, memo| now.duration_since(memo.fetched) < cache::ttl());
+        map.insert(
+            tool.to_string(),
+            VersionMemo {
+                fetched: now,
+                tool: tool.to_string(),
+                value: value.clone(),
+            },
+        );
+    }
     value
 }
 
@@ -73,9 +153,9 @@ mod tests {
 
     #[test]
     fn memoized_version_caches_successful_fetches() {
-        let slot: VersionSlot = OnceLock::new();
+        let slot: VersionSlot = RwLock::new(None);
         let calls = AtomicUsize::new(0);
-        let value = memoized_version(&slot, || {
+        let value = memoized_version(&slot, "test", || {
             calls.fetch_add(1, Ordering::SeqCst);
             Some("1.2.3".to_string())
         })
@@ -83,7 +163,7 @@ mod tests {
         assert_eq!(calls.load(Ordering::SeqCst), 1);
         assert_eq!(value.as_ref(), "1.2.3");
 
-        let second = memoized_version(&slot, || {
+        let second = memoized_version(&slot, "test", || {
             calls.fetch_add(1, Ordering::SeqCst);
             Some("should not run".to_string())
         })
@@ -92,18 +172,49 @@ mod tests {
         assert!(Arc::ptr_eq(&value, &second));
     }
 
+    #[test]
+    fn memoized_version_for_keeps_one_value_per_binary() {
+        let versions: VersionMap = Lazy::new(|| RwLock::new(HashMap::new()));
+        let first = memoized_version_for(&versions, "node+/a@1", || Some("20.0.0".to_string()));
+        let second = memoized_version_for(&versions, "node+/b@1", || Some("22.0.0".to_string()));
+        let again = memoized_version_for(&versions, "node+/a@1", || Some("unexpected".to_string()));
+
+        assert_eq!(first.as_deref(), Some("20.0.0"));
+        assert_eq!(second.as_deref(), Some("22.0.0"));
+        assert_eq!(again.as_deref(), Some("20.0.0"));
+    }
+
+    #[test]
+    fn memoized_versions_expire_and_follow_the_tool_key() {
+        let Some(fetched) = Instant::now().checked_sub(cache::ttl()) else {
+            return;
+        };
+        let slot: VersionSlot = RwLock::new(Some(VersionMemo {
+            fetched,
+            tool: "test".to_string(),
+            value: Some(Arc::from("1.0.0")),
+        }));
+        let value = memoized_version(&slot, "test", || Some("2.0.0".to_string()));
+        assert_eq!(value.as_deref(), Some("2.0.0"));
+
+        let value = memoized_version(&slot, "test+other", || Some("3.0.0".to_string()));
+        assert_eq!(value.as_deref(), Some("3.0.0"));
+        let again = memoized_version(&slot, "test+other", || Some("unexpected".to_string()));
+        assert_eq!(again.as_deref(), Some("3.0.0"));
+    }
+
     #[test]
     fn memoized_version_caches_absence() {
-        let slot: VersionSlot = OnceLock::new();
+        let slot: VersionSlot = RwLock::new(None);
         let calls = AtomicUsize::new(0);
-        let value = memoized_version(&slot, || {
+        let value = memoized_version(&slot, "test", || {
             calls.fetch_add(1, Ordering::SeqCst);
             None
         });
         assert!(value.is_none());
         assert_eq!(calls.load(Ordering::SeqCst), 1);
 
-        let second = memoized_version(&slot, || {
+        let second = memoized_version(&slot, "test", || {
             calls.fetch_add(1, Ordering::SeqCst);
             Some("unexpected".to_string())
         });
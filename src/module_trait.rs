@@ -1,34 +1,68 @@
-use crate::detector::DetectionContext;
+use crate::detector::{DetectionContext, detect_from};
 use crate::error::Result;
 use crate::style::Shell;
-use std::env;
+use std::collections::{HashMap, HashSet};
 use std::path::{Path, PathBuf};
-use std::sync::Arc;
+use std::sync::{Arc, OnceLock};
+use std::time::Duration;
 
+const PROJECT_MARKER: &str = ".git";
+
+/// The project root of a [`ModuleContext`], looked up on first use and shared by its clones.
+#[derive(Debug, Clone, Default)]
+pub struct ProjectRoot(Arc<OnceLock<Option<PathBuf>>>);
+
+/// What a module renders for. Fields are added as modules need more data, so outside this
+/// crate start from [`ModuleContext::new`] or [`Default`] and set the fields you need.
 #[derive(Debug, Clone)]
+#[non_exhaustive]
 pub struct ModuleContext {
     pub no_version: bool,
     pub exit_code: Option<i32>,
+    pub duration: Option<Duration>,
+    pub command: Option<String>,
     pub detection: DetectionContext,
     pub shell: Shell,
     pub stdin_data: Option<Arc<serde_json::Value>>,
+    /// The directory prompted for, resolved once so every module sees the same one even when
+    /// the host process changes directories mid-render.
     pub cwd: Option<PathBuf>,
+    /// Whether `cwd` is the shell's `$PWD` because the process's working directory was
+    /// deleted under it.
+    pub cwd_deleted: bool,
+    pub project_root: ProjectRoot,
+    /// Free-form values from the caller, such as `--set jobs=2`, for modules that need data
+    /// the fields above do not cover.
+    pub values: Arc<HashMap<String, String>>,
 }
 
 impl Default for ModuleContext {
     fn default() -> Self {
+        let (cwd, cwd_deleted) = crate::modules::utils::working_dir();
+        let mut context = Self::new(cwd);
+        context.cwd_deleted = cwd_deleted;
+        context
+    }
+}
+
+impl ModuleContext {
+    /// A context for prompting in `cwd`, with no markers detected and no last command.
+    pub fn new(cwd: Option<PathBuf>) -> Self {
         Self {
             no_version: false,
             exit_code: None,
+            duration: None,
+            command: None,
             detection: DetectionContext::default(),
             shell: Shell::None,
             stdin_data: None,
-            cwd: env::current_dir().ok(),
+            cwd,
+            cwd_deleted: false,
+            project_root: ProjectRoot::default(),
+            values: Arc::default(),
         }
     }
-}
 
-impl ModuleContext {
     pub fn marker_path(&self, marker: &str) -> Option<&Path> {
         self.detection.get(marker)
     }
@@ -36,6 +70,31 @@ impl ModuleContext {
     pub fn current_dir(&self) -> Option<&Path> {
         self.cwd.as_deref()
     }
+
+    /// The repository containing [`current_dir`](Self::current_dir): the directory of the
+    /// nearest `.git`, reusing the detected marker when a module asked for it.
+    pub fn project_root(&self) -> Option<&Path> {
+        self.project_root
+            .0
+            .get_or_init(|| {
+                let marker = match self.marker_path(PROJECT_MARKER) {
+                    Some(marker) => marker.to_path_buf(),
+                    None => {
+                        let required = HashSet::from([PROJECT_MARKER]);
+                        detect_from(&required, self.current_dir()?)
+                            .get(PROJECT_MARKER)?
+                            .to_path_buf()
+                    }
+                };
+                marker.parent().map(Path::to_path_buf)
+            })
+            .as_deref()
+    }
+
+    #[allow(dead_code)]
+    pub fn value(&self, key: &str) -> Option<&str> {
+        self.values.get(key).map(String::as_str)
+    }
 }
 
 pub trait Module: Send + Sync {
@@ -47,7 +106,38 @@ pub trait Module: Send + Sync {
         false
     }
 
+    /// Describes the formats `validate_format` accepts, for `prmt modules`.
+    fn formats(&self) -> &'static str {
+        "any"
+    }
+
+    /// Checks a placeholder format without rendering, so templates can be linted.
+    fn validate_format(&self, format: &str) -> Result<()> {
+        let _ = format;
+        Ok(())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>>;
 }
 
 pub type ModuleRef = Arc<dyn Module>;
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::fs;
+
+    #[test]
+    fn project_root_is_the_nearest_git_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        let nested = dir.path().join("src").join("modules");
+        fs::create_dir_all(&nested).unwrap();
+        fs::create_dir(dir.path().join(".git")).unwrap();
+
+        let context = ModuleContext::new(Some(nested));
+        assert_eq!(context.project_root(), Some(dir.path()));
+        assert_eq!(context.clone().project_root(), Some(dir.path()));
+
+        assert_eq!(ModuleContext::new(None).project_root(), None);
+    }
+}
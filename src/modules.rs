@@ -1,15 +1,29 @@
 pub mod bun;
+pub mod cloud;
+pub mod cmd;
+pub mod command;
 pub mod deno;
+pub mod duration;
 pub mod elixir;
 pub mod env;
 pub mod fail;
 pub mod git;
 pub mod go;
+pub mod guix;
+pub mod host;
+pub mod java;
 pub mod json;
 pub mod node;
 pub mod ok;
 pub mod path;
+pub mod plugin;
+pub mod pm;
 pub mod python;
 pub mod rust;
+pub mod status;
+pub mod terraform;
 pub mod time;
+pub mod user;
 pub mod utils;
+#[cfg(feature = "wasm-plugins")]
+pub mod wasm;
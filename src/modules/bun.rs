@@ -2,7 +2,7 @@ use crate::error::Result;
 use crate::memo::{BUN_VERSION, memoized_version};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
-use std::process::Command;
+use crate::trace::trace_spawn;
 
 const BUN_MARKERS: &[&str] = &["bun.lock", "bun.lockb", "bunfig.toml"];
 
@@ -21,6 +21,10 @@ impl BunModule {
 }
 
 impl Module for BunModule {
+    fn formats(&self) -> &'static str {
+        utils::VERSION_FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         BUN_MARKERS
     }
@@ -29,6 +33,10 @@ impl Module for BunModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        utils::validate_version_format(format, "bun").map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
         let has_marker = BUN_MARKERS
             .iter()
@@ -45,7 +53,8 @@ impl Module for BunModule {
         // Validate and normalize format
         let normalized_format = utils::validate_version_format(format, "bun")?;
 
-        let version = match memoized_version(&BUN_VERSION, get_bun_version) {
+        let tool = utils::tool_key("bun", utils::resolve_binary("bun").as_deref());
+        let version = match memoized_version(&BUN_VERSION, &tool, get_bun_version) {
             Some(v) => v,
             None => return Ok(None),
         };
@@ -62,7 +71,8 @@ impl Module for BunModule {
 
 #[cold]
 fn get_bun_version() -> Option<String> {
-    let output = Command::new("bun").arg("--version").output().ok()?;
+    trace_spawn!("bun");
+    let output = utils::tool_command("bun").arg("--version").output().ok()?;
     if !output.status.success() {
         return None;
     }
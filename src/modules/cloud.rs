@@ -0,0 +1,231 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use std::sync::OnceLock;
+
+const FORMATS: &str = "short (default, e.g. aws), full (e.g. Amazon EC2), icon (Nerd Font glyph)";
+
+/// Firmware strings the hypervisor exposes, readable without root.
+#[cfg(target_os = "linux")]
+const DMI_DIR: &str = "/sys/class/dmi/id";
+/// Xen guests on older EC2 instance types have no DMI vendor, but their UUID starts with `ec2`.
+#[cfg(target_os = "linux")]
+const XEN_UUID: &str = "/sys/hypervisor/uuid";
+/// Asset tag Azure writes into the chassis of every VM.
+const AZURE_ASSET_TAG: &str = "7783-7084-3265-9085-8269-3286-77";
+
+#[derive(Debug, PartialEq)]
+struct Provider {
+    short: &'static str,
+    full: &'static str,
+    glyph: &'static str,
+}
+
+const AWS: Provider = Provider {
+    short: "aws",
+    full: "Amazon EC2",
+    glyph: "\u{e7ad}",
+};
+const GCP: Provider = Provider {
+    short: "gcp",
+    full: "Google Compute Engine",
+    glyph: "\u{f11f6}",
+};
+const AZURE: Provider = Provider {
+    short: "azure",
+    full: "Microsoft Azure",
+    glyph: "\u{f0805}",
+};
+const DIGITALOCEAN: Provider = Provider {
+    short: "do",
+    full: "DigitalOcean",
+    glyph: "\u{f0c2}",
+};
+const HETZNER: Provider = Provider {
+    short: "hetzner",
+    full: "Hetzner Cloud",
+    glyph: "\u{f0c2}",
+};
+const ORACLE: Provider = Provider {
+    short: "oci",
+    full: "Oracle Cloud",
+    glyph: "\u{f0c2}",
+};
+const ALIBABA: Provider = Provider {
+    short: "aliyun",
+    full: "Alibaba Cloud",
+    glyph: "\u{f0c2}",
+};
+
+static PROVIDER: OnceLock<Option<&'static Provider>> = OnceLock::new();
+
+/// Shows which cloud the machine runs in, e.g. `{cloud:red}` for `aws`, so a shell on a
+/// cloud VM stands out. Detection only reads firmware strings and never calls a metadata
+/// service, so it costs no network round trip.
+pub struct CloudModule;
+
+impl Default for CloudModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CloudModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// The provider named by the DMI fields `dmi` returns, and the Xen UUID for old EC2 guests.
+fn detect_provider(
+    dmi: impl Fn(&str) -> Option<String>,
+    xen_uuid: Option<String>,
+) -> Option<&'static Provider> {
+    let field = |name: &str| dmi(name).map(|value| value.trim().to_string());
+    let sys_vendor = field("sys_vendor").unwrap_or_default();
+    let product_name = field("product_name").unwrap_or_default();
+    let asset_tag = field("chassis_asset_tag").unwrap_or_default();
+    let amazon_firmware = ["bios_vendor", "product_version"]
+        .iter()
+        .any(|name| field(name).is_some_and(|value| value.to_ascii_lowercase().contains("amazon")));
+
+    if sys_vendor == "Amazon EC2"
+        || amazon_firmware
+        || xen_uuid.is_some_and(|uuid| uuid.trim().to_ascii_lowercase().starts_with("ec2"))
+    {
+        Some(&AWS)
+    } else if sys_vendor == "Google" || product_name == "Google Compute Engine" {
+        Some(&GCP)
+    } else if asset_tag == AZURE_ASSET_TAG {
+        Some(&AZURE)
+    } else if sys_vendor == "DigitalOcean" {
+        Some(&DIGITALOCEAN)
+    } else if sys_vendor == "Hetzner" {
+        Some(&HETZNER)
+    } else if asset_tag == "OracleCloud.com" {
+        Some(&ORACLE)
+    } else if sys_vendor == "Alibaba Cloud" || product_name.starts_with("Alibaba Cloud") {
+        Some(&ALIBABA)
+    } else {
+        None
+    }
+}
+
+#[cfg(target_os = "linux")]
+fn local_provider() -> Option<&'static Provider> {
+    let read = |path: &str| std::fs::read_to_string(path).ok();
+    detect_provider(
+        |name| read(&format!("{}/{}", DMI_DIR, name)),
+        read(XEN_UUID),
+    )
+}
+
+/// Firmware strings are only read on Linux.
+#[cfg(not(target_os = "linux"))]
+fn local_provider() -> Option<&'static Provider> {
+    detect_provider(|_| None, None)
+}
+
+fn validate_cloud_format(format: &str) -> Result<()> {
+    match format {
+        "" | "short" | "s" | "full" | "f" | "icon" | "i" => Ok(()),
+        _ => Err(PromptError::InvalidFormat {
+            module: "cloud".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+impl Module for CloudModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        validate_cloud_format(format)
+    }
+
+    fn render(&self, format: &str, _context: &ModuleContext) -> Result<Option<String>> {
+        validate_cloud_format(format)?;
+        let Some(provider) = *PROVIDER.get_or_init(local_provider) else {
+            return Ok(None);
+        };
+        let text = match format {
+            "full" | "f" => provider.full,
+            "icon" | "i" => provider.glyph,
+            _ => provider.short,
+        };
+        Ok(Some(text.to_string()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn detect(fields: &[(&str, &str)], xen_uuid: Option<&str>) -> Option<&'static str> {
+        detect_provider(
+            |name| {
+                fields
+                    .iter()
+                    .find(|(field, _)| *field == name)
+                    .map(|(_, value)| format!("{}\n", value))
+            },
+            xen_uuid.map(String::from),
+        )
+        .map(|provider| provider.short)
+    }
+
+    #[test]
+    fn detects_providers_from_firmware_strings() {
+        assert_eq!(detect(&[("sys_vendor", "Amazon EC2")], None), Some("aws"));
+        assert_eq!(
+            detect(&[("product_version", "4.11.amazon")], None),
+            Some("aws")
+        );
+        assert_eq!(
+            detect(&[], Some("ec2e1916-9099-7caf-fd21-012345abcdef")),
+            Some("aws")
+        );
+        assert_eq!(
+            detect(&[("product_name", "Google Compute Engine")], None),
+            Some("gcp")
+        );
+        assert_eq!(
+            detect(
+                &[
+                    ("sys_vendor", "Microsoft Corporation"),
+                    ("chassis_asset_tag", AZURE_ASSET_TAG)
+                ],
+                None
+            ),
+            Some("azure")
+        );
+        assert_eq!(detect(&[("sys_vendor", "DigitalOcean")], None), Some("do"));
+    }
+
+    #[test]
+    fn local_machines_are_not_clouds() {
+        assert_eq!(detect(&[], None), None);
+        assert_eq!(
+            detect(
+                &[
+                    ("sys_vendor", "Microsoft Corporation"),
+                    ("product_name", "Virtual Machine")
+                ],
+                None
+            ),
+            None
+        );
+        assert_eq!(
+            detect(&[("sys_vendor", "LENOVO")], Some("4c4c4544-0000")),
+            None
+        );
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        assert!(CloudModule.validate_format("icon").is_ok());
+        assert!(CloudModule.validate_format("region").is_err());
+    }
+}
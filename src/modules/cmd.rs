@@ -0,0 +1,247 @@
+use crate::error::{PromptError, Result};
+use crate::executor::MAX_SEGMENT_LEN;
+use crate::module_trait::{Module, ModuleContext};
+use crate::trace::trace_spawn;
+use std::io::{self, Read};
+#[cfg(unix)]
+use std::os::unix::process::CommandExt;
+use std::path::Path;
+use std::process::{Child, Command, Stdio};
+use std::sync::mpsc;
+use std::thread;
+use std::time::{Duration, Instant};
+
+const TIMEOUT_ENV: &str = "PRMT_CMD_TIMEOUT";
+const DEFAULT_TIMEOUT: Duration = Duration::from_millis(500);
+const POLL_INTERVAL: Duration = Duration::from_millis(5);
+const FORMATS: &str = "Provide a shell command, e.g., {cmd:blue:hostname -s}";
+
+/// Runs the format as a shell command and shows its trimmed output, e.g.
+/// `{cmd:blue:kubectl config current-context}`.
+pub struct CmdModule;
+
+impl Default for CmdModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl CmdModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn validate_cmd_format(format: &str) -> Result<()> {
+    if format.trim().is_empty() {
+        return Err(PromptError::InvalidFormat {
+            module: "cmd".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        });
+    }
+    Ok(())
+}
+
+/// How long a command or plugin may run before it is killed: `PRMT_CMD_TIMEOUT` in ms, else
+/// 500ms.
+pub(crate) fn command_timeout() -> Duration {
+    std::env::var(TIMEOUT_ENV)
+        .ok()
+        .and_then(|ms| ms.parse().ok())
+        .map_or(DEFAULT_TIMEOUT, Duration::from_millis)
+}
+
+fn shell_command(command: &str) -> Command {
+    if cfg!(windows) {
+        let mut shell = Command::new("cmd");
+        shell.args(["/C", command]);
+        shell
+    } else {
+        let mut shell = Command::new("sh");
+        shell.args(["-c", command]);
+        shell
+    }
+}
+
+fn run_command(command: &str, cwd: Option<&Path>, timeout: Duration) -> Option<String> {
+    run_with_timeout(shell_command(command), cwd, timeout)
+}
+
+/// Trimmed stdout of `command`, cut just past [`MAX_SEGMENT_LEN`] bytes, or `None` when it
+/// fails, prints nothing, or is still running after `timeout`, in which case it is killed.
+pub(crate) fn run_with_timeout(
+    mut command: Command,
+    cwd: Option<&Path>,
+    timeout: Duration,
+) -> Option<String> {
+    trace_spawn!(command.get_program().to_string_lossy());
+    command
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::null());
+    // A deleted prompt directory cannot be entered, but the inherited one still works.
+    if let Some(cwd) = cwd.filter(|cwd| cwd.is_dir()) {
+        command.current_dir(cwd);
+    }
+    // A group of its own, so a timeout also kills whatever the command started.
+    #[cfg(unix)]
+    command.process_group(0);
+    let mut child = command.spawn().ok()?;
+
+    // Read on another thread so a chatty command cannot fill the pipe and stall. Only what
+    // fits in a segment is kept; the rest is drained rather than left to break the pipe, so
+    // a command that prints a lot and exits still shows its first part.
+    let mut stdout = child.stdout.take()?;
+    let (output_tx, output_rx) = mpsc::channel();
+    thread::spawn(move || {
+        let mut output = Vec::new();
+        let _ = (&mut stdout)
+            .take(MAX_SEGMENT_LEN as u64 + 1)
+            .read_to_end(&mut output);
+        let _ = output_tx.send(output);
+        let _ = io::copy(&mut stdout, &mut io::sink());
+    });
+
+    let deadline = Instant::now() + timeout;
+    let status = loop {
+        match child.try_wait() {
+            Ok(Some(status)) => break status,
+            Ok(None) if Instant::now() < deadline => thread::sleep(POLL_INTERVAL),
+            _ => {
+                kill(&mut child);
+                let _ = child.wait();
+                return None;
+            }
+        }
+    };
+    if !status.success() {
+        return None;
+    }
+
+    // A background process started by the command may keep the pipe open.
+    let remaining = deadline.saturating_duration_since(Instant::now());
+    let output = output_rx.recv_timeout(remaining.max(POLL_INTERVAL)).ok()?;
+    let text = String::from_utf8_lossy(&output).trim().to_string();
+    (!text.is_empty()).then_some(text)
+}
+
+/// Kills `child` and, on Unix, the rest of its process group.
+fn kill(child: &mut Child) {
+    #[cfg(unix)]
+    if let Ok(group) = libc::pid_t::try_from(child.id()) {
+        // SAFETY: `killpg` only sends a signal; the group is the one `child` leads.
+        unsafe { libc::killpg(group, libc::SIGKILL) };
+    }
+    let _ = child.kill();
+}
+
+impl Module for CmdModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        validate_cmd_format(format)
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        validate_cmd_format(format)?;
+        Ok(run_command(
+            format,
+            context.current_dir(),
+            command_timeout(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn empty_command_is_rejected() {
+        assert!(CmdModule.validate_format("").is_err());
+        assert!(CmdModule.validate_format("  ").is_err());
+        assert!(CmdModule.validate_format("hostname").is_ok());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn renders_trimmed_stdout() {
+        let output = run_command("printf '  pod-1\\n'", None, Duration::from_secs(5));
+        assert_eq!(output.as_deref(), Some("pod-1"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn long_output_is_kept_up_to_a_segment() {
+        let output = run_command(
+            "head -c 50000000 /dev/zero | tr '\\0' x",
+            None,
+            Duration::from_secs(10),
+        );
+        assert_eq!(output.map(|text| text.len()), Some(MAX_SEGMENT_LEN + 1));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn failing_or_silent_commands_are_hidden() {
+        let timeout = Duration::from_secs(5);
+        assert_eq!(run_command("echo partial; exit 3", None, timeout), None);
+        assert_eq!(run_command("true", None, timeout), None);
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn slow_commands_are_killed_at_the_timeout() {
+        let started = Instant::now();
+        let output = run_command("sleep 5; echo late", None, Duration::from_millis(50));
+        assert_eq!(output, None);
+        assert!(started.elapsed() < Duration::from_secs(2));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn timeout_kills_processes_the_command_started() {
+        let dir = tempfile::tempdir().unwrap();
+        let pid_file = dir.path().join("pid");
+        let command = format!("sleep 30 & echo $! > '{}'; wait", pid_file.display());
+        assert_eq!(
+            run_command(&command, None, Duration::from_millis(200)),
+            None
+        );
+
+        let pid: libc::pid_t = std::fs::read_to_string(&pid_file)
+            .unwrap()
+            .trim()
+            .parse()
+            .unwrap();
+        // Once killed, the orphan is gone, or a zombie until init gets to it.
+        let is_running = || {
+            // SAFETY: signal 0 only checks that the process exists.
+            let exists = unsafe { libc::kill(pid, 0) } == 0;
+            exists
+                && std::fs::read_to_string(format!("/proc/{pid}/stat"))
+                    .map_or(true, |stat| !stat.contains(") Z "))
+        };
+        let deadline = Instant::now() + Duration::from_secs(2);
+        while is_running() && Instant::now() < deadline {
+            thread::sleep(POLL_INTERVAL);
+        }
+        assert!(!is_running(), "grandchild {pid} outlived the timeout");
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn runs_in_the_prompt_directory() {
+        let dir = tempfile::tempdir().unwrap();
+        std::fs::write(dir.path().join("marker"), "").unwrap();
+        let output = run_command("ls", Some(dir.path()), Duration::from_secs(5));
+        assert_eq!(output.as_deref(), Some("marker"));
+    }
+}
@@ -0,0 +1,61 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+
+const FORMATS: &str = "(none)";
+
+pub struct CommandModule;
+
+impl Default for CommandModule {
+    fn default() -> Self {
+        Self
+    }
+}
+
+impl Module for CommandModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        if format.is_empty() {
+            return Ok(());
+        }
+        Err(PromptError::InvalidFormat {
+            module: "command".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        })
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        self.validate_format(format)?;
+        Ok(context
+            .command
+            .as_deref()
+            .filter(|command| !command.is_empty())
+            .map(str::to_string))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn renders_last_command_name() {
+        let context = ModuleContext {
+            command: Some("cargo".to_string()),
+            ..ModuleContext::default()
+        };
+        assert_eq!(
+            CommandModule.render("", &context).unwrap(),
+            Some("cargo".to_string())
+        );
+    }
+
+    #[test]
+    fn hidden_without_command() {
+        let context = ModuleContext::default();
+        assert_eq!(CommandModule.render("", &context).unwrap(), None);
+    }
+}
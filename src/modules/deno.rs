@@ -2,7 +2,7 @@ use crate::error::Result;
 use crate::memo::{DENO_VERSION, memoized_version};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
-use std::process::Command;
+use crate::trace::trace_spawn;
 
 pub struct DenoModule;
 
@@ -19,6 +19,10 @@ impl DenoModule {
 }
 
 impl Module for DenoModule {
+    fn formats(&self) -> &'static str {
+        utils::VERSION_FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &["deno.json", "deno.jsonc"]
     }
@@ -27,6 +31,10 @@ impl Module for DenoModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        utils::validate_version_format(format, "deno").map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
         let has_marker = ["deno.json", "deno.jsonc"]
             .into_iter()
@@ -42,7 +50,8 @@ impl Module for DenoModule {
         // Validate and normalize format
         let normalized_format = utils::validate_version_format(format, "deno")?;
 
-        let version = match memoized_version(&DENO_VERSION, get_deno_version) {
+        let tool = utils::tool_key("deno", utils::resolve_binary("deno").as_deref());
+        let version = match memoized_version(&DENO_VERSION, &tool, get_deno_version) {
             Some(v) => v,
             None => return Ok(None),
         };
@@ -59,7 +68,8 @@ impl Module for DenoModule {
 
 #[cold]
 fn get_deno_version() -> Option<String> {
-    let output = Command::new("deno").arg("--version").output().ok()?;
+    trace_spawn!("deno");
+    let output = utils::tool_command("deno").arg("--version").output().ok()?;
     if !output.status.success() {
         return None;
     }
@@ -0,0 +1,93 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use std::time::Duration;
+
+const FORMATS: &str = "minimum seconds to show, e.g. 0 or 5 (default 2)";
+
+/// Hide durations shorter than this many seconds unless the format says otherwise.
+const DEFAULT_MIN_SECONDS: u64 = 2;
+
+pub struct DurationModule;
+
+impl Default for DurationModule {
+    fn default() -> Self {
+        Self
+    }
+}
+
+fn parse_min_seconds(format: &str) -> Result<u64> {
+    if format.is_empty() {
+        return Ok(DEFAULT_MIN_SECONDS);
+    }
+    format.parse().map_err(|_| PromptError::InvalidFormat {
+        module: "duration".to_string(),
+        format: format.to_string(),
+        valid_formats: FORMATS.to_string(),
+    })
+}
+
+fn humanize(duration: Duration) -> String {
+    let millis = duration.as_millis();
+    if millis < 1000 {
+        return format!("{}ms", millis);
+    }
+
+    let total = duration.as_secs();
+    let (hours, minutes, seconds) = (total / 3600, total / 60 % 60, total % 60);
+    if hours > 0 {
+        format!("{}h{}m{}s", hours, minutes, seconds)
+    } else if minutes > 0 {
+        format!("{}m{}s", minutes, seconds)
+    } else {
+        format!("{}s", seconds)
+    }
+}
+
+impl Module for DurationModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_min_seconds(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        let min_seconds = parse_min_seconds(format)?;
+        Ok(context
+            .duration
+            .filter(|duration| *duration >= Duration::from_secs(min_seconds))
+            .map(humanize))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: &str, millis: u64) -> Option<String> {
+        let context = ModuleContext {
+            duration: Some(Duration::from_millis(millis)),
+            ..ModuleContext::default()
+        };
+        DurationModule.render(format, &context).unwrap()
+    }
+
+    #[test]
+    fn hides_short_durations_by_default() {
+        assert_eq!(render("", 1500), None);
+        assert_eq!(render("", 2500), Some("2s".to_string()));
+        assert_eq!(render("0", 450), Some("450ms".to_string()));
+    }
+
+    #[test]
+    fn humanizes_long_durations() {
+        assert_eq!(render("", 63_000), Some("1m3s".to_string()));
+        assert_eq!(render("", 3_723_000), Some("1h2m3s".to_string()));
+    }
+
+    #[test]
+    fn rejects_non_numeric_format() {
+        assert!(DurationModule.validate_format("long").is_err());
+    }
+}
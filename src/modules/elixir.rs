@@ -2,7 +2,8 @@ use crate::error::Result;
 use crate::memo::{ELIXIR_VERSION, memoized_version};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
-use std::process::Command;
+use crate::trace::trace_spawn;
+use std::path::Path;
 
 pub struct ElixirModule;
 
@@ -19,6 +20,10 @@ impl ElixirModule {
 }
 
 impl Module for ElixirModule {
+    fn formats(&self) -> &'static str {
+        utils::VERSION_FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &["mix.exs"]
     }
@@ -27,10 +32,14 @@ impl Module for ElixirModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        utils::validate_version_format(format, "elixir").map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
-        if context.marker_path("mix.exs").is_none() {
+        let Some(mix_exs) = context.marker_path("mix.exs") else {
             return Ok(None);
-        }
+        };
 
         if context.no_version {
             return Ok(Some(String::new()));
@@ -38,7 +47,15 @@ impl Module for ElixirModule {
 
         let normalized_format = utils::validate_version_format(format, "elixir")?;
 
-        let version = match memoized_version(&ELIXIR_VERSION, get_elixir_version) {
+        // Version managers pick Elixir per project, so it runs where `mix.exs` is.
+        let project = mix_exs.parent().unwrap_or(mix_exs);
+        let tool = utils::project_tool_key(
+            "elixir",
+            utils::resolve_binary("elixir").as_deref(),
+            project,
+        );
+        let version = match memoized_version(&ELIXIR_VERSION, &tool, || get_elixir_version(project))
+        {
             Some(v) => v,
             None => return Ok(None),
         };
@@ -54,8 +71,13 @@ impl Module for ElixirModule {
 }
 
 #[cold]
-fn get_elixir_version() -> Option<String> {
-    let output = Command::new("elixir").arg("--version").output().ok()?;
+fn get_elixir_version(project: &Path) -> Option<String> {
+    trace_spawn!("elixir");
+    let output = utils::tool_command("elixir")
+        .arg("--version")
+        .current_dir(project)
+        .output()
+        .ok()?;
     if !output.status.success() {
         return None;
     }
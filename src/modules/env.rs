@@ -2,6 +2,8 @@ use crate::error::{PromptError, Result};
 use crate::module_trait::{Module, ModuleContext};
 use std::env;
 
+const FORMATS: &str = "Provide an environment variable name, e.g., {env:blue:USER}";
+
 pub struct EnvModule;
 
 impl Default for EnvModule {
@@ -16,16 +18,28 @@ impl EnvModule {
     }
 }
 
+fn validate_env_format(format: &str) -> Result<()> {
+    if format.is_empty() {
+        return Err(PromptError::InvalidFormat {
+            module: "env".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        });
+    }
+    Ok(())
+}
+
 impl Module for EnvModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        validate_env_format(format)
+    }
+
     fn render(&self, format: &str, _context: &ModuleContext) -> Result<Option<String>> {
-        if format.is_empty() {
-            return Err(PromptError::InvalidFormat {
-                module: "env".to_string(),
-                format: format.to_string(),
-                valid_formats: "Provide an environment variable name, e.g., {env:blue:USER}"
-                    .to_string(),
-            });
-        }
+        validate_env_format(format)?;
 
         match env::var_os(format) {
             None => Ok(None),
@@ -1,5 +1,6 @@
 use crate::error::Result;
 use crate::module_trait::{Module, ModuleContext};
+use crate::modules::utils::signal_name;
 
 pub struct FailModule;
 
@@ -16,6 +17,10 @@ impl FailModule {
 }
 
 impl Module for FailModule {
+    fn formats(&self) -> &'static str {
+        "symbol to show (default ❯), full, code, signal"
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
         let exit_code = context.exit_code.unwrap_or(0);
         if exit_code == 0 {
@@ -25,6 +30,7 @@ impl Module for FailModule {
         let symbol = match format {
             "" | "full" => "❯".to_string(),
             "code" => exit_code.to_string(),
+            "signal" => signal_name(exit_code).map_or_else(|| exit_code.to_string(), String::from),
             custom => custom.to_string(),
         };
 
@@ -69,6 +75,21 @@ mod tests {
         assert_eq!(result, Some("42".to_string()));
     }
 
+    #[cfg(unix)]
+    #[test]
+    fn test_fail_shows_signal_name() {
+        let module = FailModule::new();
+        let render = |exit_code| {
+            let context = ModuleContext {
+                exit_code: Some(exit_code),
+                ..ModuleContext::default()
+            };
+            module.render("signal", &context).unwrap()
+        };
+        assert_eq!(render(130), Some("SIGINT".to_string()));
+        assert_eq!(render(2), Some("2".to_string()));
+    }
+
     #[test]
     fn test_fail_custom_symbol() {
         let module = FailModule::new();
@@ -1,6 +1,9 @@
+use crate::cache;
 use crate::error::{PromptError, Result};
 use crate::memo::{GIT_MEMO, GitInfo};
 use crate::module_trait::{Module, ModuleContext};
+use crate::modules::utils::{is_network_filesystem, tool_command};
+use crate::trace::trace_spawn;
 use bitflags::bitflags;
 #[cfg(feature = "git-gix")]
 use gix::bstr::{BString, ByteSlice};
@@ -16,12 +19,20 @@ use gix::status::Item as StatusItem;
 use gix::status::index_worktree::Item as IndexWorktreeItem;
 #[cfg(feature = "git-gix")]
 use gix::status::plumbing::index_as_worktree::EntryStatus as IndexEntryStatus;
+#[cfg(feature = "git-gix")]
+use gix::worktree::IndexPersistedOrInMemory;
 use std::path::Path;
 use std::process::Command;
 #[cfg(feature = "git-gix")]
 use std::sync::Arc;
 use std::thread;
 
+const FORMATS: &str = "full, f, short, s, +o, +owned; full ends in ~ when the status was skipped on a network filesystem";
+/// Set to check the status of repositories on network filesystems anyway.
+const NETWORK_STATUS_ENV: &str = "PRMT_GIT_NETWORK_STATUS";
+/// Follows the branch in place of the status when the status check was skipped.
+const SKIPPED_STATUS_MARKER: &str = "~";
+
 bitflags! {
     #[derive(Debug, Clone, Copy)]
     struct GitStatus: u8 {
@@ -57,12 +68,22 @@ impl GitModule {
     }
 }
 
+/// Recomputes the status of `repo_root` and stores it for the next prompt; this is the
+/// background refresh scheduled for repositories rendered from a stored status.
+pub fn refresh_status_cache(repo_root: &Path) {
+    cache::with_git_lock(repo_root, || {
+        let (_, status) = branch_and_status(repo_root, true);
+        cache::store_git_status(repo_root, status.bits());
+    });
+}
+
 #[cold]
 fn get_git_status_slow(repo_root: &Path) -> GitStatus {
     let mut status = GitStatus::empty();
 
     // Only run git status if not memoized
-    if let Ok(output) = std::process::Command::new("git")
+    trace_spawn!("git status");
+    if let Ok(output) = git_command()
         .arg("status")
         .arg("--porcelain=v1")
         .arg("--untracked-files=normal")
@@ -113,9 +134,18 @@ fn collect_git_status_fast(repo: &gix::Repository) -> Option<GitStatus> {
     let mut status = GitStatus::empty();
     let workdir = repo.workdir()?;
 
+    // The status runs against the read-only index snapshot and its outcome is never written
+    // back with `write_changes`, so refreshed stat data stays in memory and the index is never
+    // locked, as with `GIT_OPTIONAL_LOCKS=0` for the git CLI. Rename tracking and submodules
+    // are off, since only the three flags are needed.
     let platform = repo
         .status(Discard)
         .ok()?
+        .index(IndexPersistedOrInMemory::Persisted(
+            repo.index_or_empty().ok()?,
+        ))
+        .index_worktree_rewrites(None)
+        .index_worktree_submodules(None)
         .dirwalk_options(|opts| opts.emit_ignored(Some(DirwalkEmissionMode::CollapseDirectory)));
     let iter = platform.into_iter(Vec::<BString>::new()).ok()?;
 
@@ -176,6 +206,38 @@ fn current_branch_from_repo(repo: &gix::Repository) -> String {
     }
 }
 
+/// Branch name without opening the repository: `HEAD` is read directly, which is all the
+/// `short` format needs. Falls back to a full lookup for anything unusual.
+fn current_branch(git_path: &Path, repo_root: &Path) -> String {
+    branch_from_head(git_path).unwrap_or_else(|| branch_and_status(repo_root, false).0)
+}
+
+/// Reads `HEAD` in `git_path`, following the `gitdir:` file that worktrees and submodules
+/// use instead of a `.git` directory. A detached `HEAD` gives the abbreviated commit id.
+fn branch_from_head(git_path: &Path) -> Option<String> {
+    let git_dir = if git_path.is_file() {
+        let text = std::fs::read_to_string(git_path).ok()?;
+        let target = text.trim().strip_prefix("gitdir:")?.trim();
+        git_path.parent()?.join(target)
+    } else {
+        git_path.to_path_buf()
+    };
+
+    let head = std::fs::read_to_string(git_dir.join("HEAD")).ok()?;
+    let head = head.trim();
+    if let Some(reference) = head.strip_prefix("ref:") {
+        let reference = reference.trim();
+        return Some(
+            reference
+                .strip_prefix("refs/heads/")
+                .unwrap_or(reference)
+                .to_string(),
+        );
+    }
+    (head.len() >= 7 && head.bytes().all(|byte| byte.is_ascii_hexdigit()))
+        .then(|| head[..7].to_string())
+}
+
 fn current_branch_from_cli(repo_root: &Path) -> Option<String> {
     run_git(&["symbolic-ref", "--quiet", "--short", "HEAD"], repo_root)
         .or_else(|| run_git(&["rev-parse", "--short", "HEAD"], repo_root))
@@ -228,8 +290,17 @@ fn branch_and_status(repo_root: &Path, need_status: bool) -> (String, GitStatus)
     branch_and_status_cli(repo_root, need_status)
 }
 
+/// A `git` invocation that takes no optional locks, so `git status` never refreshes the index
+/// under a rebase or an editor holding `index.lock`, nor leaves a lock behind when killed.
+fn git_command() -> Command {
+    let mut command = tool_command("git");
+    command.env("GIT_OPTIONAL_LOCKS", "0");
+    command
+}
+
 fn run_git(args: &[&str], repo_root: &Path) -> Option<String> {
-    let output = Command::new("git")
+    trace_spawn!(format!("git {}", args.join(" ")));
+    let output = git_command()
         .args(args)
         .current_dir(repo_root)
         .output()
@@ -241,6 +312,14 @@ fn run_git(args: &[&str], repo_root: &Path) -> Option<String> {
     if value.is_empty() { None } else { Some(value) }
 }
 
+/// Whether to skip the worktree status of `repo_root`: walking a repository on NFS, SMB, or
+/// FUSE can take seconds, so only the branch is shown unless `PRMT_GIT_NETWORK_STATUS` is set.
+/// `is_network` is [`is_network_filesystem`] outside tests.
+fn skips_status(repo_root: &Path, is_network: fn(&Path) -> bool) -> bool {
+    std::env::var_os(NETWORK_STATUS_ENV).is_none_or(|value| value.is_empty())
+        && is_network(repo_root)
+}
+
 fn parse_git_format(format: &str) -> Result<GitFormat> {
     let mut mode = None;
     let mut owned_only = false;
@@ -258,7 +337,7 @@ fn parse_git_format(format: &str) -> Result<GitFormat> {
                 return Err(PromptError::InvalidFormat {
                     module: "git".to_string(),
                     format: format.to_string(),
-                    valid_formats: "full, f, short, s, +o, +owned".to_string(),
+                    valid_formats: FORMATS.to_string(),
                 });
             }
         }
@@ -289,6 +368,10 @@ fn is_repo_owned_by_user(repo_root: &Path) -> bool {
 }
 
 impl Module for GitModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &[".git"]
     }
@@ -297,6 +380,10 @@ impl Module for GitModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_git_format(format).map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
         let format = parse_git_format(format)?;
 
@@ -305,8 +392,8 @@ impl Module for GitModule {
             Some(path) => path,
             None => return Ok(None),
         };
-        let repo_root = match git_dir.parent() {
-            Some(p) => p,
+        let repo_root = match context.project_root() {
+            Some(root) => root,
             None => return Ok(None),
         };
 
@@ -335,7 +422,34 @@ impl Module for GitModule {
         }
 
         let need_status = matches!(format.mode, GitMode::Full);
-        let (branch_name, status) = branch_and_status(repo_root, need_status);
+        let cached = need_status && cache::git_status_enabled();
+        let (branch_name, status) = match cached.then(|| cache::git_status(repo_root)).flatten() {
+            Some(flags) => {
+                cache::mark_git_stale(repo_root);
+                (
+                    current_branch(git_dir, repo_root),
+                    GitStatus::from_bits_truncate(flags),
+                )
+            }
+            None if !need_status => (current_branch(git_dir, repo_root), GitStatus::empty()),
+            // The background refresh can walk a network filesystem without holding up the
+            // prompt, so with the status cache on only its first check is skipped.
+            None if skips_status(repo_root, is_network_filesystem) => {
+                if cached {
+                    cache::mark_git_stale(repo_root);
+                }
+                let mut result = current_branch(git_dir, repo_root);
+                result.push_str(SKIPPED_STATUS_MARKER);
+                return Ok(Some(result));
+            }
+            None => {
+                let (branch_name, status) = branch_and_status(repo_root, need_status);
+                if cached {
+                    cache::store_git_status(repo_root, status.bits());
+                }
+                (branch_name, status)
+            }
+        };
 
         // Memoize the result for other placeholders during this render
         let info = GitInfo {
@@ -427,6 +541,103 @@ mod tests {
         assert!(status.success(), "git init should succeed");
     }
 
+    #[test]
+    fn branch_from_head_reads_refs_detached_heads_and_gitdir_files() {
+        let tmp = tempdir().expect("tempdir");
+        let git_dir = tmp.path().join(".git");
+        fs::create_dir_all(&git_dir).expect("create .git");
+
+        fs::write(git_dir.join("HEAD"), "ref: refs/heads/feature/fast-head\n").unwrap();
+        assert_eq!(
+            branch_from_head(&git_dir).as_deref(),
+            Some("feature/fast-head")
+        );
+
+        fs::write(
+            git_dir.join("HEAD"),
+            "3f786850e387550fdab836ed7e6dc881de23001b\n",
+        )
+        .unwrap();
+        assert_eq!(branch_from_head(&git_dir).as_deref(), Some("3f78685"));
+
+        let worktree = tmp.path().join("worktree");
+        let worktree_git = tmp.path().join("worktrees/wt");
+        fs::create_dir_all(&worktree).unwrap();
+        fs::create_dir_all(&worktree_git).unwrap();
+        fs::write(worktree_git.join("HEAD"), "ref: refs/heads/wt\n").unwrap();
+        fs::write(worktree.join(".git"), "gitdir: ../worktrees/wt\n").unwrap();
+        assert_eq!(
+            branch_from_head(&worktree.join(".git")).as_deref(),
+            Some("wt")
+        );
+
+        fs::write(git_dir.join("HEAD"), "garbage").unwrap();
+        assert_eq!(branch_from_head(&git_dir), None);
+    }
+
+    #[test]
+    fn git_commands_take_no_optional_locks() {
+        let command = git_command();
+        let locks = command
+            .get_envs()
+            .find(|(key, _)| *key == "GIT_OPTIONAL_LOCKS")
+            .and_then(|(_, value)| value);
+        assert_eq!(locks, Some(std::ffi::OsStr::new("0")));
+    }
+
+    #[test]
+    fn status_leaves_a_stale_index_untouched() {
+        let repo = tempdir().unwrap();
+        git_init(repo.path());
+        let file = repo.path().join("file.txt");
+        fs::write(&file, "x").unwrap();
+        let git = |args: &[&str]| {
+            let status = Command::new("git")
+                .args(["-c", "user.name=prmt", "-c", "user.email=prmt@example.com"])
+                .args(args)
+                .current_dir(repo.path())
+                .status()
+                .expect("git");
+            assert!(status.success(), "git {:?} should succeed", args);
+        };
+        git(&["add", "file.txt"]);
+        git(&["commit", "-q", "-m", "init"]);
+
+        // Same content, newer mtime: the stat data in the index is out of date, which a
+        // status that refreshes the index would write back.
+        let touched = std::time::SystemTime::now() + std::time::Duration::from_secs(10);
+        fs::File::options()
+            .write(true)
+            .open(&file)
+            .and_then(|file| file.set_modified(touched))
+            .unwrap();
+        let index = repo.path().join(".git").join("index");
+        let read_index = || {
+            let modified = fs::metadata(&index).and_then(|m| m.modified()).unwrap();
+            (fs::read(&index).unwrap(), modified)
+        };
+        let before = read_index();
+
+        assert!(get_git_status_slow(repo.path()).is_empty());
+        assert!(read_index() == before, "git status rewrote the index");
+        let (_, status) = branch_and_status(repo.path(), true);
+        assert!(status.is_empty());
+        assert!(read_index() == before, "the status check rewrote the index");
+        assert!(!repo.path().join(".git").join("index.lock").exists());
+    }
+
+    #[test]
+    #[serial]
+    fn network_repositories_skip_the_status_unless_asked() {
+        let repo = Path::new("/mnt/share/repo");
+        let _unset = EnvVarGuard::unset(NETWORK_STATUS_ENV);
+        assert!(skips_status(repo, |_| true));
+        assert!(!skips_status(repo, |_| false));
+
+        let _set = EnvVarGuard::set(NETWORK_STATUS_ENV, "1");
+        assert!(!skips_status(repo, |_| true));
+    }
+
     #[test]
     fn parse_git_format_defaults_to_full() {
         let format = parse_git_format("").expect("format");
@@ -1,8 +1,12 @@
-use crate::error::Result;
+use crate::error::{PromptError, Result};
 use crate::memo::{GO_VERSION, memoized_version};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
-use std::process::Command;
+use crate::trace::trace_spawn;
+use std::fs;
+use std::path::Path;
+
+const FORMATS: &str = "pinned, p, full, f, short, s, major, m";
 
 pub struct GoModule;
 
@@ -19,6 +23,10 @@ impl GoModule {
 }
 
 impl Module for GoModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &["go.mod"]
     }
@@ -27,19 +35,29 @@ impl Module for GoModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_go_format(format).map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
-        if context.marker_path("go.mod").is_none() {
+        let Some(go_mod) = context.marker_path("go.mod") else {
             return Ok(None);
-        }
+        };
 
         if context.no_version {
             return Ok(Some(String::new()));
         }
 
         // Validate and normalize format
-        let normalized_format = utils::validate_version_format(format, "go")?;
+        let normalized_format = parse_go_format(format)?;
+        if normalized_format == "pinned" {
+            return Ok(pinned_version(go_mod));
+        }
 
-        let version = match memoized_version(&GO_VERSION, get_go_version) {
+        // `go` switches to the toolchain `go.mod` asks for, so it runs in the module.
+        let project = go_mod.parent().unwrap_or(go_mod);
+        let tool = utils::project_tool_key("go", utils::resolve_binary("go").as_deref(), project);
+        let version = match memoized_version(&GO_VERSION, &tool, || get_go_version(project)) {
             Some(v) => v,
             None => return Ok(None),
         };
@@ -54,9 +72,55 @@ impl Module for GoModule {
     }
 }
 
+/// `pinned` (the default) reads the version from `go.mod`; the installed-version formats
+/// run `go version`, which is much slower.
+fn parse_go_format(format: &str) -> Result<&str> {
+    match format {
+        "" | "pinned" | "p" => Ok("pinned"),
+        "full" | "f" | "short" | "s" | "major" | "m" => {
+            utils::validate_version_format(format, "go")
+        }
+        _ => Err(PromptError::InvalidFormat {
+            module: "go".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// The `toolchain` directive of `go.mod` when it names a version, else the `go` directive.
+fn pinned_version(go_mod: &Path) -> Option<String> {
+    let text = fs::read_to_string(go_mod).ok()?;
+    parse_go_mod(&text)
+}
+
+fn parse_go_mod(text: &str) -> Option<String> {
+    let mut go = None;
+    let mut toolchain = None;
+    for line in text.lines() {
+        let line = line.split("//").next().unwrap_or_default().trim();
+        let mut words = line.split_whitespace();
+        match (words.next(), words.next()) {
+            (Some("go"), Some(version)) => go = Some(version),
+            (Some("toolchain"), Some(name)) => {
+                toolchain = name
+                    .strip_prefix("go")
+                    .filter(|version| !version.is_empty())
+            }
+            _ => {}
+        }
+    }
+    toolchain.or(go).map(str::to_string)
+}
+
 #[cold]
-fn get_go_version() -> Option<String> {
-    let output = Command::new("go").arg("version").output().ok()?;
+fn get_go_version(project: &Path) -> Option<String> {
+    trace_spawn!("go");
+    let output = utils::tool_command("go")
+        .arg("version")
+        .current_dir(project)
+        .output()
+        .ok()?;
     if !output.status.success() {
         return None;
     }
@@ -66,3 +130,45 @@ fn get_go_version() -> Option<String> {
         .nth(2)
         .map(|v| v.trim_start_matches("go").to_string())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::detect_from;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    #[test]
+    fn go_mod_toolchain_wins_over_go_directive() {
+        let go_mod = "module example.com/app\n\ngo 1.22 // minimum\ntoolchain go1.22.3\n";
+        assert_eq!(parse_go_mod(go_mod).as_deref(), Some("1.22.3"));
+        assert_eq!(
+            parse_go_mod("module a\ngo 1.21.0\ntoolchain default\n").as_deref(),
+            Some("1.21.0")
+        );
+        assert_eq!(parse_go_mod("module a\n"), None);
+    }
+
+    #[test]
+    fn pinned_format_reads_go_mod_without_spawning_go() {
+        let tmp = tempdir().expect("tempdir");
+        fs::write(tmp.path().join("go.mod"), "module a\n\ngo 1.23\n").expect("write go.mod");
+        let required: HashSet<&'static str> = ["go.mod"].into_iter().collect();
+        let context = ModuleContext {
+            detection: detect_from(&required, tmp.path()),
+            ..ModuleContext::default()
+        };
+        let module = GoModule::new();
+
+        assert_eq!(
+            module.render("", &context).unwrap().as_deref(),
+            Some("1.23")
+        );
+        assert_eq!(
+            module.render("p", &context).unwrap().as_deref(),
+            Some("1.23")
+        );
+        assert!(module.validate_format("major").is_ok());
+        assert!(module.validate_format("minor").is_err());
+    }
+}
@@ -0,0 +1,127 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use std::env;
+use std::path::Path;
+
+const ENVIRONMENT_ENV: &str = "GUIX_ENVIRONMENT";
+const STORE_HASH_LEN: usize = 32;
+const FORMATS: &str = "name (default, the profile's name), path (the profile directory)";
+
+/// Shows the profile of the `guix shell` or `guix environment` the prompt runs in, e.g.
+/// `{guix:yellow::guix(:)}`. Outside of one it shows nothing.
+pub struct GuixModule;
+
+impl Default for GuixModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl GuixModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+fn validate_guix_format(format: &str) -> Result<()> {
+    match format {
+        "" | "name" | "n" | "path" | "p" => Ok(()),
+        _ => Err(PromptError::InvalidFormat {
+            module: "guix".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// Name of the profile at `path`: a store item such as `/gnu/store/<hash>-profile` loses its
+/// hash, and a named profile such as `~/.guix-extra-profiles/dev/dev` keeps its name.
+fn profile_name(path: &str) -> Option<&str> {
+    let name = Path::new(path.trim_end_matches('/'))
+        .file_name()?
+        .to_str()?;
+    Some(match name.split_once('-') {
+        Some((hash, rest))
+            if hash.len() == STORE_HASH_LEN && hash.bytes().all(|b| b.is_ascii_alphanumeric()) =>
+        {
+            rest
+        }
+        _ => name,
+    })
+}
+
+impl Module for GuixModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        validate_guix_format(format)
+    }
+
+    fn render(&self, format: &str, _context: &ModuleContext) -> Result<Option<String>> {
+        validate_guix_format(format)?;
+        let Some(environment) = env::var(ENVIRONMENT_ENV)
+            .ok()
+            .filter(|path| !path.is_empty())
+        else {
+            return Ok(None);
+        };
+        Ok(match format {
+            "path" | "p" => Some(environment),
+            _ => profile_name(&environment).map(String::from),
+        })
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+
+    #[test]
+    fn profile_names_drop_the_store_hash() {
+        assert_eq!(
+            profile_name("/gnu/store/4a2bqxbj3l0dhs8mrf8f0j5a6qkzm8iz-profile"),
+            Some("profile")
+        );
+        assert_eq!(
+            profile_name("/home/ada/.guix-extra-profiles/dev-tools/dev-tools/"),
+            Some("dev-tools")
+        );
+        assert_eq!(profile_name("/"), None);
+    }
+
+    #[test]
+    #[serial]
+    fn shows_only_inside_a_guix_shell() {
+        let saved = env::var_os(ENVIRONMENT_ENV);
+        let context = ModuleContext::default();
+        unsafe {
+            env::remove_var(ENVIRONMENT_ENV);
+        }
+        let outside = GuixModule.render("", &context).unwrap();
+        unsafe {
+            env::set_var(
+                ENVIRONMENT_ENV,
+                "/gnu/store/4a2bqxbj3l0dhs8mrf8f0j5a6qkzm8iz-profile",
+            );
+        }
+        let name = GuixModule.render("", &context).unwrap();
+        let path = GuixModule.render("path", &context).unwrap();
+        unsafe {
+            match saved {
+                Some(value) => env::set_var(ENVIRONMENT_ENV, value),
+                None => env::remove_var(ENVIRONMENT_ENV),
+            }
+        }
+
+        assert_eq!(outside, None);
+        assert_eq!(name.as_deref(), Some("profile"));
+        assert_eq!(
+            path.as_deref(),
+            Some("/gnu/store/4a2bqxbj3l0dhs8mrf8f0j5a6qkzm8iz-profile")
+        );
+        assert!(GuixModule.validate_format("store").is_err());
+    }
+}
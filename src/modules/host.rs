@@ -0,0 +1,290 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use crate::modules::utils::is_ssh_session;
+
+const FORMATS: &str = "short (default, up to the first dot), full (as the system reports it), fqdn (with the DNS domain), trim (without .local, .lan, and similar suffixes), ssh (only over SSH); combine with +, e.g. full+trim+ssh";
+
+/// Suffixes of names that only mean something on the local network.
+const LOCAL_SUFFIXES: &[&str] = &[
+    ".localdomain",
+    ".local",
+    ".lan",
+    ".home.arpa",
+    ".home",
+    ".internal",
+];
+
+/// Shows the machine's name, e.g. `{host:green:short+ssh}` for the remote host only.
+pub struct HostModule;
+
+impl Default for HostModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl HostModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum HostMode {
+    Short,
+    Full,
+    Fqdn,
+}
+
+#[derive(Debug)]
+struct HostFormat {
+    mode: HostMode,
+    trim: bool,
+    ssh_only: bool,
+}
+
+fn parse_host_format(format: &str) -> Result<HostFormat> {
+    let mut host = HostFormat {
+        mode: HostMode::Short,
+        trim: false,
+        ssh_only: false,
+    };
+
+    for part in format.split('+') {
+        match part {
+            "" => {}
+            "short" | "s" => host.mode = HostMode::Short,
+            "full" | "f" => host.mode = HostMode::Full,
+            "fqdn" => host.mode = HostMode::Fqdn,
+            "trim" | "t" => host.trim = true,
+            "ssh" | "ssh-only" | "ssh_only" => host.ssh_only = true,
+            _ => {
+                return Err(PromptError::InvalidFormat {
+                    module: "host".to_string(),
+                    format: format.to_string(),
+                    valid_formats: FORMATS.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(host)
+}
+
+/// The name as `format` shows it, with `domain` completing a name that has none for `fqdn`.
+fn format_host(name: &str, domain: impl FnOnce() -> Option<String>, format: &HostFormat) -> String {
+    let name = match format.mode {
+        HostMode::Short => name.split('.').next().unwrap_or(name).to_string(),
+        HostMode::Full => name.to_string(),
+        HostMode::Fqdn if name.contains('.') => name.to_string(),
+        HostMode::Fqdn => match domain() {
+            Some(domain) => format!("{}.{}", name, domain),
+            None => name.to_string(),
+        },
+    };
+    if format.trim {
+        trim_local_suffix(&name).to_string()
+    } else {
+        name
+    }
+}
+
+fn trim_local_suffix(name: &str) -> &str {
+    let lower = name.to_ascii_lowercase();
+    LOCAL_SUFFIXES
+        .iter()
+        .find(|suffix| lower.len() > suffix.len() && lower.ends_with(*suffix))
+        .map_or(name, |suffix| &name[..name.len() - suffix.len()])
+}
+
+/// The machine's name as the system reports it.
+#[cfg(unix)]
+pub(crate) fn hostname() -> Option<String> {
+    let mut buf = [0u8; 256];
+    if unsafe { libc::gethostname(buf.as_mut_ptr().cast(), buf.len()) } != 0 {
+        return None;
+    }
+    let len = buf.iter().position(|&byte| byte == 0).unwrap_or(buf.len());
+    let name = String::from_utf8_lossy(&buf[..len]).into_owned();
+    (!name.is_empty()).then_some(name)
+}
+
+#[cfg(not(unix))]
+pub(crate) fn hostname() -> Option<String> {
+    std::env::var("COMPUTERNAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+/// The local DNS domain, read from the resolver config rather than asked of a name server
+/// so a slow network cannot stall the prompt.
+#[cfg(unix)]
+fn dns_domain() -> Option<String> {
+    parse_resolv_domain(&std::fs::read_to_string("/etc/resolv.conf").ok()?)
+}
+
+#[cfg(not(unix))]
+fn dns_domain() -> Option<String> {
+    std::env::var("USERDNSDOMAIN")
+        .ok()
+        .filter(|domain| !domain.is_empty())
+        .map(|domain| domain.to_ascii_lowercase())
+}
+
+/// The `domain` of a resolv.conf, or the first `search` domain; the last of them wins.
+#[cfg(any(unix, test))]
+fn parse_resolv_domain(text: &str) -> Option<String> {
+    let mut domain = None;
+    for line in text.lines() {
+        let mut words = line.split_whitespace();
+        if let (Some("domain" | "search"), Some(name)) = (words.next(), words.next()) {
+            domain = Some(name.trim_end_matches('.').to_string());
+        }
+    }
+    domain.filter(|domain| !domain.is_empty())
+}
+
+impl Module for HostModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_host_format(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, _context: &ModuleContext) -> Result<Option<String>> {
+        let format = parse_host_format(format)?;
+        if format.ssh_only && !is_ssh_session() {
+            return Ok(None);
+        }
+        let Some(name) = hostname() else {
+            return Ok(None);
+        };
+        Ok(Some(format_host(&name, dns_domain, &format)))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use std::env;
+    use std::ffi::OsString;
+
+    struct EnvVarGuard {
+        key: String,
+        original: Option<OsString>,
+    }
+
+    impl EnvVarGuard {
+        fn set(key: &str, value: &str) -> Self {
+            let original = env::var_os(key);
+            unsafe {
+                env::set_var(key, value);
+            }
+            Self {
+                key: key.to_string(),
+                original,
+            }
+        }
+
+        fn unset(key: &str) -> Self {
+            let original = env::var_os(key);
+            unsafe {
+                env::remove_var(key);
+            }
+            Self {
+                key: key.to_string(),
+                original,
+            }
+        }
+    }
+
+    impl Drop for EnvVarGuard {
+        fn drop(&mut self) {
+            if let Some(value) = &self.original {
+                unsafe {
+                    env::set_var(&self.key, value);
+                }
+            } else {
+                unsafe {
+                    env::remove_var(&self.key);
+                }
+            }
+        }
+    }
+
+    fn format(name: &str, domain: Option<&str>, format: &str) -> String {
+        let format = parse_host_format(format).unwrap();
+        format_host(name, || domain.map(String::from), &format)
+    }
+
+    #[test]
+    fn modes_shorten_or_complete_the_name() {
+        assert_eq!(format("build.example.com", None, ""), "build");
+        assert_eq!(
+            format("build.example.com", None, "full"),
+            "build.example.com"
+        );
+        assert_eq!(
+            format("build", Some("example.com"), "fqdn"),
+            "build.example.com"
+        );
+        assert_eq!(
+            format("build.corp", Some("example.com"), "fqdn"),
+            "build.corp"
+        );
+        assert_eq!(format("build", None, "fqdn"), "build");
+    }
+
+    #[test]
+    fn trim_drops_local_suffixes() {
+        assert_eq!(format("laptop.local", None, "full+trim"), "laptop");
+        assert_eq!(format("NAS.LAN", None, "full+t"), "NAS");
+        assert_eq!(format("pi.home.arpa", None, "full+trim"), "pi");
+        assert_eq!(
+            format("box.example.com", None, "full+trim"),
+            "box.example.com"
+        );
+        assert_eq!(format("local", None, "full+trim"), "local");
+    }
+
+    #[test]
+    fn resolv_conf_domain() {
+        assert_eq!(
+            parse_resolv_domain(
+                "# generated\nnameserver 10.0.0.1\nsearch corp.example.com example.com\n"
+            ),
+            Some("corp.example.com".to_string())
+        );
+        assert_eq!(
+            parse_resolv_domain("search a.example\ndomain b.example.\n"),
+            Some("b.example".to_string())
+        );
+        assert_eq!(parse_resolv_domain("nameserver 1.1.1.1\n"), None);
+    }
+
+    #[test]
+    fn unknown_formats_are_rejected() {
+        assert!(HostModule.validate_format("short+ssh").is_ok());
+        assert!(HostModule.validate_format("fqdn+trim+ssh-only").is_ok());
+        assert!(HostModule.validate_format("long").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn ssh_only_hides_local_sessions() {
+        let context = ModuleContext::default();
+        let _connection = EnvVarGuard::unset("SSH_CONNECTION");
+        let _client = EnvVarGuard::unset("SSH_CLIENT");
+        let _tty = EnvVarGuard::unset("SSH_TTY");
+        assert_eq!(HostModule.render("ssh", &context).unwrap(), None);
+
+        let _remote = EnvVarGuard::set("SSH_CONNECTION", "10.0.0.2 50000 10.0.0.1 22");
+        assert_eq!(
+            HostModule.render("ssh", &context).unwrap(),
+            hostname().map(|name| format(&name, None, ""))
+        );
+    }
+}
@@ -0,0 +1,233 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use crate::modules::utils;
+use std::env;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FORMATS: &str = "pinned, p, full, f, short, s, major, m";
+const MARKERS: &[&str] = &[
+    "pom.xml",
+    "build.gradle",
+    "build.gradle.kts",
+    ".sdkmanrc",
+    ".java-version",
+];
+const GRADLE_BUILD_FILES: &[&str] = &["build.gradle.kts", "build.gradle"];
+const GRADLE_TOOLCHAIN: &str = "JavaLanguageVersion.of(";
+
+/// Shows the Java version of a Maven or Gradle project without starting a JVM, which takes
+/// longer than the rest of a prompt: the version pinned by `.sdkmanrc`, `.java-version`, or a
+/// Gradle toolchain, else the `release` file of the JDK in `JAVA_HOME` or on `PATH`.
+pub struct JavaModule;
+
+impl Default for JavaModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl JavaModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Module for JavaModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn fs_markers(&self) -> &'static [&'static str] {
+        MARKERS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_java_format(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        if !MARKERS
+            .iter()
+            .any(|marker| context.marker_path(marker).is_some())
+        {
+            return Ok(None);
+        }
+
+        if context.no_version {
+            return Ok(Some(String::new()));
+        }
+
+        let normalized_format = parse_java_format(format)?;
+        match normalized_format {
+            "pinned" => return Ok(pinned_version(context)),
+            "" => return Ok(pinned_version(context).or_else(installed_version)),
+            _ => {}
+        }
+
+        let Some(version) = installed_version() else {
+            return Ok(None);
+        };
+        match normalized_format {
+            "full" => Ok(Some(version)),
+            "short" => Ok(Some(utils::shorten_version(&version))),
+            "major" => Ok(version.split('.').next().map(|s| s.to_string())),
+            _ => unreachable!("validate_version_format should have caught this"),
+        }
+    }
+}
+
+/// The default shows the pinned version, or the installed one when nothing is pinned;
+/// `pinned` shows only a pin and the version formats only the installed JDK.
+fn parse_java_format(format: &str) -> Result<&str> {
+    match format {
+        "" => Ok(""),
+        "pinned" | "p" => Ok("pinned"),
+        "full" | "f" | "short" | "s" | "major" | "m" => {
+            utils::validate_version_format(format, "java")
+        }
+        _ => Err(PromptError::InvalidFormat {
+            module: "java".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// The version a project pins: SDKMAN's `.sdkmanrc`, jenv's `.java-version`, or the
+/// toolchain of the Gradle build, in that order.
+fn pinned_version(context: &ModuleContext) -> Option<String> {
+    let read = |marker: &str| fs::read_to_string(context.marker_path(marker)?).ok();
+    read(".sdkmanrc")
+        .as_deref()
+        .and_then(parse_sdkmanrc)
+        .or_else(|| {
+            read(".java-version")
+                .map(|text| text.trim().to_string())
+                .filter(|version| !version.is_empty())
+        })
+        .or_else(|| {
+            GRADLE_BUILD_FILES
+                .iter()
+                .find_map(|build| parse_gradle_toolchain(&read(build)?))
+        })
+}
+
+/// `java=17.0.9-tem` in an `.sdkmanrc` pins `17.0.9`; the suffix names the vendor.
+fn parse_sdkmanrc(text: &str) -> Option<String> {
+    text.lines()
+        .filter_map(|line| line.trim().strip_prefix("java="))
+        .filter_map(|identifier| identifier.trim().split('-').next())
+        .find(|version| !version.is_empty())
+        .map(str::to_string)
+}
+
+/// The `N` of `JavaLanguageVersion.of(N)` in a Groovy or Kotlin Gradle build.
+fn parse_gradle_toolchain(text: &str) -> Option<String> {
+    let (_, rest) = text.split_once(GRADLE_TOOLCHAIN)?;
+    let (argument, _) = rest.split_once(')')?;
+    let version = argument.trim().trim_matches(['"', '\'']);
+    (!version.is_empty() && version.bytes().all(|byte| byte.is_ascii_digit()))
+        .then(|| version.to_string())
+}
+
+/// The version in the `release` file of the JDK in `JAVA_HOME`, else of the one whose
+/// `java` is first on `PATH`.
+fn installed_version() -> Option<String> {
+    java_home()
+        .and_then(|home| fs::read_to_string(home.join("release")).ok())
+        .and_then(|text| parse_release(&text))
+}
+
+fn java_home() -> Option<PathBuf> {
+    if let Some(home) = env::var_os("JAVA_HOME").filter(|home| !home.is_empty()) {
+        return Some(PathBuf::from(home));
+    }
+    let java = utils::resolve_binary("java")?;
+    java.parent().and_then(Path::parent).map(Path::to_path_buf)
+}
+
+/// `JAVA_VERSION="21.0.2"` in a JDK `release` file.
+fn parse_release(text: &str) -> Option<String> {
+    text.lines()
+        .find_map(|line| line.trim().strip_prefix("JAVA_VERSION="))
+        .map(|version| version.trim().trim_matches('"').to_string())
+        .filter(|version| !version.is_empty())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::detect_from;
+    use serial_test::serial;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_pins() {
+        assert_eq!(
+            parse_sdkmanrc("# Enable auto-env\njava=17.0.9-tem\nmaven=3.9.6\n").as_deref(),
+            Some("17.0.9")
+        );
+        assert_eq!(parse_sdkmanrc("maven=3.9.6\n"), None);
+        assert_eq!(
+            parse_gradle_toolchain(
+                "java {\n    toolchain {\n        languageVersion = JavaLanguageVersion.of(21)\n    }\n}\n"
+            )
+            .as_deref(),
+            Some("21")
+        );
+        assert_eq!(
+            parse_gradle_toolchain("languageVersion.set(JavaLanguageVersion.of(\"17\"))")
+                .as_deref(),
+            Some("17")
+        );
+        assert_eq!(
+            parse_gradle_toolchain("JavaLanguageVersion.of(javaVersion)"),
+            None
+        );
+    }
+
+    #[test]
+    fn parses_jdk_release_file() {
+        let release = "IMPLEMENTOR=\"Eclipse Adoptium\"\nJAVA_VERSION=\"21.0.2\"\nJAVA_VERSION_DATE=\"2024-01-16\"\n";
+        assert_eq!(parse_release(release).as_deref(), Some("21.0.2"));
+        assert_eq!(parse_release("IMPLEMENTOR=\"Oracle\"\n"), None);
+    }
+
+    #[test]
+    #[serial]
+    fn renders_without_spawning_java() {
+        let project = tempdir().unwrap();
+        let jdk = tempdir().unwrap();
+        fs::write(project.path().join("pom.xml"), "<project/>").unwrap();
+        fs::write(jdk.path().join("release"), "JAVA_VERSION=\"17.0.10\"\n").unwrap();
+        let required: HashSet<&'static str> = MARKERS.iter().copied().collect();
+        let context = || ModuleContext {
+            detection: detect_from(&required, project.path()),
+            ..ModuleContext::default()
+        };
+        let saved = env::var_os("JAVA_HOME");
+        unsafe {
+            env::set_var("JAVA_HOME", jdk.path());
+        }
+
+        let module = JavaModule::new();
+        let installed = module.render("", &context()).unwrap();
+        let major = module.render("major", &context()).unwrap();
+        let unpinned = module.render("pinned", &context()).unwrap();
+        fs::write(project.path().join(".java-version"), "11\n").unwrap();
+        let pinned = module.render("", &context()).unwrap();
+        unsafe {
+            match saved {
+                Some(home) => env::set_var("JAVA_HOME", home),
+                None => env::remove_var("JAVA_HOME"),
+            }
+        }
+
+        assert_eq!(installed.as_deref(), Some("17.0.10"));
+        assert_eq!(major.as_deref(), Some("17"));
+        assert_eq!(unpinned, None);
+        assert_eq!(pinned.as_deref(), Some("11"));
+    }
+}
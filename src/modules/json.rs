@@ -1,6 +1,8 @@
 use crate::error::{PromptError, Result};
 use crate::module_trait::{Module, ModuleContext};
 
+const FORMATS: &str = "Provide a dot-path, e.g., {json::.field.nested}";
+
 pub struct JsonModule;
 
 impl Default for JsonModule {
@@ -15,15 +17,28 @@ impl JsonModule {
     }
 }
 
+fn validate_json_format(format: &str) -> Result<()> {
+    if format.is_empty() {
+        return Err(PromptError::InvalidFormat {
+            module: "json".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        });
+    }
+    Ok(())
+}
+
 impl Module for JsonModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        validate_json_format(format)
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
-        if format.is_empty() {
-            return Err(PromptError::InvalidFormat {
-                module: "json".to_string(),
-                format: format.to_string(),
-                valid_formats: "Provide a dot-path, e.g., {json::.field.nested}".to_string(),
-            });
-        }
+        validate_json_format(format)?;
 
         let Some(root) = context.stdin_data.as_deref() else {
             return Ok(None);
@@ -1,8 +1,12 @@
-use crate::error::Result;
-use crate::memo::{NODE_VERSION, memoized_version};
+use crate::error::{PromptError, Result};
+use crate::memo::{NODE_VERSIONS, memoized_version_for};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
-use std::process::Command;
+use crate::trace::trace_spawn;
+use std::fs;
+use std::path::Path;
+
+const FORMATS: &str = "full, f, short, s, major, m, engines, e";
 
 pub struct NodeModule;
 
@@ -19,8 +23,9 @@ impl NodeModule {
 }
 
 #[cold]
-fn get_node_version() -> Option<String> {
-    let output = Command::new("node").arg("--version").output().ok()?;
+fn get_node_version(binary: &Path) -> Option<String> {
+    trace_spawn!(binary.display());
+    let output = utils::tool_command(binary).arg("--version").output().ok()?;
 
     if !output.status.success() {
         return None;
@@ -31,6 +36,10 @@ fn get_node_version() -> Option<String> {
 }
 
 impl Module for NodeModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &["package.json"]
     }
@@ -39,30 +48,165 @@ impl Module for NodeModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_node_format(format).map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
-        if context.marker_path("package.json").is_none() {
+        let Some(package_json) = context.marker_path("package.json") else {
             return Ok(None);
-        }
+        };
 
         if context.no_version {
             return Ok(Some(String::new()));
         }
 
         // Validate and normalize format
-        let normalized_format = utils::validate_version_format(format, "node")?;
+        let normalized_format = parse_node_format(format)?;
 
-        // Check memoized value first
-        let version = match memoized_version(&NODE_VERSION, get_node_version) {
-            Some(v) => v,
-            None => return Ok(None),
+        // nvm and fnm switch versions by changing which `node` is on PATH, so the cached
+        // version belongs to the resolved binary.
+        let Some(binary) = utils::resolve_binary("node") else {
+            return Ok(None);
         };
+        let tool = utils::tool_key("node", Some(&binary));
+        let version =
+            match memoized_version_for(&NODE_VERSIONS, &tool, || get_node_version(&binary)) {
+                Some(v) => v,
+                None => return Ok(None),
+            };
         let version_str = version.as_ref();
 
         match normalized_format {
             "full" => Ok(Some(version_str.to_string())),
             "short" => Ok(Some(utils::shorten_version(version_str))),
             "major" => Ok(version_str.split('.').next().map(|s| s.to_string())),
+            "engines" => Ok(Some(check_engines(version_str, package_json))),
             _ => unreachable!("validate_version_format should have caught this"),
         }
     }
 }
+
+/// `engines` shows the installed version like `full`, with a warning when it does not
+/// satisfy `engines.node` of `package.json`.
+fn parse_node_format(format: &str) -> Result<&str> {
+    match format {
+        "engines" | "e" => Ok("engines"),
+        "" | "full" | "f" | "short" | "s" | "major" | "m" => {
+            utils::validate_version_format(format, "node")
+        }
+        _ => Err(PromptError::InvalidFormat {
+            module: "node".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// `version`, or `⚠ 18≠>=20` when its major does not satisfy the `engines.node` range.
+fn check_engines(version: &str, package_json: &Path) -> String {
+    let engines = fs::read_to_string(package_json)
+        .ok()
+        .and_then(|text| serde_json::from_str::<serde_json::Value>(&text).ok())
+        .and_then(|package| {
+            Some(
+                package
+                    .get("engines")?
+                    .get("node")?
+                    .as_str()?
+                    .trim()
+                    .to_string(),
+            )
+        });
+    match engines {
+        Some(range) if !satisfies_range(version, &range) => {
+            let major = version.split('.').next().unwrap_or(version);
+            utils::version_mismatch(major, &range)
+        }
+        _ => version.to_string(),
+    }
+}
+
+/// Whether `version` satisfies an npm semver range such as `^18.17 || >=20`.
+fn satisfies_range(version: &str, range: &str) -> bool {
+    let version = utils::version_numbers(version);
+    range
+        .split("||")
+        .any(|set| satisfies_set(&version, set.trim()))
+}
+
+/// A hyphen range (`18 - 20`) or space-separated comparators that must all hold.
+fn satisfies_set(version: &[u64], set: &str) -> bool {
+    if let Some((low, high)) = set.split_once(" - ") {
+        return satisfies_comparator(version, &format!(">={}", low.trim()))
+            && satisfies_comparator(version, &format!("<={}", high.trim()));
+    }
+    // `>= 20` is one comparator, so operators standing alone join the next word.
+    let mut comparators = Vec::new();
+    let mut operator = String::new();
+    for word in set.split_whitespace() {
+        if word.chars().all(|c| "<>=~^".contains(c)) {
+            operator.push_str(word);
+        } else {
+            comparators.push(format!("{}{}", std::mem::take(&mut operator), word));
+        }
+    }
+    comparators
+        .iter()
+        .all(|comparator| satisfies_comparator(version, comparator))
+}
+
+/// One comparator, where the missing or `x` components of a partial version such as
+/// `20` or `1.2.x` match anything: `>20` means `>=21`, `<=20` means `<21`, and `20` means
+/// `>=20 <21`.
+fn satisfies_comparator(version: &[u64], comparator: &str) -> bool {
+    let (operator, bound) = utils::split_comparator(comparator);
+    let bound = utils::version_numbers(bound);
+    if bound.is_empty() || bound.len() >= 3 {
+        return utils::satisfies_comparator(version, operator, &bound);
+    }
+    let next = utils::bump_version(&bound, bound.len() - 1);
+    match operator {
+        ">" => utils::satisfies_comparator(version, ">=", &next),
+        "<=" => utils::satisfies_comparator(version, "<", &next),
+        "" | "=" => {
+            utils::satisfies_comparator(version, ">=", &bound)
+                && utils::satisfies_comparator(version, "<", &next)
+        }
+        _ => utils::satisfies_comparator(version, operator, &bound),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn npm_ranges() {
+        assert!(satisfies_range("20.11.0", ">=20"));
+        assert!(!satisfies_range("18.19.0", ">= 20"));
+        assert!(satisfies_range("18.19.0", "^18.17 || >=20"));
+        assert!(!satisfies_range("18.16.0", "^18.17 || >=20"));
+        assert!(satisfies_range("0.2.5", "^0.2.3"));
+        assert!(!satisfies_range("0.3.0", "^0.2.3"));
+        assert!(satisfies_range("20.5.1", "~20.5"));
+        assert!(!satisfies_range("20.6.0", "~20.5"));
+        assert!(satisfies_range("20.1.0", "20.x"));
+        assert!(!satisfies_range("21.0.0", "18 - 20"));
+        assert!(satisfies_range("20.9.0", "18 - 20"));
+        assert!(satisfies_range("22.0.0", "*"));
+        assert!(!satisfies_range("20.0.0", ">16 <20"));
+    }
+
+    #[test]
+    fn engines_mismatch_warns() {
+        let dir = tempfile::tempdir().unwrap();
+        let package_json = dir.path().join("package.json");
+        fs::write(&package_json, r#"{"engines": {"node": ">=20"}}"#).unwrap();
+        assert_eq!(check_engines("18.19.0", &package_json), "⚠ 18≠>=20");
+        assert_eq!(check_engines("20.11.0", &package_json), "20.11.0");
+
+        fs::write(&package_json, r#"{"name": "app"}"#).unwrap();
+        assert_eq!(check_engines("18.19.0", &package_json), "18.19.0");
+    }
+}
@@ -16,6 +16,10 @@ impl OkModule {
 }
 
 impl Module for OkModule {
+    fn formats(&self) -> &'static str {
+        "symbol to show (default ❯), code"
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
         if context.exit_code != Some(0) {
             return Ok(None);
@@ -1,6 +1,10 @@
 use crate::error::{PromptError, Result};
 use crate::module_trait::{Module, ModuleContext};
-use std::path::Path;
+use std::path::{Path, PathBuf};
+use std::sync::OnceLock;
+
+const DELETED_MARKER: &str = " (deleted)";
+const FORMATS: &str = "relative, r, absolute, a, f, initials, i, unvowel, u, short, s";
 
 pub struct PathModule;
 
@@ -30,19 +34,18 @@ fn first_char(segment: &str) -> Option<char> {
     segment.chars().next()
 }
 
-fn shorten_segment_to_initial(segment: &str) -> String {
+fn shorten_segment_to_initial(segment: &str, output: &mut String) {
     if let Some(rest) = segment.strip_prefix('.')
         && let Some(initial) = first_char(rest)
     {
-        let mut shortened = String::with_capacity(2);
-        shortened.push('.');
-        shortened.push(initial);
-        return shortened;
+        output.push('.');
+        output.push(initial);
+        return;
     }
 
-    first_char(segment)
-        .map(|initial| initial.to_string())
-        .unwrap_or_default()
+    if let Some(initial) = first_char(segment) {
+        output.push(initial);
+    }
 }
 
 fn is_ascii_vowel(ch: char) -> bool {
@@ -52,112 +55,157 @@ fn is_ascii_vowel(ch: char) -> bool {
     )
 }
 
-fn unvowel_body(segment: &str) -> String {
+fn unvowel_body(segment: &str, output: &mut String) {
     if segment.chars().count() <= 3 {
-        return segment.to_string();
+        output.push_str(segment);
+        return;
     }
 
     let mut chars = segment.chars();
-    let Some(first) = chars.next() else {
-        return String::new();
-    };
-
-    let mut shortened = String::with_capacity(segment.len());
-    shortened.push(first);
-    for ch in chars {
-        if !is_ascii_vowel(ch) {
-            shortened.push(ch);
-        }
+    if let Some(first) = chars.next() {
+        output.push(first);
     }
-    shortened
+    output.extend(chars.filter(|ch| !is_ascii_vowel(*ch)));
 }
 
-fn unvowel_segment(segment: &str) -> String {
+fn unvowel_segment(segment: &str, output: &mut String) {
     if let Some(rest) = segment.strip_prefix('.') {
-        let mut shortened = String::with_capacity(segment.len());
-        shortened.push('.');
-        shortened.push_str(&unvowel_body(rest));
-        return shortened;
+        output.push('.');
+        unvowel_body(rest, output);
+        return;
     }
 
-    unvowel_body(segment)
+    unvowel_body(segment, output)
 }
 
 fn transform_relative_path(
     path: &str,
     preserve_last: bool,
-    transform: fn(&str) -> String,
+    transform: fn(&str, &mut String),
 ) -> String {
-    let segments: Vec<&str> = path.split('/').collect();
-    let last_index = segments.len().saturating_sub(1);
-    let mut rendered = Vec::with_capacity(segments.len());
-
-    for (index, segment) in segments.iter().enumerate() {
-        let transformed =
-            if segment.is_empty() || *segment == "~" || (preserve_last && index == last_index) {
-                (*segment).to_string()
-            } else {
-                transform(segment)
-            };
-        rendered.push(transformed);
+    let mut rendered = String::with_capacity(path.len());
+    let mut segments = path.split('/').peekable();
+
+    while let Some(segment) = segments.next() {
+        let is_last = segments.peek().is_none();
+        if segment.is_empty() || segment == "~" || (preserve_last && is_last) {
+            rendered.push_str(segment);
+        } else {
+            transform(segment, &mut rendered);
+        }
+        if !is_last {
+            rendered.push('/');
+        }
     }
 
-    rendered.join("/")
+    rendered
 }
 
-fn normalize_relative_path(current_dir: &Path) -> String {
-    let current_canon = current_dir
-        .canonicalize()
-        .unwrap_or_else(|_| current_dir.to_path_buf());
-
-    if let Some(home) = dirs::home_dir() {
-        let home_canon = home.canonicalize().unwrap_or(home);
-        if let Ok(stripped) = current_canon.strip_prefix(&home_canon) {
-            if stripped.as_os_str().is_empty() {
-                return "~".to_string();
-            }
+/// The home directory as configured and with symlinks resolved, looked up once per process.
+struct Home {
+    path: PathBuf,
+    canonical: PathBuf,
+}
 
-            let mut result = String::from("~");
-            result.push(std::path::MAIN_SEPARATOR);
-            result.push_str(&stripped.to_string_lossy());
-            return normalize_separators(result);
-        }
+static HOME: OnceLock<Option<Home>> = OnceLock::new();
+
+fn home() -> Option<&'static Home> {
+    HOME.get_or_init(|| {
+        let path = dirs::home_dir()?;
+        let canonical = path.canonicalize().unwrap_or_else(|_| path.clone());
+        Some(Home { path, canonical })
+    })
+    .as_ref()
+}
+
+/// The working directory with the home directory shown as `~`. The working directory comes
+/// from the OS with symlinks already resolved, so only the home directory needs resolving,
+/// and that happens once.
+fn normalize_relative_path(current_dir: &Path) -> String {
+    let stripped = home().and_then(|home| {
+        current_dir
+            .strip_prefix(&home.path)
+            .or_else(|_| current_dir.strip_prefix(&home.canonical))
+            .ok()
+    });
+
+    let Some(stripped) = stripped else {
+        return normalize_separators(current_dir.to_string_lossy().into_owned());
+    };
+    if stripped.as_os_str().is_empty() {
+        return "~".to_string();
     }
 
-    normalize_separators(current_dir.to_string_lossy().to_string())
+    let stripped = stripped.to_string_lossy();
+    let mut result = String::with_capacity(stripped.len() + 2);
+    result.push('~');
+    result.push(std::path::MAIN_SEPARATOR);
+    result.push_str(&stripped);
+    normalize_separators(result)
+}
+
+#[derive(Clone, Copy, Debug)]
+enum PathFormat {
+    Relative,
+    Absolute,
+    Initials,
+    Unvowel,
+    Short,
+}
+
+fn parse_path_format(format: &str) -> Result<PathFormat> {
+    match format {
+        "" | "relative" | "r" => Ok(PathFormat::Relative),
+        "absolute" | "a" | "f" => Ok(PathFormat::Absolute),
+        "initials" | "i" => Ok(PathFormat::Initials),
+        "unvowel" | "u" => Ok(PathFormat::Unvowel),
+        "short" | "s" => Ok(PathFormat::Short),
+        _ => Err(PromptError::InvalidFormat {
+            module: "path".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
 }
 
 impl Module for PathModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_path_format(format).map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        let format = parse_path_format(format)?;
         let Some(current_dir) = context.current_dir() else {
             return Ok(None);
         };
 
-        match format {
-            "" | "relative" | "r" => Ok(Some(normalize_relative_path(current_dir))),
-            "absolute" | "a" | "f" => Ok(Some(current_dir.to_string_lossy().to_string())),
-            "initials" | "i" => Ok(Some(transform_relative_path(
+        let mut path = match format {
+            PathFormat::Relative => normalize_relative_path(current_dir),
+            PathFormat::Absolute => current_dir.to_string_lossy().into_owned(),
+            PathFormat::Initials => transform_relative_path(
                 &normalize_relative_path(current_dir),
                 true,
                 shorten_segment_to_initial,
-            ))),
-            "unvowel" | "u" => Ok(Some(transform_relative_path(
+            ),
+            PathFormat::Unvowel => transform_relative_path(
                 &normalize_relative_path(current_dir),
                 false,
                 unvowel_segment,
-            ))),
-            "short" | "s" => Ok(current_dir
-                .file_name()
-                .and_then(|n| n.to_str())
-                .map(|s| s.to_string())
-                .or_else(|| Some(".".to_string()))),
-            _ => Err(PromptError::InvalidFormat {
-                module: "path".to_string(),
-                format: format.to_string(),
-                valid_formats: "relative, r, absolute, a, f, initials, i, unvowel, u, short, s"
-                    .to_string(),
-            }),
+            ),
+            PathFormat::Short => current_dir.file_name().map_or_else(
+                || ".".to_string(),
+                |name| name.to_string_lossy().into_owned(),
+            ),
+        };
+        // The shell can sit in a directory that was removed under it.
+        if context.cwd_deleted {
+            path.push_str(DELETED_MARKER);
         }
+        Ok(Some(path))
     }
 }
 
@@ -275,6 +323,41 @@ mod tests {
         let _ = fs::remove_dir_all(&base);
     }
 
+    #[test]
+    fn deleted_directory_is_marked() {
+        let gone = std::env::temp_dir().join("prmt-gone");
+        let mut context = ModuleContext::new(Some(gone));
+        assert_eq!(
+            PathModule.render("short", &context).unwrap().as_deref(),
+            Some("prmt-gone")
+        );
+
+        context.cwd_deleted = true;
+        assert_eq!(
+            PathModule.render("short", &context).unwrap().as_deref(),
+            Some("prmt-gone (deleted)")
+        );
+    }
+
+    #[test]
+    fn home_is_matched_as_configured_and_resolved() {
+        let Some(home) = home() else {
+            return;
+        };
+        let sep = std::path::MAIN_SEPARATOR;
+
+        assert_eq!(normalize_relative_path(&home.path), "~");
+        assert_eq!(
+            normalize_relative_path(&home.canonical.join("dev").join("prmt")),
+            normalize_separators(format!("~{sep}dev{sep}prmt"))
+        );
+        let outside = Path::new("/prmt-outside-home");
+        assert_eq!(
+            normalize_relative_path(outside),
+            normalize_separators(outside.to_string_lossy().into_owned())
+        );
+    }
+
     #[test]
     fn initials_preserve_last_segment_and_hidden_prefixes() {
         assert_eq!(
@@ -0,0 +1,110 @@
+use crate::error::Result;
+use crate::module_trait::{Module, ModuleContext};
+use crate::modules::cmd::{command_timeout, run_with_timeout};
+use crate::modules::utils;
+use std::path::PathBuf;
+use std::process::Command;
+
+/// A module provided by a `prmt-<name>` executable on `PATH`. The executable gets the
+/// placeholder format and the prompt context in `PRMT_PLUGIN_*` variables, runs in the
+/// prompt directory, and its trimmed stdout becomes the segment.
+pub struct PluginModule {
+    binary: PathBuf,
+}
+
+impl PluginModule {
+    pub fn new(binary: PathBuf) -> Self {
+        Self { binary }
+    }
+
+    /// The `prmt-<name>` plugin on `PATH`. Names are limited to letters, digits, `-`, and
+    /// `_`, so a placeholder cannot point outside the `PATH` directories.
+    pub fn find(name: &str) -> Option<Self> {
+        let valid = !name.is_empty()
+            && name
+                .chars()
+                .all(|ch| ch.is_ascii_alphanumeric() || ch == '-' || ch == '_');
+        if !valid {
+            return None;
+        }
+        utils::resolve_binary(&format!("prmt-{}", name)).map(Self::new)
+    }
+
+    fn command(&self, format: &str, context: &ModuleContext) -> Command {
+        let mut command = Command::new(&self.binary);
+        command
+            .env("PRMT_PLUGIN_FORMAT", format)
+            .env("PRMT_PLUGIN_SHELL", context.shell.name())
+            .env(
+                "PRMT_PLUGIN_NO_VERSION",
+                if context.no_version { "1" } else { "" },
+            );
+        if let Some(exit_code) = context.exit_code {
+            command.env("PRMT_PLUGIN_EXIT_CODE", exit_code.to_string());
+        }
+        if let Some(duration) = context.duration {
+            command.env("PRMT_PLUGIN_DURATION_MS", duration.as_millis().to_string());
+        }
+        if let Some(name) = &context.command {
+            command.env("PRMT_PLUGIN_COMMAND", name);
+        }
+        if let Some(root) = context.project_root() {
+            command.env("PRMT_PLUGIN_PROJECT_ROOT", root);
+        }
+        if !context.values.is_empty() {
+            let values = serde_json::to_string(&*context.values).unwrap_or_default();
+            command.env("PRMT_PLUGIN_VALUES", values);
+        }
+        command
+    }
+}
+
+impl Module for PluginModule {
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        Ok(run_with_timeout(
+            self.command(format, context),
+            context.current_dir(),
+            command_timeout(),
+        ))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn names_cannot_leave_the_path_directories() {
+        assert!(PluginModule::find("../bin/sh").is_none());
+        assert!(PluginModule::find("").is_none());
+        assert!(PluginModule::find("a/b").is_none());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn plugin_receives_format_and_context() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let binary = dir.path().join("prmt-echo");
+        std::fs::write(
+            &binary,
+            "#!/bin/sh\necho \"$PRMT_PLUGIN_FORMAT:$PRMT_PLUGIN_EXIT_CODE:$PRMT_PLUGIN_COMMAND:$PRMT_PLUGIN_VALUES\"\n",
+        )
+        .unwrap();
+        std::fs::set_permissions(&binary, std::fs::Permissions::from_mode(0o755)).unwrap();
+
+        let context = ModuleContext {
+            exit_code: Some(2),
+            command: Some("make".to_string()),
+            values: std::sync::Arc::new([("jobs".to_string(), "2".to_string())].into()),
+            ..ModuleContext::default()
+        };
+        let output = PluginModule::new(binary).render("short", &context).unwrap();
+        assert_eq!(output.as_deref(), Some(r#"short:2:make:{"jobs":"2"}"#));
+    }
+}
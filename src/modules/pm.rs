@@ -0,0 +1,187 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use std::fs;
+use std::path::Path;
+
+const FORMATS: &str = "name, n, full, f";
+const PACKAGE_JSON: &str = "package.json";
+/// Lockfiles with the manager that writes them, preferred in this order when several sit in
+/// the same directory.
+const LOCKFILES: &[(&str, &str)] = &[
+    ("pnpm-lock.yaml", "pnpm"),
+    ("yarn.lock", "yarn"),
+    ("bun.lock", "bun"),
+    ("bun.lockb", "bun"),
+    ("package-lock.json", "npm"),
+];
+const MARKERS: &[&str] = &[
+    PACKAGE_JSON,
+    "pnpm-lock.yaml",
+    "yarn.lock",
+    "bun.lock",
+    "bun.lockb",
+    "package-lock.json",
+];
+
+/// Shows which package manager a JavaScript project uses, so installs do not go through the
+/// wrong one: the `packageManager` field of `package.json`, else the nearest lockfile.
+pub struct PmModule;
+
+impl Default for PmModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl PmModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Module for PmModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn fs_markers(&self) -> &'static [&'static str] {
+        MARKERS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_pm_format(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        let normalized_format = parse_pm_format(format)?;
+        let Some(package_json) = context.marker_path(PACKAGE_JSON) else {
+            return Ok(None);
+        };
+
+        if let Some((name, version)) = fs::read_to_string(package_json)
+            .ok()
+            .and_then(|text| parse_package_manager(&text))
+        {
+            return Ok(Some(match (normalized_format, version) {
+                ("full", Some(version)) if !context.no_version => format!("{name}@{version}"),
+                _ => name,
+            }));
+        }
+
+        Ok(nearest_lockfile(context).map(str::to_string))
+    }
+}
+
+fn parse_pm_format(format: &str) -> Result<&str> {
+    match format {
+        "" | "name" | "n" => Ok("name"),
+        "full" | "f" => Ok("full"),
+        _ => Err(PromptError::InvalidFormat {
+            module: "pm".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// The name and version of `"packageManager": "pnpm@9.1.0+sha512..."`, as read by Corepack.
+fn parse_package_manager(text: &str) -> Option<(String, Option<String>)> {
+    let package: serde_json::Value = serde_json::from_str(text).ok()?;
+    let field = package.get("packageManager")?.as_str()?.trim();
+    let (name, version) = match field.split_once('@') {
+        Some((name, version)) => {
+            let version = version.split('+').next().unwrap_or_default();
+            (name, (!version.is_empty()).then(|| version.to_string()))
+        }
+        None => (field, None),
+    };
+    (!name.is_empty()).then(|| (name.to_string(), version))
+}
+
+/// The manager of the lockfile closest to the current directory.
+fn nearest_lockfile(context: &ModuleContext) -> Option<&'static str> {
+    let depth = |path: &Path| path.components().count();
+    LOCKFILES
+        .iter()
+        .filter_map(|(lockfile, name)| Some((depth(context.marker_path(lockfile)?), *name)))
+        .rev()
+        .max_by_key(|(depth, _)| *depth)
+        .map(|(_, name)| name)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::detector::detect_from;
+    use std::collections::HashSet;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_package_manager_field() {
+        assert_eq!(
+            parse_package_manager(r#"{"packageManager": "pnpm@9.1.0+sha512.abc"}"#),
+            Some(("pnpm".to_string(), Some("9.1.0".to_string())))
+        );
+        assert_eq!(
+            parse_package_manager(r#"{"packageManager": "yarn"}"#),
+            Some(("yarn".to_string(), None))
+        );
+        assert_eq!(parse_package_manager(r#"{"name": "app"}"#), None);
+    }
+
+    #[test]
+    fn nearest_lockfile_names_the_manager() {
+        let root = tempdir().unwrap();
+        let member = root.path().join("packages").join("web");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(root.path().join("package.json"), r#"{"private": true}"#).unwrap();
+        fs::write(root.path().join("yarn.lock"), "").unwrap();
+        fs::write(member.join("package.json"), r#"{"name": "web"}"#).unwrap();
+        let required: HashSet<&'static str> = MARKERS.iter().copied().collect();
+        let context = |dir: &Path| ModuleContext {
+            detection: detect_from(&required, dir),
+            ..ModuleContext::default()
+        };
+        let module = PmModule::new();
+
+        assert_eq!(
+            module.render("", &context(&member)).unwrap().as_deref(),
+            Some("yarn")
+        );
+
+        fs::write(member.join("package-lock.json"), "{}").unwrap();
+        fs::write(member.join("pnpm-lock.yaml"), "").unwrap();
+        assert_eq!(
+            module.render("", &context(&member)).unwrap().as_deref(),
+            Some("pnpm")
+        );
+
+        fs::write(
+            member.join("package.json"),
+            r#"{"packageManager": "bun@1.1.0"}"#,
+        )
+        .unwrap();
+        assert_eq!(
+            module.render("full", &context(&member)).unwrap().as_deref(),
+            Some("bun@1.1.0")
+        );
+        assert_eq!(
+            module.render("n", &context(&member)).unwrap().as_deref(),
+            Some("bun")
+        );
+    }
+
+    #[test]
+    fn package_json_alone_names_no_manager() {
+        let dir = tempdir().unwrap();
+        fs::write(dir.path().join("package.json"), "{}").unwrap();
+        let required: HashSet<&'static str> = MARKERS.iter().copied().collect();
+        let context = ModuleContext {
+            detection: detect_from(&required, dir.path()),
+            ..ModuleContext::default()
+        };
+
+        assert_eq!(PmModule::new().render("", &context).unwrap(), None);
+        assert!(PmModule::new().validate_format("version").is_err());
+    }
+}
@@ -2,7 +2,7 @@ use crate::error::Result;
 use crate::memo::{PYTHON_VERSION, memoized_version};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
-use std::process::Command;
+use crate::trace::trace_spawn;
 
 pub struct PythonModule;
 
@@ -19,6 +19,10 @@ impl PythonModule {
 }
 
 impl Module for PythonModule {
+    fn formats(&self) -> &'static str {
+        utils::VERSION_FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &["requirements.txt", "pyproject.toml", "setup.py"]
     }
@@ -27,6 +31,10 @@ impl Module for PythonModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        utils::validate_version_format(format, "python").map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
         let has_marker = ["requirements.txt", "pyproject.toml", "setup.py"]
             .into_iter()
@@ -42,7 +50,9 @@ impl Module for PythonModule {
         // Validate and normalize format
         let normalized_format = utils::validate_version_format(format, "python")?;
 
-        let version = match memoized_version(&PYTHON_VERSION, get_python_version) {
+        let binary = utils::resolve_binary("python3").or_else(|| utils::resolve_binary("python"));
+        let tool = utils::tool_key("python", binary.as_deref());
+        let version = match memoized_version(&PYTHON_VERSION, &tool, get_python_version) {
             Some(v) => v,
             None => return Ok(None),
         };
@@ -59,10 +69,11 @@ impl Module for PythonModule {
 
 #[cold]
 fn get_python_version() -> Option<String> {
-    let output = Command::new("python3")
+    trace_spawn!("python3");
+    let output = utils::tool_command("python3")
         .arg("--version")
         .output()
-        .or_else(|_| Command::new("python").arg("--version").output())
+        .or_else(|_| utils::tool_command("python").arg("--version").output())
         .ok()?;
     if !output.status.success() {
         return None;
@@ -1,7 +1,9 @@
-use crate::error::Result;
+use crate::cache;
+use crate::error::{PromptError, Result};
 use crate::memo::{RUST_VERSION, memoized_version};
 use crate::module_trait::{Module, ModuleContext};
 use crate::modules::utils;
+use crate::trace::trace_spawn;
 use dirs::home_dir;
 use std::collections::HashMap;
 use std::env;
@@ -11,6 +13,8 @@ use std::process::Command;
 use std::sync::OnceLock;
 use toml::Value;
 
+const FORMATS: &str = "full, f, short, s, major, m, edition, e, msrv, check, c, crate";
+
 pub struct RustModule;
 
 impl Default for RustModule {
@@ -26,6 +30,10 @@ impl RustModule {
 }
 
 impl Module for RustModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
     fn fs_markers(&self) -> &'static [&'static str] {
         &["Cargo.toml"]
     }
@@ -34,18 +42,34 @@ impl Module for RustModule {
         true
     }
 
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_rust_format(format).map(|_| ())
+    }
+
     fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
-        if context.marker_path("Cargo.toml").is_none() {
+        let Some(manifest) = context.marker_path("Cargo.toml") else {
             return Ok(None);
+        };
+
+        // These read `Cargo.toml` rather than the toolchain, so `no_version` leaves them be.
+        let normalized_format = parse_rust_format(format)?;
+        match normalized_format {
+            "crate" => return Ok(crate_name(manifest)),
+            "edition" => return Ok(package_field(manifest, "edition")),
+            "msrv" => return Ok(package_field(manifest, "rust-version")),
+            _ => {}
         }
 
         if context.no_version {
             return Ok(Some(String::new()));
         }
 
-        let normalized_format = utils::validate_version_format(format, "rust")?;
-
-        let version = match memoized_version(&RUST_VERSION, get_rust_version) {
+        let cwd = context.current_dir();
+        let toolchain = toolchain_override(cwd);
+        let tool = version_key(toolchain.as_deref(), cwd);
+        let version = match memoized_version(&RUST_VERSION, &tool, || {
+            get_rust_version(toolchain.as_deref())
+        }) {
             Some(v) => v,
             None => return Ok(None),
         };
@@ -55,11 +79,104 @@ impl Module for RustModule {
             "full" => Ok(Some(version_str.to_string())),
             "short" => Ok(Some(utils::shorten_version(version_str))),
             "major" => Ok(version_str.split('.').next().map(|s| s.to_string())),
+            "check" => Ok(Some(check_msrv(
+                version_str,
+                package_field(manifest, "rust-version").as_deref(),
+            ))),
             _ => unreachable!("validate_version_format should have caught this"),
         }
     }
 }
 
+/// `edition`, `msrv`, and `crate` read `Cargo.toml` without running `rustc`; `check` shows
+/// the toolchain version with a warning when it is older than the MSRV.
+fn parse_rust_format(format: &str) -> Result<&str> {
+    match format {
+        "edition" | "e" => Ok("edition"),
+        "msrv" => Ok("msrv"),
+        "check" | "c" => Ok("check"),
+        "crate" => Ok("crate"),
+        "" | "full" | "f" | "short" | "s" | "major" | "m" => {
+            utils::validate_version_format(format, "rust")
+        }
+        _ => Err(PromptError::InvalidFormat {
+            module: "rust".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// `version`, or `⚠ 1.70.0≠1.74` when it is older than `msrv`.
+fn check_msrv(version: &str, msrv: Option<&str>) -> String {
+    match msrv {
+        Some(msrv)
+            if !utils::satisfies_comparator(
+                &utils::version_numbers(version),
+                ">=",
+                &utils::version_numbers(msrv),
+            ) =>
+        {
+            utils::version_mismatch(version, msrv)
+        }
+        _ => version.to_string(),
+    }
+}
+
+/// A `[package]` setting of `manifest`, following `key.workspace = true` to the
+/// `[workspace.package]` of the workspace root. A virtual manifest yields its own
+/// `[workspace.package]` setting.
+fn package_field(manifest: &Path, key: &str) -> Option<String> {
+    let value = read_manifest(manifest)?;
+    let Some(package) = value.get("package") else {
+        return workspace_package_field(&value, key);
+    };
+    match package.get(key)? {
+        Value::String(setting) => Some(setting.clone()),
+        Value::Table(table) if table.get("workspace").and_then(Value::as_bool) == Some(true) => {
+            workspace_package_field(&workspace_manifest(manifest, &value)?, key)
+        }
+        _ => None,
+    }
+}
+
+/// The package name of the nearest `Cargo.toml`, which inside a workspace is the member the
+/// current directory belongs to. A virtual workspace manifest has none.
+fn crate_name(manifest: &Path) -> Option<String> {
+    read_manifest(manifest)?
+        .get("package")?
+        .get("name")?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn workspace_package_field(manifest: &Value, key: &str) -> Option<String> {
+    manifest
+        .get("workspace")?
+        .get("package")?
+        .get(key)?
+        .as_str()
+        .map(str::to_string)
+}
+
+fn read_manifest(path: &Path) -> Option<Value> {
+    toml::from_str(&fs::read_to_string(path).ok()?).ok()
+}
+
+/// The manifest of the workspace `manifest` belongs to: itself when it has a `[workspace]`,
+/// else the nearest `Cargo.toml` above it that has one.
+fn workspace_manifest(manifest: &Path, value: &Value) -> Option<Value> {
+    if value.get("workspace").is_some() {
+        return Some(value.clone());
+    }
+    manifest
+        .parent()?
+        .ancestors()
+        .skip(1)
+        .filter_map(|dir| read_manifest(&dir.join("Cargo.toml")))
+        .find(|value| value.get("workspace").is_some())
+}
+
 #[derive(Default)]
 struct RustupSettings {
     default_toolchain: Option<String>,
@@ -94,23 +211,57 @@ impl RustupSettings {
 }
 
 static RUSTUP_SETTINGS: OnceLock<RustupSettings> = OnceLock::new();
-static TOOLCHAIN_OVERRIDE: OnceLock<Option<String>> = OnceLock::new();
 
 fn rustup_settings() -> &'static RustupSettings {
     RUSTUP_SETTINGS.get_or_init(load_rustup_settings)
 }
 
-fn toolchain_override() -> Option<String> {
-    TOOLCHAIN_OVERRIDE
-        .get_or_init(compute_toolchain_override)
-        .clone()
+/// The toolchain selected for `cwd`: `RUSTUP_TOOLCHAIN`, a `rustup override`, the nearest
+/// toolchain file, or the rustup default, in that order.
+fn toolchain_override(cwd: Option<&Path>) -> Option<String> {
+    if let Ok(toolchain) = env::var("RUSTUP_TOOLCHAIN") {
+        let trimmed = toolchain.trim();
+        if !trimmed.is_empty() {
+            return Some(trimmed.to_string());
+        }
+    }
+
+    let settings = rustup_settings();
+    if let Some(dir) = cwd {
+        if let Some(toolchain) = settings.lookup_override(dir) {
+            return Some(toolchain);
+        }
+        if let Some(toolchain) = find_toolchain_file(dir) {
+            return Some(toolchain);
+        }
+    }
+
+    settings.default_toolchain().map(|s| s.to_string())
+}
+
+/// Version cache key: the selected toolchain, the rustc binary it runs and the contents of
+/// the nearest toolchain file, so `rustup update` or an edited `rust-toolchain.toml` shows
+/// up on the next prompt.
+fn version_key(toolchain: Option<&str>, cwd: Option<&Path>) -> String {
+    let rustc = toolchain
+        .and_then(|toolchain| resolve_rustc_path(toolchain, rustup_settings()))
+        .or_else(|| utils::resolve_binary("rustc"));
+    let tool = match toolchain {
+        Some(toolchain) => format!("rust+{}", toolchain),
+        None => "rust".to_string(),
+    };
+    let mut key = utils::tool_key(&tool, rustc.as_deref());
+    if let Some(hash) = cwd.and_then(toolchain_file_hash) {
+        key.push_str(&format!("+{:016x}", hash));
+    }
+    key
 }
 
-fn get_rust_version() -> Option<String> {
+fn get_rust_version(toolchain: Option<&str>) -> Option<String> {
     let settings = rustup_settings();
 
-    if let Some(toolchain) = toolchain_override()
-        && let Some(version) = run_rustc_for_toolchain(&toolchain, settings)
+    if let Some(toolchain) = toolchain
+        && let Some(version) = run_rustc_for_toolchain(toolchain, settings)
     {
         return Some(version);
     }
@@ -120,25 +271,26 @@ fn get_rust_version() -> Option<String> {
 
 fn run_rustc_for_toolchain(toolchain: &str, settings: &RustupSettings) -> Option<String> {
     if let Some(path) = resolve_rustc_path(toolchain, settings) {
-        let mut cmd = Command::new(path);
+        let mut cmd = utils::tool_command(path);
         cmd.arg("--version");
         if let Some(output) = run_command(cmd) {
             return parse_rustc_version(&output);
         }
     }
 
-    let mut cmd = Command::new("rustup");
+    let mut cmd = utils::tool_command("rustup");
     cmd.args(["run", toolchain, "rustc", "--version"]);
     run_command(cmd).and_then(|out| parse_rustc_version(&out))
 }
 
 fn run_plain_rustc() -> Option<String> {
-    let mut cmd = Command::new("rustc");
+    let mut cmd = utils::tool_command("rustc");
     cmd.arg("--version");
     run_command(cmd).and_then(|out| parse_rustc_version(&out))
 }
 
 fn run_command(mut command: Command) -> Option<String> {
+    trace_spawn!(command.get_program().to_string_lossy());
     let output = command.output().ok()?;
     if !output.status.success() {
         return None;
@@ -150,29 +302,6 @@ fn parse_rustc_version(stdout: &str) -> Option<String> {
     stdout.split_whitespace().nth(1).map(|s| s.to_string())
 }
 
-fn compute_toolchain_override() -> Option<String> {
-    if let Ok(toolchain) = env::var("RUSTUP_TOOLCHAIN") {
-        let trimmed = toolchain.trim();
-        if !trimmed.is_empty() {
-            return Some(trimmed.to_string());
-        }
-    }
-
-    let settings = rustup_settings();
-    let cwd = env::current_dir().ok();
-
-    if let Some(ref dir) = cwd {
-        if let Some(toolchain) = settings.lookup_override(dir) {
-            return Some(toolchain);
-        }
-        if let Some(toolchain) = find_toolchain_file(dir) {
-            return Some(toolchain);
-        }
-    }
-
-    settings.default_toolchain().map(|s| s.to_string())
-}
-
 fn load_rustup_settings() -> RustupSettings {
     let mut settings = RustupSettings::default();
 
@@ -275,6 +404,16 @@ fn find_toolchain_file(start: &Path) -> Option<String> {
     None
 }
 
+/// Hash of the nearest `rust-toolchain` or `rust-toolchain.toml` file.
+fn toolchain_file_hash(start: &Path) -> Option<u64> {
+    start.ancestors().find_map(|dir| {
+        ["rust-toolchain", "rust-toolchain.toml"]
+            .iter()
+            .find_map(|name| fs::read_to_string(dir.join(name)).ok())
+            .map(|contents| cache::hash(&contents))
+    })
+}
+
 fn read_toolchain_file(path: &Path, toml_only: bool) -> Option<String> {
     let contents = fs::read_to_string(path).ok()?;
     let trimmed = contents.trim();
@@ -322,15 +461,164 @@ fn rustc_binary_name() -> &'static str {
 #[cfg(test)]
 mod tests {
     use super::*;
+    use crate::detector::detect_from;
+    use serial_test::serial;
+    use std::collections::HashSet;
+    use std::ffi::OsString;
     use std::io::Write;
     use tempfile::tempdir;
 
+    struct EnvVarGuard {
+        key: String,
+        original: Option<OsString>,
+    }
+
+    impl EnvVarGuard {
+        fn unset(key: &str) -> Self {
+            let original = env::var_os(key);
+            unsafe {
+                env::remove_var(key);
+            }
+            Self {
+                key: key.to_string(),
+                original,
+            }
+        }
+    }
+
+    impl Drop for EnvVarGuard {
+        fn drop(&mut self) {
+            if let Some(value) = &self.original {
+                unsafe {
+                    env::set_var(&self.key, value);
+                }
+            } else {
+                unsafe {
+                    env::remove_var(&self.key);
+                }
+            }
+        }
+    }
+
     #[test]
     fn parses_rustc_version() {
         let input = "rustc 1.76.0 (a58dcd2a3 2024-01-17)";
         assert_eq!(parse_rustc_version(input), Some("1.76.0".to_string()));
     }
 
+    #[test]
+    fn package_fields_follow_workspace_inheritance() {
+        let dir = tempdir().unwrap();
+        let member = dir.path().join("crates").join("core");
+        fs::create_dir_all(&member).unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[workspace]\nmembers = [\"crates/*\"]\n\n[workspace.package]\nedition = \"2024\"\nrust-version = \"1.85\"\n",
+        )
+        .unwrap();
+        fs::write(
+            member.join("Cargo.toml"),
+            "[package]\nname = \"core\"\nedition.workspace = true\nrust-version = \"1.80\"\n",
+        )
+        .unwrap();
+
+        let manifest = member.join("Cargo.toml");
+        assert_eq!(package_field(&manifest, "edition").as_deref(), Some("2024"));
+        assert_eq!(
+            package_field(&manifest, "rust-version").as_deref(),
+            Some("1.80")
+        );
+        assert_eq!(
+            package_field(&dir.path().join("Cargo.toml"), "edition").as_deref(),
+            Some("2024")
+        );
+        assert_eq!(package_field(&manifest, "license"), None);
+        assert_eq!(crate_name(&manifest).as_deref(), Some("core"));
+        assert_eq!(crate_name(&dir.path().join("Cargo.toml")), None);
+    }
+
+    #[test]
+    fn manifest_formats_render_without_versions() {
+        let dir = tempdir().unwrap();
+        fs::write(
+            dir.path().join("Cargo.toml"),
+            "[package]\nname = \"app\"\nedition = \"2021\"\nrust-version = \"1.74\"\n",
+        )
+        .unwrap();
+        let required: HashSet<&'static str> = ["Cargo.toml"].into_iter().collect();
+        let mut context = ModuleContext::new(Some(dir.path().to_path_buf()));
+        context.detection = detect_from(&required, dir.path());
+        context.no_version = true;
+        let module = RustModule::new();
+
+        assert_eq!(
+            module.render("edition", &context).unwrap().as_deref(),
+            Some("2021")
+        );
+        assert_eq!(
+            module.render("msrv", &context).unwrap().as_deref(),
+            Some("1.74")
+        );
+        assert_eq!(
+            module.render("crate", &context).unwrap().as_deref(),
+            Some("app")
+        );
+        assert_eq!(
+            module.render("full", &context).unwrap().as_deref(),
+            Some("")
+        );
+    }
+
+    #[test]
+    fn check_flags_toolchains_older_than_msrv() {
+        assert_eq!(check_msrv("1.70.0", Some("1.74")), "⚠ 1.70.0≠1.74");
+        assert_eq!(check_msrv("1.74.0", Some("1.74")), "1.74.0");
+        assert_eq!(check_msrv("1.70.0", None), "1.70.0");
+    }
+
+    #[test]
+    fn toolchain_file_hash_follows_the_nearest_file() {
+        let dir = tempdir().unwrap();
+        let nested = dir.path().join("crate");
+        fs::create_dir(&nested).unwrap();
+        assert_eq!(toolchain_file_hash(&nested), None);
+
+        fs::write(
+            dir.path().join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.80\"\n",
+        )
+        .unwrap();
+        let pinned = toolchain_file_hash(&nested).expect("toolchain file hash");
+        fs::write(
+            dir.path().join("rust-toolchain.toml"),
+            "[toolchain]\nchannel = \"1.81\"\n",
+        )
+        .unwrap();
+        assert_ne!(toolchain_file_hash(&nested), Some(pinned));
+
+        fs::write(nested.join("rust-toolchain"), "nightly\n").unwrap();
+        assert_eq!(toolchain_file_hash(&nested), Some(cache::hash("nightly\n")));
+    }
+
+    #[test]
+    #[serial]
+    fn toolchain_follows_the_context_directory() {
+        let _toolchain = EnvVarGuard::unset("RUSTUP_TOOLCHAIN");
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        fs::write(first.path().join("rust-toolchain"), "1.80\n").unwrap();
+        fs::write(second.path().join("rust-toolchain"), "nightly\n").unwrap();
+
+        assert_eq!(
+            toolchain_override(Some(first.path())).as_deref(),
+            Some("1.80")
+        );
+        assert_eq!(
+            toolchain_override(Some(second.path())).as_deref(),
+            Some("nightly")
+        );
+    }
+
     #[test]
     fn read_plain_toolchain_file() {
         let dir = tempdir().unwrap();
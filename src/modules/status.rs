@@ -0,0 +1,139 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use crate::modules::utils::signal_name;
+
+const DEFAULT_SYMBOL: &str = "❯";
+const CODE_VARIABLE: &str = "$code";
+const SIGNAL_VARIABLE: &str = "$signal";
+const FORMATS: &str = "symbol for both cases (default ❯), or ok=TEXT;fail=TEXT, where $code is the exit code and $signal the signal name";
+
+/// Shows one text after a successful command and another after a failed one, so a single
+/// placeholder replaces an `{ok}{fail}` pair, e.g. `{status:green|red:ok=✓;fail=✗ $code}`.
+pub struct StatusModule;
+
+impl Default for StatusModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl StatusModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+/// Texts for success and failure. A format without `ok=`/`fail=` cases is used for both.
+struct Cases<'a> {
+    ok: &'a str,
+    fail: &'a str,
+}
+
+fn parse_cases(format: &str) -> Result<Cases<'_>> {
+    if format.is_empty() {
+        return Ok(Cases {
+            ok: DEFAULT_SYMBOL,
+            fail: DEFAULT_SYMBOL,
+        });
+    }
+    if !format.contains('=') {
+        return Ok(Cases {
+            ok: format,
+            fail: format,
+        });
+    }
+
+    let mut cases = Cases {
+        ok: DEFAULT_SYMBOL,
+        fail: DEFAULT_SYMBOL,
+    };
+    for case in format.split(';') {
+        match case.split_once('=') {
+            Some(("ok", text)) => cases.ok = text,
+            Some(("fail", text)) => cases.fail = text,
+            _ => {
+                return Err(PromptError::InvalidFormat {
+                    module: "status".to_string(),
+                    format: format.to_string(),
+                    valid_formats: FORMATS.to_string(),
+                });
+            }
+        }
+    }
+    Ok(cases)
+}
+
+impl Module for StatusModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_cases(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        let cases = parse_cases(format)?;
+        let exit_code = context.exit_code.unwrap_or(0);
+        let text = if exit_code == 0 { cases.ok } else { cases.fail };
+        if text.is_empty() {
+            return Ok(None);
+        }
+        let code = exit_code.to_string();
+        let mut text = text.replace(CODE_VARIABLE, &code);
+        if text.contains(SIGNAL_VARIABLE) {
+            text = text.replace(SIGNAL_VARIABLE, signal_name(exit_code).unwrap_or(&code));
+        }
+        Ok(Some(text))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn render(format: &str, exit_code: Option<i32>) -> Option<String> {
+        let context = ModuleContext {
+            exit_code,
+            ..ModuleContext::default()
+        };
+        StatusModule::new().render(format, &context).unwrap()
+    }
+
+    #[test]
+    fn picks_the_case_for_the_exit_code() {
+        let format = "ok=✓;fail=✗ $code";
+        assert_eq!(render(format, Some(0)).as_deref(), Some("✓"));
+        assert_eq!(render(format, None).as_deref(), Some("✓"));
+        assert_eq!(render(format, Some(127)).as_deref(), Some("✗ 127"));
+    }
+
+    #[test]
+    fn plain_format_is_used_for_both_cases() {
+        assert_eq!(render("", Some(1)).as_deref(), Some("❯"));
+        assert_eq!(render("[$code]", Some(0)).as_deref(), Some("[0]"));
+        assert_eq!(render("[$code]", Some(2)).as_deref(), Some("[2]"));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signal_variable_names_the_signal() {
+        let format = "fail=✗ $signal";
+        assert_eq!(render(format, Some(139)).as_deref(), Some("✗ SIGSEGV"));
+        assert_eq!(render(format, Some(1)).as_deref(), Some("✗ 1"));
+    }
+
+    #[test]
+    fn empty_or_missing_cases() {
+        assert_eq!(render("ok=;fail=$code", Some(0)), None);
+        assert_eq!(render("fail=✗", Some(0)).as_deref(), Some("❯"));
+    }
+
+    #[test]
+    fn unknown_cases_are_rejected() {
+        let module = StatusModule::new();
+        assert!(module.validate_format("ok=✓;error=✗").is_err());
+        assert!(module.validate_format("ok=✓;;fail=✗").is_err());
+        assert!(module.validate_format("ok=✓;fail=✗").is_ok());
+    }
+}
@@ -0,0 +1,232 @@
+use crate::error::{PromptError, Result};
+use crate::memo::{TERRAFORM_VERSION, memoized_version};
+use crate::module_trait::{Module, ModuleContext};
+use crate::modules::utils;
+use crate::trace::trace_spawn;
+use std::fs;
+use std::path::{Path, PathBuf};
+
+const FORMATS: &str = "full, f, short, s, major, m, pinned, p, check, c";
+
+/// Shows the Terraform version in a directory with `*.tf` files, which Terraform only reads
+/// from the directory it runs in: the installed version, the `required_version` the
+/// configuration pins, or the installed one checked against that pin.
+pub struct TerraformModule;
+
+impl Default for TerraformModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl TerraformModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+impl Module for TerraformModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_terraform_format(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        let files = context.current_dir().map(tf_files).unwrap_or_default();
+        if files.is_empty() {
+            return Ok(None);
+        }
+
+        if context.no_version {
+            return Ok(Some(String::new()));
+        }
+
+        let normalized_format = parse_terraform_format(format)?;
+        if normalized_format == "pinned" {
+            return Ok(required_version(&files));
+        }
+
+        let tool = utils::tool_key("terraform", utils::resolve_binary("terraform").as_deref());
+        let version = match memoized_version(&TERRAFORM_VERSION, &tool, get_terraform_version) {
+            Some(v) => v,
+            None => return Ok(None),
+        };
+        let version_str = version.as_ref();
+
+        match normalized_format {
+            "full" => Ok(Some(version_str.to_string())),
+            "short" => Ok(Some(utils::shorten_version(version_str))),
+            "major" => Ok(version_str.split('.').next().map(|s| s.to_string())),
+            "check" => Ok(Some(check(
+                version_str,
+                required_version(&files).as_deref(),
+            ))),
+            _ => unreachable!("validate_version_format should have caught this"),
+        }
+    }
+}
+
+/// The version formats show the installed Terraform, `pinned` the `required_version`
+/// constraint without running `terraform`, and `check` the installed version with a warning
+/// when it does not satisfy the constraint.
+fn parse_terraform_format(format: &str) -> Result<&str> {
+    match format {
+        "pinned" | "p" => Ok("pinned"),
+        "check" | "c" => Ok("check"),
+        "" | "full" | "f" | "short" | "s" | "major" | "m" => {
+            utils::validate_version_format(format, "terraform")
+        }
+        _ => Err(PromptError::InvalidFormat {
+            module: "terraform".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
+/// The `*.tf` files of `dir`, sorted so constraints join in a stable order.
+fn tf_files(dir: &Path) -> Vec<PathBuf> {
+    let Ok(entries) = fs::read_dir(dir) else {
+        return Vec::new();
+    };
+    let mut files: Vec<PathBuf> = entries
+        .flatten()
+        .map(|entry| entry.path())
+        .filter(|path| path.extension().is_some_and(|extension| extension == "tf"))
+        .collect();
+    files.sort();
+    files
+}
+
+/// Every `required_version` of the configuration joined with `, `, as Terraform requires all
+/// of them to hold.
+fn required_version(files: &[PathBuf]) -> Option<String> {
+    let constraints: Vec<String> = files
+        .iter()
+        .filter_map(|file| fs::read_to_string(file).ok())
+        .flat_map(|text| parse_required_versions(&text))
+        .collect();
+    (!constraints.is_empty()).then(|| constraints.join(", "))
+}
+
+/// The `required_version = "..."` settings of a `.tf` file, skipping comment lines.
+fn parse_required_versions(text: &str) -> Vec<String> {
+    text.lines()
+        .map(str::trim)
+        .filter(|line| !line.starts_with('#') && !line.starts_with("//"))
+        .filter_map(|line| {
+            let rest = line.strip_prefix("required_version")?.trim_start();
+            let value = rest.strip_prefix('=')?.trim().strip_prefix('"')?;
+            let (constraint, _) = value.split_once('"')?;
+            let constraint = constraint.trim();
+            (!constraint.is_empty()).then(|| constraint.to_string())
+        })
+        .collect()
+}
+
+/// `installed`, or `⚠ installed≠constraint` when it does not satisfy `constraint`.
+fn check(installed: &str, constraint: Option<&str>) -> String {
+    match constraint {
+        Some(constraint) if !satisfies(installed, constraint) => {
+            utils::version_mismatch(installed, constraint)
+        }
+        _ => installed.to_string(),
+    }
+}
+
+/// Whether `version` satisfies every comma-separated part of a Terraform version constraint.
+fn satisfies(version: &str, constraint: &str) -> bool {
+    let version = utils::version_numbers(version);
+    constraint.split(',').all(|part| {
+        let (operator, bound) = utils::split_comparator(part);
+        utils::satisfies_comparator(&version, operator, &utils::version_numbers(bound))
+    })
+}
+
+#[cold]
+fn get_terraform_version() -> Option<String> {
+    trace_spawn!("terraform");
+    // Without this, `terraform version` asks checkpoint.hashicorp.com for newer releases.
+    let output = utils::tool_command("terraform")
+        .arg("version")
+        .env("CHECKPOINT_DISABLE", "1")
+        .output()
+        .ok()?;
+    if !output.status.success() {
+        return None;
+    }
+    let stdout = String::from_utf8_lossy(&output.stdout);
+    stdout
+        .lines()
+        .next()?
+        .strip_prefix("Terraform v")
+        .map(|version| version.trim().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tempfile::tempdir;
+
+    #[test]
+    fn parses_required_version() {
+        let versions =
+            "terraform {\n  required_version = \">= 1.5.0, < 2.0.0\"\n  required_providers {}\n}\n";
+        assert_eq!(parse_required_versions(versions), vec![">= 1.5.0, < 2.0.0"]);
+        assert!(parse_required_versions("# required_version = \"1.0\"\n").is_empty());
+        assert!(parse_required_versions("resource \"null_resource\" \"a\" {}\n").is_empty());
+    }
+
+    #[test]
+    fn checks_constraints() {
+        assert!(satisfies("1.5.7", ">= 1.5.0, < 2.0.0"));
+        assert!(!satisfies("1.4.6", ">= 1.5.0"));
+        assert!(satisfies("1.9.0", "~> 1.2"));
+        assert!(!satisfies("2.0.0", "~> 1.2"));
+        assert!(satisfies("1.2.9", "~> 1.2.0"));
+        assert!(!satisfies("1.3.0", "~> 1.2.0"));
+        assert!(satisfies("1.6.0", "1.6.0"));
+        assert!(!satisfies("1.6.0", "!= 1.6.0"));
+
+        assert_eq!(check("1.5.7", Some(">= 1.5")), "1.5.7");
+        assert_eq!(check("1.4.6", Some(">= 1.5")), "⚠ 1.4.6≠>= 1.5");
+        assert_eq!(check("1.4.6", None), "1.4.6");
+    }
+
+    #[test]
+    fn pinned_format_reads_tf_files_in_the_current_directory() {
+        let dir = tempdir().unwrap();
+        let context = || ModuleContext {
+            cwd: Some(dir.path().to_path_buf()),
+            ..ModuleContext::default()
+        };
+        let module = TerraformModule::new();
+        assert_eq!(module.render("pinned", &context()).unwrap(), None);
+
+        fs::write(
+            dir.path().join("main.tf"),
+            "resource \"null_resource\" \"a\" {}\n",
+        )
+        .unwrap();
+        assert_eq!(module.render("pinned", &context()).unwrap(), None);
+
+        fs::write(
+            dir.path().join("versions.tf"),
+            "terraform {\n  required_version = \"~> 1.6\"\n}\n",
+        )
+        .unwrap();
+        assert_eq!(
+            module.render("p", &context()).unwrap().as_deref(),
+            Some("~> 1.6")
+        );
+        assert!(module.validate_format("check").is_ok());
+        assert!(module.validate_format("minor").is_err());
+    }
+}
@@ -1,10 +1,11 @@
 use crate::error::{PromptError, Result};
 use crate::module_trait::{Module, ModuleContext};
-use libc::c_int;
-use std::convert::TryInto;
+use crate::tz;
 use std::io;
 use std::time::{SystemTime, UNIX_EPOCH};
 
+const FORMATS: &str = "24h (default), 12h, 12H, 12hs, 12HS, 24hs, 24HS";
+
 pub struct TimeModule;
 
 impl Default for TimeModule {
@@ -43,21 +44,31 @@ impl FormatSpec {
     }
 }
 
+fn parse_format_spec(format: &str) -> Result<FormatSpec> {
+    match format {
+        "" | "24h" => Ok(FormatSpec::Hm24),
+        "24hs" | "24HS" => Ok(FormatSpec::Hms24),
+        "12h" | "12H" => Ok(FormatSpec::Hm12),
+        "12hs" | "12HS" => Ok(FormatSpec::Hms12),
+        _ => Err(PromptError::InvalidFormat {
+            module: "time".to_string(),
+            format: format.to_string(),
+            valid_formats: FORMATS.to_string(),
+        }),
+    }
+}
+
 impl Module for TimeModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_format_spec(format).map(|_| ())
+    }
+
     fn render(&self, format: &str, _context: &ModuleContext) -> Result<Option<String>> {
-        let spec = match format {
-            "" | "24h" => FormatSpec::Hm24,
-            "24hs" | "24HS" => FormatSpec::Hms24,
-            "12h" | "12H" => FormatSpec::Hm12,
-            "12hs" | "12HS" => FormatSpec::Hms12,
-            _ => {
-                return Err(PromptError::InvalidFormat {
-                    module: "time".to_string(),
-                    format: format.to_string(),
-                    valid_formats: "24h (default), 12h, 12H, 12hs, 12HS, 24hs, 24HS".to_string(),
-                });
-            }
-        };
+        let spec = parse_format_spec(format)?;
 
         let parts = current_local_time()?;
         Ok(Some(spec.render(&parts)))
@@ -83,65 +94,18 @@ impl TimeParts {
 }
 
 fn current_local_time() -> Result<TimeParts> {
-    let timestamp = system_time_to_time_t()?;
-    let tm = platform_local_tm(timestamp)?;
+    let timestamp = SystemTime::now()
+        .duration_since(UNIX_EPOCH)
+        .map_err(|err| PromptError::IoError(io::Error::other(err)))?
+        .as_secs();
+    let seconds = tz::local_seconds_of_day(timestamp as i64);
     Ok(TimeParts {
-        hour24: clamp_component(tm.tm_hour, 23),
-        minute: clamp_component(tm.tm_min, 59),
-        second: clamp_component(tm.tm_sec, 60),
+        hour24: (seconds / 3600) as u8,
+        minute: (seconds / 60 % 60) as u8,
+        second: (seconds % 60) as u8,
     })
 }
 
-fn system_time_to_time_t() -> Result<libc::time_t> {
-    let duration = SystemTime::now()
-        .duration_since(UNIX_EPOCH)
-        .map_err(|err| PromptError::IoError(io::Error::other(err)))?;
-
-    duration
-        .as_secs()
-        .try_into()
-        .map_err(|err| PromptError::IoError(io::Error::other(err)))
-}
-
-fn clamp_component(value: c_int, max: u8) -> u8 {
-    value.clamp(0, max as c_int) as u8
-}
-
-#[cfg(unix)]
-fn platform_local_tm(timestamp: libc::time_t) -> Result<libc::tm> {
-    use std::mem::MaybeUninit;
-
-    unsafe {
-        let mut tm = MaybeUninit::<libc::tm>::uninit();
-        if libc::localtime_r(&timestamp as *const _, tm.as_mut_ptr()).is_null() {
-            return Err(PromptError::IoError(io::Error::last_os_error()));
-        }
-        Ok(tm.assume_init())
-    }
-}
-
-#[cfg(windows)]
-fn platform_local_tm(timestamp: libc::time_t) -> Result<libc::tm> {
-    use std::mem::MaybeUninit;
-
-    unsafe {
-        let mut tm = MaybeUninit::<libc::tm>::uninit();
-        let err = libc::localtime_s(tm.as_mut_ptr(), &timestamp as *const _);
-        if err != 0 {
-            return Err(PromptError::IoError(io::Error::from_raw_os_error(err)));
-        }
-        Ok(tm.assume_init())
-    }
-}
-
-#[cfg(not(any(unix, windows)))]
-fn platform_local_tm(_timestamp: libc::time_t) -> Result<libc::tm> {
-    Err(PromptError::IoError(io::Error::new(
-        io::ErrorKind::Other,
-        "time module is not supported on this platform",
-    )))
-}
-
 #[cfg(test)]
 mod tests {
     use super::*;
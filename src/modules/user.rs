@@ -0,0 +1,188 @@
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use crate::modules::host::hostname;
+use crate::modules::utils::is_ssh_session;
+
+const FORMATS: &str = "auto (default, as root or over SSH), always, root (as root only), ssh (over SSH only); add +host to append @host, e.g. ssh+host";
+
+/// Shows the user name when it tells something, e.g. `{user:yellow:auto+host}` for
+/// `root@build` on a remote machine and nothing in a local shell.
+pub struct UserModule;
+
+impl Default for UserModule {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl UserModule {
+    pub fn new() -> Self {
+        Self
+    }
+}
+
+#[derive(Debug, Clone, Copy, PartialEq)]
+enum UserMode {
+    Auto,
+    Always,
+    Root,
+    Ssh,
+}
+
+#[derive(Debug)]
+struct UserFormat {
+    mode: UserMode,
+    host: bool,
+}
+
+fn parse_user_format(format: &str) -> Result<UserFormat> {
+    let mut user = UserFormat {
+        mode: UserMode::Auto,
+        host: false,
+    };
+
+    for part in format.split('+') {
+        match part {
+            "" => {}
+            "auto" | "a" => user.mode = UserMode::Auto,
+            "always" => user.mode = UserMode::Always,
+            "root" | "root-only" | "root_only" => user.mode = UserMode::Root,
+            "ssh" | "ssh-only" | "ssh_only" => user.mode = UserMode::Ssh,
+            "host" | "h" => user.host = true,
+            _ => {
+                return Err(PromptError::InvalidFormat {
+                    module: "user".to_string(),
+                    format: format.to_string(),
+                    valid_formats: FORMATS.to_string(),
+                });
+            }
+        }
+    }
+
+    Ok(user)
+}
+
+/// Whether `mode` shows the user name for a session as root and over SSH as given.
+fn shows(mode: UserMode, root: bool, ssh: bool) -> bool {
+    match mode {
+        UserMode::Auto => root || ssh,
+        UserMode::Always => true,
+        UserMode::Root => root,
+        UserMode::Ssh => ssh,
+    }
+}
+
+#[cfg(unix)]
+fn is_root() -> bool {
+    unsafe { libc::geteuid() == 0 }
+}
+
+#[cfg(not(unix))]
+fn is_root() -> bool {
+    false
+}
+
+/// Name of the effective user, which differs from `USER` after `sudo -s` or `su`.
+#[cfg(unix)]
+fn username() -> Option<String> {
+    let mut passwd: libc::passwd = unsafe { std::mem::zeroed() };
+    let mut result = std::ptr::null_mut();
+    let mut buf = vec![0 as libc::c_char; 1024];
+    let status = unsafe {
+        libc::getpwuid_r(
+            libc::geteuid(),
+            &mut passwd,
+            buf.as_mut_ptr(),
+            buf.len(),
+            &mut result,
+        )
+    };
+    if status == 0 && !result.is_null() && !passwd.pw_name.is_null() {
+        let name = unsafe { std::ffi::CStr::from_ptr(passwd.pw_name) };
+        let name = name.to_string_lossy().into_owned();
+        if !name.is_empty() {
+            return Some(name);
+        }
+    }
+    std::env::var("USER").ok().filter(|name| !name.is_empty())
+}
+
+#[cfg(not(unix))]
+fn username() -> Option<String> {
+    std::env::var("USERNAME")
+        .ok()
+        .filter(|name| !name.is_empty())
+}
+
+impl Module for UserModule {
+    fn formats(&self) -> &'static str {
+        FORMATS
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        parse_user_format(format).map(|_| ())
+    }
+
+    fn render(&self, format: &str, _context: &ModuleContext) -> Result<Option<String>> {
+        let format = parse_user_format(format)?;
+        if !shows(format.mode, is_root(), is_ssh_session()) {
+            return Ok(None);
+        }
+        let Some(user) = username() else {
+            return Ok(None);
+        };
+        let host = format
+            .host
+            .then(hostname)
+            .flatten()
+            .map(|host| host.split('.').next().unwrap_or(&host).to_string());
+        Ok(Some(match host {
+            Some(host) => format!("{}@{}", user, host),
+            None => user,
+        }))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn modes_decide_when_the_name_shows() {
+        for (mode, expected) in [
+            (UserMode::Auto, [false, true, true]),
+            (UserMode::Always, [true, true, true]),
+            (UserMode::Root, [false, true, false]),
+            (UserMode::Ssh, [false, false, true]),
+        ] {
+            let cases = [
+                shows(mode, false, false),
+                shows(mode, true, false),
+                shows(mode, false, true),
+            ];
+            assert_eq!(cases, expected, "{:?}", mode);
+        }
+    }
+
+    #[test]
+    fn formats_combine_with_host() {
+        let format = parse_user_format("ssh-only+host").unwrap();
+        assert_eq!(format.mode, UserMode::Ssh);
+        assert!(format.host);
+        assert_eq!(parse_user_format("").unwrap().mode, UserMode::Auto);
+        assert!(UserModule.validate_format("root+h").is_ok());
+        assert!(UserModule.validate_format("sometimes").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn always_appends_the_short_host() {
+        let text = UserModule
+            .render("always+host", &ModuleContext::default())
+            .unwrap()
+            .expect("user name");
+        let (user, host) = text.split_once('@').expect("user@host");
+        assert_eq!(Some(user.to_string()), username());
+        assert!(!host.is_empty() && !host.contains('.'));
+    }
+}
@@ -1,4 +1,36 @@
 use crate::error::{PromptError, Result};
+use std::cmp::Ordering;
+use std::env;
+use std::ffi::{OsStr, OsString};
+use std::fs;
+use std::io;
+use std::path::{Path, PathBuf};
+use std::process::Command;
+use std::time::UNIX_EPOCH;
+
+/// Variables that make tools trace themselves, print debug output, or start a debugger, which
+/// would slow down or clutter every prompt.
+const NOISY_ENV: &[&str] = &[
+    "NODE_OPTIONS",
+    "NODE_DEBUG",
+    "GODEBUG",
+    "RUST_LOG",
+    "RUSTUP_LOG",
+    "PYTHONVERBOSE",
+    "PYTHONDEBUG",
+    "PYTHONSTARTUP",
+    "ERL_AFLAGS",
+    "ERL_FLAGS",
+    "ERL_ZFLAGS",
+    "ELIXIR_ERL_OPTIONS",
+    "GIT_CURL_VERBOSE",
+    "LD_DEBUG",
+];
+/// Prefixes of further such variables, e.g. `GIT_TRACE_PERFORMANCE`.
+const NOISY_ENV_PREFIXES: &[&str] = &["GIT_TRACE"];
+
+/// Formats accepted by the version modules.
+pub const VERSION_FORMATS: &str = "full, f, short, s, major, m";
 
 pub fn validate_version_format<'a>(format: &'a str, module_name: &str) -> Result<&'a str> {
     match format {
@@ -8,7 +40,7 @@ pub fn validate_version_format<'a>(format: &'a str, module_name: &str) -> Result
         _ => Err(PromptError::InvalidFormat {
             module: module_name.to_string(),
             format: format.to_string(),
-            valid_formats: "full, f, short, s, major, m".to_string(),
+            valid_formats: VERSION_FORMATS.to_string(),
         }),
     }
 }
@@ -22,3 +54,499 @@ pub fn shorten_version(version: &str) -> String {
         version.to_string()
     }
 }
+
+/// The numeric components of a version such as `v1.5.0-beta1`, up to the first part that is
+/// not a number.
+pub fn version_numbers(version: &str) -> Vec<u64> {
+    version
+        .trim()
+        .trim_start_matches('v')
+        .split('.')
+        .map_while(|part| {
+            let digits = part.split(|c: char| !c.is_ascii_digit()).next()?;
+            digits.parse().ok()
+        })
+        .collect()
+}
+
+/// Compares two versions split by [`version_numbers`] component by component, missing
+/// components counting as zero.
+pub fn compare_version_numbers(left: &[u64], right: &[u64]) -> Ordering {
+    (0..left.len().max(right.len()))
+        .map(|i| {
+            let part = |numbers: &[u64]| numbers.get(i).copied().unwrap_or(0);
+            part(left).cmp(&part(right))
+        })
+        .find(|ordering| ordering.is_ne())
+        .unwrap_or(Ordering::Equal)
+}
+
+/// Operators of version constraints, longest first so `~>` and `>=` are not read as `~`
+/// and `>`.
+const COMPARATOR_OPERATORS: &[&str] = &["~>", ">=", "<=", "!=", ">", "<", "=", "^", "~"];
+
+/// The operator and version of a comparator such as `>= 1.5` or `^18.17`, the operator
+/// empty for a bare version.
+pub fn split_comparator(comparator: &str) -> (&str, &str) {
+    let comparator = comparator.trim();
+    COMPARATOR_OPERATORS
+        .iter()
+        .find_map(|operator| Some((*operator, comparator.strip_prefix(operator)?.trim())))
+        .unwrap_or(("", comparator))
+}
+
+/// Whether `version` satisfies `operator bound`, the way npm, Cargo, and Terraform read
+/// them: `^` lets everything right of the first non-zero component grow, `~` the patch,
+/// `~>` only the last component of `bound`, and a bare version must match exactly. An
+/// empty bound such as `*` matches every version.
+pub fn satisfies_comparator(version: &[u64], operator: &str, bound: &[u64]) -> bool {
+    if bound.is_empty() {
+        return !matches!(operator, "<" | ">");
+    }
+    let cmp = |other: &[u64]| compare_version_numbers(version, other);
+    match operator {
+        ">=" => cmp(bound).is_ge(),
+        ">" => cmp(bound).is_gt(),
+        "<=" => cmp(bound).is_le(),
+        "<" => cmp(bound).is_lt(),
+        "!=" => cmp(bound).is_ne(),
+        "^" => {
+            let significant = bound
+                .iter()
+                .position(|&part| part != 0)
+                .unwrap_or(bound.len() - 1);
+            cmp(bound).is_ge() && cmp(&bump_version(bound, significant)).is_lt()
+        }
+        "~" => cmp(bound).is_ge() && cmp(&bump_version(bound, 1.min(bound.len() - 1))).is_lt(),
+        "~>" => {
+            cmp(bound).is_ge() && cmp(&bump_version(bound, bound.len().saturating_sub(2))).is_lt()
+        }
+        _ => cmp(bound).is_eq(),
+    }
+}
+
+/// `numbers` cut after `index`, with that component incremented: the exclusive upper bound
+/// of everything sharing the components up to it.
+pub fn bump_version(numbers: &[u64], index: usize) -> Vec<u64> {
+    let mut bumped = numbers[..=index].to_vec();
+    bumped[index] += 1;
+    bumped
+}
+
+/// How the version formats that check a constraint show an `installed` version that does
+/// not satisfy it.
+pub fn version_mismatch(installed: &str, constraint: &str) -> String {
+    format!("⚠ {installed}≠{constraint}")
+}
+
+/// The process's working directory, or `$PWD` when it cannot be read because the directory
+/// was deleted or its permissions revoked, so the prompt still knows where the shell is.
+pub fn current_dir() -> Option<PathBuf> {
+    working_dir().0
+}
+
+/// [`current_dir`], and whether it is `$PWD` standing in for a working directory that was
+/// deleted.
+pub fn working_dir() -> (Option<PathBuf>, bool) {
+    resolve_working_dir(env::current_dir(), env::var_os("PWD"))
+}
+
+fn resolve_working_dir(
+    current: io::Result<PathBuf>,
+    pwd: Option<OsString>,
+) -> (Option<PathBuf>, bool) {
+    match current {
+        Ok(dir) => (Some(dir), false),
+        Err(error) => {
+            let dir = shell_dir(pwd);
+            let deleted = dir.is_some() && error.kind() == io::ErrorKind::NotFound;
+            (dir, deleted)
+        }
+    }
+}
+
+fn shell_dir(pwd: Option<OsString>) -> Option<PathBuf> {
+    pwd.map(PathBuf::from).filter(|dir| dir.is_absolute())
+}
+
+/// First `name` executable on `PATH`, with symlinks resolved, the way a shell would find it.
+pub fn resolve_binary(name: &str) -> Option<PathBuf> {
+    resolve_binary_in(name, &env::var_os("PATH")?)
+}
+
+fn resolve_binary_in(name: &str, path: &OsStr) -> Option<PathBuf> {
+    let file_name = if cfg!(windows) {
+        format!("{}.exe", name)
+    } else {
+        name.to_string()
+    };
+    env::split_paths(path)
+        .map(|dir| dir.join(&file_name))
+        .find(|candidate| candidate.is_file())
+        .map(|binary| fs::canonicalize(&binary).unwrap_or(binary))
+}
+
+/// A command for a tool prmt runs itself, such as `git` or `node --version`, without the
+/// variables in [`NOISY_ENV`] and in the C locale so its output parses the same everywhere.
+/// Commands from the format (`{cmd}`) and plugins keep the user's environment.
+pub fn tool_command(program: impl AsRef<OsStr>) -> Command {
+    let mut command = Command::new(program);
+    for (key, _) in env::vars_os() {
+        if is_noisy_var(&key) {
+            command.env_remove(key);
+        }
+    }
+    command.env("LC_ALL", "C");
+    command
+}
+
+fn is_noisy_var(key: &OsStr) -> bool {
+    let Some(key) = key.to_str() else {
+        return false;
+    };
+    NOISY_ENV.contains(&key)
+        || NOISY_ENV_PREFIXES
+            .iter()
+            .any(|prefix| key.starts_with(prefix))
+}
+
+/// Identifies a resolved binary by path and modification time, so upgrading it in place or
+/// switching to another install changes the key.
+pub fn binary_key(binary: &Path) -> String {
+    let modified = fs::metadata(binary)
+        .and_then(|metadata| metadata.modified())
+        .ok()
+        .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+        .map_or(0, |elapsed| elapsed.as_secs());
+    format!("{}@{}", binary.display(), modified)
+}
+
+/// Version cache key for `tool`, naming the binary that would run when it is on `PATH`, so
+/// a reinstall or a switch to another install is picked up without waiting for the TTL.
+pub fn tool_key(tool: &str, binary: Option<&Path>) -> String {
+    match binary {
+        Some(binary) => format!("{}+{}", tool, binary_key(binary)),
+        None => tool.to_string(),
+    }
+}
+
+/// Version cache key for a tool that picks its version per project, such as Go from the
+/// `toolchain` of `go.mod` or a version manager from `.tool-versions`: [`tool_key`] plus the
+/// project directory it runs in.
+pub fn project_tool_key(tool: &str, binary: Option<&Path>, project: &Path) -> String {
+    format!("{}+{}", tool_key(tool, binary), project.display())
+}
+
+/// Whether `path` is on a network or FUSE filesystem, where walking a large tree can take
+/// long enough to stall the prompt.
+#[cfg(target_os = "linux")]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    is_network_magic(stat.f_type as u32)
+}
+
+/// Filesystem magic numbers from `statfs(2)` for NFS, SMB/CIFS, FUSE, and other remote
+/// filesystems.
+#[cfg(any(target_os = "linux", test))]
+fn is_network_magic(magic: u32) -> bool {
+    matches!(
+        magic,
+        0x6969 // NFS
+            | 0x517b // SMB
+            | 0xff53_4d42 // CIFS
+            | 0xfe53_4d42 // SMB2
+            | 0x6573_5546 // FUSE
+            | 0x5346_414f // AFS
+            | 0x7375_7245 // Coda
+            | 0x0102_1997 // 9P
+            | 0x00c3_6400 // Ceph
+            | 0x564c // NCP
+    )
+}
+
+#[cfg(any(target_os = "macos", target_os = "freebsd"))]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    use std::ffi::CStr;
+    use std::os::unix::ffi::OsStrExt;
+
+    let Ok(path) = std::ffi::CString::new(path.as_os_str().as_bytes()) else {
+        return false;
+    };
+    let mut stat: libc::statfs = unsafe { std::mem::zeroed() };
+    if unsafe { libc::statfs(path.as_ptr(), &mut stat) } != 0 {
+        return false;
+    }
+    let name = unsafe { CStr::from_ptr(stat.f_fstypename.as_ptr()) };
+    matches!(
+        name.to_bytes(),
+        b"nfs" | b"smbfs" | b"afpfs" | b"webdav" | b"fusefs" | b"macfuse" | b"osxfuse"
+    )
+}
+
+/// UNC paths and mapped network drives.
+#[cfg(windows)]
+pub fn is_network_filesystem(path: &Path) -> bool {
+    use std::os::windows::ffi::OsStrExt;
+    use std::path::{Component, Prefix};
+
+    const DRIVE_REMOTE: u32 = 4;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetDriveTypeW(root: *const u16) -> u32;
+    }
+
+    let Some(Component::Prefix(prefix)) = path.components().next() else {
+        return false;
+    };
+    match prefix.kind() {
+        Prefix::UNC(..) | Prefix::VerbatimUNC(..) => true,
+        Prefix::Disk(letter) | Prefix::VerbatimDisk(letter) => {
+            let root: Vec<u16> = OsStr::new(&format!("{}:\\", letter as char))
+                .encode_wide()
+                .chain([0])
+                .collect();
+            unsafe { GetDriveTypeW(root.as_ptr()) == DRIVE_REMOTE }
+        }
+        _ => false,
+    }
+}
+
+#[cfg(not(any(
+    target_os = "linux",
+    target_os = "macos",
+    target_os = "freebsd",
+    windows
+)))]
+pub fn is_network_filesystem(_path: &Path) -> bool {
+    false
+}
+
+#[cfg(unix)]
+const SIGNALS: &[(i32, &str)] = &[
+    (libc::SIGHUP, "SIGHUP"),
+    (libc::SIGINT, "SIGINT"),
+    (libc::SIGQUIT, "SIGQUIT"),
+    (libc::SIGILL, "SIGILL"),
+    (libc::SIGTRAP, "SIGTRAP"),
+    (libc::SIGABRT, "SIGABRT"),
+    (libc::SIGBUS, "SIGBUS"),
+    (libc::SIGFPE, "SIGFPE"),
+    (libc::SIGKILL, "SIGKILL"),
+    (libc::SIGUSR1, "SIGUSR1"),
+    (libc::SIGSEGV, "SIGSEGV"),
+    (libc::SIGUSR2, "SIGUSR2"),
+    (libc::SIGPIPE, "SIGPIPE"),
+    (libc::SIGALRM, "SIGALRM"),
+    (libc::SIGTERM, "SIGTERM"),
+    (libc::SIGCHLD, "SIGCHLD"),
+    (libc::SIGCONT, "SIGCONT"),
+    (libc::SIGSTOP, "SIGSTOP"),
+    (libc::SIGTSTP, "SIGTSTP"),
+    (libc::SIGTTIN, "SIGTTIN"),
+    (libc::SIGTTOU, "SIGTTOU"),
+    (libc::SIGURG, "SIGURG"),
+    (libc::SIGXCPU, "SIGXCPU"),
+    (libc::SIGXFSZ, "SIGXFSZ"),
+    (libc::SIGVTALRM, "SIGVTALRM"),
+    (libc::SIGPROF, "SIGPROF"),
+    (libc::SIGWINCH, "SIGWINCH"),
+    (libc::SIGIO, "SIGIO"),
+    (libc::SIGSYS, "SIGSYS"),
+];
+
+#[cfg(not(unix))]
+const SIGNALS: &[(i32, &str)] = &[];
+
+/// Name of the signal that killed a command, from the 128 + N exit code shells report for
+/// it, e.g. `SIGINT` for 130. Codes of other failures, and all codes on Windows, have none.
+pub fn signal_name(exit_code: i32) -> Option<&'static str> {
+    let signal = exit_code.checked_sub(128)?;
+    SIGNALS
+        .iter()
+        .find(|&&(number, _)| number == signal)
+        .map(|&(_, name)| name)
+}
+
+/// Whether the shell runs over SSH, as told by the variables sshd sets for the session.
+pub fn is_ssh_session() -> bool {
+    ["SSH_CONNECTION", "SSH_CLIENT", "SSH_TTY"]
+        .iter()
+        .any(|name| env::var_os(name).is_some_and(|value| !value.is_empty()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serial_test::serial;
+    use tempfile::tempdir;
+
+    #[test]
+    fn versions_compare_numerically() {
+        assert_eq!(version_numbers("v1.5.0-beta1"), vec![1, 5, 0]);
+        let compare =
+            |left, right| compare_version_numbers(&version_numbers(left), &version_numbers(right));
+        assert_eq!(compare("1.10.0", "1.9.2"), Ordering::Greater);
+        assert_eq!(compare("1.5", "1.5.0"), Ordering::Equal);
+        assert_eq!(compare("18.19.0", "20"), Ordering::Less);
+    }
+
+    #[test]
+    fn comparators_follow_their_operator() {
+        let satisfies = |version: &str, comparator: &str| {
+            let (operator, bound) = split_comparator(comparator);
+            satisfies_comparator(&version_numbers(version), operator, &version_numbers(bound))
+        };
+        assert_eq!(split_comparator(" >= 1.5 "), (">=", "1.5"));
+        assert_eq!(split_comparator("~>1.2"), ("~>", "1.2"));
+        assert_eq!(split_comparator("1.6.0"), ("", "1.6.0"));
+
+        assert!(satisfies("1.5.7", ">= 1.5.0"));
+        assert!(!satisfies("1.4.6", ">= 1.5"));
+        assert!(satisfies("1.9.0", "~> 1.2"));
+        assert!(!satisfies("2.0.0", "~> 1.2"));
+        assert!(satisfies("1.2.9", "~> 1.2.0"));
+        assert!(!satisfies("1.3.0", "~> 1.2.0"));
+        assert!(satisfies("0.2.5", "^0.2.3"));
+        assert!(!satisfies("0.3.0", "^0.2.3"));
+        assert!(satisfies("20.5.1", "~20.5"));
+        assert!(!satisfies("20.6.0", "~20.5"));
+        assert!(satisfies("1.6.0", "1.6"));
+        assert!(!satisfies("1.6.0", "!= 1.6.0"));
+        assert!(satisfies("22.0.0", "*"));
+
+        assert_eq!(version_mismatch("1.70.0", "1.74"), "⚠ 1.70.0≠1.74");
+    }
+
+    #[test]
+    fn noisy_variables_are_recognized() {
+        assert!(is_noisy_var(OsStr::new("NODE_OPTIONS")));
+        assert!(is_noisy_var(OsStr::new("GIT_TRACE")));
+        assert!(is_noisy_var(OsStr::new("GIT_TRACE2_PERF")));
+        assert!(!is_noisy_var(OsStr::new("PATH")));
+        assert!(!is_noisy_var(OsStr::new("GIT_DIR")));
+    }
+
+    #[test]
+    #[serial]
+    fn tool_commands_drop_noisy_variables() {
+        unsafe {
+            env::set_var("GIT_TRACE_PERFORMANCE", "1");
+        }
+        let command = tool_command("git");
+        unsafe {
+            env::remove_var("GIT_TRACE_PERFORMANCE");
+        }
+
+        let envs: Vec<_> = command.get_envs().collect();
+        assert!(envs.contains(&(OsStr::new("GIT_TRACE_PERFORMANCE"), None)));
+        assert!(envs.contains(&(OsStr::new("LC_ALL"), Some(OsStr::new("C")))));
+    }
+
+    #[test]
+    fn network_magic_numbers() {
+        assert!(is_network_magic(0x6969));
+        assert!(is_network_magic(0xff53_4d42));
+        assert!(is_network_magic(0x6573_5546));
+        assert!(!is_network_magic(0xef53)); // ext4
+        assert!(!is_network_magic(0x0102_1994)); // tmpfs
+    }
+
+    #[test]
+    fn local_directories_are_not_network_filesystems() {
+        assert!(!is_network_filesystem(&env::temp_dir()));
+        assert!(!is_network_filesystem(Path::new("/definitely/missing/dir")));
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn signal_names_come_from_exit_codes() {
+        assert_eq!(signal_name(130), Some("SIGINT"));
+        assert_eq!(signal_name(128 + libc::SIGSEGV), Some("SIGSEGV"));
+        assert_eq!(signal_name(128), None);
+        assert_eq!(signal_name(1), None);
+        assert_eq!(signal_name(255), None);
+    }
+
+    #[test]
+    fn shell_dir_only_trusts_absolute_pwd() {
+        let absolute = env::temp_dir().join("gone");
+        assert_eq!(shell_dir(Some(absolute.clone().into())), Some(absolute));
+        assert_eq!(shell_dir(Some("relative/dir".into())), None);
+        assert_eq!(shell_dir(None), None);
+    }
+
+    #[test]
+    fn only_a_missing_working_directory_counts_as_deleted() {
+        let pwd = env::temp_dir().join("gone");
+        let failed = |kind| Err(io::Error::from(kind));
+        assert_eq!(
+            resolve_working_dir(failed(io::ErrorKind::NotFound), Some(pwd.clone().into())),
+            (Some(pwd.clone()), true)
+        );
+        assert_eq!(
+            resolve_working_dir(
+                failed(io::ErrorKind::PermissionDenied),
+                Some(pwd.clone().into())
+            ),
+            (Some(pwd.clone()), false)
+        );
+        assert_eq!(
+            resolve_working_dir(failed(io::ErrorKind::NotFound), None),
+            (None, false)
+        );
+        assert_eq!(
+            resolve_working_dir(Ok(pwd.clone()), Some("/elsewhere".into())),
+            (Some(pwd), false)
+        );
+    }
+
+    #[test]
+    fn resolve_binary_takes_the_first_match_on_path() {
+        let first = tempdir().unwrap();
+        let second = tempdir().unwrap();
+        let file_name = if cfg!(windows) { "node.exe" } else { "node" };
+        fs::write(second.path().join(file_name), "").unwrap();
+        let path = env::join_paths([first.path(), second.path()]).unwrap();
+
+        let binary = resolve_binary_in("node", &path).expect("node on path");
+        assert_eq!(
+            binary,
+            fs::canonicalize(second.path().join(file_name)).unwrap()
+        );
+        assert!(binary_key(&binary).starts_with(&format!("{}@", binary.display())));
+
+        fs::write(first.path().join(file_name), "").unwrap();
+        let binary = resolve_binary_in("node", &path).expect("node on path");
+        assert_eq!(
+            binary,
+            fs::canonicalize(first.path().join(file_name)).unwrap()
+        );
+        assert_eq!(resolve_binary_in("deno", &path), None);
+    }
+
+    #[test]
+    fn tool_key_names_the_binary_when_resolved() {
+        let dir = tempdir().unwrap();
+        let binary = dir.path().join("bun");
+        fs::write(&binary, "").unwrap();
+
+        assert_eq!(tool_key("bun", None), "bun");
+        assert_eq!(
+            tool_key("bun", Some(&binary)),
+            format!("bun+{}", binary_key(&binary))
+        );
+        assert_ne!(
+            project_tool_key("go", None, Path::new("/src/a")),
+            project_tool_key("go", None, Path::new("/src/b"))
+        );
+    }
+}
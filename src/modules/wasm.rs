@@ -0,0 +1,224 @@
+//! Sandboxed WASM plugins: components implementing `wit/plugin.wit`, loaded from the `plugins`
+//! directory next to the config. A plugin imports nothing, and each call is bounded in
+//! instructions and memory, so a broken plugin fails its segment instead of the prompt.
+
+use crate::cache;
+use crate::config;
+use crate::error::{PromptError, Result};
+use crate::module_trait::{Module, ModuleContext};
+use sha2::{Digest, Sha256};
+use std::fs;
+use std::path::{Path, PathBuf};
+use std::sync::{Mutex, OnceLock, PoisonError};
+use wasmtime::component::{Component, Linker};
+use wasmtime::{Config, Engine, Store, StoreLimits, StoreLimitsBuilder};
+
+wasmtime::component::bindgen!({ path: "wit/plugin.wit", world: "plugin" });
+
+use exports::prmt::plugin::module::Context as PluginContext;
+
+/// Instructions a single call may execute before it traps.
+const FUEL: u64 = 500_000_000;
+/// Linear memory a plugin may grow to.
+const MEMORY_LIMIT: usize = 16 << 20;
+
+const COMPILED_DIR: &str = "plugins";
+
+struct Instance {
+    store: Store<StoreLimits>,
+    plugin: Plugin,
+}
+
+/// A module backed by a WASM plugin, compiled and instantiated on first use.
+pub struct WasmModule {
+    name: String,
+    path: PathBuf,
+    instance: OnceLock<std::result::Result<Mutex<Instance>, String>>,
+}
+
+impl WasmModule {
+    pub fn new(name: &str, path: PathBuf) -> Self {
+        Self {
+            name: name.to_string(),
+            path,
+            instance: OnceLock::new(),
+        }
+    }
+
+    /// The `<name>.wasm` plugin in the plugins directory.
+    pub fn find(name: &str) -> Option<Self> {
+        config::plugin_path(name).map(|path| Self::new(name, path))
+    }
+
+    fn error(&self, error: impl ToString) -> PromptError {
+        PromptError::PluginError {
+            module: self.name.clone(),
+            error: error.to_string(),
+        }
+    }
+
+    fn call<T>(
+        &self,
+        call: impl FnOnce(&Plugin, &mut Store<StoreLimits>) -> wasmtime::Result<T>,
+    ) -> Result<T> {
+        let instance = self
+            .instance
+            .get_or_init(|| {
+                instantiate(&self.path)
+                    .map(Mutex::new)
+                    .map_err(|error| error.to_string())
+            })
+            .as_ref()
+            .map_err(|error| self.error(error))?;
+        let mut instance = instance.lock().unwrap_or_else(PoisonError::into_inner);
+        let Instance { store, plugin } = &mut *instance;
+        store.set_fuel(FUEL).map_err(|error| self.error(error))?;
+        call(plugin, store).map_err(|error| self.error(error))
+    }
+}
+
+fn engine() -> std::result::Result<&'static Engine, String> {
+    static ENGINE: OnceLock<std::result::Result<Engine, String>> = OnceLock::new();
+    ENGINE
+        .get_or_init(|| {
+            let mut config = Config::new();
+            config.consume_fuel(true);
+            Engine::new(&config).map_err(|error| error.to_string())
+        })
+        .as_ref()
+        .map_err(Clone::clone)
+}
+
+fn instantiate(path: &Path) -> wasmtime::Result<Instance> {
+    let engine = engine().map_err(wasmtime::Error::msg)?;
+    let component = compile(engine, path)?;
+    let limits = StoreLimitsBuilder::new()
+        .memory_size(MEMORY_LIMIT)
+        .instances(1)
+        .build();
+    let mut store = Store::new(engine, limits);
+    store.limiter(|limits| limits);
+    let plugin = Plugin::instantiate(&mut store, &component, &Linker::new(engine))?;
+    Ok(Instance { store, plugin })
+}
+
+/// Compiling a component takes far longer than rendering a prompt, so the machine code is kept
+/// in the cache directory, named after the SHA-256 of the plugin it was compiled from.
+fn compile(engine: &Engine, path: &Path) -> wasmtime::Result<Component> {
+    let source = fs::read(path)?;
+    let dir = cache::cache_dir().map(|dir| dir.join(COMPILED_DIR));
+    let name = format!("{}.cwasm", cache::hex(&Sha256::digest(&source)));
+    if let Some(dir) = &dir
+        && is_private(dir)
+        && is_private(&dir.join(&name))
+    {
+        // SAFETY: the artifact is named after the plugin's content and sits in a directory only
+        // this user can write, so it is the `precompile_component` output written below, renamed
+        // into place once complete; wasmtime rejects artifacts from another version or
+        // configuration.
+        if let Ok(component) = unsafe { Component::deserialize_file(engine, dir.join(&name)) } {
+            return Ok(component);
+        }
+    }
+    let compiled = engine.precompile_component(&source)?;
+    if let Some(dir) = &dir {
+        let _ = cache::write_atomic(dir, &name, &compiled);
+    }
+    // SAFETY: `compiled` was just produced by this engine.
+    unsafe { Component::deserialize(engine, &compiled) }
+}
+
+/// Whether `path` belongs to the current user and nobody else may write it, so machine code
+/// loaded from it cannot have been planted by another account.
+#[cfg(unix)]
+fn is_private(path: &Path) -> bool {
+    use std::os::unix::fs::MetadataExt;
+    // SAFETY: `geteuid` has no preconditions and cannot fail.
+    let uid = unsafe { libc::geteuid() };
+    fs::symlink_metadata(path)
+        .is_ok_and(|metadata| metadata.uid() == uid && metadata.mode() & 0o022 == 0)
+}
+
+/// Ownership is not checked here, so artifacts are never loaded and plugins are recompiled.
+#[cfg(not(unix))]
+fn is_private(_path: &Path) -> bool {
+    false
+}
+
+impl Module for WasmModule {
+    fn is_blocking(&self) -> bool {
+        true
+    }
+
+    fn validate_format(&self, format: &str) -> Result<()> {
+        self.call(|plugin, store| {
+            plugin
+                .prmt_plugin_module()
+                .call_validate_format(store, format)
+        })?
+        .map_err(|valid_formats| PromptError::InvalidFormat {
+            module: self.name.clone(),
+            format: format.to_string(),
+            valid_formats,
+        })
+    }
+
+    fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+        let context = PluginContext {
+            no_version: context.no_version,
+            exit_code: context.exit_code,
+            duration_ms: context
+                .duration
+                .map(|duration| duration.as_millis().try_into().unwrap_or(u64::MAX)),
+            command: context.command.clone(),
+            shell: context.shell.name().to_string(),
+            cwd: context
+                .current_dir()
+                .map(|cwd| cwd.to_string_lossy().into_owned()),
+            values: context
+                .values
+                .iter()
+                .map(|(key, value)| (key.clone(), value.clone()))
+                .collect(),
+        };
+        self.call(|plugin, store| {
+            plugin
+                .prmt_plugin_module()
+                .call_render(store, format, &context)
+        })?
+        .map_err(|error| self.error(error))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn broken_plugins_fail_their_segment() {
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("broken.wasm");
+        fs::write(&path, b"not wasm").unwrap();
+        let module = WasmModule::new("broken", path);
+
+        let error = module.render("", &ModuleContext::default()).unwrap_err();
+        assert!(matches!(error, PromptError::PluginError { ref module, .. } if module == "broken"));
+        assert!(module.validate_format("").is_err());
+    }
+
+    #[cfg(unix)]
+    #[test]
+    fn artifacts_writable_by_others_are_not_loaded() {
+        use std::os::unix::fs::PermissionsExt;
+
+        let dir = tempfile::tempdir().unwrap();
+        let artifact = dir.path().join("plugin.cwasm");
+        fs::write(&artifact, b"").unwrap();
+        fs::set_permissions(&artifact, fs::Permissions::from_mode(0o644)).unwrap();
+        assert!(is_private(&artifact));
+
+        fs::set_permissions(&artifact, fs::Permissions::from_mode(0o666)).unwrap();
+        assert!(!is_private(&artifact));
+        assert!(!is_private(&dir.path().join("missing.cwasm")));
+    }
+}
@@ -1,21 +1,49 @@
+//! Template parsing. The scanner walks bytes with `memchr`, but every token boundary it
+//! produces is either an end of the input or the position of an ASCII delimiter (`{`, `}`,
+//! `\`, `:`, `$`, `=`). UTF-8 never uses ASCII bytes inside a multi-byte sequence, so those
+//! positions are always char boundaries. Slices are taken from the `&str` rather than the
+//! bytes, so a broken invariant panics instead of producing invalid strings.
+#![forbid(unsafe_code)]
+
+use crate::error::PromptError;
+use crate::trace::trace_span;
 use std::borrow::Cow;
-
+use std::collections::HashMap;
+use std::hash::{DefaultHasher, Hash, Hasher};
+use std::ops::{Deref, Range};
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Arc, RwLock};
+
+/// The fields of one `{module:style:format:prefix:suffix:key=value}` placeholder. Options
+/// are added over time, so outside this crate build it with [`Params::new`].
 #[derive(Debug, Clone, PartialEq)]
+#[non_exhaustive]
 pub struct Params<'a> {
     pub module: Cow<'a, str>,
     pub style: Cow<'a, str>,
     pub format: Cow<'a, str>,
     pub prefix: Cow<'a, str>,
     pub suffix: Cow<'a, str>,
+    pub when: Option<Cow<'a, str>>,
+    /// `timeout=MS`: how long this placeholder may take before it is shown as timed out.
+    pub timeout: Option<Cow<'a, str>>,
+    /// `truncate=N`: the most display columns the module text may take; a negative `N` keeps
+    /// the end of the text instead of the start.
+    pub truncate: Option<Cow<'a, str>>,
 }
 
 #[derive(Debug, Clone, PartialEq)]
 pub enum Token<'a> {
     Text(Cow<'a, str>),
     Placeholder(Params<'a>),
+    Var(Cow<'a, str>),
 }
 
+/// Named options accepted as trailing `:key=value` placeholder fields.
+pub const OPTION_KEYS: &[&str] = &["when", "timeout", "truncate"];
+
 pub struct Parser<'a> {
+    input: &'a str,
     bytes: &'a [u8],
     pos: usize,
 }
@@ -23,6 +51,7 @@ pub struct Parser<'a> {
 impl<'a> Parser<'a> {
     pub fn new(input: &'a str) -> Self {
         Self {
+            input,
             bytes: input.as_bytes(),
             pos: 0,
         }
@@ -32,11 +61,9 @@ impl<'a> Parser<'a> {
         self.pos = pos.min(self.bytes.len());
     }
 
-    /// # Safety
-    /// `start` must be less than or equal to `self.pos`, and the range
-    /// `start..self.pos` must lie on UTF-8 character boundaries within `self.bytes`.
-    unsafe fn current_slice(&self, start: usize) -> &'a str {
-        unsafe { std::str::from_utf8_unchecked(&self.bytes[start..self.pos]) }
+    /// The input from `start` up to the current position.
+    fn current_slice(&self, start: usize) -> &'a str {
+        &self.input[start..self.pos]
     }
 
     fn remaining(&self) -> &'a [u8] {
@@ -44,6 +71,7 @@ impl<'a> Parser<'a> {
     }
 
     pub fn parse(mut self) -> Vec<Token<'a>> {
+        trace_span!("parse", len = self.bytes.len());
         // Pre-allocate capacity based on open brace count
         let open_count = memchr::memchr_iter(b'{', self.bytes).count();
 
@@ -62,6 +90,20 @@ impl<'a> Parser<'a> {
         tokens
     }
 
+    /// Like [`parse`](Self::parse), pairing every token with the byte range of the input it
+    /// was read from.
+    pub fn parse_spanned(mut self) -> Vec<(Token<'a>, Range<usize>)> {
+        let mut tokens = Vec::new();
+        loop {
+            let start = self.pos;
+            let Some(token) = self.next_token() else {
+                break;
+            };
+            tokens.push((token, start..self.pos));
+        }
+        tokens
+    }
+
     #[inline]
     fn next_token(&mut self) -> Option<Token<'a>> {
         if self.pos >= self.bytes.len() {
@@ -70,13 +112,22 @@ impl<'a> Parser<'a> {
 
         let start = self.pos;
 
+        if self.remaining().starts_with(b"${") {
+            if let Some(token) = self.parse_var() {
+                return Some(token);
+            }
+            self.skip_to(start + 1);
+            return Some(Token::Text(Cow::Borrowed("$")));
+        }
+
         if let Some(offset) = memchr::memchr3(b'{', b'\\', b'}', self.remaining()) {
-            let abs_pos = self.pos + offset;
+            let mut abs_pos = self.pos + offset;
+            if self.bytes[abs_pos] == b'{' && abs_pos > start && self.bytes[abs_pos - 1] == b'$' {
+                abs_pos -= 1;
+            }
             if abs_pos > start {
                 self.skip_to(abs_pos);
-                return Some(Token::Text(Cow::Borrowed(unsafe {
-                    self.current_slice(start)
-                })));
+                return Some(Token::Text(Cow::Borrowed(self.current_slice(start))));
             }
 
             let token = match self.bytes[abs_pos] {
@@ -96,6 +147,16 @@ impl<'a> Parser<'a> {
                                 self.skip_to(abs_pos + 2);
                                 Some(Token::Text(Cow::Borrowed(escaped)))
                             }
+                            b'u' => match decode_unicode_escape(self.bytes, abs_pos) {
+                                Some((ch, end)) => {
+                                    self.skip_to(end);
+                                    Some(Token::Text(Cow::Owned(ch.to_string())))
+                                }
+                                None => {
+                                    self.skip_to(abs_pos + 1);
+                                    Some(Token::Text(Cow::Borrowed("\\")))
+                                }
+                            },
                             _ => {
                                 self.skip_to(abs_pos + 1);
                                 Some(Token::Text(Cow::Borrowed("\\")))
@@ -108,11 +169,9 @@ impl<'a> Parser<'a> {
                 }
                 b'{' => {
                     if let Some(end_pos) = find_unescaped(self.bytes, abs_pos + 1, b'}') {
-                        let content = &self.bytes[abs_pos + 1..end_pos];
+                        let content = &self.input[abs_pos + 1..end_pos];
 
-                        if let Some(params) =
-                            parse_placeholder(unsafe { std::str::from_utf8_unchecked(content) })
-                        {
+                        if let Some(params) = parse_placeholder(content) {
                             self.skip_to(end_pos + 1);
                             Some(Token::Placeholder(params))
                         } else {
@@ -135,18 +194,54 @@ impl<'a> Parser<'a> {
         }
 
         self.skip_to(self.bytes.len());
-        Some(Token::Text(Cow::Borrowed(unsafe {
-            self.current_slice(start)
-        })))
+        Some(Token::Text(Cow::Borrowed(self.current_slice(start))))
+    }
+
+    fn parse_var(&mut self) -> Option<Token<'a>> {
+        let name_start = self.pos + 2;
+        let end_pos = name_start + memchr::memchr(b'}', &self.bytes[name_start..])?;
+        let name = &self.input[name_start..end_pos];
+        if !is_var_name(name.as_bytes()) {
+            return None;
+        }
+
+        self.skip_to(end_pos + 1);
+        Some(Token::Var(Cow::Borrowed(name)))
+    }
+}
+
+fn is_var_name(name: &[u8]) -> bool {
+    match name.first() {
+        Some(first) if first.is_ascii_alphabetic() || *first == b'_' => name
+            .iter()
+            .all(|byte| byte.is_ascii_alphanumeric() || *byte == b'_'),
+        _ => false,
     }
 }
 
+/// Decodes `\u{XXXX}` starting at the backslash in `pos`, returning the
+/// character and the index just past the closing brace.
+fn decode_unicode_escape(bytes: &[u8], pos: usize) -> Option<(char, usize)> {
+    let rest = bytes.get(pos..)?.strip_prefix(b"\\u{")?;
+    let len = memchr::memchr(b'}', rest)?;
+    if len == 0 || len > 6 {
+        return None;
+    }
+    let hex = std::str::from_utf8(&rest[..len]).ok()?;
+    let code = u32::from_str_radix(hex, 16).ok()?;
+    let ch = char::from_u32(code)?;
+    Some((ch, pos + 3 + len + 1))
+}
+
 fn find_unescaped(bytes: &[u8], mut i: usize, target: u8) -> Option<usize> {
     while i < bytes.len() {
         let offset = memchr::memchr2(b'\\', target, &bytes[i..])?;
         let pos = i + offset;
         if bytes[pos] == b'\\' {
-            i = pos + 2;
+            i = match decode_unicode_escape(bytes, pos) {
+                Some((_, end)) => end,
+                None => pos + 2,
+            };
             continue;
         }
         return Some(pos);
@@ -155,6 +250,20 @@ fn find_unescaped(bytes: &[u8], mut i: usize, target: u8) -> Option<usize> {
 }
 
 fn parse_placeholder<'a>(content: &'a str) -> Option<Params<'a>> {
+    let mut content = content;
+    let mut when = None;
+    let mut timeout = None;
+    let mut truncate = None;
+    while let Some((rest, key, value)) = split_trailing_option(content) {
+        match key {
+            "when" if when.is_none() => when = Some(unescape_if_needed(value)),
+            "timeout" if timeout.is_none() => timeout = Some(unescape_if_needed(value)),
+            "truncate" if truncate.is_none() => truncate = Some(unescape_if_needed(value)),
+            _ => break,
+        }
+        content = rest;
+    }
+
     let fields = split_fields(content);
 
     if fields[0].is_empty() {
@@ -167,9 +276,43 @@ fn parse_placeholder<'a>(content: &'a str) -> Option<Params<'a>> {
         format: unescape_if_needed(fields[2]),
         prefix: unescape_if_needed(fields[3]),
         suffix: unescape_if_needed(fields[4]),
+        when,
+        timeout,
+        truncate,
     })
 }
 
+/// Splits a trailing `:key=value` option off placeholder content, returning the
+/// remaining content, the key, and the raw value.
+fn split_trailing_option(content: &str) -> Option<(&str, &str, &str)> {
+    let bytes = content.as_bytes();
+    let mut last_colon = None;
+    let mut i = 0;
+
+    while i < bytes.len() {
+        match bytes[i] {
+            b'\\' => {
+                i = match decode_unicode_escape(bytes, i) {
+                    Some((_, end)) => end,
+                    None => i + 2,
+                };
+            }
+            b':' => {
+                last_colon = Some(i);
+                i += 1;
+            }
+            _ => i += 1,
+        }
+    }
+
+    let colon = last_colon?;
+    let (key, value) = content[colon + 1..].split_once('=')?;
+    if !OPTION_KEYS.contains(&key) {
+        return None;
+    }
+    Some((&content[..colon], key, value))
+}
+
 fn split_fields(s: &str) -> [&str; 5] {
     let mut fields = [""; 5];
     let mut field_idx = 0;
@@ -179,9 +322,12 @@ fn split_fields(s: &str) -> [&str; 5] {
 
     while i < bytes.len() && field_idx < 4 {
         if bytes[i] == b'\\' {
-            i += 2;
+            i = match decode_unicode_escape(bytes, i) {
+                Some((_, end)) => end,
+                None => i + 2,
+            };
         } else if bytes[i] == b':' {
-            fields[field_idx] = unsafe { std::str::from_utf8_unchecked(&bytes[start..i]) };
+            fields[field_idx] = &s[start..i];
             field_idx += 1;
             start = i + 1;
             i += 1;
@@ -190,7 +336,7 @@ fn split_fields(s: &str) -> [&str; 5] {
         }
     }
 
-    fields[field_idx] = unsafe { std::str::from_utf8_unchecked(&bytes[start..]) };
+    fields[field_idx] = &s[start..];
     fields
 }
 
@@ -200,11 +346,16 @@ fn unescape_if_needed(s: &str) -> Cow<'_, str> {
     }
 
     let mut result = String::with_capacity(s.len());
-    let mut chars = s.chars();
+    let mut chars = s.char_indices().peekable();
 
-    while let Some(ch) = chars.next() {
+    while let Some((index, ch)) = chars.next() {
         if ch == '\\' {
-            if let Some(next) = chars.next() {
+            if let Some((ch, end)) = decode_unicode_escape(s.as_bytes(), index) {
+                result.push(ch);
+                while chars.next_if(|&(next, _)| next < end).is_some() {}
+                continue;
+            }
+            if let Some((_, next)) = chars.next() {
                 match next {
                     'n' => result.push('\n'),
                     't' => result.push('\t'),
@@ -232,10 +383,207 @@ pub fn parse(template: &str) -> Vec<Token<'_>> {
     Parser::new(template).parse()
 }
 
+pub fn parse_spanned(template: &str) -> Vec<(Token<'_>, Range<usize>)> {
+    Parser::new(template).parse_spanned()
+}
+
+/// The mistake behind a `{` that the parser kept as literal text, given the span of that
+/// text token: a placeholder without a module name, or one that is never closed. `None` for
+/// any other text.
+pub fn syntax_error(template: &str, span: Range<usize>) -> Option<PromptError> {
+    if template.get(span.clone())? != "{" {
+        return None;
+    }
+    Some(
+        match find_unescaped(template.as_bytes(), span.start + 1, b'}') {
+            Some(end) => PromptError::Parse {
+                span: span.start..end + 1,
+                message: "placeholder has no module name".to_string(),
+            },
+            None => PromptError::Parse {
+                span,
+                message: "unclosed placeholder; escape a literal brace as \\{".to_string(),
+            },
+        },
+    )
+}
+
+fn owned(text: Cow<'_, str>) -> Cow<'static, str> {
+    Cow::Owned(text.into_owned())
+}
+
+fn borrowed(text: &str) -> Cow<'_, str> {
+    Cow::Borrowed(text)
+}
+
+impl<'a> Params<'a> {
+    /// A `{module}` placeholder with every other field empty.
+    #[allow(dead_code)]
+    pub fn new(module: impl Into<Cow<'a, str>>) -> Self {
+        Params {
+            module: module.into(),
+            style: Cow::Borrowed(""),
+            format: Cow::Borrowed(""),
+            prefix: Cow::Borrowed(""),
+            suffix: Cow::Borrowed(""),
+            when: None,
+            timeout: None,
+            truncate: None,
+        }
+    }
+}
+
+impl Params<'_> {
+    pub fn into_owned(self) -> Params<'static> {
+        Params {
+            module: owned(self.module),
+            style: owned(self.style),
+            format: owned(self.format),
+            prefix: owned(self.prefix),
+            suffix: owned(self.suffix),
+            when: self.when.map(owned),
+            timeout: self.timeout.map(owned),
+            truncate: self.truncate.map(owned),
+        }
+    }
+
+    /// A copy whose fields borrow from `self` instead of allocating.
+    pub fn as_borrowed(&self) -> Params<'_> {
+        Params {
+            module: borrowed(&self.module),
+            style: borrowed(&self.style),
+            format: borrowed(&self.format),
+            prefix: borrowed(&self.prefix),
+            suffix: borrowed(&self.suffix),
+            when: self.when.as_deref().map(borrowed),
+            timeout: self.timeout.as_deref().map(borrowed),
+            truncate: self.truncate.as_deref().map(borrowed),
+        }
+    }
+}
+
+impl Token<'_> {
+    pub fn into_owned(self) -> Token<'static> {
+        match self {
+            Token::Text(text) => Token::Text(owned(text)),
+            Token::Placeholder(params) => Token::Placeholder(params.into_owned()),
+            Token::Var(name) => Token::Var(owned(name)),
+        }
+    }
+
+    /// A copy whose fields borrow from `self` instead of allocating.
+    pub fn as_borrowed(&self) -> Token<'_> {
+        match self {
+            Token::Text(text) => Token::Text(borrowed(text)),
+            Token::Placeholder(params) => Token::Placeholder(params.as_borrowed()),
+            Token::Var(name) => Token::Var(borrowed(name)),
+        }
+    }
+}
+
+struct CachedTemplate {
+    source: Box<str>,
+    tokens: Arc<[Token<'static>]>,
+}
+
+/// Parsed templates by hash of their source. Formats are few and repeat on every prompt, so
+/// the cache is simply dropped once it grows past `PARSE_CACHE_LIMIT` entries.
+static PARSE_CACHE: RwLock<Option<HashMap<u64, CachedTemplate>>> = RwLock::new(None);
+const PARSE_CACHE_LIMIT: usize = 64;
+static PARSE_CACHE_DISABLED: AtomicBool = AtomicBool::new(false);
+
+fn template_hash(template: &str) -> u64 {
+    let mut hasher = DefaultHasher::new();
+    template.hash(&mut hasher);
+    hasher.finish()
+}
+
+/// Like [`parse`], but returns owned tokens shared with every other caller parsing the same
+/// template, so repeated renders of one format skip parsing entirely.
+pub fn parse_cached(template: &str) -> Arc<[Token<'static>]> {
+    let key = template_hash(template);
+    if let Ok(cache) = PARSE_CACHE.read()
+        && let Some(cached) = cache.as_ref().and_then(|cache| cache.get(&key))
+        && &*cached.source == template
+    {
+        return Arc::clone(&cached.tokens);
+    }
+
+    let tokens: Arc<[Token<'static>]> =
+        parse(template).into_iter().map(Token::into_owned).collect();
+    if let Ok(mut cache) = PARSE_CACHE.write() {
+        let cache = cache.get_or_insert_with(HashMap::new);
+        if cache.len() >= PARSE_CACHE_LIMIT {
+            cache.clear();
+        }
+        cache.insert(
+            key,
+            CachedTemplate {
+                source: template.into(),
+                tokens: Arc::clone(&tokens),
+            },
+        );
+    }
+    tokens
+}
+
+/// Makes the renderer parse each template in place instead of going through
+/// [`parse_cached`]. A prompt process renders each format once, so an owned copy in the cache
+/// would only cost time; library callers and loops like `--bench` keep the cache.
+pub fn disable_parse_cache() {
+    PARSE_CACHE_DISABLED.store(true, Ordering::Relaxed);
+}
+
+/// Tokens of a template, shared from the parse cache or parsed for a single render.
+pub(crate) enum Parsed<'a> {
+    Cached(Arc<[Token<'static>]>),
+    Fresh(Vec<Token<'a>>),
+}
+
+impl<'a> Deref for Parsed<'a> {
+    type Target = [Token<'a>];
+
+    fn deref(&self) -> &[Token<'a>] {
+        match self {
+            Parsed::Cached(tokens) => tokens,
+            Parsed::Fresh(tokens) => tokens,
+        }
+    }
+}
+
+/// [`parse_cached`], or [`parse`] once [`disable_parse_cache`] has been called.
+pub(crate) fn parse_template(template: &str) -> Parsed<'_> {
+    if PARSE_CACHE_DISABLED.load(Ordering::Relaxed) {
+        Parsed::Fresh(parse(template))
+    } else {
+        Parsed::Cached(parse_cached(template))
+    }
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
 
+    #[test]
+    fn test_parse_cached_shares_tokens_per_template() {
+        let first = parse_cached("{path:cyan} > ");
+        let second = parse_cached("{path:cyan} > ");
+        assert!(Arc::ptr_eq(&first, &second));
+        assert_eq!(&*first, parse("{path:cyan} > ").as_slice());
+
+        let borrowed: Vec<Token<'_>> = first.iter().map(Token::as_borrowed).collect();
+        assert_eq!(borrowed, parse("{path:cyan} > "));
+        assert_ne!(&*parse_cached("{git}"), &*first);
+    }
+
+    #[test]
+    fn test_parse_template_skips_the_cache_once_disabled() {
+        disable_parse_cache();
+        let parsed = parse_template("{node:green} $ ");
+        assert!(matches!(parsed, Parsed::Fresh(_)));
+        assert_eq!(&*parsed, parse("{node:green} $ ").as_slice());
+    }
+
     #[test]
     fn test_simple_text() {
         let tokens = parse("Hello, World!");
@@ -348,6 +696,98 @@ mod tests {
         assert_eq!(combined, "{unclosed");
     }
 
+    #[test]
+    fn test_spans_cover_the_template() {
+        let template = "a {path:cyan} ${HOME}\\n";
+        let spans: Vec<_> = parse_spanned(template)
+            .into_iter()
+            .map(|(_, span)| &template[span])
+            .collect();
+        assert_eq!(spans, ["a ", "{path:cyan}", " ", "${HOME}", "\\n"]);
+    }
+
+    #[test]
+    fn test_escapes_before_multibyte_characters_stay_on_char_boundaries() {
+        let tokens = parse("\\é{a:\\é:日本}\\🦀");
+        assert_eq!(tokens.len(), 5, "{tokens:?}");
+        let Token::Placeholder(params) = &tokens[2] else {
+            panic!("expected placeholder, got {:?}", tokens[2]);
+        };
+        assert_eq!(params.module, "a");
+        assert_eq!(params.style, "\\é");
+        assert_eq!(params.format, "日本");
+    }
+
+    /// Templates built from delimiters, escapes, and multi-byte characters, like the
+    /// `fuzz_parser` target: spans must tile the template on char boundaries and agree with
+    /// [`parse`].
+    #[test]
+    fn test_generated_templates_keep_spans_on_char_boundaries() {
+        const PIECES: &[&str] = &[
+            "{",
+            "}",
+            "\\",
+            ":",
+            "$",
+            "${",
+            "=",
+            "when=X",
+            "a",
+            "n",
+            "u{",
+            "é",
+            "日本",
+            "🦀",
+            "\\u{1F980}",
+            "\\u{D800}",
+            " ",
+        ];
+        let mut state: u64 = 0x9e37_79b9_7f4a_7c15;
+        let mut next = || {
+            state ^= state << 13;
+            state ^= state >> 7;
+            state ^= state << 17;
+            state
+        };
+
+        for _ in 0..5_000 {
+            let len = next() % 12;
+            let template: String = (0..len)
+                .map(|_| PIECES[(next() % PIECES.len() as u64) as usize])
+                .collect();
+
+            let spanned = parse_spanned(&template);
+            let mut end = 0;
+            for (_, span) in &spanned {
+                assert_eq!(span.start, end, "gap in {template:?}");
+                assert!(
+                    template.get(span.clone()).is_some(),
+                    "{template:?} at {span:?}"
+                );
+                end = span.end;
+            }
+            assert_eq!(end, template.len(), "{template:?} not fully covered");
+
+            let tokens: Vec<_> = spanned.into_iter().map(|(token, _)| token).collect();
+            assert_eq!(parse(&template), tokens, "{template:?}");
+        }
+    }
+
+    #[test]
+    fn test_syntax_errors_point_at_the_brace() {
+        let errors: Vec<_> = ["ok {:red} {git", "\\{ and {path}"]
+            .into_iter()
+            .flat_map(|template| {
+                parse_spanned(template)
+                    .into_iter()
+                    .filter_map(|(_, span)| syntax_error(template, span))
+            })
+            .collect();
+        assert_eq!(errors.len(), 2, "{errors:?}");
+        assert!(matches!(&errors[0], PromptError::Parse { span, .. } if *span == (3..9)));
+        assert!(matches!(&errors[1], PromptError::Parse { span, .. } if *span == (10..11)));
+    }
+
     #[test]
     fn test_empty_fields() {
         let tokens = parse("{module::::}");
@@ -362,6 +802,138 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_when_option_is_split_from_fields() {
+        let tokens = parse("{time:::when=SSH_CONNECTION}");
+        if let Token::Placeholder(params) = &tokens[0] {
+            assert_eq!(params.module, "time");
+            assert_eq!(params.prefix, "");
+            assert_eq!(params.when.as_deref(), Some("SSH_CONNECTION"));
+        } else {
+            panic!("Expected placeholder");
+        }
+
+        let tokens = parse("{path:cyan:s:[:]:when=!NO_PATH}");
+        if let Token::Placeholder(params) = &tokens[0] {
+            assert_eq!(params.prefix, "[");
+            assert_eq!(params.suffix, "]");
+            assert_eq!(params.when.as_deref(), Some("!NO_PATH"));
+        } else {
+            panic!("Expected placeholder");
+        }
+    }
+
+    #[test]
+    fn test_timeout_option_combines_with_when() {
+        let tokens = parse("{git:purple:full:timeout=50}");
+        if let Token::Placeholder(params) = &tokens[0] {
+            assert_eq!(params.format, "full");
+            assert_eq!(params.prefix, "");
+            assert_eq!(params.timeout.as_deref(), Some("50"));
+        } else {
+            panic!("Expected placeholder");
+        }
+
+        let tokens = parse("{git:::when=GIT:timeout=20}");
+        if let Token::Placeholder(params) = &tokens[0] {
+            assert_eq!(params.when.as_deref(), Some("GIT"));
+            assert_eq!(params.timeout.as_deref(), Some("20"));
+        } else {
+            panic!("Expected placeholder");
+        }
+    }
+
+    #[test]
+    fn test_unknown_option_key_stays_in_fields() {
+        let tokens = parse("{path:::a=b}");
+        if let Token::Placeholder(params) = &tokens[0] {
+            assert_eq!(params.prefix, "a=b");
+            assert_eq!(params.when, None);
+        } else {
+            panic!("Expected placeholder");
+        }
+    }
+
+    #[test]
+    fn test_unicode_escape_in_text() {
+        let tokens = parse("\\u{250C}\\u{2500}>");
+        let combined: String = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text(s) => s.as_ref(),
+                _ => panic!("Expected text token"),
+            })
+            .collect();
+        assert_eq!(combined, "┌─>");
+    }
+
+    #[test]
+    fn test_invalid_unicode_escapes_are_rejected() {
+        for input in [
+            "\\u{}",
+            "\\u{110000}",
+            "\\u{d800}",
+            "\\u{zz}",
+            "\\u{1234567}",
+            "\\u{41",
+        ] {
+            assert_eq!(decode_unicode_escape(input.as_bytes(), 0), None, "{input}");
+        }
+        assert_eq!(decode_unicode_escape(b"\\u{41}", 0), Some(('A', 6)));
+    }
+
+    #[test]
+    fn test_unicode_escape_in_placeholder_fields() {
+        let tokens = parse("{path:::\\u{e0a0} :\\u{2502}}");
+        assert_eq!(tokens.len(), 1);
+        if let Token::Placeholder(params) = &tokens[0] {
+            assert_eq!(params.module, "path");
+            assert_eq!(params.prefix, "\u{e0a0} ");
+            assert_eq!(params.suffix, "\u{2502}");
+        } else {
+            panic!("Expected placeholder");
+        }
+    }
+
+    #[test]
+    fn test_var_interpolation_in_text() {
+        let tokens = parse("user ${USER}@host");
+        assert_eq!(
+            tokens,
+            vec![
+                Token::Text(Cow::Borrowed("user ")),
+                Token::Var(Cow::Borrowed("USER")),
+                Token::Text(Cow::Borrowed("@host")),
+            ]
+        );
+    }
+
+    #[test]
+    fn test_empty_var_name_is_text() {
+        let tokens = parse("${}");
+        let combined: String = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text(s) => s.as_ref(),
+                _ => panic!("Expected text token"),
+            })
+            .collect();
+        assert_eq!(combined, "${}");
+    }
+
+    #[test]
+    fn test_escaped_var_is_text() {
+        let tokens = parse("$\\{HOME\\}");
+        let combined: String = tokens
+            .iter()
+            .map(|t| match t {
+                Token::Text(s) => s.as_ref(),
+                _ => panic!("Expected text token"),
+            })
+            .collect();
+        assert_eq!(combined, "${HOME}");
+    }
+
     #[test]
     fn test_mixed_content() {
         let tokens = parse("Hello {user:yellow}, welcome to {path:cyan:short}!");
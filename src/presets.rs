@@ -0,0 +1,61 @@
+/// Curated formats selectable with `--preset`, as `(name, format)`.
+const PRESETS: &[(&str, &str)] = &[
+    ("minimal", "{path:cyan:s} {ok:green}{fail:red} "),
+    (
+        "powerline",
+        "{path:white+blue:: : }{pl}{git:black+yellow:: : }{pl}{node:black+green:: : }{pl}\
+         {rust:white+red:: : }{pl} {ok:green}{fail:red} ",
+    ),
+    (
+        "verbose",
+        r"{path:cyan}{sep}{git:purple}{sep}{node:green:s:node }{sep}{python:yellow:s:py }{sep}{rust:red:s:rust }{sep}{go:cyan::go }{sep}{duration:yellow:1:took }{sep}{time:dim:24hs}\n{ok:green}{fail:red:code} ",
+    ),
+    (
+        "nerd-icons",
+        r"{path:cyan::\u{f07c} }{sep}{git:purple::\u{e0a0} }{sep}{node:green:s:\u{e718} }{sep}{python:yellow:s:\u{e73c} }{sep}{rust:red:s:\u{e7a8} }{sep}{go:cyan::\u{e627} }\n{ok:green:\u{f054}}{fail:red:\u{f054}} ",
+    ),
+];
+
+pub fn names() -> impl Iterator<Item = &'static str> {
+    PRESETS.iter().map(|(name, _)| *name)
+}
+
+/// Format of the preset called `name`.
+pub fn find(name: &str) -> Result<&'static str, String> {
+    let name = name.trim().to_ascii_lowercase();
+    PRESETS
+        .iter()
+        .find(|(preset, _)| *preset == name)
+        .map(|(_, format)| *format)
+        .ok_or_else(|| {
+            format!(
+                "Unknown preset: {} (available: {})",
+                name,
+                names().collect::<Vec<_>>().join(", ")
+            )
+        })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn presets_are_valid_formats() {
+        for name in names() {
+            let errors = crate::executor::check(find(name).unwrap());
+            assert!(errors.is_empty(), "{}: {:?}", name, errors);
+        }
+    }
+
+    #[test]
+    fn find_rejects_unknown_presets() {
+        assert_eq!(find("Minimal"), Ok(PRESETS[0].1));
+        assert_eq!(
+            find("fancy"),
+            Err(
+                "Unknown preset: fancy (available: minimal, powerline, verbose, nerd-icons)".into()
+            )
+        );
+    }
+}
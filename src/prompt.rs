@@ -0,0 +1,154 @@
+use crate::error::Result;
+use crate::executor::{LastCommand, RenderOptions, check_with, execute_to, execute_with_registry};
+use crate::hooks::RenderEvent;
+use crate::module_trait::ModuleRef;
+use crate::registry::ModuleRegistry;
+use crate::style::{ColorMode, Shell};
+use std::io::Write;
+use std::path::PathBuf;
+use std::sync::Arc;
+use std::time::Duration;
+
+/// Collects everything a prompt needs and validates the format once, producing a
+/// [`Prompt`] that can be rendered again and again.
+pub struct PromptBuilder {
+    format: String,
+    registry: ModuleRegistry,
+    last_command: LastCommand,
+    options: RenderOptions,
+}
+
+impl PromptBuilder {
+    pub fn new(format: impl Into<String>) -> Self {
+        Self {
+            format: format.into(),
+            registry: ModuleRegistry::new(),
+            last_command: LastCommand::default(),
+            options: RenderOptions::default(),
+        }
+    }
+
+    /// Shell whose prompt escaping wraps the ANSI sequences.
+    pub fn shell(mut self, shell: Shell) -> Self {
+        self.options.shell = shell;
+        self
+    }
+
+    /// Exit code of the last command, for the `ok` and `fail` modules.
+    pub fn exit_code(mut self, exit_code: i32) -> Self {
+        self.last_command.exit_code = Some(exit_code);
+        self
+    }
+
+    /// Everything known about the last command, replacing an earlier [`exit_code`](Self::exit_code).
+    pub fn last_command(mut self, last_command: LastCommand) -> Self {
+        self.last_command = last_command;
+        self
+    }
+
+    /// Directory the prompt is rendered for, instead of the process's current directory.
+    pub fn cwd(mut self, cwd: impl Into<PathBuf>) -> Self {
+        self.options.cwd = Some(cwd.into());
+        self
+    }
+
+    /// Registers a module for `{name}` placeholders, taking precedence over a built-in module
+    /// of the same name.
+    pub fn module(mut self, name: impl Into<String>, module: ModuleRef) -> Self {
+        self.registry.register(name, module);
+        self
+    }
+
+    pub fn color(mut self, color: ColorMode) -> Self {
+        self.options.color = color;
+        self
+    }
+
+    /// Skips version lookups, so version modules only show whether they apply.
+    pub fn no_version(mut self, no_version: bool) -> Self {
+        self.options.no_version = no_version;
+        self
+    }
+
+    /// Budget for the whole prompt; modules that miss it show the timeout text.
+    pub fn timeout(mut self, timeout: Duration) -> Self {
+        self.options.timeout = Some(timeout);
+        self
+    }
+
+    /// Sets a value modules read with [`ModuleContext::value`](crate::ModuleContext::value).
+    pub fn value(mut self, key: impl Into<String>, value: impl Into<String>) -> Self {
+        Arc::make_mut(&mut self.options.values).insert(key.into(), value.into());
+        self
+    }
+
+    /// Runs `hook` with the module name and format before each module render; returning
+    /// `false` skips the module and hides its segment.
+    pub fn before_render(
+        mut self,
+        hook: impl Fn(&str, &str) -> bool + Send + Sync + 'static,
+    ) -> Self {
+        self.options.hooks.before_render(hook);
+        self
+    }
+
+    /// Runs `hook` after each module render with its duration and result, e.g. to log
+    /// errors or collect timings.
+    pub fn after_render(mut self, hook: impl Fn(&RenderEvent<'_>) + Send + Sync + 'static) -> Self {
+        self.options.hooks.after_render(hook);
+        self
+    }
+
+    /// Validates the format, reporting the first problem. Placeholders of registered modules
+    /// are checked with their own [`validate_format`](crate::Module::validate_format).
+    pub fn build(self) -> Result<Prompt> {
+        if let Some(error) = check_with(&self.format, Some(&self.registry))
+            .into_iter()
+            .next()
+        {
+            return Err(error);
+        }
+
+        Ok(Prompt {
+            format: self.format,
+            registry: self.registry,
+            last_command: self.last_command,
+            options: self.options,
+        })
+    }
+}
+
+/// A validated prompt that renders with the settings of the [`PromptBuilder`] that made it.
+pub struct Prompt {
+    format: String,
+    registry: ModuleRegistry,
+    last_command: LastCommand,
+    options: RenderOptions,
+}
+
+impl Prompt {
+    pub fn render(&self) -> Result<String> {
+        self.render_after(&self.last_command)
+    }
+
+    /// Renders for a different last command, such as the one that just finished in an
+    /// embedding shell.
+    pub fn render_after(&self, last_command: &LastCommand) -> Result<String> {
+        execute_with_registry(&self.format, &self.registry, last_command, &self.options)
+    }
+
+    /// Streams the prompt to `writer`, writing leading segments before slow modules finish.
+    pub fn render_to<W: Write + ?Sized>(&self, writer: &mut W) -> Result<()> {
+        execute_to(
+            &self.format,
+            writer,
+            &self.registry,
+            &self.last_command,
+            &self.options,
+        )
+    }
+
+    pub fn format(&self) -> &str {
+        &self.format
+    }
+}
@@ -1,6 +1,10 @@
+use std::borrow::Cow;
+use std::collections::HashMap;
 use std::fmt::Write;
 use std::str::FromStr;
 use std::sync::atomic::{AtomicU8, Ordering};
+use std::sync::{Arc, RwLock};
+use unicode_width::UnicodeWidthChar;
 
 const COLOR_UNKNOWN: u8 = 0;
 const COLOR_FALSE: u8 = 1;
@@ -23,25 +27,325 @@ pub fn global_no_color() -> bool {
     }
 }
 
+/// Whether output is styled. `Auto` leaves colors off when `NO_COLOR` is set.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum ColorMode {
+    #[default]
+    Auto,
+    Always,
+    Never,
+}
+
+impl FromStr for ColorMode {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "auto" => Ok(ColorMode::Auto),
+            "always" => Ok(ColorMode::Always),
+            "never" => Ok(ColorMode::Never),
+            other => Err(format!(
+                "Unknown color mode: {} (supported values: auto, always, never)",
+                other
+            )),
+        }
+    }
+}
+
+impl ColorMode {
+    pub fn no_color(self) -> bool {
+        match self {
+            ColorMode::Auto => global_no_color(),
+            ColorMode::Always => false,
+            ColorMode::Never => true,
+        }
+    }
+}
+
 #[cfg(test)]
 pub fn reset_global_no_color_for_tests() {
     NO_COLOR_STATE.store(COLOR_UNKNOWN, Ordering::Relaxed);
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq, PartialOrd, Ord)]
+pub enum ColorDepth {
+    Ansi16 = 1,
+    Ansi256 = 2,
+    TrueColor = 3,
+}
+
+impl FromStr for ColorDepth {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "truecolor" | "24bit" | "24" => Ok(ColorDepth::TrueColor),
+            "256" | "ansi256" | "8bit" => Ok(ColorDepth::Ansi256),
+            "16" | "ansi16" | "8" => Ok(ColorDepth::Ansi16),
+            other => Err(format!(
+                "Unknown color depth: {} (supported values: truecolor, 256, 16)",
+                other
+            )),
+        }
+    }
+}
+
+static COLOR_DEPTH_STATE: AtomicU8 = AtomicU8::new(COLOR_UNKNOWN);
+
+/// Color depth of the terminal, detected once from the environment. `PRMT_COLOR_DEPTH`
+/// overrides detection.
+pub fn color_depth() -> ColorDepth {
+    match COLOR_DEPTH_STATE.load(Ordering::Relaxed) {
+        1 => ColorDepth::Ansi16,
+        2 => ColorDepth::Ansi256,
+        3 => ColorDepth::TrueColor,
+        _ => {
+            let var = |name| std::env::var(name).ok();
+            let depth = var("PRMT_COLOR_DEPTH")
+                .and_then(|value| value.parse().ok())
+                .unwrap_or_else(|| {
+                    detect_color_depth(
+                        var("COLORTERM").as_deref(),
+                        var("TERM").as_deref(),
+                        var("TERM_PROGRAM").as_deref(),
+                        std::env::var_os("WT_SESSION").is_some(),
+                    )
+                });
+            set_color_depth(depth);
+            depth
+        }
+    }
+}
+
+pub fn set_color_depth(depth: ColorDepth) {
+    COLOR_DEPTH_STATE.store(depth as u8, Ordering::Relaxed);
+}
+
+fn detect_color_depth(
+    colorterm: Option<&str>,
+    term: Option<&str>,
+    term_program: Option<&str>,
+    windows_terminal: bool,
+) -> ColorDepth {
+    if matches!(colorterm, Some("truecolor" | "24bit")) || windows_terminal {
+        return ColorDepth::TrueColor;
+    }
+    if matches!(
+        term_program,
+        Some("iTerm.app" | "WezTerm" | "vscode" | "Hyper" | "ghostty")
+    ) {
+        return ColorDepth::TrueColor;
+    }
+
+    // Without TERM there is nothing to go on, so keep colors as written
+    let Some(term) = term.filter(|term| !term.is_empty()) else {
+        return ColorDepth::TrueColor;
+    };
+    const TRUECOLOR_TERMS: [&str; 7] = [
+        "-direct",
+        "truecolor",
+        "kitty",
+        "alacritty",
+        "ghostty",
+        "wezterm",
+        "foot",
+    ];
+    if TRUECOLOR_TERMS.iter().any(|name| term.contains(name)) {
+        ColorDepth::TrueColor
+    } else if term.contains("256color") {
+        ColorDepth::Ansi256
+    } else {
+        ColorDepth::Ansi16
+    }
+}
+
+/// xterm's default RGB values for the 16 basic colors.
+const ANSI16_RGB: [(u8, u8, u8); 16] = [
+    (0, 0, 0),
+    (205, 0, 0),
+    (0, 205, 0),
+    (205, 205, 0),
+    (0, 0, 238),
+    (205, 0, 205),
+    (0, 205, 205),
+    (229, 229, 229),
+    (127, 127, 127),
+    (255, 0, 0),
+    (0, 255, 0),
+    (255, 255, 0),
+    (92, 92, 255),
+    (255, 0, 255),
+    (0, 255, 255),
+    (255, 255, 255),
+];
+
+const CUBE_LEVELS: [u8; 6] = [0, 95, 135, 175, 215, 255];
+
+fn distance((r1, g1, b1): (u8, u8, u8), (r2, g2, b2): (u8, u8, u8)) -> u32 {
+    let d = |a: u8, b: u8| (a as i32 - b as i32).unsigned_abs();
+    d(r1, r2).pow(2) + d(g1, g2).pow(2) + d(b1, b2).pow(2)
+}
+
+pub(crate) fn ansi256_to_rgb(index: u8) -> (u8, u8, u8) {
+    match index {
+        0..=15 => ANSI16_RGB[index as usize],
+        16..=231 => {
+            let cube = index - 16;
+            (
+                CUBE_LEVELS[(cube / 36) as usize],
+                CUBE_LEVELS[(cube / 6 % 6) as usize],
+                CUBE_LEVELS[(cube % 6) as usize],
+            )
+        }
+        _ => {
+            let level = 8 + (index - 232) * 10;
+            (level, level, level)
+        }
+    }
+}
+
+fn rgb_to_ansi256(rgb: (u8, u8, u8)) -> u8 {
+    let level = |value: u8| match value {
+        0..=47 => 0,
+        48..=114 => 1,
+        _ => (value - 35) / 40,
+    };
+    let (r, g, b) = (level(rgb.0), level(rgb.1), level(rgb.2));
+    let cube = 16 + 36 * r + 6 * g + b;
+
+    let average = (rgb.0 as u32 + rgb.1 as u32 + rgb.2 as u32) / 3;
+    let gray = if average > 238 {
+        255
+    } else {
+        232 + (average.saturating_sub(3) / 10) as u8
+    };
+
+    if distance(rgb, ansi256_to_rgb(gray)) < distance(rgb, ansi256_to_rgb(cube)) {
+        gray
+    } else {
+        cube
+    }
+}
+
+fn rgb_to_ansi16(rgb: (u8, u8, u8)) -> u8 {
+    (0..16u8)
+        .min_by_key(|&index| distance(rgb, ANSI16_RGB[index as usize]))
+        .unwrap_or(7)
+}
+
 #[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
 pub enum Shell {
     #[default]
     None,
     Zsh,
     Bash,
+    /// Bash output assigned to `PS1` from a function, as `prmt init bash` does. Bash decodes
+    /// and expands it again, so module output is escaped; `PS1='$(prmt ...)'` wants `Bash`.
+    BashPs1,
+    /// Fish measures prompt width itself, so escapes need no zero-width wrappers
+    Fish,
+    /// Styles are translated to xonsh color fields, see [`crate::xonsh::to_prompt`]
+    Xonsh,
+    /// cmd.exe through Clink's prompt filter; newlines are written as CRLF
+    Clink,
 }
 
 impl Shell {
-    fn delimiters(self) -> (&'static str, &'static str) {
+    pub(crate) fn delimiters(self) -> (&'static str, &'static str) {
         match self {
             Shell::Zsh => ("%{", "%}"),
-            Shell::Bash => ("\x01", "\x02"),
-            Shell::None => ("", ""),
+            Shell::Bash | Shell::BashPs1 => ("\x01", "\x02"),
+            Shell::Fish | Shell::Xonsh | Shell::Clink | Shell::None => ("", ""),
+        }
+    }
+
+    /// Escapes dynamic text such as module output so the shell's prompt expansion prints it
+    /// literally. [`Shell::BashPs1`] output is decoded and then expanded, so its escapes are
+    /// doubled; plain bash output from `$(prmt ...)` is never expanded and stays as it is.
+    pub(crate) fn escape_text(self, text: &str) -> Cow<'_, str> {
+        match self {
+            Shell::Zsh if text.contains('%') => Cow::Owned(text.replace('%', "%%")),
+            Shell::BashPs1 if text.contains(['\\', '`', '$']) => {
+                let mut escaped = String::with_capacity(text.len() + 8);
+                for ch in text.chars() {
+                    match ch {
+                        '\\' => escaped.push_str(r"\\\\"),
+                        '`' | '$' => {
+                            escaped.push_str(r"\\");
+                            escaped.push(ch);
+                        }
+                        ch => escaped.push(ch),
+                    }
+                }
+                Cow::Owned(escaped)
+            }
+            _ => Cow::Borrowed(text),
+        }
+    }
+
+    /// Byte length of the escape produced by [`Shell::escape_text`] at the start of `text`,
+    /// which prints as one character.
+    pub(crate) fn escaped_char_len(self, text: &str) -> Option<usize> {
+        match self {
+            Shell::Zsh if text.starts_with("%%") => Some(2),
+            Shell::BashPs1 if text.starts_with(r"\\\\") => Some(4),
+            Shell::BashPs1 if text.starts_with(r"\\`") || text.starts_with(r"\\$") => Some(3),
+            _ => None,
+        }
+    }
+}
+
+/// OSC 0 sequence that sets the terminal window and tab title, wrapped for `shell`.
+/// Control characters are dropped so the title cannot terminate the sequence early.
+pub fn title_sequence(title: &str, shell: Shell) -> String {
+    let (start, end) = shell.delimiters();
+    let mut output = String::with_capacity(title.len() + 8 + start.len() + end.len());
+    output.push_str(start);
+    output.push_str("\x1b]0;");
+    output.extend(
+        shell
+            .escape_text(title)
+            .chars()
+            .filter(|ch| !ch.is_control()),
+    );
+    output.push('\x07');
+    output.push_str(end);
+    output
+}
+
+/// Wraps a prompt in the FinalTerm semantic marks (OSC 133) used by iTerm2, WezTerm, and
+/// others: `D` reports the previous command's exit status, `A` starts the prompt, and `B`
+/// ends it where command input begins.
+pub fn mark_prompt(prompt: &str, exit_code: Option<i32>, shell: Shell) -> String {
+    let (start, end) = shell.delimiters();
+    let mut output = String::with_capacity(prompt.len() + 32);
+    output.push_str(start);
+    match exit_code {
+        Some(code) => {
+            let _ = write!(output, "\x1b]133;D;{}\x07", code);
+        }
+        None => output.push_str("\x1b]133;D\x07"),
+    }
+    output.push_str("\x1b]133;A\x07");
+    output.push_str(end);
+    output.push_str(prompt);
+    output.push_str(start);
+    output.push_str("\x1b]133;B\x07");
+    output.push_str(end);
+    output
+}
+
+impl Shell {
+    /// Name accepted by `--shell`.
+    pub fn name(self) -> &'static str {
+        match self {
+            Shell::Zsh => "zsh",
+            Shell::Bash => "bash",
+            Shell::BashPs1 => "bash-ps1",
+            Shell::Fish => "fish",
+            Shell::Xonsh => "xonsh",
+            Shell::Clink => "clink",
+            Shell::None => "none",
         }
     }
 }
@@ -53,9 +357,13 @@ impl FromStr for Shell {
         match value.trim().to_ascii_lowercase().as_str() {
             "zsh" => Ok(Shell::Zsh),
             "bash" => Ok(Shell::Bash),
+            "bash-ps1" => Ok(Shell::BashPs1),
+            "fish" => Ok(Shell::Fish),
+            "xonsh" => Ok(Shell::Xonsh),
+            "clink" | "cmd" => Ok(Shell::Clink),
             "none" | "" => Ok(Shell::None),
             other => Err(format!(
-                "Unknown shell: {} (supported values: bash, zsh, none)",
+                "Unknown shell: {} (supported values: bash, bash-ps1, zsh, fish, xonsh, clink, none)",
                 other
             )),
         }
@@ -72,7 +380,7 @@ pub trait ModuleStyle: Sized {
     }
 }
 
-#[derive(Debug, Clone, PartialEq)]
+#[derive(Debug, Clone, Copy, PartialEq)]
 pub enum Color {
     Black,
     Red,
@@ -82,12 +390,83 @@ pub enum Color {
     Purple,
     Cyan,
     White,
+    BrightBlack,
+    BrightRed,
+    BrightGreen,
+    BrightYellow,
+    BrightBlue,
+    BrightPurple,
+    BrightCyan,
+    BrightWhite,
+    Ansi256(u8),
     Rgb(u8, u8, u8),
 }
 
+impl std::fmt::Display for Color {
+    /// Writes the color in the syntax styles accept: a name, a 256-color index, or `#rrggbb`.
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let name = match self {
+            Color::Black => "black",
+            Color::Red => "red",
+            Color::Green => "green",
+            Color::Yellow => "yellow",
+            Color::Blue => "blue",
+            Color::Purple => "purple",
+            Color::Cyan => "cyan",
+            Color::White => "white",
+            Color::BrightBlack => "bright-black",
+            Color::BrightRed => "bright-red",
+            Color::BrightGreen => "bright-green",
+            Color::BrightYellow => "bright-yellow",
+            Color::BrightBlue => "bright-blue",
+            Color::BrightPurple => "bright-purple",
+            Color::BrightCyan => "bright-cyan",
+            Color::BrightWhite => "bright-white",
+            Color::Ansi256(index) => return write!(f, "{}", index),
+            Color::Rgb(r, g, b) => return write!(f, "#{:02x}{:02x}{:02x}", r, g, b),
+        };
+        f.write_str(name)
+    }
+}
+
 impl Color {
+    fn from_ansi16(index: u8) -> Self {
+        match index {
+            0 => Color::Black,
+            1 => Color::Red,
+            2 => Color::Green,
+            3 => Color::Yellow,
+            4 => Color::Blue,
+            5 => Color::Purple,
+            6 => Color::Cyan,
+            7 => Color::White,
+            8 => Color::BrightBlack,
+            9 => Color::BrightRed,
+            10 => Color::BrightGreen,
+            11 => Color::BrightYellow,
+            12 => Color::BrightBlue,
+            13 => Color::BrightPurple,
+            14 => Color::BrightCyan,
+            _ => Color::BrightWhite,
+        }
+    }
+
+    /// Nearest equivalent the terminal can display at `depth`.
+    fn for_depth(self, depth: ColorDepth) -> Self {
+        match (self, depth) {
+            (Color::Rgb(r, g, b), ColorDepth::Ansi256) => Color::Ansi256(rgb_to_ansi256((r, g, b))),
+            (Color::Rgb(r, g, b), ColorDepth::Ansi16) => {
+                Color::from_ansi16(rgb_to_ansi16((r, g, b)))
+            }
+            (Color::Ansi256(index), ColorDepth::Ansi16) => {
+                Color::from_ansi16(rgb_to_ansi16(ansi256_to_rgb(index)))
+            }
+            (color, _) => color,
+        }
+    }
+
     fn push_ansi_code(&self, buf: &mut String) {
-        match self {
+        match self.for_depth(color_depth()) {
             Color::Black => buf.push_str("\x1b[30m"),
             Color::Red => buf.push_str("\x1b[31m"),
             Color::Green => buf.push_str("\x1b[32m"),
@@ -96,14 +475,52 @@ impl Color {
             Color::Purple => buf.push_str("\x1b[35m"),
             Color::Cyan => buf.push_str("\x1b[36m"),
             Color::White => buf.push_str("\x1b[37m"),
+            Color::BrightBlack => buf.push_str("\x1b[90m"),
+            Color::BrightRed => buf.push_str("\x1b[91m"),
+            Color::BrightGreen => buf.push_str("\x1b[92m"),
+            Color::BrightYellow => buf.push_str("\x1b[93m"),
+            Color::BrightBlue => buf.push_str("\x1b[94m"),
+            Color::BrightPurple => buf.push_str("\x1b[95m"),
+            Color::BrightCyan => buf.push_str("\x1b[96m"),
+            Color::BrightWhite => buf.push_str("\x1b[97m"),
+            Color::Ansi256(index) => {
+                let _ = write!(buf, "\x1b[38;5;{}m", index);
+            }
             Color::Rgb(r, g, b) => {
                 let _ = write!(buf, "\x1b[38;2;{};{};{}m", r, g, b);
             }
         }
     }
 
+    fn push_ansi_underline_code(&self, buf: &mut String) {
+        let index = match self.for_depth(color_depth()) {
+            Color::Rgb(r, g, b) => {
+                let _ = write!(buf, "\x1b[58;2;{};{};{}m", r, g, b);
+                return;
+            }
+            Color::Ansi256(index) => index,
+            Color::Black => 0,
+            Color::Red => 1,
+            Color::Green => 2,
+            Color::Yellow => 3,
+            Color::Blue => 4,
+            Color::Purple => 5,
+            Color::Cyan => 6,
+            Color::White => 7,
+            Color::BrightBlack => 8,
+            Color::BrightRed => 9,
+            Color::BrightGreen => 10,
+            Color::BrightYellow => 11,
+            Color::BrightBlue => 12,
+            Color::BrightPurple => 13,
+            Color::BrightCyan => 14,
+            Color::BrightWhite => 15,
+        };
+        let _ = write!(buf, "\x1b[58;5;{}m", index);
+    }
+
     fn push_ansi_bg_code(&self, buf: &mut String) {
-        match self {
+        match self.for_depth(color_depth()) {
             Color::Black => buf.push_str("\x1b[40m"),
             Color::Red => buf.push_str("\x1b[41m"),
             Color::Green => buf.push_str("\x1b[42m"),
@@ -112,6 +529,17 @@ impl Color {
             Color::Purple => buf.push_str("\x1b[45m"),
             Color::Cyan => buf.push_str("\x1b[46m"),
             Color::White => buf.push_str("\x1b[47m"),
+            Color::BrightBlack => buf.push_str("\x1b[100m"),
+            Color::BrightRed => buf.push_str("\x1b[101m"),
+            Color::BrightGreen => buf.push_str("\x1b[102m"),
+            Color::BrightYellow => buf.push_str("\x1b[103m"),
+            Color::BrightBlue => buf.push_str("\x1b[104m"),
+            Color::BrightPurple => buf.push_str("\x1b[105m"),
+            Color::BrightCyan => buf.push_str("\x1b[106m"),
+            Color::BrightWhite => buf.push_str("\x1b[107m"),
+            Color::Ansi256(index) => {
+                let _ = write!(buf, "\x1b[48;5;{}m", index);
+            }
             Color::Rgb(r, g, b) => {
                 let _ = write!(buf, "\x1b[48;2;{};{};{}m", r, g, b);
             }
@@ -119,13 +547,124 @@ impl Color {
     }
 }
 
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum UnderlineStyle {
+    Double,
+    Curly,
+    Dotted,
+    Dashed,
+}
+
+impl UnderlineStyle {
+    /// Extended SGR code; terminals without support ignore it and keep the plain underline
+    fn ansi_code(self) -> &'static str {
+        match self {
+            UnderlineStyle::Double => "\x1b[4:2m",
+            UnderlineStyle::Curly => "\x1b[4:3m",
+            UnderlineStyle::Dotted => "\x1b[4:4m",
+            UnderlineStyle::Dashed => "\x1b[4:5m",
+        }
+    }
+}
+
+#[derive(Debug, Clone, PartialEq)]
+pub enum Gradient {
+    /// Evenly spaced truecolor stops, e.g. `gradient:#ff0000-#0000ff`
+    Linear(Vec<(u8, u8, u8)>),
+    /// Full hue sweep
+    Rainbow,
+}
+
+impl Gradient {
+    fn parse(stops: &str) -> Result<Self, String> {
+        let colors = stops
+            .split('-')
+            .map(|stop| match parse_color(stop) {
+                Ok(Color::Rgb(r, g, b)) => Ok((r, g, b)),
+                _ => Err(format!("Gradient stops must be hex colors: {}", stop)),
+            })
+            .collect::<Result<Vec<_>, _>>()?;
+
+        if colors.len() < 2 {
+            return Err(format!("Gradient needs at least two colors: {}", stops));
+        }
+        Ok(Gradient::Linear(colors))
+    }
+
+    fn color_at(&self, index: usize, count: usize) -> (u8, u8, u8) {
+        let t = if count > 1 {
+            index as f32 / (count - 1) as f32
+        } else {
+            0.0
+        };
+
+        match self {
+            Gradient::Linear(stops) => {
+                let scaled = t * (stops.len() - 1) as f32;
+                let segment = (scaled as usize).min(stops.len() - 2);
+                let local = scaled - segment as f32;
+                let (from, to) = (stops[segment], stops[segment + 1]);
+                let mix = |a: u8, b: u8| (a as f32 + (b as f32 - a as f32) * local).round() as u8;
+                (mix(from.0, to.0), mix(from.1, to.1), mix(from.2, to.2))
+            }
+            Gradient::Rainbow => hue_to_rgb(t * 300.0),
+        }
+    }
+}
+
+fn hue_to_rgb(hue: f32) -> (u8, u8, u8) {
+    let sector = hue / 60.0;
+    let x = 1.0 - (sector % 2.0 - 1.0).abs();
+    let (r, g, b) = match sector as u32 {
+        0 => (1.0, x, 0.0),
+        1 => (x, 1.0, 0.0),
+        2 => (0.0, 1.0, x),
+        3 => (0.0, x, 1.0),
+        4 => (x, 0.0, 1.0),
+        _ => (1.0, 0.0, x),
+    };
+    let channel = |value: f32| (value * 255.0).round() as u8;
+    (channel(r), channel(g), channel(b))
+}
+
+/// Splits text into user-perceived characters: a base character plus any zero-width
+/// marks, and characters joined by a zero-width joiner. Shell escapes such as `%%` stay whole.
+fn clusters(text: &str, shell: Shell) -> impl Iterator<Item = &str> {
+    let mut rest = text;
+    std::iter::from_fn(move || {
+        if let Some(len) = shell.escaped_char_len(rest) {
+            let (cluster, tail) = rest.split_at(len);
+            rest = tail;
+            return Some(cluster);
+        }
+        let mut chars = rest.char_indices();
+        let (_, first) = chars.next()?;
+        let mut end = first.len_utf8();
+        let mut joined = false;
+        for (index, ch) in chars {
+            let attaches = joined || ch == '\u{200d}' || ch.width() == Some(0);
+            if !attaches {
+                break;
+            }
+            joined = ch == '\u{200d}';
+            end = index + ch.len_utf8();
+        }
+        let (cluster, tail) = rest.split_at(end);
+        rest = tail;
+        Some(cluster)
+    })
+}
+
 #[derive(Debug, Clone, Default, PartialEq)]
 pub struct AnsiStyle {
     pub color: Option<Color>,
+    pub gradient: Option<Gradient>,
     pub background: Option<Color>,
     pub bold: bool,
     pub italic: bool,
     pub underline: bool,
+    pub underline_style: Option<UnderlineStyle>,
+    pub underline_color: Option<Color>,
     pub dim: bool,
     pub reverse: bool,
     pub strikethrough: bool,
@@ -139,6 +678,10 @@ impl ModuleStyle for AnsiStyle {
             return Ok(style);
         }
 
+        if style_str.contains('@') {
+            return AnsiStyle::parse(&expand_theme_refs(style_str)?);
+        }
+
         for part in style_str.split('.') {
             match part {
                 "bold" => style.bold = true,
@@ -147,6 +690,24 @@ impl ModuleStyle for AnsiStyle {
                 "dim" => style.dim = true,
                 "reverse" => style.reverse = true,
                 "strikethrough" => style.strikethrough = true,
+                "underline-double" | "underline-curly" | "underline-dotted"
+                | "underline-dashed" => {
+                    style.underline = true;
+                    style.underline_style = Some(match &part["underline-".len()..] {
+                        "double" => UnderlineStyle::Double,
+                        "curly" => UnderlineStyle::Curly,
+                        "dotted" => UnderlineStyle::Dotted,
+                        _ => UnderlineStyle::Dashed,
+                    });
+                }
+                _ if part.starts_with("underline:") || part.starts_with("underline=") => {
+                    style.underline = true;
+                    style.underline_color = Some(parse_color(&part["underline:".len()..])?);
+                }
+                "rainbow" => style.gradient = Some(Gradient::Rainbow),
+                _ if part.starts_with("gradient:") || part.starts_with("gradient=") => {
+                    style.gradient = Some(Gradient::parse(&part["gradient:".len()..])?);
+                }
                 _ => {
                     if part.contains('+') {
                         let mut split = part.splitn(2, '+');
@@ -180,12 +741,32 @@ impl ModuleStyle for AnsiStyle {
 
         let mut output = String::with_capacity(text.len() + 16);
         self.write_start_codes(&mut output, shell);
-        output.push_str(text);
+        match &self.gradient {
+            Some(gradient) => write_gradient(&mut output, gradient, text, shell),
+            None => self.write_text(&mut output, text, shell),
+        }
         self.write_reset(&mut output, shell);
         output
     }
 }
 
+/// Substitutes `@name` parts with their definitions from the active theme.
+fn expand_theme_refs(style_str: &str) -> Result<String, String> {
+    let theme = crate::theme::active_theme();
+    let mut expanded = Vec::new();
+    for part in style_str.split('.') {
+        match part.strip_prefix('@') {
+            Some(name) => expanded.push(
+                theme
+                    .get(name)
+                    .ok_or_else(|| format!("Unknown theme style: @{}", name))?,
+            ),
+            None => expanded.push(part),
+        }
+    }
+    Ok(expanded.join("."))
+}
+
 fn parse_hex_color(hex: &str) -> Result<(u8, u8, u8), String> {
     let hex = hex.trim_start_matches('#');
 
@@ -203,9 +784,55 @@ fn parse_hex_color(hex: &str) -> Result<(u8, u8, u8), String> {
     Ok((r, g, b))
 }
 
+fn write_gradient(buf: &mut String, gradient: &Gradient, text: &str, shell: Shell) {
+    let (start, end) = shell.delimiters();
+    let count = clusters(text, shell).count();
+    for (index, cluster) in clusters(text, shell).enumerate() {
+        let (r, g, b) = gradient.color_at(index, count);
+        buf.push_str(start);
+        Color::Rgb(r, g, b).push_ansi_code(buf);
+        buf.push_str(end);
+        buf.push_str(cluster);
+    }
+}
+
+/// Parsed styles keyed by style string. Parsing resolves `@name` references and palette
+/// colors, so the theme module clears this whenever either changes.
+static STYLE_CACHE: RwLock<Option<HashMap<String, Arc<AnsiStyle>>>> = RwLock::new(None);
+
+pub(crate) fn clear_style_cache() {
+    if let Ok(mut cache) = STYLE_CACHE.write() {
+        *cache = None;
+    }
+}
+
 impl AnsiStyle {
+    /// [`ModuleStyle::parse`], memoized per style string so templates that repeat a style
+    /// parse it once. Errors are not cached.
+    pub fn parse_cached(style_str: &str) -> Result<Arc<AnsiStyle>, String> {
+        if let Ok(cache) = STYLE_CACHE.read()
+            && let Some(style) = cache.as_ref().and_then(|cache| cache.get(style_str))
+        {
+            return Ok(Arc::clone(style));
+        }
+
+        let style = Arc::new(AnsiStyle::parse(style_str)?);
+        if let Ok(mut cache) = STYLE_CACHE.write() {
+            cache
+                .get_or_insert_with(HashMap::new)
+                .insert(style_str.to_string(), Arc::clone(&style));
+        }
+        Ok(style)
+    }
+
+    #[allow(dead_code)]
+    pub fn has_gradient(&self) -> bool {
+        self.gradient.is_some()
+    }
+
     fn has_style(&self) -> bool {
         self.color.is_some()
+            || self.gradient.is_some()
             || self.background.is_some()
             || self.bold
             || self.italic
@@ -216,7 +843,9 @@ impl AnsiStyle {
     }
 
     fn write_raw_codes(&self, buf: &mut String) {
-        if let Some(ref color) = self.color {
+        if let Some(ref color) = self.color
+            && self.gradient.is_none()
+        {
             color.push_ansi_code(buf);
         }
         if let Some(ref background) = self.background {
@@ -233,6 +862,12 @@ impl AnsiStyle {
         }
         if self.underline {
             buf.push_str("\x1b[4m");
+            if let Some(underline_style) = self.underline_style {
+                buf.push_str(underline_style.ansi_code());
+            }
+            if let Some(ref color) = self.underline_color {
+                color.push_ansi_underline_code(buf);
+            }
         }
         if self.reverse {
             buf.push_str("\x1b[7m");
@@ -247,14 +882,43 @@ impl AnsiStyle {
             return;
         }
 
-        if shell == Shell::None {
-            self.write_raw_codes(buf);
-        } else {
-            let (start, end) = shell.delimiters();
-            buf.push_str(start);
-            self.write_raw_codes(buf);
-            buf.push_str(end);
+        let mut codes = String::new();
+        self.write_raw_codes(&mut codes);
+        if codes.is_empty() {
+            return;
         }
+
+        let (start, end) = shell.delimiters();
+        buf.push_str(start);
+        buf.push_str(&codes);
+        buf.push_str(end);
+    }
+
+    /// Writes styled content, re-applying this style after every reset embedded in `text`
+    /// so nested styling does not end the outer style early.
+    pub fn write_text(&self, buf: &mut String, text: &str, shell: Shell) {
+        if !self.has_style() || !text.contains('\x1b') {
+            buf.push_str(text);
+            return;
+        }
+
+        let mut rest = text;
+        while let Some(start) = rest.find("\x1b[") {
+            let after = &rest[start + 2..];
+            let reset_len = if after.starts_with("0m") {
+                4
+            } else if after.starts_with('m') {
+                3
+            } else {
+                buf.push_str(&rest[..start + 2]);
+                rest = after;
+                continue;
+            };
+            buf.push_str(&rest[..start + reset_len]);
+            self.write_start_codes(buf, shell);
+            rest = &rest[start + reset_len..];
+        }
+        buf.push_str(rest);
     }
 
     pub fn write_reset(&self, buf: &mut String, shell: Shell) {
@@ -274,6 +938,14 @@ impl AnsiStyle {
 }
 
 fn parse_color(value: &str) -> Result<Color, String> {
+    parse_builtin_color(value).or_else(|error| {
+        let palette = crate::theme::palette();
+        palette.get(value).ok_or(error)
+    })
+}
+
+/// Parses a color without consulting the user palette.
+pub(crate) fn parse_builtin_color(value: &str) -> Result<Color, String> {
     match value {
         "black" => Ok(Color::Black),
         "red" => Ok(Color::Red),
@@ -283,11 +955,31 @@ fn parse_color(value: &str) -> Result<Color, String> {
         "purple" | "magenta" => Ok(Color::Purple),
         "cyan" => Ok(Color::Cyan),
         "white" => Ok(Color::White),
+        "bright-black" | "gray" | "grey" => Ok(Color::BrightBlack),
+        "bright-red" => Ok(Color::BrightRed),
+        "bright-green" => Ok(Color::BrightGreen),
+        "bright-yellow" => Ok(Color::BrightYellow),
+        "bright-blue" => Ok(Color::BrightBlue),
+        "bright-purple" | "bright-magenta" => Ok(Color::BrightPurple),
+        "bright-cyan" => Ok(Color::BrightCyan),
+        "bright-white" => Ok(Color::BrightWhite),
         hex if hex.starts_with('#') => {
             let (r, g, b) = parse_hex_color(hex)?;
             Ok(Color::Rgb(r, g, b))
         }
-        _ => Err(format!("Unknown style component: {}", value)),
+        _ => {
+            let index = value
+                .strip_prefix("ansi256:")
+                .or_else(|| value.strip_prefix("ansi256="))
+                .unwrap_or(value);
+            if !index.is_empty() && index.bytes().all(|byte| byte.is_ascii_digit()) {
+                return index
+                    .parse()
+                    .map(Color::Ansi256)
+                    .map_err(|_| format!("Invalid 256-color index: {}", index));
+            }
+            Err(format!("Unknown style component: {}", value))
+        }
     }
 }
 
@@ -361,7 +1053,9 @@ mod tests {
     }
 
     #[test]
+    #[serial]
     fn test_apply_with_background() {
+        set_color_depth(ColorDepth::TrueColor);
         let style = AnsiStyle::parse("red+#00ff00").unwrap();
         let result = style.apply("test");
         assert!(result.contains("\x1b[31m"));
@@ -377,12 +1071,320 @@ mod tests {
         assert!(result.ends_with("ok\x01\x1b[0m\x02"));
     }
 
+    #[test]
+    #[serial]
+    fn test_parse_theme_references() {
+        crate::theme::set_active_theme("primary=#112233,accent=red.bold".parse().unwrap());
+
+        let style = AnsiStyle::parse("@primary").unwrap();
+        assert_eq!(style.color, Some(Color::Rgb(17, 34, 51)));
+
+        let style = AnsiStyle::parse("@accent.italic").unwrap();
+        assert_eq!(style.color, Some(Color::Red));
+        assert!(style.bold && style.italic);
+
+        let error = AnsiStyle::parse("@missing").unwrap_err();
+        assert_eq!(error, "Unknown theme style: @missing");
+
+        crate::theme::reset_active_theme_for_tests();
+    }
+
+    #[test]
+    #[serial]
+    fn test_parse_palette_colors() {
+        crate::theme::set_palette("rosewater=#f5e0dc,base=#1e1e2e".parse().unwrap());
+
+        let style = AnsiStyle::parse("rosewater+base.bold").unwrap();
+        assert_eq!(style.color, Some(Color::Rgb(0xf5, 0xe0, 0xdc)));
+        assert_eq!(style.background, Some(Color::Rgb(0x1e, 0x1e, 0x2e)));
+        assert_eq!(
+            AnsiStyle::parse("gradient=rosewater-#000000")
+                .unwrap()
+                .gradient,
+            Some(Gradient::Linear(vec![(0xf5, 0xe0, 0xdc), (0, 0, 0)]))
+        );
+        assert!(AnsiStyle::parse("flamingo").is_err());
+
+        crate::theme::reset_palette_for_tests();
+    }
+
+    #[test]
+    #[serial]
+    fn test_parse_cached_follows_palette_changes() {
+        crate::theme::set_palette("accent=#112233".parse().unwrap());
+        let first = AnsiStyle::parse_cached("accent.bold").unwrap();
+        let again = AnsiStyle::parse_cached("accent.bold").unwrap();
+        assert!(Arc::ptr_eq(&first, &again));
+
+        crate::theme::set_palette("accent=#445566".parse().unwrap());
+        let changed = AnsiStyle::parse_cached("accent.bold").unwrap();
+        assert_eq!(changed.color, Some(Color::Rgb(0x44, 0x55, 0x66)));
+        assert!(AnsiStyle::parse_cached("nope").is_err());
+
+        crate::theme::reset_palette_for_tests();
+    }
+
+    #[test]
+    #[serial]
+    fn test_parse_underline_variants() {
+        set_color_depth(ColorDepth::TrueColor);
+        let style = AnsiStyle::parse("underline-curly.underline=#ff0000").unwrap();
+        assert!(style.underline);
+        assert_eq!(style.underline_style, Some(UnderlineStyle::Curly));
+        assert_eq!(style.underline_color, Some(Color::Rgb(255, 0, 0)));
+        assert_eq!(
+            style.apply("x"),
+            "\x1b[4m\x1b[4:3m\x1b[58;2;255;0;0mx\x1b[0m"
+        );
+
+        let style = AnsiStyle::parse("underline:bright-red").unwrap();
+        assert_eq!(style.apply("x"), "\x1b[4m\x1b[58;5;9mx\x1b[0m");
+
+        assert_eq!(
+            AnsiStyle::parse("underline-double").unwrap().apply("x"),
+            "\x1b[4m\x1b[4:2mx\x1b[0m"
+        );
+        assert!(AnsiStyle::parse("underline-wavy").is_err());
+        assert!(AnsiStyle::parse("underline=nope").is_err());
+    }
+
+    #[test]
+    fn test_parse_bright_colors() {
+        let style = AnsiStyle::parse("bright-red+bright-black").unwrap();
+        assert_eq!(style.color, Some(Color::BrightRed));
+        assert_eq!(style.background, Some(Color::BrightBlack));
+        assert_eq!(style.apply("x"), "\x1b[91m\x1b[100mx\x1b[0m");
+
+        assert_eq!(
+            AnsiStyle::parse("gray").unwrap().color,
+            Some(Color::BrightBlack)
+        );
+        assert_eq!(
+            AnsiStyle::parse("bright-white.bold").unwrap().apply("x"),
+            "\x1b[97m\x1b[1mx\x1b[0m"
+        );
+        assert!(AnsiStyle::parse("bright-orange").is_err());
+    }
+
+    #[test]
+    fn test_parse_ansi256_colors() {
+        let style = AnsiStyle::parse("ansi256:208.bold").unwrap();
+        assert_eq!(style.color, Some(Color::Ansi256(208)));
+        assert!(style.bold);
+
+        let style = AnsiStyle::parse("33+ansi256=236").unwrap();
+        assert_eq!(style.color, Some(Color::Ansi256(33)));
+        assert_eq!(style.background, Some(Color::Ansi256(236)));
+
+        assert!(AnsiStyle::parse("256").is_err());
+        assert!(AnsiStyle::parse("ansi256:").is_err());
+    }
+
+    #[test]
+    fn color_display_round_trips() {
+        for color in ["cyan", "bright-purple", "208", "#88c0d0"] {
+            assert_eq!(parse_builtin_color(color).unwrap().to_string(), color);
+        }
+    }
+
+    #[test]
+    #[serial]
+    fn test_apply_ansi256_colors() {
+        set_color_depth(ColorDepth::TrueColor);
+        let style = AnsiStyle::parse("208+236").unwrap();
+        assert_eq!(style.apply("x"), "\x1b[38;5;208m\x1b[48;5;236mx\x1b[0m");
+    }
+
+    #[test]
+    fn test_parse_gradient() {
+        let style = AnsiStyle::parse("gradient:#ff0000-#0000ff.bold").unwrap();
+        assert_eq!(
+            style.gradient,
+            Some(Gradient::Linear(vec![(255, 0, 0), (0, 0, 255)]))
+        );
+        assert!(style.bold);
+        assert_eq!(
+            AnsiStyle::parse("gradient=#000000-#ffffff")
+                .unwrap()
+                .gradient,
+            Some(Gradient::Linear(vec![(0, 0, 0), (255, 255, 255)]))
+        );
+        assert_eq!(
+            AnsiStyle::parse("rainbow").unwrap().gradient,
+            Some(Gradient::Rainbow)
+        );
+        assert!(AnsiStyle::parse("gradient:#ff0000").is_err());
+        assert!(AnsiStyle::parse("gradient:red-blue").is_err());
+    }
+
+    #[test]
+    #[serial]
+    fn test_apply_gradient_colors_each_character() {
+        set_color_depth(ColorDepth::TrueColor);
+        let style = AnsiStyle::parse("gradient:#ff0000-#0000ff").unwrap();
+        let result = style.apply("abc");
+        assert_eq!(
+            result,
+            "\x1b[38;2;255;0;0ma\x1b[38;2;128;0;128mb\x1b[38;2;0;0;255mc\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_gradient_keeps_combining_marks_together() {
+        let parts: Vec<&str> = clusters("e\u{301}x👩\u{200d}💻", Shell::None).collect();
+        assert_eq!(parts, vec!["e\u{301}", "x", "👩\u{200d}💻"]);
+        let parts: Vec<&str> = clusters("5%%", Shell::Zsh).collect();
+        assert_eq!(parts, vec!["5", "%%"]);
+    }
+
+    #[test]
+    #[serial]
+    fn test_gradient_wraps_codes_for_shell() {
+        set_color_depth(ColorDepth::TrueColor);
+        let style = AnsiStyle::parse("gradient:#000000-#ffffff").unwrap();
+        let result = style.apply_with_shell("ab", Shell::Zsh);
+        assert_eq!(
+            result,
+            "%{\x1b[38;2;0;0;0m%}a%{\x1b[38;2;255;255;255m%}b%{\x1b[0m%}"
+        );
+    }
+
+    #[test]
+    fn test_detect_color_depth() {
+        assert_eq!(
+            detect_color_depth(Some("truecolor"), Some("xterm"), None, false),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect_color_depth(None, Some("xterm-256color"), None, false),
+            ColorDepth::Ansi256
+        );
+        assert_eq!(
+            detect_color_depth(None, Some("xterm-256color"), Some("iTerm.app"), false),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect_color_depth(None, Some("xterm-kitty"), None, false),
+            ColorDepth::TrueColor
+        );
+        assert_eq!(
+            detect_color_depth(None, Some("linux"), None, false),
+            ColorDepth::Ansi16
+        );
+        assert_eq!(
+            detect_color_depth(None, None, None, true),
+            ColorDepth::TrueColor
+        );
+    }
+
+    #[test]
+    fn test_color_for_depth() {
+        let orange = Color::Rgb(255, 135, 0);
+        assert_eq!(orange.for_depth(ColorDepth::TrueColor), orange);
+        assert_eq!(orange.for_depth(ColorDepth::Ansi256), Color::Ansi256(208));
+        assert_eq!(orange.for_depth(ColorDepth::Ansi16), Color::Yellow);
+
+        assert_eq!(
+            Color::Rgb(128, 128, 128).for_depth(ColorDepth::Ansi256),
+            Color::Ansi256(244)
+        );
+        assert_eq!(
+            Color::Ansi256(46).for_depth(ColorDepth::Ansi16),
+            Color::BrightGreen
+        );
+        assert_eq!(Color::Cyan.for_depth(ColorDepth::Ansi16), Color::Cyan);
+    }
+
+    #[test]
+    #[serial]
+    fn test_apply_downgrades_hex_colors() {
+        set_color_depth(ColorDepth::Ansi256);
+        let style = AnsiStyle::parse("#ff8700+#000000").unwrap();
+        assert_eq!(style.apply("x"), "\x1b[38;5;208m\x1b[48;5;16mx\x1b[0m");
+
+        set_color_depth(ColorDepth::Ansi16);
+        assert_eq!(style.apply("x"), "\x1b[33m\x1b[40mx\x1b[0m");
+
+        set_color_depth(ColorDepth::TrueColor);
+    }
+
+    #[test]
+    fn test_apply_reapplies_style_after_nested_reset() {
+        let style = AnsiStyle::parse("red.bold").unwrap();
+        assert_eq!(
+            style.apply("a\x1b[32mb\x1b[0mc\x1b[md"),
+            "\x1b[31m\x1b[1ma\x1b[32mb\x1b[0m\x1b[31m\x1b[1mc\x1b[m\x1b[31m\x1b[1md\x1b[0m"
+        );
+        assert_eq!(
+            style.apply_with_shell("x\x1b[0my", Shell::Zsh),
+            "%{\x1b[31m\x1b[1m%}x\x1b[0m%{\x1b[31m\x1b[1m%}y%{\x1b[0m%}"
+        );
+        assert_eq!(
+            style.apply("\x1b[1;4mz"),
+            "\x1b[31m\x1b[1m\x1b[1;4mz\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn test_title_sequence() {
+        assert_eq!(
+            title_sequence("user@host: ~/src", Shell::None),
+            "\x1b]0;user@host: ~/src\x07"
+        );
+        assert_eq!(title_sequence("~", Shell::Zsh), "%{\x1b]0;~\x07%}");
+        assert_eq!(
+            title_sequence("a\x1b[31mb\nc", Shell::Bash),
+            "\x01\x1b]0;a[31mbc\x07\x02"
+        );
+    }
+
+    #[test]
+    fn test_escape_text_doubles_zsh_percent() {
+        assert_eq!(Shell::Zsh.escape_text("100% %n"), "100%% %%n");
+        assert_eq!(Shell::Bash.escape_text("100%"), "100%");
+        assert_eq!(title_sequence("50%", Shell::Zsh), "%{\x1b]0;50%%\x07%}");
+    }
+
+    #[test]
+    fn test_escape_text_blocks_bash_expansion() {
+        assert_eq!(Shell::Bash.escape_text(r"a\w`id`$(id)"), r"a\w`id`$(id)");
+        assert_eq!(
+            Shell::BashPs1.escape_text(r"a\w`id`$(id)"),
+            r"a\\\\w\\`id\\`\\$(id)"
+        );
+        assert_eq!(Shell::Zsh.escape_text("$x"), "$x");
+    }
+
+    #[test]
+    fn test_mark_prompt() {
+        assert_eq!(
+            mark_prompt("~ ❯ ", Some(1), Shell::Bash),
+            "\x01\x1b]133;D;1\x07\x1b]133;A\x07\x02~ ❯ \x01\x1b]133;B\x07\x02"
+        );
+        assert_eq!(
+            mark_prompt("❯", None, Shell::None),
+            "\x1b]133;D\x07\x1b]133;A\x07❯\x1b]133;B\x07"
+        );
+    }
+
+    #[test]
+    fn test_apply_with_fish_leaves_sequences_unwrapped() {
+        let style = AnsiStyle::parse("red").unwrap();
+        assert_eq!(
+            style.apply_with_shell("ok", Shell::Fish),
+            "\x1b[31mok\x1b[0m"
+        );
+    }
+
     #[test]
     fn test_shell_from_str() {
         assert_eq!(Shell::from_str("bash").unwrap(), Shell::Bash);
         assert_eq!(Shell::from_str("ZSH").unwrap(), Shell::Zsh);
         assert_eq!(Shell::from_str("none").unwrap(), Shell::None);
-        assert!(Shell::from_str("fish").is_err());
+        assert_eq!(Shell::from_str("fish").unwrap(), Shell::Fish);
+        assert_eq!(Shell::from_str("xonsh").unwrap(), Shell::Xonsh);
+        assert_eq!(Shell::from_str("cmd").unwrap(), Shell::Clink);
+        assert!(Shell::from_str("tcsh").is_err());
     }
 
     #[test]
@@ -1,9 +1,14 @@
 use crate::error::Result;
+use crate::executor::{
+    SegmentJoiner, condition_holds, default_format, is_directive, render_isolated, render_separator,
+};
 use crate::module_trait::ModuleContext;
 use crate::parser::{Token, parse};
 use crate::registry::ModuleRegistry;
-use crate::style::{AnsiStyle, ModuleStyle, global_no_color};
+use crate::style::{AnsiStyle, ModuleStyle, Shell, global_no_color};
 use is_terminal::IsTerminal;
+use std::borrow::Cow;
+use std::io::Write;
 
 /// A parsed template that can be rendered multiple times efficiently
 pub struct Template<'a> {
@@ -26,44 +31,134 @@ impl<'a> Template<'a> {
     /// Render the template with the given registry and context
     pub fn render(&self, registry: &ModuleRegistry, context: &ModuleContext) -> Result<String> {
         let mut output = String::with_capacity(self.estimated_size);
+        self.render_into(&mut output, registry, context)?;
+        Ok(output)
+    }
+
+    /// Render the template onto the end of `buf`, reusing its allocation. On error `buf` is
+    /// left as it was.
+    pub fn render_into(
+        &self,
+        buf: &mut String,
+        registry: &ModuleRegistry,
+        context: &ModuleContext,
+    ) -> Result<()> {
+        let start = buf.len();
+        buf.reserve(self.estimated_size);
+        let result = self.write(buf, registry, context);
+        if result.is_err() {
+            buf.truncate(start);
+        }
+        result
+    }
 
+    /// Render the template and write it to `writer` in one call.
+    pub fn render_to<W: Write + ?Sized>(
+        &self,
+        writer: &mut W,
+        registry: &ModuleRegistry,
+        context: &ModuleContext,
+    ) -> Result<()> {
+        let mut output = String::with_capacity(self.estimated_size);
+        self.write(&mut output, registry, context)?;
+        writer.write_all(output.as_bytes())?;
+        Ok(())
+    }
+
+    fn write(
+        &self,
+        output: &mut String,
+        registry: &ModuleRegistry,
+        context: &ModuleContext,
+    ) -> Result<()> {
         let no_color = global_no_color() || !IsTerminal::is_terminal(&std::io::stdout());
+        let mut joiner = SegmentJoiner::new(context.shell, no_color);
+        let default_styles = crate::theme::module_styles();
 
         for token in &self.tokens {
             match token {
                 Token::Text(text) => {
-                    output.push_str(text);
+                    joiner.text(output, text);
+                }
+                Token::Var(name) => {
+                    let value = crate::executor::lookup_var(name);
+                    joiner.text(output, &context.shell.escape_text(&value));
+                }
+                Token::Placeholder(params)
+                    if params
+                        .when
+                        .as_deref()
+                        .is_some_and(|when| !condition_holds(when)) => {}
+                Token::Placeholder(params) if is_directive(&params.module) => {
+                    joiner.separator(render_separator(params, context, no_color)?);
                 }
                 Token::Placeholder(params) => {
                     let module = registry.get(&params.module).ok_or_else(|| {
                         crate::error::PromptError::UnknownModule(params.module.to_string())
                     })?;
 
-                    if let Some(text) = module.render(&params.format, context)?
+                    let format = match default_format(&params.module) {
+                        Some(format) if params.format.is_empty() => Cow::Owned(format),
+                        _ => Cow::Borrowed(params.format.as_ref()),
+                    };
+                    if let Some(text) = render_isolated(&params.module, &module, &format, context)?
                         && !text.is_empty()
                     {
+                        let text = crate::width::truncate_to_len(
+                            &text,
+                            crate::executor::MAX_SEGMENT_LEN,
+                            Shell::None,
+                        );
+                        let text = context.shell.escape_text(&text);
+                        let icon = crate::icons::segment_icon(&params.module);
                         let has_prefix = !params.prefix.is_empty();
                         let has_suffix = !params.suffix.is_empty();
-                        let styled = !params.style.is_empty() && !no_color;
+                        let style_str = match default_styles.get(&params.module) {
+                            Some(style) if params.style.is_empty() => style,
+                            _ => &params.style,
+                        };
+                        let styled = !style_str.is_empty() && !no_color;
 
                         if styled {
-                            let style = AnsiStyle::parse(&params.style).map_err(|error| {
+                            let style = AnsiStyle::parse_cached(style_str).map_err(|error| {
                                 crate::error::PromptError::StyleError {
                                     module: params.module.to_string(),
                                     error,
                                 }
                             })?;
+                            joiner.begin_segment(output, style.background);
+
+                            if style.has_gradient() {
+                                let segment = format!(
+                                    "{}{}{}{}",
+                                    icon.map(|icon| format!("{} ", icon)).unwrap_or_default(),
+                                    params.prefix,
+                                    text,
+                                    params.suffix
+                                );
+                                output.push_str(&style.apply_with_shell(&segment, context.shell));
+                                continue;
+                            }
 
-                            style.write_start_codes(&mut output, context.shell);
+                            style.write_start_codes(output, context.shell);
+                            if let Some(icon) = icon {
+                                output.push_str(icon);
+                                output.push(' ');
+                            }
                             if has_prefix {
-                                output.push_str(&params.prefix);
+                                style.write_text(output, &params.prefix, context.shell);
                             }
-                            output.push_str(&text);
+                            style.write_text(output, &text, context.shell);
                             if has_suffix {
-                                output.push_str(&params.suffix);
+                                style.write_text(output, &params.suffix, context.shell);
                             }
-                            style.write_reset(&mut output, context.shell);
+                            style.write_reset(output, context.shell);
                         } else {
+                            joiner.begin_segment(output, None);
+                            if let Some(icon) = icon {
+                                output.push_str(icon);
+                                output.push(' ');
+                            }
                             if has_prefix {
                                 output.push_str(&params.prefix);
                             }
@@ -76,8 +171,9 @@ impl<'a> Template<'a> {
                 }
             }
         }
+        joiner.finish(output);
 
-        Ok(output)
+        Ok(())
     }
 
     /// Get an iterator over the tokens in this template
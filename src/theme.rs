@@ -0,0 +1,519 @@
+use crate::style::{Color, parse_builtin_color};
+use std::collections::HashMap;
+use std::str::FromStr;
+use std::sync::{Arc, RwLock};
+
+const DEFAULT_THEME: &str = "default";
+
+type PaletteEntries = &'static [(&'static str, &'static str)];
+
+/// Built-in themes as `(name, dark palette, light palette)`.
+const BUILTIN_THEMES: &[(&str, PaletteEntries, PaletteEntries)] = &[
+    (
+        "default",
+        &[
+            ("primary", "cyan"),
+            ("secondary", "blue"),
+            ("accent", "purple"),
+            ("success", "green"),
+            ("warning", "yellow"),
+            ("error", "red"),
+            ("muted", "bright-black"),
+        ],
+        &[
+            ("primary", "blue"),
+            ("secondary", "cyan"),
+            ("accent", "purple"),
+            ("success", "green"),
+            ("warning", "#af5f00"),
+            ("error", "red"),
+            ("muted", "bright-black"),
+        ],
+    ),
+    (
+        "catppuccin",
+        &[
+            ("primary", "#89b4fa"),
+            ("secondary", "#74c7ec"),
+            ("accent", "#cba6f7"),
+            ("success", "#a6e3a1"),
+            ("warning", "#f9e2af"),
+            ("error", "#f38ba8"),
+            ("muted", "#6c7086"),
+        ],
+        &[
+            ("primary", "#1e66f5"),
+            ("secondary", "#209fb5"),
+            ("accent", "#8839ef"),
+            ("success", "#40a02b"),
+            ("warning", "#df8e1d"),
+            ("error", "#d20f39"),
+            ("muted", "#9ca0b0"),
+        ],
+    ),
+    (
+        "nord",
+        &[
+            ("primary", "#88c0d0"),
+            ("secondary", "#81a1c1"),
+            ("accent", "#b48ead"),
+            ("success", "#a3be8c"),
+            ("warning", "#ebcb8b"),
+            ("error", "#bf616a"),
+            ("muted", "#4c566a"),
+        ],
+        &[
+            ("primary", "#5e81ac"),
+            ("secondary", "#4c6a92"),
+            ("accent", "#8f6b8a"),
+            ("success", "#6a8f4e"),
+            ("warning", "#b07d2b"),
+            ("error", "#bf616a"),
+            ("muted", "#7b88a1"),
+        ],
+    ),
+];
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Default)]
+pub enum Background {
+    #[default]
+    Dark,
+    Light,
+}
+
+impl FromStr for Background {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        match value.trim().to_ascii_lowercase().as_str() {
+            "dark" => Ok(Background::Dark),
+            "light" => Ok(Background::Light),
+            other => Err(format!(
+                "Unknown background: {} (supported values: dark, light)",
+                other
+            )),
+        }
+    }
+}
+
+impl Background {
+    /// Terminal background from `PRMT_BACKGROUND`, else `COLORFGBG`, defaulting to dark.
+    pub fn detect() -> Self {
+        std::env::var("PRMT_BACKGROUND")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .or_else(|| Self::from_colorfgbg(&std::env::var("COLORFGBG").ok()?))
+            .unwrap_or_default()
+    }
+
+    /// `COLORFGBG` is `fg;bg` (sometimes `fg;default;bg`) in ANSI color indexes; white and
+    /// the bright colors other than bright black mean a light background.
+    fn from_colorfgbg(value: &str) -> Option<Self> {
+        let background: u8 = value.rsplit(';').next()?.trim().parse().ok()?;
+        Some(match background {
+            7 | 9..=15 => Background::Light,
+            _ => Background::Dark,
+        })
+    }
+}
+
+static ACTIVE_THEME: RwLock<Option<Arc<Theme>>> = RwLock::new(None);
+static MODULE_STYLES: RwLock<Option<Arc<ModuleStyles>>> = RwLock::new(None);
+static PALETTE: RwLock<Option<Arc<Palette>>> = RwLock::new(None);
+
+fn store<T>(slot: &RwLock<Option<Arc<T>>>, value: Option<T>) {
+    if let Ok(mut active) = slot.write() {
+        *active = value.map(Arc::new);
+    }
+    crate::style::clear_style_cache();
+}
+
+fn load_or_init<T>(slot: &RwLock<Option<Arc<T>>>, init: impl FnOnce() -> T) -> Arc<T> {
+    if let Ok(active) = slot.read()
+        && let Some(value) = active.as_ref()
+    {
+        return Arc::clone(value);
+    }
+
+    let value = Arc::new(init());
+    if let Ok(mut active) = slot.write() {
+        return Arc::clone(active.get_or_insert(value));
+    }
+    value
+}
+
+fn sorted_entries(map: &HashMap<String, String>) -> Vec<(&str, &str)> {
+    let mut entries: Vec<_> = map
+        .iter()
+        .map(|(key, value)| (key.as_str(), value.as_str()))
+        .collect();
+    entries.sort_unstable();
+    entries
+}
+
+/// Semantic style names (`@primary`, `@accent`, ...) mapped to style strings.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Theme {
+    styles: HashMap<String, String>,
+}
+
+impl Theme {
+    pub fn builtin(name: &str, background: Background) -> Option<Self> {
+        let (_, dark, light) = BUILTIN_THEMES
+            .iter()
+            .find(|(builtin, ..)| *builtin == name)?;
+        let entries = match background {
+            Background::Dark => dark,
+            Background::Light => light,
+        };
+        let styles = entries
+            .iter()
+            .map(|(key, style)| (key.to_string(), style.to_string()))
+            .collect();
+        Some(Self { styles })
+    }
+
+    pub fn builtin_names() -> impl Iterator<Item = &'static str> {
+        BUILTIN_THEMES.iter().map(|(name, ..)| *name)
+    }
+
+    pub fn get(&self, name: &str) -> Option<&str> {
+        self.styles.get(name).map(String::as_str)
+    }
+
+    /// Style names and their styles, sorted by name.
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        sorted_entries(&self.styles)
+    }
+
+    pub fn set(&mut self, name: &str, style: &str) -> Result<(), String> {
+        if name.is_empty()
+            || !name
+                .bytes()
+                .all(|b| b.is_ascii_alphanumeric() || b == b'_' || b == b'-')
+        {
+            return Err(format!("Invalid theme style name: {}", name));
+        }
+        if style.contains('@') {
+            return Err(format!(
+                "Theme style @{} cannot reference another theme style",
+                name
+            ));
+        }
+        self.styles.insert(name.to_string(), style.to_string());
+        Ok(())
+    }
+}
+
+impl Theme {
+    /// Parses `nord`, `primary=cyan,accent=#ff8800.bold` or `nord,light.accent=red`: a
+    /// built-in theme name picks the base palette for `background` and `name=style` entries
+    /// override it. Names prefixed with `dark.` or `light.` only apply on that background.
+    pub fn parse_for(value: &str, background: Background) -> Result<Self, String> {
+        let mut theme = Theme::builtin(DEFAULT_THEME, background).unwrap_or_default();
+
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            match entry.split_once('=') {
+                Some((name, style)) => {
+                    let name = name.trim();
+                    let name = match (name.split_once('.'), background) {
+                        (Some(("dark", name)), Background::Dark)
+                        | (Some(("light", name)), Background::Light) => name,
+                        (Some(("dark" | "light", _)), _) => continue,
+                        _ => name,
+                    };
+                    theme.set(name, style.trim())?;
+                }
+                None => {
+                    let base = Theme::builtin(entry, background).ok_or_else(|| {
+                        format!(
+                            "Unknown theme: {} (built-in themes: {})",
+                            entry,
+                            Theme::builtin_names().collect::<Vec<_>>().join(", ")
+                        )
+                    })?;
+                    theme.styles.extend(base.styles);
+                }
+            }
+        }
+
+        Ok(theme)
+    }
+}
+
+impl FromStr for Theme {
+    type Err = String;
+
+    /// Parses a theme for the detected terminal background, see [`Theme::parse_for`].
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        Theme::parse_for(value, Background::detect())
+    }
+}
+
+/// Replaces the theme used to resolve `@name` style references.
+pub fn set_active_theme(theme: Theme) {
+    store(&ACTIVE_THEME, Some(theme));
+}
+
+/// The active theme, loaded from `PRMT_THEME` on first use unless one was set explicitly.
+pub fn active_theme() -> Arc<Theme> {
+    load_or_init(&ACTIVE_THEME, || {
+        std::env::var("PRMT_THEME")
+            .ok()
+            .and_then(|value| Theme::from_str(&value).ok())
+            .or_else(|| Theme::builtin(DEFAULT_THEME, Background::detect()))
+            .unwrap_or_default()
+    })
+}
+
+/// Styles applied to modules whose placeholder leaves the style field empty.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct ModuleStyles {
+    styles: HashMap<String, String>,
+}
+
+impl ModuleStyles {
+    pub fn get(&self, module: &str) -> Option<&str> {
+        self.styles.get(module).map(String::as_str)
+    }
+
+    /// Modules and their default styles, sorted by module.
+    pub fn entries(&self) -> Vec<(&str, &str)> {
+        sorted_entries(&self.styles)
+    }
+
+    pub fn insert(&mut self, module: &str, style: &str) -> Result<(), String> {
+        let module = module.trim();
+        if module.is_empty() {
+            return Err(format!("Invalid module style: ={}", style));
+        }
+        self.styles
+            .insert(module.to_string(), style.trim().to_string());
+        Ok(())
+    }
+
+    /// Adds `other`'s styles, replacing entries for the same module.
+    pub fn extend(&mut self, other: ModuleStyles) {
+        self.styles.extend(other.styles);
+    }
+}
+
+impl FromStr for ModuleStyles {
+    type Err = String;
+
+    /// Parses `git=purple,path=cyan.bold,rust=@error` entries.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut styles = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((module, style)) = entry.split_once('=') else {
+                return Err(format!(
+                    "Invalid module style: {} (expected module=style)",
+                    entry
+                ));
+            };
+            styles.insert(module, style)?;
+        }
+        Ok(styles)
+    }
+}
+
+/// Replaces the per-module default styles.
+pub fn set_module_styles(styles: ModuleStyles) {
+    store(&MODULE_STYLES, Some(styles));
+}
+
+/// Per-module default styles, loaded from `PRMT_STYLES` on first use unless set explicitly.
+pub fn module_styles() -> Arc<ModuleStyles> {
+    load_or_init(&MODULE_STYLES, || {
+        std::env::var("PRMT_STYLES")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    })
+}
+
+/// User-defined color names usable anywhere a color is expected, including theme styles.
+#[derive(Debug, Clone, Default, PartialEq)]
+pub struct Palette {
+    colors: HashMap<String, Color>,
+}
+
+impl Palette {
+    pub fn get(&self, name: &str) -> Option<Color> {
+        self.colors.get(name).copied()
+    }
+
+    /// Color names and their colors, sorted by name.
+    pub fn entries(&self) -> Vec<(&str, Color)> {
+        let mut entries: Vec<_> = self
+            .colors
+            .iter()
+            .map(|(name, color)| (name.as_str(), *color))
+            .collect();
+        entries.sort_unstable_by_key(|(name, _)| *name);
+        entries
+    }
+
+    /// Adds a named color; the value must be a plain color, not another palette name.
+    pub fn insert(&mut self, name: &str, color: &str) -> Result<(), String> {
+        let name = name.trim();
+        if name.is_empty()
+            || !name.bytes().all(|b| b.is_ascii_alphanumeric() || b == b'_')
+            || name.bytes().all(|b| b.is_ascii_digit())
+        {
+            return Err(format!("Invalid palette color name: {}", name));
+        }
+        if parse_builtin_color(name).is_ok() {
+            return Err(format!("Palette cannot redefine built-in color: {}", name));
+        }
+        self.colors
+            .insert(name.to_string(), parse_builtin_color(color.trim())?);
+        Ok(())
+    }
+
+    /// Adds `other`'s colors, replacing entries with the same name.
+    pub fn extend(&mut self, other: Palette) {
+        self.colors.extend(other.colors);
+    }
+}
+
+impl FromStr for Palette {
+    type Err = String;
+
+    /// Parses `rosewater=#f5e0dc,surface0=#313244` entries; values must be plain colors.
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let mut palette = Self::default();
+        for entry in value.split(',').map(str::trim).filter(|e| !e.is_empty()) {
+            let Some((name, color)) = entry.split_once('=') else {
+                return Err(format!(
+                    "Invalid palette color: {} (expected name=color)",
+                    entry
+                ));
+            };
+            palette.insert(name, color)?;
+        }
+        Ok(palette)
+    }
+}
+
+/// Replaces the palette of named colors.
+pub fn set_palette(palette: Palette) {
+    store(&PALETTE, Some(palette));
+}
+
+/// The palette of named colors, loaded from `PRMT_PALETTE` on first use unless set explicitly.
+pub fn palette() -> Arc<Palette> {
+    load_or_init(&PALETTE, || {
+        std::env::var("PRMT_PALETTE")
+            .ok()
+            .and_then(|value| value.parse().ok())
+            .unwrap_or_default()
+    })
+}
+
+#[cfg(test)]
+pub fn reset_module_styles_for_tests() {
+    store(&MODULE_STYLES, None);
+}
+
+#[cfg(test)]
+pub fn reset_active_theme_for_tests() {
+    store(&ACTIVE_THEME, None);
+}
+
+#[cfg(test)]
+pub fn reset_palette_for_tests() {
+    store(&PALETTE, None);
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn builtin_theme_defines_semantic_names() {
+        let theme = Theme::builtin("default", Background::Dark).unwrap();
+        assert_eq!(theme.get("primary"), Some("cyan"));
+        assert_eq!(theme.get("muted"), Some("bright-black"));
+        assert!(Theme::builtin("missing", Background::Dark).is_none());
+    }
+
+    #[test]
+    fn builtin_theme_has_light_palette() {
+        let theme = Theme::builtin("catppuccin", Background::Light).unwrap();
+        assert_eq!(theme.get("primary"), Some("#1e66f5"));
+    }
+
+    #[test]
+    fn parse_applies_background_specific_entries() {
+        let value = "dark.prompt=white,light.prompt=black,accent=red";
+        let dark = Theme::parse_for(value, Background::Dark).unwrap();
+        let light = Theme::parse_for(value, Background::Light).unwrap();
+        assert_eq!(dark.get("prompt"), Some("white"));
+        assert_eq!(light.get("prompt"), Some("black"));
+        assert_eq!(light.get("accent"), Some("red"));
+        assert_eq!(light.get("primary"), Some("blue"));
+    }
+
+    #[test]
+    fn background_from_colorfgbg() {
+        assert_eq!(Background::from_colorfgbg("15;0"), Some(Background::Dark));
+        assert_eq!(Background::from_colorfgbg("0;15"), Some(Background::Light));
+        assert_eq!(
+            Background::from_colorfgbg("0;default;7"),
+            Some(Background::Light)
+        );
+        assert_eq!(Background::from_colorfgbg("12;8"), Some(Background::Dark));
+        assert_eq!(Background::from_colorfgbg("garbage"), None);
+    }
+
+    #[test]
+    fn parse_overrides_on_top_of_base_theme() {
+        let theme =
+            Theme::parse_for("nord, accent=#ff8800.bold, prompt=green", Background::Dark).unwrap();
+        assert_eq!(theme.get("primary"), Some("#88c0d0"));
+        assert_eq!(theme.get("accent"), Some("#ff8800.bold"));
+        assert_eq!(theme.get("prompt"), Some("green"));
+    }
+
+    #[test]
+    fn parse_inline_definitions_keep_default_names() {
+        let theme = Theme::parse_for("primary=#ffffff", Background::Dark).unwrap();
+        assert_eq!(theme.get("primary"), Some("#ffffff"));
+        assert_eq!(theme.get("error"), Some("red"));
+    }
+
+    #[test]
+    fn module_styles_from_str() {
+        let styles = ModuleStyles::from_str("git=purple, path=cyan.bold").unwrap();
+        assert_eq!(styles.get("git"), Some("purple"));
+        assert_eq!(styles.get("path"), Some("cyan.bold"));
+        assert_eq!(styles.get("rust"), None);
+        assert!(ModuleStyles::from_str("purple").is_err());
+        assert!(ModuleStyles::from_str("=purple").is_err());
+    }
+
+    #[test]
+    fn palette_from_str() {
+        let palette = Palette::from_str("rosewater=#f5e0dc, base=ansi256:235").unwrap();
+        assert_eq!(palette.get("rosewater"), Some(Color::Rgb(0xf5, 0xe0, 0xdc)));
+        assert_eq!(palette.get("base"), Some(Color::Ansi256(235)));
+        assert_eq!(palette.get("missing"), None);
+    }
+
+    #[test]
+    fn palette_rejects_invalid_entries() {
+        assert!(Palette::from_str("red=#ff0000").is_err());
+        assert!(Palette::from_str("pink=rosewater").is_err());
+        assert!(Palette::from_str("my-pink=#ff00ff").is_err());
+        assert!(Palette::from_str("42=#ff00ff").is_err());
+        assert!(Palette::from_str("pink").is_err());
+    }
+
+    #[test]
+    fn parse_rejects_invalid_definitions() {
+        assert!(Theme::from_str("solarized-neon").is_err());
+        assert!(Theme::from_str("accent=@primary").is_err());
+        assert!(Theme::from_str("=red").is_err());
+    }
+}
@@ -0,0 +1,135 @@
+use std::fmt::Write;
+
+const COLOR_NAMES: [&str; 8] = [
+    "black", "red", "green", "yellow", "blue", "magenta", "cyan", "white",
+];
+
+const UNDERLINE_STYLES: [&str; 6] = [
+    "nounderscore",
+    "underscore",
+    "double-underscore",
+    "curly-underscore",
+    "dotted-underscore",
+    "dashed-underscore",
+];
+
+/// Reads an extended color (`5;N` or `2;R;G;B`) following a 38, 48, or 58 code.
+fn extended_color<'a>(codes: &mut impl Iterator<Item = &'a str>) -> Option<String> {
+    let mut next = || codes.next().and_then(|code| code.parse::<u8>().ok());
+    match next()? {
+        5 => next().map(|index| format!("colour{}", index)),
+        2 => {
+            let (r, g, b) = (next()?, next()?, next()?);
+            Some(format!("#{:02x}{:02x}{:02x}", r, g, b))
+        }
+        _ => None,
+    }
+}
+
+/// Translates one SGR parameter list into tmux style attributes.
+fn push_attributes(params: &str, attributes: &mut Vec<String>) {
+    let mut codes = params.split(';');
+    while let Some(code) = codes.next() {
+        let (code, sub) = code.split_once(':').unwrap_or((code, ""));
+        let Ok(code) = code.parse::<u8>() else {
+            continue;
+        };
+        let attribute = match code {
+            0 => Some("default".to_string()),
+            1 => Some("bold".to_string()),
+            2 => Some("dim".to_string()),
+            3 => Some("italics".to_string()),
+            4 => UNDERLINE_STYLES
+                .get(sub.parse::<usize>().unwrap_or(1))
+                .map(|style| style.to_string()),
+            7 => Some("reverse".to_string()),
+            9 => Some("strikethrough".to_string()),
+            30..=37 => Some(format!("fg={}", COLOR_NAMES[(code - 30) as usize])),
+            90..=97 => Some(format!("fg=bright{}", COLOR_NAMES[(code - 90) as usize])),
+            40..=47 => Some(format!("bg={}", COLOR_NAMES[(code - 40) as usize])),
+            100..=107 => Some(format!("bg=bright{}", COLOR_NAMES[(code - 100) as usize])),
+            39 => Some("fg=default".to_string()),
+            49 => Some("bg=default".to_string()),
+            38 | 48 | 58 => extended_color(&mut codes).map(|color| {
+                let key = match code {
+                    38 => "fg",
+                    48 => "bg",
+                    _ => "us",
+                };
+                format!("{}={}", key, color)
+            }),
+            _ => None,
+        };
+        if let Some(attribute) = attribute {
+            attributes.push(attribute);
+        }
+    }
+}
+
+/// Rewrites rendered ANSI output for tmux's `status-left`/`status-right`: SGR escapes become
+/// `#[...]` style directives and literal `#` is doubled so it is not read as a format.
+pub fn to_status(output: &str) -> String {
+    let mut result = String::with_capacity(output.len() + 16);
+    let mut attributes: Vec<String> = Vec::new();
+    let mut rest = output;
+
+    while let Some(ch) = rest.chars().next() {
+        if let Some(params) = rest.strip_prefix("\x1b[")
+            && let Some(end) = params.find(|c: char| !(c.is_ascii_digit() || c == ';' || c == ':'))
+            && params[end..].starts_with('m')
+        {
+            let params = &params[..end];
+            push_attributes(
+                if params.is_empty() { "0" } else { params },
+                &mut attributes,
+            );
+            rest = &rest[2 + end + 1..];
+            continue;
+        }
+
+        if !attributes.is_empty() {
+            let _ = write!(result, "#[{}]", attributes.join(","));
+            attributes.clear();
+        }
+        match ch {
+            '#' => result.push_str("##"),
+            ch => result.push(ch),
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+    if !attributes.is_empty() {
+        let _ = write!(result, "#[{}]", attributes.join(","));
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn converts_named_colors_and_modifiers() {
+        assert_eq!(
+            to_status("\x1b[31m\x1b[1m~/src\x1b[0m ❯"),
+            "#[fg=red,bold]~/src#[default] ❯"
+        );
+        assert_eq!(
+            to_status("\x1b[93;104mx\x1b[m"),
+            "#[fg=brightyellow,bg=brightblue]x#[default]"
+        );
+    }
+
+    #[test]
+    fn converts_extended_colors_and_underlines() {
+        assert_eq!(
+            to_status("\x1b[38;2;255;0;16m\x1b[48;5;16m\x1b[4:3m\x1b[58;5;1mx"),
+            "#[fg=#ff0010,bg=colour16,curly-underscore,us=colour1]x"
+        );
+    }
+
+    #[test]
+    fn escapes_literal_hash() {
+        assert_eq!(to_status("#1 #[x]"), "##1 ##[x]");
+    }
+}
@@ -0,0 +1,166 @@
+//! Instrumentation. With the `tracing` feature, [`trace_span!`] enters a debug-level span for
+//! the rest of the enclosing block and modules render inside a `module` span; without it,
+//! both compile to nothing. Independently, `--debug=json` turns on a recorder that collects
+//! subprocess spawns, cache lookups, and detected markers for its report.
+
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::{Mutex, PoisonError};
+use std::time::{Duration, Instant};
+
+#[cfg(feature = "tracing")]
+macro_rules! trace_span {
+    ($($arg:tt)*) => {
+        let _span = tracing::debug_span!($($arg)*).entered();
+    };
+}
+
+#[cfg(not(feature = "tracing"))]
+macro_rules! trace_span {
+    ($($arg:tt)*) => {};
+}
+
+pub(crate) use trace_span;
+
+/// Enters a `spawn` span for the rest of the enclosing block and records the spawn, with how
+/// long the block took, while recording is on.
+macro_rules! trace_spawn {
+    ($program:expr) => {
+        $crate::trace::trace_span!("spawn", program = %$program);
+        let _spawn = $crate::trace::SpawnGuard::new(|| $program.to_string());
+    };
+}
+
+pub(crate) use trace_spawn;
+
+static RECORDING: AtomicBool = AtomicBool::new(false);
+static EVENTS: Mutex<Vec<Event>> = Mutex::new(Vec::new());
+
+/// Something the recorder saw while a prompt rendered.
+#[allow(dead_code)]
+#[derive(Debug, Clone, PartialEq)]
+pub(crate) enum Event {
+    Spawn {
+        program: String,
+        elapsed: Duration,
+    },
+    /// A lookup in an on-disk cache: `kind` is `version` or `git`.
+    Cache {
+        kind: &'static str,
+        key: String,
+        hit: bool,
+    },
+    Detected {
+        marker: &'static str,
+        path: PathBuf,
+    },
+}
+
+/// Starts collecting events for [`take_events`]. Recording stays on for the rest of the
+/// process.
+#[allow(dead_code)]
+pub(crate) fn start_recording() {
+    RECORDING.store(true, Ordering::Relaxed);
+}
+
+fn recording() -> bool {
+    RECORDING.load(Ordering::Relaxed)
+}
+
+/// Records the event made by `event`, which only runs while recording is on.
+pub(crate) fn record(event: impl FnOnce() -> Event) {
+    if recording() {
+        EVENTS
+            .lock()
+            .unwrap_or_else(PoisonError::into_inner)
+            .push(event());
+    }
+}
+
+/// Events recorded so far, oldest first, leaving none behind.
+#[allow(dead_code)]
+pub(crate) fn take_events() -> Vec<Event> {
+    std::mem::take(&mut *EVENTS.lock().unwrap_or_else(PoisonError::into_inner))
+}
+
+/// Records a [`Event::Spawn`] when dropped; see [`trace_spawn!`].
+pub(crate) struct SpawnGuard {
+    program: Option<String>,
+    started: Instant,
+}
+
+impl SpawnGuard {
+    pub(crate) fn new(program: impl FnOnce() -> String) -> Self {
+        Self {
+            program: recording().then(program),
+            started: Instant::now(),
+        }
+    }
+}
+
+impl Drop for SpawnGuard {
+    fn drop(&mut self) {
+        if let Some(program) = self.program.take() {
+            let elapsed = self.started.elapsed();
+            record(|| Event::Spawn { program, elapsed });
+        }
+    }
+}
+
+#[cfg(feature = "tracing")]
+mod traced {
+    use crate::error::Result;
+    use crate::module_trait::{Module, ModuleContext, ModuleRef};
+    use std::sync::Arc;
+
+    struct TracedModule {
+        name: String,
+        module: ModuleRef,
+    }
+
+    impl Module for TracedModule {
+        fn fs_markers(&self) -> &'static [&'static str] {
+            self.module.fs_markers()
+        }
+
+        fn is_blocking(&self) -> bool {
+            self.module.is_blocking()
+        }
+
+        fn formats(&self) -> &'static str {
+            self.module.formats()
+        }
+
+        fn validate_format(&self, format: &str) -> Result<()> {
+            self.module.validate_format(format)
+        }
+
+        fn render(&self, format: &str, context: &ModuleContext) -> Result<Option<String>> {
+            let span = tracing::debug_span!("module", name = %self.name, format, shown = tracing::field::Empty);
+            let _entered = span.enter();
+            let result = self.module.render(format, context);
+            if let Ok(text) = &result {
+                span.record("shown", text.is_some());
+            }
+            result
+        }
+    }
+
+    pub(crate) fn instrument(name: &str, module: ModuleRef) -> ModuleRef {
+        Arc::new(TracedModule {
+            name: name.to_string(),
+            module,
+        })
+    }
+}
+
+#[cfg(feature = "tracing")]
+pub(crate) use traced::instrument;
+
+#[cfg(not(feature = "tracing"))]
+pub(crate) fn instrument(
+    _name: &str,
+    module: crate::module_trait::ModuleRef,
+) -> crate::module_trait::ModuleRef {
+    module
+}
@@ -0,0 +1,698 @@
+//! Local time without the C library. The zone comes from `TZ` or `/etc/localtime`, read as a
+//! TZif file from the zoneinfo database or as a POSIX TZ string such as `EST5EDT,M3.2.0,M11.1.0`;
+//! Windows describes its zone with the same kind of rule. Unknown zones fall back to UTC.
+
+use std::env;
+use std::fs;
+use std::path::{Component, Path};
+
+const SECONDS_PER_DAY: i64 = 86_400;
+const ZONEINFO_DIRS: &[&str] = &[
+    "/usr/share/zoneinfo",
+    "/usr/lib/zoneinfo",
+    "/usr/share/lib/zoneinfo",
+];
+/// Used when a POSIX string names a daylight zone but no rule, as the US does.
+const DEFAULT_DST_RULE: &str = "M3.2.0,M11.1.0";
+
+/// Seconds east of UTC in the local zone at `timestamp` (seconds since the Unix epoch).
+pub fn local_offset(timestamp: i64) -> i32 {
+    local_zone().map_or(0, |zone| zone.offset_at(timestamp))
+}
+
+/// Seconds since local midnight at `timestamp`.
+pub fn local_seconds_of_day(timestamp: i64) -> u32 {
+    seconds_of_day(timestamp, local_offset(timestamp))
+}
+
+fn seconds_of_day(timestamp: i64, offset: i32) -> u32 {
+    (timestamp + i64::from(offset)).rem_euclid(SECONDS_PER_DAY) as u32
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum TimeZone {
+    Tzif(Tzif),
+    Posix(PosixTz),
+}
+
+impl TimeZone {
+    fn offset_at(&self, timestamp: i64) -> i32 {
+        match self {
+            TimeZone::Tzif(tzif) => tzif.offset_at(timestamp),
+            TimeZone::Posix(rule) => rule.offset_at(timestamp),
+        }
+    }
+}
+
+fn local_zone() -> Option<TimeZone> {
+    match env::var("TZ") {
+        Ok(tz) => zone_from_tz(&tz),
+        Err(_) => platform_zone(),
+    }
+}
+
+/// The zone `TZ` names: a zoneinfo file, by name or path, or else a POSIX TZ string. An empty
+/// `TZ` means UTC.
+fn zone_from_tz(tz: &str) -> Option<TimeZone> {
+    let name = tz.strip_prefix(':').unwrap_or(tz);
+    if name.is_empty() {
+        return None;
+    }
+    zoneinfo_file(name)
+        .and_then(|data| Tzif::parse(&data))
+        .map(TimeZone::Tzif)
+        .or_else(|| PosixTz::parse(name).map(TimeZone::Posix))
+}
+
+fn zoneinfo_file(name: &str) -> Option<Vec<u8>> {
+    let path = Path::new(name);
+    if path.is_absolute() {
+        return fs::read(path).ok();
+    }
+    if !path
+        .components()
+        .all(|component| matches!(component, Component::Normal(_)))
+    {
+        return None;
+    }
+    let tzdir = env::var("TZDIR").ok();
+    tzdir
+        .as_deref()
+        .into_iter()
+        .chain(ZONEINFO_DIRS.iter().copied())
+        .find_map(|dir| fs::read(Path::new(dir).join(path)).ok())
+}
+
+#[cfg(not(windows))]
+fn platform_zone() -> Option<TimeZone> {
+    Tzif::parse(&fs::read("/etc/localtime").ok()?).map(TimeZone::Tzif)
+}
+
+/// The zone Windows is set to, as a rule of the same shape as a POSIX TZ string.
+#[cfg(windows)]
+fn platform_zone() -> Option<TimeZone> {
+    #[allow(dead_code)]
+    #[derive(Default, Clone, Copy)]
+    #[repr(C)]
+    struct SystemTime {
+        year: u16,
+        month: u16,
+        day_of_week: u16,
+        day: u16,
+        hour: u16,
+        minute: u16,
+        second: u16,
+        milliseconds: u16,
+    }
+
+    #[allow(dead_code)]
+    #[derive(Default)]
+    #[repr(C)]
+    struct TimeZoneInformation {
+        bias: i32,
+        standard_name: [u16; 32],
+        standard_date: SystemTime,
+        standard_bias: i32,
+        daylight_name: [u16; 32],
+        daylight_date: SystemTime,
+        daylight_bias: i32,
+    }
+
+    const TIME_ZONE_ID_INVALID: u32 = u32::MAX;
+
+    #[link(name = "kernel32")]
+    unsafe extern "system" {
+        fn GetTimeZoneInformation(info: *mut TimeZoneInformation) -> u32;
+    }
+
+    let mut info = TimeZoneInformation::default();
+    if unsafe { GetTimeZoneInformation(&mut info) } == TIME_ZONE_ID_INVALID {
+        return None;
+    }
+
+    // Both dates use the day-of-month field as the week, with 5 meaning the last one.
+    let transition = |date: SystemTime| Transition {
+        date: RuleDate::Month {
+            month: date.month as u8,
+            week: date.day as u8,
+            weekday: date.day_of_week as u8,
+        },
+        time: i32::from(date.hour) * 3600 + i32::from(date.minute) * 60 + i32::from(date.second),
+    };
+    let std_offset = -(info.bias + info.standard_bias) * 60;
+    let dst = (info.daylight_date.month != 0).then(|| Dst {
+        offset: -(info.bias + info.daylight_bias) * 60,
+        start: transition(info.daylight_date),
+        end: transition(info.standard_date),
+    });
+    Some(TimeZone::Posix(PosixTz { std_offset, dst }))
+}
+
+/// A compiled zoneinfo file: UTC transition times, the local time type each one starts, and
+/// the POSIX rule for times after the last transition.
+#[derive(Debug, Clone, PartialEq)]
+struct Tzif {
+    transitions: Vec<i64>,
+    types: Vec<u8>,
+    offsets: Vec<i32>,
+    footer: Option<PosixTz>,
+}
+
+impl Tzif {
+    /// Parses version 1 files and the 64-bit data of version 2 and later, ignoring leap
+    /// seconds.
+    fn parse(data: &[u8]) -> Option<Self> {
+        let mut reader = Reader { data, pos: 0 };
+        let header = TzifHeader::read(&mut reader)?;
+        if header.version == 0 {
+            return Self::read_body(&mut reader, &header, 4);
+        }
+
+        reader.skip(header.body_len(4))?;
+        let header = TzifHeader::read(&mut reader)?;
+        let mut tzif = Self::read_body(&mut reader, &header, 8)?;
+        let footer = reader.rest();
+        tzif.footer = footer
+            .strip_prefix(b"\n")
+            .and_then(|footer| footer.split(|&byte| byte == b'\n').next())
+            .and_then(|footer| std::str::from_utf8(footer).ok())
+            .and_then(PosixTz::parse);
+        Some(tzif)
+    }
+
+    fn read_body(reader: &mut Reader<'_>, header: &TzifHeader, time_size: usize) -> Option<Self> {
+        let transitions = (0..header.time_count)
+            .map(|_| reader.int(time_size))
+            .collect::<Option<Vec<_>>>()?;
+        let types = reader.take(header.time_count)?.to_vec();
+        let mut offsets = Vec::with_capacity(header.type_count);
+        for _ in 0..header.type_count {
+            offsets.push(reader.int(4)? as i32);
+            reader.skip(2)?;
+        }
+        if offsets.is_empty() || types.iter().any(|&index| index as usize >= offsets.len()) {
+            return None;
+        }
+        reader.skip(
+            header.char_count
+                + header.leap_count * (time_size + 4)
+                + header.std_count
+                + header.ut_count,
+        )?;
+        Some(Self {
+            transitions,
+            types,
+            offsets,
+            footer: None,
+        })
+    }
+
+    fn offset_at(&self, timestamp: i64) -> i32 {
+        let index = self
+            .transitions
+            .partition_point(|&transition| transition <= timestamp);
+        if index == self.transitions.len()
+            && let Some(footer) = &self.footer
+        {
+            return footer.offset_at(timestamp);
+        }
+        match index.checked_sub(1) {
+            Some(index) => self.offsets[self.types[index] as usize],
+            None => self.offsets[0],
+        }
+    }
+}
+
+struct TzifHeader {
+    version: u8,
+    ut_count: usize,
+    std_count: usize,
+    leap_count: usize,
+    time_count: usize,
+    type_count: usize,
+    char_count: usize,
+}
+
+impl TzifHeader {
+    fn read(reader: &mut Reader<'_>) -> Option<Self> {
+        if reader.take(4)? != b"TZif" {
+            return None;
+        }
+        let version = match reader.take(1)?[0] {
+            0 => 0,
+            byte @ b'2'..=b'9' => byte - b'0',
+            _ => return None,
+        };
+        reader.skip(15)?;
+        let mut count = || reader.int(4).and_then(|count| usize::try_from(count).ok());
+        Some(Self {
+            version,
+            ut_count: count()?,
+            std_count: count()?,
+            leap_count: count()?,
+            time_count: count()?,
+            type_count: count()?,
+            char_count: count()?,
+        })
+    }
+
+    fn body_len(&self, time_size: usize) -> usize {
+        self.time_count * (time_size + 1)
+            + self.type_count * 6
+            + self.char_count
+            + self.leap_count * (time_size + 4)
+            + self.std_count
+            + self.ut_count
+    }
+}
+
+struct Reader<'a> {
+    data: &'a [u8],
+    pos: usize,
+}
+
+impl<'a> Reader<'a> {
+    fn take(&mut self, len: usize) -> Option<&'a [u8]> {
+        let bytes = self.data.get(self.pos..self.pos.checked_add(len)?)?;
+        self.pos += len;
+        Some(bytes)
+    }
+
+    fn skip(&mut self, len: usize) -> Option<()> {
+        self.take(len).map(|_| ())
+    }
+
+    /// A big-endian signed integer of 4 or 8 bytes.
+    fn int(&mut self, size: usize) -> Option<i64> {
+        let bytes = self.take(size)?;
+        Some(match size {
+            4 => i64::from(i32::from_be_bytes(bytes.try_into().ok()?)),
+            _ => i64::from_be_bytes(bytes.try_into().ok()?),
+        })
+    }
+
+    fn rest(&self) -> &'a [u8] {
+        &self.data[self.pos..]
+    }
+}
+
+/// A POSIX TZ string: a standard offset and optionally a daylight saving offset with the
+/// rules for when it starts and ends.
+#[derive(Debug, Clone, PartialEq)]
+struct PosixTz {
+    std_offset: i32,
+    dst: Option<Dst>,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+struct Dst {
+    offset: i32,
+    start: Transition,
+    end: Transition,
+}
+
+/// A yearly switch, at `time` seconds after local midnight of `date`.
+#[derive(Debug, Clone, PartialEq)]
+struct Transition {
+    date: RuleDate,
+    time: i32,
+}
+
+#[derive(Debug, Clone, PartialEq)]
+enum RuleDate {
+    /// `Jn`: day 1 to 365, never counting February 29.
+    Julian(u16),
+    /// `n`: day 0 to 365, counting February 29 in leap years.
+    Ordinal(u16),
+    /// `Mm.w.d`: weekday `d` (0 is Sunday) of week `w` of month `m`, where week 5 is the last.
+    Month { month: u8, week: u8, weekday: u8 },
+}
+
+impl PosixTz {
+    fn parse(tz: &str) -> Option<Self> {
+        let mut parser = PosixParser { rest: tz };
+        parser.name()?;
+        // POSIX offsets count hours west of UTC.
+        let std_offset = -parser.offset()?;
+        if parser.rest.is_empty() {
+            return Some(Self {
+                std_offset,
+                dst: None,
+            });
+        }
+
+        parser.name()?;
+        let offset = match parser.rest.as_bytes().first() {
+            None | Some(b',') => std_offset + 3600,
+            Some(_) => -parser.offset()?,
+        };
+        if parser.rest.is_empty() {
+            parser.rest = DEFAULT_DST_RULE;
+        } else {
+            parser.rest = parser.rest.strip_prefix(',')?;
+        }
+        let start = parser.transition()?;
+        parser.rest = parser.rest.strip_prefix(',')?;
+        let end = parser.transition()?;
+        if !parser.rest.is_empty() {
+            return None;
+        }
+        Some(Self {
+            std_offset,
+            dst: Some(Dst { offset, start, end }),
+        })
+    }
+
+    fn offset_at(&self, timestamp: i64) -> i32 {
+        let Some(dst) = &self.dst else {
+            return self.std_offset;
+        };
+        let year =
+            civil_from_days((timestamp + i64::from(self.std_offset)).div_euclid(SECONDS_PER_DAY)).0;
+        // The start is given in standard time and the end in daylight saving time.
+        let start = dst.start.local_seconds(year) - i64::from(self.std_offset);
+        let end = dst.end.local_seconds(year) - i64::from(dst.offset);
+        let in_dst = if start < end {
+            start <= timestamp && timestamp < end
+        } else {
+            !(end <= timestamp && timestamp < start)
+        };
+        if in_dst { dst.offset } else { self.std_offset }
+    }
+}
+
+impl Transition {
+    /// Local seconds since the epoch at which this transition happens in `year`.
+    fn local_seconds(&self, year: i64) -> i64 {
+        let day = match self.date {
+            RuleDate::Julian(day) => {
+                let day = i64::from(day) - 1;
+                let skipped_leap_day = is_leap_year(year) && day >= 59;
+                days_from_civil(year, 1, 1) + day + i64::from(skipped_leap_day)
+            }
+            RuleDate::Ordinal(day) => days_from_civil(year, 1, 1) + i64::from(day),
+            RuleDate::Month {
+                month,
+                week,
+                weekday,
+            } => {
+                let first = days_from_civil(year, month.into(), 1);
+                let first_weekday = (first + 4).rem_euclid(7);
+                let mut day =
+                    (i64::from(weekday) - first_weekday).rem_euclid(7) + 7 * (i64::from(week) - 1);
+                while day >= days_in_month(year, month.into()) {
+                    day -= 7;
+                }
+                first + day
+            }
+        };
+        day * SECONDS_PER_DAY + i64::from(self.time)
+    }
+}
+
+struct PosixParser<'a> {
+    rest: &'a str,
+}
+
+impl PosixParser<'_> {
+    /// A zone abbreviation: three or more letters, or anything but `>` between `<` and `>`.
+    fn name(&mut self) -> Option<()> {
+        let len = if let Some(quoted) = self.rest.strip_prefix('<') {
+            quoted.find('>')? + 2
+        } else {
+            let len = self
+                .rest
+                .find(|ch: char| !ch.is_ascii_alphabetic())
+                .unwrap_or(self.rest.len());
+            if len < 3 {
+                return None;
+            }
+            len
+        };
+        self.rest = &self.rest[len..];
+        Some(())
+    }
+
+    /// `[+|-]hh[:mm[:ss]]` in seconds.
+    fn offset(&mut self) -> Option<i32> {
+        let sign = match self.rest.as_bytes().first()? {
+            b'-' => -1,
+            b'+' => 1,
+            _ => 0,
+        };
+        if sign != 0 {
+            self.rest = &self.rest[1..];
+        }
+        let mut seconds = 0;
+        for (index, scale) in [3600, 60, 1].into_iter().enumerate() {
+            if index > 0 {
+                match self.rest.strip_prefix(':') {
+                    Some(rest) => self.rest = rest,
+                    None => break,
+                }
+            }
+            seconds += self.number(167)? * scale;
+        }
+        Some(if sign < 0 { -seconds } else { seconds })
+    }
+
+    fn transition(&mut self) -> Option<Transition> {
+        let date = if let Some(rest) = self.rest.strip_prefix('J') {
+            self.rest = rest;
+            RuleDate::Julian(
+                u16::try_from(self.number(365)?)
+                    .ok()
+                    .filter(|&day| day > 0)?,
+            )
+        } else if let Some(rest) = self.rest.strip_prefix('M') {
+            self.rest = rest;
+            let month = self.number(12)?;
+            self.rest = self.rest.strip_prefix('.')?;
+            let week = self.number(5)?;
+            self.rest = self.rest.strip_prefix('.')?;
+            let weekday = self.number(6)?;
+            if month == 0 || week == 0 {
+                return None;
+            }
+            RuleDate::Month {
+                month: month as u8,
+                week: week as u8,
+                weekday: weekday as u8,
+            }
+        } else {
+            RuleDate::Ordinal(self.number(365)? as u16)
+        };
+        let time = match self.rest.strip_prefix('/') {
+            Some(rest) => {
+                self.rest = rest;
+                self.offset()?
+            }
+            None => 2 * 3600,
+        };
+        Some(Transition { date, time })
+    }
+
+    /// A decimal number no larger than `max`.
+    fn number(&mut self, max: i32) -> Option<i32> {
+        let len = self
+            .rest
+            .find(|ch: char| !ch.is_ascii_digit())
+            .unwrap_or(self.rest.len());
+        let number = self.rest[..len]
+            .parse()
+            .ok()
+            .filter(|&number| number <= max)?;
+        self.rest = &self.rest[len..];
+        Some(number)
+    }
+}
+
+fn is_leap_year(year: i64) -> bool {
+    year % 4 == 0 && (year % 100 != 0 || year % 400 == 0)
+}
+
+fn days_in_month(year: i64, month: i64) -> i64 {
+    match month {
+        2 if is_leap_year(year) => 29,
+        2 => 28,
+        4 | 6 | 9 | 11 => 30,
+        _ => 31,
+    }
+}
+
+/// Days since 1970-01-01 of a proleptic Gregorian date.
+fn days_from_civil(year: i64, month: i64, day: i64) -> i64 {
+    let year = if month <= 2 { year - 1 } else { year };
+    let era = year.div_euclid(400);
+    let year_of_era = year - era * 400;
+    let month_index = (month + 9) % 12;
+    let day_of_year = (153 * month_index + 2) / 5 + day - 1;
+    let day_of_era = year_of_era * 365 + year_of_era / 4 - year_of_era / 100 + day_of_year;
+    era * 146_097 + day_of_era - 719_468
+}
+
+/// The year, month, and day of `days` since 1970-01-01.
+fn civil_from_days(days: i64) -> (i64, i64, i64) {
+    let days = days + 719_468;
+    let era = days.div_euclid(146_097);
+    let day_of_era = days - era * 146_097;
+    let year_of_era =
+        (day_of_era - day_of_era / 1460 + day_of_era / 36_524 - day_of_era / 146_096) / 365;
+    let day_of_year = day_of_era - (365 * year_of_era + year_of_era / 4 - year_of_era / 100);
+    let month_index = (5 * day_of_year + 2) / 153;
+    let day = day_of_year - (153 * month_index + 2) / 5 + 1;
+    let month = if month_index < 10 {
+        month_index + 3
+    } else {
+        month_index - 9
+    };
+    let year = year_of_era + era * 400 + i64::from(month <= 2);
+    (year, month, day)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Unix time of a UTC date and time.
+    fn utc(year: i64, month: i64, day: i64, hour: i64, minute: i64) -> i64 {
+        days_from_civil(year, month, day) * SECONDS_PER_DAY + hour * 3600 + minute * 60
+    }
+
+    #[test]
+    fn civil_dates_round_trip() {
+        assert_eq!(days_from_civil(1970, 1, 1), 0);
+        assert_eq!(days_from_civil(2000, 3, 1), 11_017);
+        for days in [-800_000, -1, 0, 59, 10_957, 11_016, 20_000, 2_000_000] {
+            let (year, month, day) = civil_from_days(days);
+            assert_eq!(days_from_civil(year, month, day), days);
+        }
+        assert_eq!(civil_from_days(19_782), (2024, 2, 29));
+    }
+
+    #[test]
+    fn posix_fixed_offsets() {
+        let tz = PosixTz::parse("UTC0").unwrap();
+        assert_eq!(tz.offset_at(0), 0);
+        let tz = PosixTz::parse("<+0530>-5:30").unwrap();
+        assert_eq!(tz.offset_at(0), 5 * 3600 + 30 * 60);
+        assert_eq!(PosixTz::parse("JST-9").unwrap().offset_at(0), 9 * 3600);
+        assert_eq!(PosixTz::parse("X1"), None);
+        assert_eq!(PosixTz::parse("EST"), None);
+    }
+
+    #[test]
+    fn posix_rules_switch_at_local_times() {
+        let tz = PosixTz::parse("EST5EDT,M3.2.0,M11.1.0").unwrap();
+        // 2024 switched on March 10 at 02:00 EST and on November 3 at 02:00 EDT.
+        assert_eq!(tz.offset_at(utc(2024, 3, 10, 6, 59)), -5 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 3, 10, 7, 0)), -4 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 11, 3, 5, 59)), -4 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 11, 3, 6, 0)), -5 * 3600);
+        assert_eq!(PosixTz::parse("EST5EDT").unwrap(), tz);
+    }
+
+    #[test]
+    fn posix_rules_in_the_southern_hemisphere() {
+        let tz = PosixTz::parse("AEST-10AEDT,M10.1.0,M4.1.0/3").unwrap();
+        assert_eq!(tz.offset_at(utc(2024, 1, 15, 0, 0)), 11 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 7, 15, 0, 0)), 10 * 3600);
+        // April 7, 2024 at 03:00 AEDT, which is 16:00 UTC the day before.
+        assert_eq!(tz.offset_at(utc(2024, 4, 6, 15, 59)), 11 * 3600);
+        assert_eq!(tz.offset_at(utc(2024, 4, 6, 16, 0)), 10 * 3600);
+    }
+
+    #[test]
+    fn posix_julian_and_last_week_dates() {
+        let julian = Transition {
+            date: RuleDate::Julian(60),
+            time: 0,
+        };
+        assert_eq!(julian.local_seconds(2024), utc(2024, 3, 1, 0, 0));
+        let ordinal = Transition {
+            date: RuleDate::Ordinal(59),
+            time: 0,
+        };
+        assert_eq!(ordinal.local_seconds(2024), utc(2024, 2, 29, 0, 0));
+        let last_sunday = Transition {
+            date: RuleDate::Month {
+                month: 3,
+                week: 5,
+                weekday: 0,
+            },
+            time: 3600,
+        };
+        assert_eq!(last_sunday.local_seconds(2024), utc(2024, 3, 31, 1, 0));
+    }
+
+    fn tzif_v2(transitions: &[(i64, u8)], offsets: &[i32], footer: &str) -> Vec<u8> {
+        fn header(data: &mut Vec<u8>, time_count: usize, type_count: usize) {
+            data.extend_from_slice(b"TZif2");
+            data.extend_from_slice(&[0; 15]);
+            for count in [0, 0, 0, time_count, type_count, 4] {
+                data.extend_from_slice(&(count as u32).to_be_bytes());
+            }
+        }
+
+        let mut data = Vec::new();
+        header(&mut data, 0, 1);
+        data.extend_from_slice(&[0; 6]);
+        data.extend_from_slice(b"UTC\0");
+        header(&mut data, transitions.len(), offsets.len());
+        for (time, _) in transitions {
+            data.extend_from_slice(&time.to_be_bytes());
+        }
+        data.extend(transitions.iter().map(|&(_, index)| index));
+        for offset in offsets {
+            data.extend_from_slice(&offset.to_be_bytes());
+            data.extend_from_slice(&[0, 0]);
+        }
+        data.extend_from_slice(b"UTC\0");
+        data.extend_from_slice(format!("\n{}\n", footer).as_bytes());
+        data
+    }
+
+    #[test]
+    fn tzif_uses_transitions_then_footer() {
+        let data = tzif_v2(&[(1000, 1), (2000, 0)], &[3600, 7200], "<+03>-3");
+        let tzif = Tzif::parse(&data).unwrap();
+        assert_eq!(tzif.offset_at(0), 3600);
+        assert_eq!(tzif.offset_at(1000), 7200);
+        assert_eq!(tzif.offset_at(1999), 7200);
+        assert_eq!(tzif.offset_at(2000), 3 * 3600);
+    }
+
+    #[test]
+    fn tzif_rejects_malformed_data() {
+        let data = tzif_v2(&[(1000, 5)], &[3600], "");
+        assert_eq!(Tzif::parse(&data), None);
+        assert_eq!(Tzif::parse(b"TZif2"), None);
+        assert_eq!(Tzif::parse(b"not a zone"), None);
+    }
+
+    #[test]
+    fn tz_names_are_files_or_posix_strings() {
+        assert_eq!(zone_from_tz(""), None);
+        assert_eq!(
+            zone_from_tz(":UTC-2"),
+            Some(TimeZone::Posix(PosixTz {
+                std_offset: 7200,
+                dst: None,
+            }))
+        );
+        assert_eq!(zoneinfo_file("../etc/passwd"), None);
+
+        let dir = tempfile::tempdir().unwrap();
+        let path = dir.path().join("Zone");
+        fs::write(&path, tzif_v2(&[], &[-3600], "")).unwrap();
+        let zone = zone_from_tz(path.to_str().unwrap()).unwrap();
+        assert_eq!(zone.offset_at(0), -3600);
+    }
+
+    #[test]
+    fn seconds_of_day_wrap_around_midnight() {
+        let timestamp = utc(2024, 1, 1, 0, 30);
+        assert_eq!(seconds_of_day(timestamp, -3600), 23 * 3600 + 30 * 60);
+        assert_eq!(seconds_of_day(timestamp, 3600), 3600 + 30 * 60);
+    }
+}
@@ -0,0 +1,399 @@
+use crate::style::Shell;
+use std::borrow::Cow;
+use std::env;
+use std::str::FromStr;
+use unicode_width::UnicodeWidthChar;
+
+const ELLIPSIS: char = '…';
+const RESET: &str = "\x1b[0m";
+
+#[derive(Debug, Clone, Copy, PartialEq, Eq)]
+pub enum MaxWidth {
+    Columns(usize),
+    Auto,
+}
+
+impl MaxWidth {
+    /// Resolves to a column count; `Auto` yields `None` when the terminal size is unknown.
+    pub fn columns(self) -> Option<usize> {
+        match self {
+            MaxWidth::Columns(columns) => Some(columns),
+            MaxWidth::Auto => terminal_columns(),
+        }
+    }
+}
+
+impl FromStr for MaxWidth {
+    type Err = String;
+
+    fn from_str(value: &str) -> Result<Self, Self::Err> {
+        let value = value.trim();
+        if value.eq_ignore_ascii_case("auto") {
+            return Ok(MaxWidth::Auto);
+        }
+        value
+            .parse()
+            .map(MaxWidth::Columns)
+            .map_err(|_| format!("Invalid max width: {} (expected a number or auto)", value))
+    }
+}
+
+/// Byte length of the zero-width sequence at the start of `s` (ANSI CSI/OSC escapes and
+/// shell prompt delimiters), or 0 when `s` starts with visible text.
+fn escape_len(s: &str, shell: Shell) -> usize {
+    let bytes = s.as_bytes();
+    match bytes {
+        [0x1b, b'[', rest @ ..] => rest
+            .iter()
+            .position(|byte| (0x40..=0x7e).contains(byte))
+            .map_or(bytes.len(), |end| end + 3),
+        [0x1b, b']', rest @ ..] => {
+            for (index, byte) in rest.iter().enumerate() {
+                match byte {
+                    0x07 => return index + 3,
+                    0x1b if rest.get(index + 1) == Some(&b'\\') => return index + 4,
+                    _ => {}
+                }
+            }
+            bytes.len()
+        }
+        [0x1b, ..] => 1 + s[1..].chars().next().map_or(0, char::len_utf8),
+        [0x01, ..] => memchr::memchr(0x02, bytes).map_or(bytes.len(), |end| end + 1),
+        [b'%', b'{', ..] if shell == Shell::Zsh => {
+            memchr::memmem::find(bytes, b"%}").map_or(bytes.len(), |end| end + 2)
+        }
+        _ => 0,
+    }
+}
+
+fn char_width(ch: char) -> usize {
+    ch.width().unwrap_or(0)
+}
+
+/// Byte length and column width of the visible character at the start of `s`. Shell
+/// escapes such as zsh's `%%` count as the single character they print.
+fn visible_char(s: &str, shell: Shell) -> Option<(usize, usize)> {
+    if let Some(len) = shell.escaped_char_len(s) {
+        return Some((len, 1));
+    }
+    let ch = s.chars().next()?;
+    Some((ch.len_utf8(), char_width(ch)))
+}
+
+/// Number of terminal columns `s` occupies, ignoring escapes and shell delimiters.
+pub fn display_width(s: &str, shell: Shell) -> usize {
+    let mut width = 0;
+    let mut pos = 0;
+
+    while pos < s.len() {
+        let skip = escape_len(&s[pos..], shell);
+        if skip > 0 {
+            pos += skip;
+            continue;
+        }
+        let Some((len, char_width)) = visible_char(&s[pos..], shell) else {
+            break;
+        };
+        width += char_width;
+        pos += len;
+    }
+
+    width
+}
+
+/// Cuts a single line down to `max` columns, ending it with an ellipsis. Escape sequences
+/// before the cut are preserved and styling is reset afterwards so it does not bleed.
+pub fn truncate_to_width(line: &str, max: usize, shell: Shell) -> Cow<'_, str> {
+    if display_width(line, shell) <= max {
+        return Cow::Borrowed(line);
+    }
+
+    let budget = max.saturating_sub(1);
+    let mut output = String::with_capacity(line.len());
+    let mut used = 0;
+    let mut styled = false;
+    let mut pos = 0;
+
+    while pos < line.len() {
+        let skip = escape_len(&line[pos..], shell);
+        if skip > 0 {
+            output.push_str(&line[pos..pos + skip]);
+            styled = true;
+            pos += skip;
+            continue;
+        }
+        let Some((len, width)) = visible_char(&line[pos..], shell) else {
+            break;
+        };
+        if used + width > budget {
+            break;
+        }
+        output.push_str(&line[pos..pos + len]);
+        used += width;
+        pos += len;
+    }
+
+    if max > 0 {
+        output.push(ELLIPSIS);
+    }
+    if styled {
+        let (start, end) = shell.delimiters();
+        output.push_str(start);
+        output.push_str(RESET);
+        output.push_str(end);
+    }
+
+    Cow::Owned(output)
+}
+
+/// Cuts a single line down to `max` columns from the left, starting it with an ellipsis so
+/// the end of the line stays visible. Escape sequences in the dropped part are kept, so the
+/// remaining text is styled as before.
+pub fn truncate_start_to_width(line: &str, max: usize, shell: Shell) -> Cow<'_, str> {
+    if display_width(line, shell) <= max {
+        return Cow::Borrowed(line);
+    }
+
+    let mut pieces = Vec::new();
+    let mut pos = 0;
+    while pos < line.len() {
+        let skip = escape_len(&line[pos..], shell);
+        if skip > 0 {
+            pieces.push((pos..pos + skip, None));
+            pos += skip;
+            continue;
+        }
+        let Some((len, width)) = visible_char(&line[pos..], shell) else {
+            break;
+        };
+        pieces.push((pos..pos + len, Some(width)));
+        pos += len;
+    }
+
+    let budget = max.saturating_sub(1);
+    let mut used = 0;
+    let mut cut = pieces.len();
+    for (index, (_, width)) in pieces.iter().enumerate().rev() {
+        let width = width.unwrap_or(0);
+        if used + width > budget {
+            break;
+        }
+        used += width;
+        cut = index;
+    }
+
+    let mut output = String::with_capacity(line.len());
+    if max > 0 {
+        output.push(ELLIPSIS);
+    }
+    for (index, (range, width)) in pieces.into_iter().enumerate() {
+        if index >= cut || width.is_none() {
+            output.push_str(&line[range]);
+        }
+    }
+
+    Cow::Owned(output)
+}
+
+/// Cuts `text` down to `max_len` bytes, ending it with an ellipsis that counts towards the
+/// limit. Characters and escape sequences are never split, and styling is reset afterwards
+/// like in [`truncate_to_width`].
+pub fn truncate_to_len(text: &str, max_len: usize, shell: Shell) -> Cow<'_, str> {
+    if text.len() <= max_len {
+        return Cow::Borrowed(text);
+    }
+
+    let budget = max_len.saturating_sub(ELLIPSIS.len_utf8());
+    let mut styled = false;
+    let mut pos = 0;
+
+    while pos < text.len() {
+        let skip = escape_len(&text[pos..], shell);
+        let len = if skip > 0 {
+            skip
+        } else {
+            match visible_char(&text[pos..], shell) {
+                Some((len, _)) => len,
+                None => break,
+            }
+        };
+        if pos + len > budget {
+            break;
+        }
+        styled |= skip > 0;
+        pos += len;
+    }
+
+    let mut output = String::with_capacity(max_len + RESET.len());
+    output.push_str(&text[..pos]);
+    if max_len >= ELLIPSIS.len_utf8() {
+        output.push(ELLIPSIS);
+    }
+    if styled {
+        let (start, end) = shell.delimiters();
+        output.push_str(start);
+        output.push_str(RESET);
+        output.push_str(end);
+    }
+
+    Cow::Owned(output)
+}
+
+/// Applies [`truncate_to_width`] to every line of a rendered prompt.
+pub fn truncate_lines(output: &str, max: usize, shell: Shell) -> String {
+    let mut result = String::with_capacity(output.len());
+    for (index, line) in output.split('\n').enumerate() {
+        if index > 0 {
+            result.push('\n');
+        }
+        result.push_str(&truncate_to_width(line, max, shell));
+    }
+    result
+}
+
+/// Terminal width from `COLUMNS`, falling back to querying the controlling terminal.
+pub fn terminal_columns() -> Option<usize> {
+    if let Some(columns) = env::var("COLUMNS")
+        .ok()
+        .and_then(|value| value.trim().parse().ok())
+        .filter(|&columns| columns > 0)
+    {
+        return Some(columns);
+    }
+
+    platform_terminal_columns()
+}
+
+#[cfg(unix)]
+fn platform_terminal_columns() -> Option<usize> {
+    // Prompts run inside command substitution, so stdout is rarely the terminal
+    for fd in [libc::STDERR_FILENO, libc::STDIN_FILENO, libc::STDOUT_FILENO] {
+        let mut size: libc::winsize = unsafe { std::mem::zeroed() };
+        if unsafe { libc::ioctl(fd, libc::TIOCGWINSZ, &mut size) } == 0 && size.ws_col > 0 {
+            return Some(size.ws_col as usize);
+        }
+    }
+    None
+}
+
+#[cfg(not(unix))]
+fn platform_terminal_columns() -> Option<usize> {
+    None
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn display_width_skips_ansi_sequences() {
+        assert_eq!(display_width("\x1b[31m\x1b[1mabc\x1b[0m", Shell::None), 3);
+        assert_eq!(display_width("\x1b]0;title\x07ab", Shell::None), 2);
+    }
+
+    #[test]
+    fn display_width_skips_shell_delimiters() {
+        assert_eq!(
+            display_width("\x01\x1b[31m\x02ok\x01\x1b[0m\x02", Shell::Bash),
+            2
+        );
+        assert_eq!(display_width("%{\x1b[31m%}ok%{\x1b[0m%}", Shell::Zsh), 2);
+        assert_eq!(display_width("%{x", Shell::None), 3);
+        assert_eq!(display_width("100%%", Shell::Zsh), 4);
+        assert_eq!(display_width(r"a\\\\b\\$", Shell::BashPs1), 4);
+        assert_eq!(display_width(r"a\\b", Shell::Bash), 4);
+    }
+
+    #[test]
+    fn display_width_counts_wide_characters() {
+        assert_eq!(display_width("日本", Shell::None), 4);
+        assert_eq!(display_width("❯", Shell::None), 1);
+    }
+
+    #[test]
+    fn truncate_keeps_short_lines() {
+        assert!(matches!(
+            truncate_to_width("short", 10, Shell::None),
+            Cow::Borrowed("short")
+        ));
+    }
+
+    #[test]
+    fn truncate_adds_ellipsis_and_reset() {
+        let line = "\x1b[36m~/projects/very/long/path\x1b[0m";
+        assert_eq!(
+            truncate_to_width(line, 8, Shell::None),
+            "\x1b[36m~/proje…\x1b[0m"
+        );
+    }
+
+    #[test]
+    fn truncate_wraps_reset_for_shell() {
+        let line = "\x01\x1b[36m\x02abcdef\x01\x1b[0m\x02";
+        assert_eq!(
+            truncate_to_width(line, 3, Shell::Bash),
+            "\x01\x1b[36m\x02ab…\x01\x1b[0m\x02"
+        );
+    }
+
+    #[test]
+    fn truncate_does_not_split_wide_characters() {
+        assert_eq!(truncate_to_width("日本語", 4, Shell::None), "日…");
+    }
+
+    #[test]
+    fn truncate_start_keeps_the_end() {
+        assert!(matches!(
+            truncate_start_to_width("short", 10, Shell::None),
+            Cow::Borrowed("short")
+        ));
+        assert_eq!(
+            truncate_start_to_width("~/projects/very/long/path", 10, Shell::None),
+            "…long/path"
+        );
+        assert_eq!(truncate_start_to_width("日本語", 4, Shell::None), "…語");
+    }
+
+    #[test]
+    fn truncate_start_keeps_dropped_escapes() {
+        let line = "\x01\x1b[36m\x02abcdef\x01\x1b[0m\x02";
+        assert_eq!(
+            truncate_start_to_width(line, 3, Shell::Bash),
+            "…\x01\x1b[36m\x02ef\x01\x1b[0m\x02"
+        );
+        assert_eq!(
+            display_width(&truncate_start_to_width(line, 3, Shell::Bash), Shell::Bash),
+            3
+        );
+    }
+
+    #[test]
+    fn truncate_to_len_never_splits_characters_or_escapes() {
+        assert!(matches!(
+            truncate_to_len("short", 5, Shell::None),
+            Cow::Borrowed("short")
+        ));
+        assert_eq!(truncate_to_len("abcdefgh", 6, Shell::None), "abc…");
+        assert_eq!(truncate_to_len("日本語日本語", 9, Shell::None), "日本…");
+        assert_eq!(
+            truncate_to_len("ab\x1b[31mcdefgh", 12, Shell::None),
+            "ab\x1b[31mcd…\x1b[0m"
+        );
+        assert_eq!(truncate_to_len("ab\x1b[31mcdefgh", 8, Shell::None), "ab…");
+    }
+
+    #[test]
+    fn truncate_lines_handles_each_line() {
+        assert_eq!(
+            truncate_lines("abcdef\nxy\n123456", 4, Shell::None),
+            "abc…\nxy\n123…"
+        );
+    }
+
+    #[test]
+    fn max_width_from_str() {
+        assert_eq!(MaxWidth::from_str("80").unwrap(), MaxWidth::Columns(80));
+        assert_eq!(MaxWidth::from_str("AUTO").unwrap(), MaxWidth::Auto);
+        assert!(MaxWidth::from_str("wide").is_err());
+    }
+}
@@ -0,0 +1,166 @@
+use crate::style::ansi256_to_rgb;
+use std::fmt::Write;
+
+const COLOR_NAMES: [&str; 8] = [
+    "BLACK", "RED", "GREEN", "YELLOW", "BLUE", "PURPLE", "CYAN", "WHITE",
+];
+
+#[derive(Default)]
+struct Sgr {
+    reset: bool,
+    foreground: Option<String>,
+    background: Option<String>,
+    modifiers: Vec<&'static str>,
+}
+
+impl Sgr {
+    fn is_empty(&self) -> bool {
+        !self.reset
+            && self.foreground.is_none()
+            && self.background.is_none()
+            && self.modifiers.is_empty()
+    }
+
+    fn modifier(&mut self, modifier: &'static str) {
+        if !self.modifiers.contains(&modifier) {
+            self.modifiers.push(modifier);
+        }
+    }
+
+    fn apply(&mut self, params: &str) {
+        let mut codes = params.split(';').map(|code| {
+            code.split(':')
+                .next()
+                .unwrap_or("")
+                .parse::<u8>()
+                .unwrap_or(0)
+        });
+
+        while let Some(code) = codes.next() {
+            match code {
+                0 => {
+                    *self = Sgr {
+                        reset: true,
+                        ..Sgr::default()
+                    }
+                }
+                1 => self.modifier("BOLD_"),
+                2 => self.modifier("FAINT_"),
+                3 => self.modifier("ITALIC_"),
+                4 => self.modifier("UNDERLINE_"),
+                7 => self.modifier("INVERT_"),
+                9 => self.modifier("STRIKETHROUGH_"),
+                30..=37 => self.foreground = Some(COLOR_NAMES[(code - 30) as usize].to_string()),
+                90..=97 => {
+                    self.foreground = Some(format!("INTENSE_{}", COLOR_NAMES[(code - 90) as usize]))
+                }
+                40..=47 => self.background = Some(COLOR_NAMES[(code - 40) as usize].to_string()),
+                100..=107 => {
+                    self.background =
+                        Some(format!("INTENSE_{}", COLOR_NAMES[(code - 100) as usize]))
+                }
+                38 | 48 | 58 => {
+                    let color = match codes.next() {
+                        Some(5) => codes.next().map(ansi256_to_rgb),
+                        Some(2) => match (codes.next(), codes.next(), codes.next()) {
+                            (Some(r), Some(g), Some(b)) => Some((r, g, b)),
+                            _ => None,
+                        },
+                        _ => None,
+                    };
+                    let hex = color.map(|(r, g, b)| format!("#{:02x}{:02x}{:02x}", r, g, b));
+                    match code {
+                        38 => self.foreground = hex.or(self.foreground.take()),
+                        48 => self.background = hex.or(self.background.take()),
+                        _ => {}
+                    }
+                }
+                _ => {}
+            }
+        }
+    }
+
+    fn write_tokens(&self, output: &mut String) {
+        if self.reset {
+            output.push_str("{RESET}");
+        }
+        if let Some(background) = &self.background {
+            let _ = write!(output, "{{BACKGROUND_{}}}", background);
+        }
+        if self.foreground.is_some() || !self.modifiers.is_empty() {
+            output.push('{');
+            for modifier in &self.modifiers {
+                output.push_str(modifier);
+            }
+            output.push_str(self.foreground.as_deref().unwrap_or("DEFAULT"));
+            output.push('}');
+        }
+    }
+}
+
+/// Rewrites rendered ANSI output for xonsh's `$PROMPT`, which is itself a format string:
+/// literal braces are doubled and SGR escapes become xonsh color fields.
+pub fn to_prompt(output: &str) -> String {
+    let mut result = String::with_capacity(output.len() + 16);
+    let mut pending = Sgr::default();
+    let mut rest = output;
+
+    while let Some(ch) = rest.chars().next() {
+        if let Some(params) = rest.strip_prefix("\x1b[")
+            && let Some(end) = params.find(|c: char| !(c.is_ascii_digit() || c == ';' || c == ':'))
+            && params[end..].starts_with('m')
+        {
+            pending.apply(&params[..end]);
+            rest = &params[end + 1..];
+            continue;
+        }
+
+        if !pending.is_empty() {
+            pending.write_tokens(&mut result);
+            pending = Sgr::default();
+        }
+        match ch {
+            '{' => result.push_str("{{"),
+            '}' => result.push_str("}}"),
+            ch => result.push(ch),
+        }
+        rest = &rest[ch.len_utf8()..];
+    }
+    if !pending.is_empty() {
+        pending.write_tokens(&mut result);
+    }
+
+    result
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn escapes_literal_braces() {
+        assert_eq!(to_prompt("{a} }{"), "{{a}} }}{{");
+    }
+
+    #[test]
+    fn converts_named_colors_and_modifiers() {
+        assert_eq!(
+            to_prompt("\x1b[31m\x1b[1m~/src\x1b[0m ❯"),
+            "{BOLD_RED}~/src{RESET} ❯"
+        );
+        assert_eq!(to_prompt("\x1b[93mx\x1b[m"), "{INTENSE_YELLOW}x{RESET}");
+    }
+
+    #[test]
+    fn converts_extended_colors_to_hex() {
+        assert_eq!(
+            to_prompt("\x1b[38;2;255;0;16m\x1b[48;5;16mx\x1b[0m"),
+            "{BACKGROUND_#000000}{#ff0010}x{RESET}"
+        );
+    }
+
+    #[test]
+    fn modifiers_without_color_use_default() {
+        assert_eq!(to_prompt("\x1b[4m\x1b[4:3mx"), "{UNDERLINE_DEFAULT}x");
+    }
+}
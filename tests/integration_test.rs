@@ -1,5 +1,9 @@
-use prmt::{Token, execute, parse};
+use prmt::{
+    LastCommand, Module, ModuleContext, ModuleRegistry, Params, PromptBuilder, PromptError,
+    RenderOptions, Template, Token, check, execute, execute_with_registry, parse,
+};
 use std::env;
+use std::sync::{Arc, Mutex};
 
 #[test]
 fn test_basic_format() {
@@ -45,6 +49,17 @@ fn test_escape_sequences() {
     assert_eq!(result, "Line1\nLine2\tTab");
 }
 
+#[test]
+fn test_var_interpolation() {
+    let home = env::var("HOME").unwrap_or_default();
+    let result = execute("[${HOME}]", true, None, false).expect("Failed to execute");
+    assert_eq!(result, format!("[{}]", home));
+
+    let result =
+        execute("[${PRMT_TEST_SURELY_UNSET_VAR}]", true, None, false).expect("Failed to execute");
+    assert_eq!(result, "[]");
+}
+
 #[test]
 fn test_multiple_modules() {
     let result = execute("{path} {git}", true, None, false).expect("Failed to execute");
@@ -212,3 +227,255 @@ fn test_mixed_text_placeholders() {
         _ => panic!("Expected text token"),
     }
 }
+
+#[test]
+fn test_check_accepts_valid_format() {
+    let errors = check("{path:cyan:short} {git:purple:s+o} {time:dim:12h} {ok:green:✓}");
+    assert!(errors.is_empty(), "unexpected errors: {:?}", errors);
+}
+
+#[test]
+fn test_check_reports_every_problem() {
+    let errors = check("{nope} {path::major} {git:notacolor} {env}");
+    assert_eq!(errors.len(), 4, "errors: {:?}", errors);
+    assert!(matches!(&errors[0], PromptError::UnknownModule(name) if name == "nope"));
+    assert!(matches!(&errors[1], PromptError::InvalidFormat { module, .. } if module == "path"));
+    assert!(matches!(&errors[2], PromptError::StyleError { module, .. } if module == "git"));
+    assert!(matches!(&errors[3], PromptError::InvalidFormat { module, .. } if module == "env"));
+}
+
+#[test]
+fn test_separator_skips_hidden_modules() {
+    let result = execute(
+        "{ok::✓}{sep}{fail::✗}{sep::|}{path::s}",
+        true,
+        Some(0),
+        false,
+    )
+    .expect("Failed to execute");
+    let current_dir = env::current_dir().unwrap();
+    let basename = current_dir.file_name().unwrap().to_str().unwrap();
+    assert_eq!(result, format!("✓ {}", basename));
+    assert!(check("{path}{sep:dim: | }{git}").is_empty());
+}
+
+#[test]
+fn test_when_condition_toggles_placeholders() {
+    let result = execute(
+        "{ok::A::when=HOME}{ok::B::when=!HOME}{ok::C::when=PRMT_TEST_SURELY_UNSET_VAR}",
+        true,
+        Some(0),
+        false,
+    )
+    .expect("Failed to execute");
+    assert_eq!(result, "A");
+
+    assert!(check("{time:::when=SSH_CONNECTION}").is_empty());
+    assert_eq!(check("{time:::when=!}").len(), 1);
+}
+
+struct Fixed(&'static str);
+
+impl Module for Fixed {
+    fn render(&self, _format: &str, _context: &ModuleContext) -> prmt::Result<Option<String>> {
+        Ok(Some(self.0.to_string()))
+    }
+}
+
+#[test]
+fn test_public_structs_are_built_through_constructors() {
+    let mut params = Params::new("path");
+    params.style = "cyan".into();
+    assert_eq!(parse("{path:cyan}"), vec![Token::Placeholder(params)]);
+
+    let dir = env::temp_dir();
+    let mut context = ModuleContext::new(Some(dir.clone()));
+    context.exit_code = Some(1);
+    assert_eq!(context.current_dir(), Some(dir.as_path()));
+    assert_eq!(context.exit_code, Some(1));
+}
+
+#[test]
+fn test_template_render_into_reuses_the_buffer() {
+    let mut registry = ModuleRegistry::new();
+    registry.register("name", Arc::new(Fixed("prmt")));
+    let context = ModuleContext::default();
+    let template = Template::new("[{name}]");
+
+    let mut buf = String::new();
+    for _ in 0..3 {
+        buf.clear();
+        template.render_into(&mut buf, &registry, &context).unwrap();
+        assert_eq!(buf, "[prmt]");
+    }
+
+    let unknown = Template::new("{missing}");
+    assert!(unknown.render_into(&mut buf, &registry, &context).is_err());
+    assert_eq!(buf, "[prmt]");
+}
+
+#[test]
+fn test_execute_with_registry_mixes_custom_and_builtin_modules() {
+    let mut registry = ModuleRegistry::new();
+    registry.register("name", Arc::new(Fixed("prmt")));
+    registry.register("path", Arc::new(Fixed("custom path")));
+    let last_command = LastCommand {
+        exit_code: Some(0),
+        ..LastCommand::default()
+    };
+    let mut options = RenderOptions::default();
+    options.no_version = true;
+    options.color = prmt::style::ColorMode::Never;
+
+    let result = execute_with_registry("[{name}] {path} {ok:}", &registry, &last_command, &options)
+        .expect("render");
+    assert_eq!(result, "[prmt] custom path ❯");
+
+    let error = execute_with_registry("{missing}", &registry, &last_command, &options);
+    assert!(matches!(error, Err(PromptError::UnknownModule(name)) if name == "missing"));
+}
+
+struct Panicking;
+
+impl Module for Panicking {
+    fn render(&self, _format: &str, _context: &ModuleContext) -> prmt::Result<Option<String>> {
+        panic!("broken module");
+    }
+}
+
+#[test]
+fn test_panicking_module_is_hidden() {
+    let mut registry = ModuleRegistry::new();
+    registry.register("name", Arc::new(Fixed("prmt")));
+    registry.register("broken", Arc::new(Panicking));
+    let mut options = RenderOptions::default();
+    options.color = prmt::style::ColorMode::Never;
+
+    let result = execute_with_registry(
+        "[{name}]{broken}",
+        &registry,
+        &LastCommand::default(),
+        &options,
+    )
+    .expect("render");
+    assert_eq!(result, "[prmt]");
+}
+
+/// `catch_unwind` cannot hide a panicking module when the shipped binary aborts on panic.
+#[test]
+fn test_release_profile_unwinds() {
+    let manifest: toml::Table = include_str!("../Cargo.toml").parse().expect("Cargo.toml");
+    let panic = manifest["profile"]["release"]
+        .get("panic")
+        .and_then(|value| value.as_str());
+    assert_ne!(panic, Some("abort"));
+}
+
+#[test]
+fn test_prompt_builder_renders_repeatedly() {
+    let dir = std::env::temp_dir();
+    let prompt = PromptBuilder::new("{name} {path::a} {ok:}{fail:}")
+        .module("name", Arc::new(Fixed("prmt")))
+        .cwd(&dir)
+        .exit_code(0)
+        .color(prmt::style::ColorMode::Never)
+        .no_version(true)
+        .build()
+        .expect("build");
+
+    let expected = format!("prmt {} ❯", dir.display());
+    assert_eq!(prompt.render().unwrap(), expected);
+    assert_eq!(prompt.render().unwrap(), expected);
+
+    let failed = LastCommand {
+        exit_code: Some(1),
+        ..LastCommand::default()
+    };
+    assert_eq!(prompt.render_after(&failed).unwrap(), expected);
+
+    assert!(PromptBuilder::new("{path::bogus}").build().is_err());
+    assert!(PromptBuilder::new("{name}").build().is_err());
+}
+
+#[test]
+fn test_render_to_writes_the_prompt() {
+    let prompt = PromptBuilder::new("[{name}]")
+        .module("name", Arc::new(Fixed("prmt")))
+        .color(prmt::style::ColorMode::Never)
+        .build()
+        .expect("build");
+    let mut out = Vec::new();
+    prompt.render_to(&mut out).unwrap();
+    assert_eq!(out, b"[prmt]");
+
+    let mut registry = ModuleRegistry::new();
+    registry.register("name", Arc::new(Fixed("tpl")));
+    let mut out = Vec::new();
+    Template::new("<{name}>")
+        .render_to(&mut out, &registry, &ModuleContext::default())
+        .unwrap();
+    assert!(String::from_utf8(out).unwrap().contains("tpl"));
+
+    struct Closed;
+    impl std::io::Write for Closed {
+        fn write(&mut self, _buf: &[u8]) -> std::io::Result<usize> {
+            Err(std::io::ErrorKind::BrokenPipe.into())
+        }
+        fn flush(&mut self) -> std::io::Result<()> {
+            Ok(())
+        }
+    }
+    assert!(matches!(
+        prompt.render_to(&mut Closed),
+        Err(PromptError::IoError(_))
+    ));
+}
+
+/// Shows the context value named by its format.
+struct Value;
+
+impl Module for Value {
+    fn render(&self, format: &str, context: &ModuleContext) -> prmt::Result<Option<String>> {
+        Ok(context.value(format).map(str::to_string))
+    }
+}
+
+#[test]
+fn test_context_values_reach_custom_modules() {
+    let prompt = PromptBuilder::new("[{value::jobs}][{value::keymap}]")
+        .module("value", Arc::new(Value))
+        .value("jobs", "2")
+        .color(prmt::style::ColorMode::Never)
+        .build()
+        .expect("build");
+    assert_eq!(prompt.render().unwrap(), "[2][]");
+}
+
+#[test]
+fn test_render_hooks_filter_and_observe_modules() {
+    let events = Arc::new(Mutex::new(Vec::new()));
+    let seen = Arc::clone(&events);
+    let prompt = PromptBuilder::new("{name:red:loud}-{other}")
+        .module("name", Arc::new(Fixed("prmt")))
+        .module("other", Arc::new(Fixed("hidden")))
+        .color(prmt::style::ColorMode::Never)
+        .before_render(|module, _format| module != "other")
+        .after_render(move |event| {
+            let text = event.result.as_ref().ok().cloned().flatten();
+            seen.lock()
+                .unwrap()
+                .push((event.module.to_string(), event.format.to_string(), text));
+        })
+        .build()
+        .expect("build");
+
+    assert_eq!(prompt.render().unwrap(), "prmt-");
+    assert_eq!(
+        *events.lock().unwrap(),
+        [(
+            "name".to_string(),
+            "loud".to_string(),
+            Some("prmt".to_string())
+        )]
+    );
+}
@@ -384,3 +384,19 @@ fn test_escape_in_placeholder_fields() {
         _ => panic!("Expected placeholder token"),
     }
 }
+
+#[test]
+fn test_unicode_escapes() {
+    let tokens = parse("\\u{250c}[{path:cyan}]\\n\\u{2514}> ");
+    assert_eq!(tokens.len(), 7);
+
+    match &tokens[0] {
+        Token::Text(text) => assert_eq!(text, "┌"),
+        _ => panic!("Expected text token"),
+    }
+
+    match &tokens[5] {
+        Token::Text(text) => assert_eq!(text, "└"),
+        _ => panic!("Expected text token"),
+    }
+}